@@ -1,5 +1,6 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::path::PathBuf;
+use std::process::Child;
 use std::sync::mpsc::Receiver;
 use std::thread::JoinHandle;
 use std::time::Instant;
@@ -10,11 +11,21 @@ use ratatui::layout::Rect;
 use ratatui::widgets::ListState;
 
 use crate::keybinds::{KeyAction, KeyBind, KeyBindings};
-use crate::lsp_client::{LspClient, LspCompletionItem};
-use crate::tab::{GitChangeSummary, GitFileStatus, GitLineStatus, ProjectSearchHit, Tab};
+use crate::lsp_client::{
+    LspClient, LspCompletionItem, LspDiagnostic, LspServerSpec, PendingLspRequest,
+};
+use crate::persistence::FileAttributes;
+use crate::spellcheck::SpellChecker;
+use crate::tab::{
+    ClosedTab, DiffLine, DiffRow, FileSymbol, GitChangeSummary, GitFileStatus, GitLineStatus,
+    IndentSettings, NavLocation, ProjectSearchHit, Tab,
+};
 use crate::theme::Theme;
 use crate::tree_item::TreeItem;
-use crate::types::{CommandAction, Focus, PendingAction, PromptState};
+use crate::types::{
+    CommandAction, ConfirmKey, EscAction, Focus, OperationProgress, PaneSide, PendingAction,
+    ProjectKind, PromptState, TreeSort,
+};
 
 pub(crate) struct GitResult {
     pub branch: Option<String>,
@@ -44,6 +55,30 @@ pub(crate) struct SearchResultsState {
     pub(crate) query: String,
     pub(crate) results: Vec<ProjectSearchHit>,
     pub(crate) index: usize,
+    pub(crate) scroll: usize,
+    pub(crate) context_lines: usize,
+}
+
+/// A project search in flight: a worker thread reads `rg`'s output and sends
+/// each file's hits over `rx`. Dropping the job kills `rg`, which cancels it.
+pub(crate) struct ProjectSearchJob {
+    pub(crate) rx: Receiver<Vec<ProjectSearchHit>>,
+    pub(crate) child: Child,
+}
+
+/// A confirmed batch rename, carried out a step at a time by
+/// `poll_batch_rename` so it can report progress and be cancelled.
+pub(crate) struct RenameJob {
+    /// Renames still to do, deepest paths first.
+    pub(crate) queue: VecDeque<(PathBuf, PathBuf)>,
+    pub(crate) renamed: usize,
+}
+
+impl Drop for ProjectSearchJob {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
 }
 
 pub(crate) struct CompletionState {
@@ -72,9 +107,93 @@ pub(crate) struct KeybindEditorState {
     pub(crate) actions: Vec<KeyAction>,
 }
 
+/// Picker for the active tab's language; row 0 is "Auto Detect", the rest
+/// follow `SyntaxLang::ALL`.
+pub(crate) struct LanguagePickerState {
+    pub(crate) open: bool,
+    pub(crate) index: usize,
+    pub(crate) rect: Rect,
+}
+
+/// Go to Symbol picker over the active tab's heuristic outline; `results`
+/// index into `symbols`, best fuzzy match first.
+pub(crate) struct SymbolPickerState {
+    pub(crate) open: bool,
+    pub(crate) query: String,
+    pub(crate) symbols: Vec<FileSymbol>,
+    pub(crate) results: Vec<usize>,
+    pub(crate) index: usize,
+    pub(crate) rect: Rect,
+}
+
+/// Directory browser behind "Open Folder": row 0 opens `dir` as the project
+/// root, row 1 goes up to its parent and the rest are its subdirectories.
+pub(crate) struct FolderPickerState {
+    pub(crate) open: bool,
+    pub(crate) dir: PathBuf,
+    pub(crate) entries: Vec<PathBuf>,
+    pub(crate) index: usize,
+    /// First row shown, so long listings keep the highlighted row in view.
+    pub(crate) offset: usize,
+    pub(crate) rect: Rect,
+}
+
+/// Problems panel listing every known diagnostic grouped by file; the list
+/// itself is rebuilt from the tabs on each draw so it follows new reports.
+pub(crate) struct DiagnosticsPanelState {
+    pub(crate) open: bool,
+    pub(crate) index: usize,
+    pub(crate) scroll: usize,
+    pub(crate) rect: Rect,
+}
+
+/// Picker for the tab to diff the active tab against; `tabs` holds the
+/// candidate tab indices in display order.
+pub(crate) struct DiffTabPickerState {
+    pub(crate) open: bool,
+    pub(crate) tabs: Vec<usize>,
+    pub(crate) index: usize,
+    pub(crate) rect: Rect,
+}
+
+/// Picker behind "Open Recent Project"; `projects` are the recent roots
+/// other than the current one, most recent first.
+pub(crate) struct RecentProjectPickerState {
+    pub(crate) open: bool,
+    pub(crate) projects: Vec<PathBuf>,
+    pub(crate) index: usize,
+    pub(crate) rect: Rect,
+}
+
+/// Side-by-side diff of two tabs, taken when it was opened. Both columns
+/// scroll together through `rows`.
+pub(crate) struct TabDiffState {
+    pub(crate) left: PathBuf,
+    pub(crate) right: PathBuf,
+    pub(crate) rows: Vec<DiffRow>,
+    pub(crate) scroll: usize,
+    pub(crate) page: usize,
+}
+
+/// Layout flags zen mode overrides, put back when it is turned off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ZenRestore {
+    pub(crate) files_view_open: bool,
+    pub(crate) focus: Focus,
+}
+
+/// Paths copied or cut in the file tree, waiting to be pasted.
+pub(crate) struct TreeClipboard {
+    pub(crate) paths: Vec<PathBuf>,
+    pub(crate) cut: bool,
+}
+
 pub(crate) struct FsChangeEvent {
     pub(crate) paths: Vec<PathBuf>,
     pub(crate) full_refresh: bool,
+    /// Files may have been created, removed or renamed, so the Quick Open
+    /// index is out of date.
+    pub(crate) files_changed: bool,
 }
 
 pub(crate) struct App {
@@ -83,24 +202,61 @@ pub(crate) struct App {
     pub(crate) selected: usize,
     pub(crate) tree_state: ListState,
     pub(crate) expanded: HashSet<PathBuf>,
+    /// Multi-selected tree paths; `selected` stays the focused row.
+    pub(crate) tree_selection: HashSet<PathBuf>,
+    /// Row where a Shift+Up/Down range started.
+    pub(crate) tree_selection_anchor: Option<PathBuf>,
+    pub(crate) tree_clipboard: Option<TreeClipboard>,
     pub(crate) focus: Focus,
     pub(crate) tabs: Vec<Tab>,
     pub(crate) active_tab: usize,
+    /// Tab indices, most recently focused first.
+    pub(crate) tab_mru: Vec<usize>,
     pub(crate) last_tree_click: Option<(Instant, usize)>,
     pub(crate) status: String,
     pub(crate) pending: PendingAction,
     pub(crate) quit: bool,
     pub(crate) files_view_open: bool,
     pub(crate) files_pane_width: u16,
+    /// Edge the files pane is docked to.
+    pub(crate) files_pane_side: PaneSide,
+    /// Layout to restore while zen mode is on; `None` when it is off.
+    pub(crate) zen_restore: Option<ZenRestore>,
+    /// Widest text column zen mode centers, in cells.
+    pub(crate) zen_width: u16,
+    /// Draw a blank row after every `line_spacing`-th line; 0 draws none.
+    pub(crate) line_spacing: usize,
     pub(crate) divider_dragging: bool,
     pub(crate) menu_open: bool,
     pub(crate) menu_index: usize,
     pub(crate) menu_query: String,
     pub(crate) menu_results: Vec<CommandAction>,
     pub(crate) menu_rect: Rect,
+    pub(crate) language_picker: LanguagePickerState,
+    pub(crate) symbol_picker: SymbolPickerState,
+    pub(crate) diff_tab_picker: DiffTabPickerState,
+    pub(crate) folder_picker: FolderPickerState,
+    pub(crate) recent_project_picker: RecentProjectPickerState,
+    /// Project roots opened most recently first, shared by every project.
+    pub(crate) recent_projects: Vec<PathBuf>,
+    /// Most entries kept in `recent_projects`.
+    pub(crate) recent_projects_limit: usize,
+    /// Past prompt input by `PromptMode::history_key`, newest first.
+    pub(crate) prompt_history: BTreeMap<String, Vec<String>>,
+    /// Language overrides and read-only flags reapplied when a file is
+    /// opened again, shared by every project.
+    pub(crate) file_attributes: BTreeMap<PathBuf, FileAttributes>,
+    pub(crate) diagnostics_panel: DiagnosticsPanelState,
+    pub(crate) tab_diff: Option<TabDiffState>,
     pub(crate) theme_browser_open: bool,
     pub(crate) theme_browser_rect: Rect,
+    /// Sample code beside the theme list.
+    pub(crate) theme_preview_rect: Rect,
     pub(crate) theme_index: usize,
+    /// Theme browser filter; `theme_results` holds the indices into
+    /// `themes` it matches, best match first.
+    pub(crate) theme_query: String,
+    pub(crate) theme_results: Vec<usize>,
     pub(crate) preview_revert_index: usize,
     pub(crate) themes: Vec<Theme>,
     pub(crate) active_theme_index: usize,
@@ -111,6 +267,24 @@ pub(crate) struct App {
     pub(crate) editor_rect: Rect,
     pub(crate) divider_rect: Rect,
     pub(crate) tab_rects: Vec<(Rect, Rect)>,
+    /// Index of the tab being dragged in the tab bar, if any.
+    pub(crate) tab_drag: Option<usize>,
+    /// Tree item pressed with the left button, moved on release elsewhere.
+    pub(crate) tree_drag: Option<PathBuf>,
+    /// First tab shown in the tab bar when tabs overflow its width.
+    pub(crate) tab_scroll_offset: usize,
+    /// Width available to the tab bar in the last rendered frame.
+    pub(crate) tab_bar_width: usize,
+    /// Recently closed tabs, most recent last.
+    pub(crate) closed_tabs: Vec<ClosedTab>,
+    /// Positions left by big jumps, most recent last; `navigate_back` pops
+    /// from `nav_back` onto `nav_forward` and `navigate_forward` the reverse.
+    pub(crate) nav_back: Vec<NavLocation>,
+    pub(crate) nav_forward: Vec<NavLocation>,
+    /// Where the most recent edit in any tab left the cursor.
+    pub(crate) last_edit_location: Option<NavLocation>,
+    /// Last number used for an `untitled-N` scratch buffer name.
+    pub(crate) untitled_count: usize,
     pub(crate) context_menu: ContextMenuState,
     pub(crate) prompt: Option<PromptState>,
     pub(crate) prompt_rect: Rect,
@@ -118,32 +292,138 @@ pub(crate) struct App {
     pub(crate) editor_context_menu_open: bool,
     pub(crate) editor_context_menu_index: usize,
     pub(crate) editor_context_menu_pos: (u16, u16),
+    /// Text position that was right-clicked to open the editor context menu.
+    pub(crate) editor_context_menu_target: Option<(usize, usize)>,
     pub(crate) editor_context_menu_rect: Rect,
     pub(crate) editor_dragging: bool,
     pub(crate) editor_drag_anchor: Option<(usize, usize)>,
     pub(crate) gutter_drag_anchor: Option<usize>,
     pub(crate) search_results: SearchResultsState,
+    pub(crate) project_search: Option<ProjectSearchJob>,
+    pub(crate) rename_job: Option<RenameJob>,
+    /// Progress of the running search or batch rename, if any.
+    pub(crate) progress: Option<OperationProgress>,
     pub(crate) search_results_rect: Rect,
     pub(crate) file_picker_open: bool,
     pub(crate) file_picker_query: String,
     pub(crate) file_picker_results: Vec<PathBuf>,
     pub(crate) file_picker_index: usize,
     pub(crate) file_picker_rect: Rect,
+    /// Folder Quick Open is limited to; `None` searches the whole project.
+    pub(crate) file_picker_scope: Option<PathBuf>,
     pub(crate) lsp: Option<LspClient>,
+    /// Language server executable to spawn.
+    pub(crate) lsp_bin: PathBuf,
+    /// The configured `lsp_server`; empty runs the project's default server.
+    pub(crate) lsp_command: String,
+    /// The configured `formatters`, by language key.
+    pub(crate) formatters: BTreeMap<String, String>,
+    /// How long a formatter may run before it is killed.
+    pub(crate) formatter_timeout_ms: u64,
+    /// The configured `file_associations`, by extension or file name.
+    pub(crate) file_associations: BTreeMap<String, String>,
+    /// Server picked for the project kind found at the root.
+    pub(crate) lsp_server: LspServerSpec,
+    /// Project kinds whose marker files are at the root.
+    pub(crate) project_kinds: Vec<ProjectKind>,
+    /// Why the language server could not be started. Set after a failed
+    /// spawn so opening more files doesn't retry it; cleared by `restart_lsp`.
+    pub(crate) lsp_unavailable: Option<String>,
+    /// `config.toml` read at startup and by Reload Config.
+    pub(crate) config_path: Option<PathBuf>,
+    /// `state.json` restored at startup and written by `persist_state`;
+    /// `None` keeps the state in memory only.
+    pub(crate) state_path: Option<PathBuf>,
+    /// Settings the root's `.lazyide.toml` overrode, with their previous
+    /// values, put back when the root changes.
+    pub(crate) project_config_restore: toml::Table,
+    /// Diagnostics the server published for files without an open tab.
+    pub(crate) unopened_diagnostics: HashMap<PathBuf, Vec<LspDiagnostic>>,
     pub(crate) completion: CompletionState,
-    pub(crate) pending_completion_request: Option<i64>,
-    pub(crate) pending_definition_request: Option<i64>,
+    pub(crate) pending_completion_request: Option<PendingLspRequest>,
+    pub(crate) pending_definition_request: Option<PendingLspRequest>,
     pub(crate) fs_watcher: Option<RecommendedWatcher>,
     pub(crate) fs_rx: Option<Receiver<FsChangeEvent>>,
     pub(crate) fs_refresh_pending: bool,
     pub(crate) fs_full_refresh_pending: bool,
     pub(crate) fs_changed_paths: HashSet<PathBuf>,
     pub(crate) last_fs_refresh: Instant,
-    pub(crate) autosave_last_write: Instant,
+    /// When dirty buffers were last copied to the recovery store.
+    pub(crate) last_recovery_snapshot: Instant,
+    /// Where dirty buffers are copied for crash recovery, next to the state
+    /// file; `None` keeps no recovery copies.
+    pub(crate) autosave_dir: Option<PathBuf>,
+    pub(crate) last_external_check: Instant,
+    /// Scroll offset and page height of the diff in the conflict/recovery
+    /// prompts.
+    pub(crate) prompt_diff_scroll: usize,
+    pub(crate) prompt_diff_page: usize,
+    /// Popup with the active tab's unsaved changes against disk.
+    pub(crate) unsaved_diff_open: bool,
+    /// What the unsaved-changes popup shows, diffed when it opened.
+    pub(crate) unsaved_diff_lines: Vec<DiffLine>,
     pub(crate) replace_after_find: bool,
+    /// Cursor position captured when the find prompt opened, restored on Esc.
+    pub(crate) find_origin: Option<(usize, usize)>,
     pub(crate) git_branch: Option<String>,
     pub(crate) enhanced_keys: bool,
     pub(crate) word_wrap: bool,
+    /// Rows kept visible above and below the cursor when scrolling.
+    pub(crate) scroll_off: usize,
+    /// Lines moved per mouse wheel notch.
+    pub(crate) scroll_lines: usize,
+    /// Multiply the wheel step while notches arrive in quick succession.
+    pub(crate) scroll_acceleration: bool,
+    /// Time and direction of the last wheel notch, and how many rapid
+    /// notches in that direction came before it.
+    pub(crate) wheel_streak: Option<(Instant, bool, usize)>,
+    /// Draw line numbers in the gutter.
+    pub(crate) line_numbers: bool,
+    /// Draw the fold arrow column in the gutter.
+    pub(crate) fold_arrows: bool,
+    /// Blocks nested at least this many levels inside others start folded
+    /// when a file opens; 0 leaves every fold open.
+    pub(crate) default_fold_level: usize,
+    /// Flag trailing spaces and tabs with the theme's error color.
+    pub(crate) highlight_trailing_whitespace: bool,
+    /// Color brackets by nesting depth; off draws them in the base color.
+    pub(crate) bracket_colorization: bool,
+    /// Tint the indentation background by indent level.
+    pub(crate) rainbow_indent: bool,
+    /// Fade runs of comment-only lines below the comment color.
+    pub(crate) dim_commented_code: bool,
+    /// Underline unknown words in comments and Markdown.
+    pub(crate) spell_check: bool,
+    pub(crate) spell_checker: SpellChecker,
+    pub(crate) lint_mixed_indent: bool,
+    /// Next/previous diagnostic skip warnings, info and hints.
+    pub(crate) diagnostic_errors_only: bool,
+    /// How long newly published diagnostics wait before they are shown.
+    pub(crate) diagnostics_delay_ms: u64,
+    /// Most diagnostics drawn on one line, 0 for no limit; the rest are
+    /// counted in the status bar.
+    pub(crate) diagnostics_max_per_line: usize,
+    /// Column that Reflow Paragraph wraps Markdown and plain text to.
+    pub(crate) reflow_width: usize,
+    /// What a bare Esc in the editor does.
+    pub(crate) editor_esc: EscAction,
+    /// Key that confirms the save-and-quit, save-and-close and delete prompts.
+    pub(crate) confirm_key: ConfirmKey,
+    /// Tab count above which the least recently used clean, unpinned tab is
+    /// closed; 0 means no limit.
+    pub(crate) max_open_tabs: usize,
+    pub(crate) tree_sort: TreeSort,
+    pub(crate) tree_sort_descending: bool,
+    pub(crate) tree_dirs_first: bool,
+    /// Right-aligned size / modified-time column in the file tree.
+    pub(crate) tree_show_details: bool,
+    pub(crate) follow_symlinks: bool,
+    /// Save dirty tabs automatically once they have been idle for
+    /// `auto_save_delay_ms`.
+    pub(crate) auto_save: bool,
+    pub(crate) auto_save_delay_ms: u64,
+    /// Indentation for new tabs when no `.editorconfig` applies.
+    pub(crate) default_indent: IndentSettings,
     pub(crate) wrap_width_cache: usize,
     pub(crate) wrap_rebuild_deadline: Option<Instant>,
     pub(crate) keybinds: KeyBindings,
@@ -154,6 +434,11 @@ pub(crate) struct App {
     pub(crate) git_refresh_in_flight: bool,
     pub(crate) git_thread_handle: Option<JoinHandle<()>>,
     pub(crate) cached_file_list: Vec<PathBuf>,
+    /// Set when the watcher saw files created, removed or renamed since
+    /// `cached_file_list` was built; the next picker refresh re-walks the
+    /// project in the background.
+    pub(crate) file_index_stale: bool,
+    pub(crate) file_index_rx: Option<Receiver<Vec<PathBuf>>>,
 }
 
 impl Drop for App {
@@ -1,42 +1,92 @@
-use super::{App, CompletionState, ContextMenuState, KeybindEditorState, SearchResultsState};
+use super::{
+    App, CompletionState, ContextMenuState, DiagnosticsPanelState, DiffTabPickerState,
+    FolderPickerState, KeybindEditorState, LanguagePickerState, RecentProjectPickerState,
+    SearchResultsState, SymbolPickerState, ZenRestore,
+};
+use ratatui::style::Color;
 use ratatui::widgets::ListState;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::mpsc;
 use std::time::{Duration, Instant};
 
 use arboard::Clipboard;
-use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use ratatui::layout::Rect;
 
+use crate::config::{Config, config_file_path, load_config, project_config_path, restore_config};
+use crate::diff::unified_diff;
 use crate::keybinds::{KeyAction, load_keybindings};
-use crate::lsp_client::resolve_rust_analyzer_bin;
+use crate::lsp_client::{LspDiagnostic, LspServerSpec, RUST_ANALYZER, resolve_rust_analyzer_bin};
 use crate::persistence::{
-    PersistedState, autosave_path_for, load_persisted_state, save_persisted_state,
+    PersistedState, ProjectLayout, autosave_dir_for, autosave_path_for, autosave_source_path,
+    load_persisted_state, remove_autosave, save_persisted_state, state_file_path, write_autosave,
 };
-use crate::syntax::syntax_lang_for_path;
-use crate::tab::{FoldRange, Tab};
+use crate::spellcheck::SpellChecker;
+use crate::syntax::syntax_lang_with_associations;
+use crate::tab::{FoldRange, IndentSettings, NavLocation, Tab};
 use crate::theme::{Theme, load_themes};
-use crate::types::{CommandAction, Focus, PendingAction, PromptMode, PromptState};
+use crate::types::{
+    CommandAction, ConfirmKey, EscAction, Focus, GutterLayout, PaneSide, PendingAction, PromptMode,
+    PromptState, TreeSort,
+};
 use crate::util::{
     command_action_label, compute_fold_ranges, compute_git_change_summary,
-    compute_git_file_statuses, detect_git_branch, relative_path, spawn_git_refresh,
-    text_to_lines, wrap_segments_for_line,
+    compute_git_file_statuses, confirm_key_label, detect_git_branch, detect_project_kinds,
+    fuzzy_score, push_prompt_history, relative_path, shift_folded_starts, spawn_git_refresh,
+    text_to_lines, to_u16_saturating, visible_diagnostics, wrap_segments_for_line,
 };
 
 impl App {
     pub(crate) const INLINE_GHOST_MIN_PREFIX: usize = 3;
-    pub(crate) const EDITOR_GUTTER_WIDTH: u16 = 11;
     pub(crate) const MIN_FILES_PANE_WIDTH: u16 = 18;
     pub(crate) const MIN_EDITOR_PANE_WIDTH: u16 = 28;
+    pub(crate) const FILES_PANE_RESIZE_STEP: u16 = 4;
+    pub(crate) const DEFAULT_ZEN_WIDTH: u16 = 100;
+    pub(crate) const MIN_ZEN_WIDTH: u16 = 20;
+    pub(crate) const MAX_LINE_SPACING: usize = 3;
     pub(crate) const FS_REFRESH_DEBOUNCE_MS: u64 = 120;
-    pub(crate) const AUTOSAVE_INTERVAL_MS: u64 = 2000;
-    pub(crate) const SCROLL_LINES: usize = 3;
+    pub(crate) const RECOVERY_SNAPSHOT_INTERVAL_MS: u64 = 2000;
+    pub(crate) const DEFAULT_AUTO_SAVE_DELAY_MS: u64 = 1500;
+    pub(crate) const DEFAULT_DIAGNOSTICS_DELAY_MS: u64 = 500;
+    pub(crate) const DEFAULT_DIAGNOSTICS_MAX_PER_LINE: usize = 3;
+    pub(crate) const DEFAULT_REFLOW_WIDTH: usize = 80;
+    pub(crate) const DEFAULT_RECENT_PROJECTS_LIMIT: usize = 10;
+    pub(crate) const PROMPT_HISTORY_LIMIT: usize = 50;
+    pub(crate) const EXTERNAL_CHECK_INTERVAL_MS: u64 = 1000;
+    pub(crate) const FORMATTER_TIMEOUT_MS: u64 = 5000;
+    pub(crate) const DEFAULT_SCROLL_LINES: usize = 3;
+    /// Wheel notches closer together than this count as one fast spin.
+    pub(crate) const SCROLL_ACCEL_WINDOW_MS: u64 = 60;
+    pub(crate) const MAX_SCROLL_ACCEL: usize = 4;
+    pub(crate) const DEFAULT_SCROLL_OFF: usize = 3;
+    pub(crate) const DEFAULT_SEARCH_CONTEXT_LINES: usize = 1;
+    pub(crate) const MAX_CLOSED_TABS: usize = 10;
+    pub(crate) const MAX_NAV_HISTORY: usize = 100;
+    pub(crate) const READING_WORDS_PER_MINUTE: usize = 200;
+    pub(crate) const TREE_EXPAND_MAX_DEPTH: usize = 32;
+    pub(crate) const TREE_EXPAND_MAX_DIRS: usize = 2000;
+    /// Entries counted under folders for the delete confirmation.
+    pub(crate) const DELETE_COUNT_LIMIT: usize = 10_000;
+    /// Renames made per main-loop tick of a batch rename.
+    pub(crate) const BATCH_RENAME_STEP: usize = 50;
 
     pub(crate) fn new(root: PathBuf) -> io::Result<Self> {
+        Self::with_paths(root, config_file_path(), state_file_path())
+    }
+
+    /// `new` with the global config and state files given explicitly rather
+    /// than found in the user's config directory. `None` skips that file, so
+    /// tests pass `None` for both. Autosaves live beside the state file.
+    pub(crate) fn with_paths(
+        root: PathBuf,
+        config_path: Option<PathBuf>,
+        state_path: Option<PathBuf>,
+    ) -> io::Result<Self> {
         let themes = load_themes();
         let default_theme_index = themes
             .iter()
@@ -50,24 +100,79 @@ impl App {
             selected: 0,
             tree_state: ListState::default(),
             expanded,
+            tree_selection: HashSet::new(),
+            tree_selection_anchor: None,
+            tree_clipboard: None,
             focus: Focus::Tree,
             tabs: Vec::new(),
             active_tab: 0,
+            tab_mru: Vec::new(),
             last_tree_click: None,
             status: String::new(),
             pending: PendingAction::None,
             quit: false,
             files_view_open: true,
             files_pane_width: 32,
+            files_pane_side: PaneSide::Left,
+            zen_restore: None,
+            zen_width: Self::DEFAULT_ZEN_WIDTH,
+            line_spacing: 0,
             divider_dragging: false,
             menu_open: false,
             menu_index: 0,
             menu_query: String::new(),
             menu_results: Vec::new(),
             menu_rect: Rect::default(),
+            language_picker: LanguagePickerState {
+                open: false,
+                index: 0,
+                rect: Rect::default(),
+            },
+            symbol_picker: SymbolPickerState {
+                open: false,
+                query: String::new(),
+                symbols: Vec::new(),
+                results: Vec::new(),
+                index: 0,
+                rect: Rect::default(),
+            },
+            diff_tab_picker: DiffTabPickerState {
+                open: false,
+                tabs: Vec::new(),
+                index: 0,
+                rect: Rect::default(),
+            },
+            tab_diff: None,
+            folder_picker: FolderPickerState {
+                open: false,
+                dir: PathBuf::new(),
+                entries: Vec::new(),
+                index: 0,
+                offset: 0,
+                rect: Rect::default(),
+            },
+            recent_project_picker: RecentProjectPickerState {
+                open: false,
+                projects: Vec::new(),
+                index: 0,
+                rect: Rect::default(),
+            },
+            recent_projects: Vec::new(),
+            recent_projects_limit: Self::DEFAULT_RECENT_PROJECTS_LIMIT,
+            prompt_history: BTreeMap::new(),
+            file_attributes: BTreeMap::new(),
+            diagnostics_panel: DiagnosticsPanelState {
+                open: false,
+                index: 0,
+                scroll: 0,
+                rect: Rect::default(),
+            },
             theme_browser_open: false,
             theme_browser_rect: Rect::default(),
+            theme_preview_rect: Rect::default(),
             theme_index: default_theme_index,
+            theme_query: String::new(),
+            theme_results: Vec::new(),
             preview_revert_index: default_theme_index,
             themes,
             active_theme_index: default_theme_index,
@@ -78,6 +183,15 @@ impl App {
             editor_rect: Rect::default(),
             divider_rect: Rect::default(),
             tab_rects: Vec::new(),
+            tab_drag: None,
+            tree_drag: None,
+            tab_scroll_offset: 0,
+            tab_bar_width: 0,
+            closed_tabs: Vec::new(),
+            nav_back: Vec::new(),
+            nav_forward: Vec::new(),
+            last_edit_location: None,
+            untitled_count: 0,
             context_menu: ContextMenuState {
                 open: false,
                 index: 0,
@@ -91,6 +205,7 @@ impl App {
             editor_context_menu_open: false,
             editor_context_menu_index: 0,
             editor_context_menu_pos: (0, 0),
+            editor_context_menu_target: None,
             editor_context_menu_rect: Rect::default(),
             editor_dragging: false,
             editor_drag_anchor: None,
@@ -100,14 +215,31 @@ impl App {
                 query: String::new(),
                 results: Vec::new(),
                 index: 0,
+                scroll: 0,
+                context_lines: Self::DEFAULT_SEARCH_CONTEXT_LINES,
             },
+            project_search: None,
+            rename_job: None,
+            progress: None,
             search_results_rect: Rect::default(),
             file_picker_open: false,
             file_picker_query: String::new(),
             file_picker_results: Vec::new(),
             file_picker_index: 0,
             file_picker_rect: Rect::default(),
+            file_picker_scope: None,
             lsp: None,
+            lsp_bin: RUST_ANALYZER.resolve_bin(),
+            lsp_command: String::new(),
+            formatters: BTreeMap::new(),
+            formatter_timeout_ms: Self::FORMATTER_TIMEOUT_MS,
+            file_associations: BTreeMap::new(),
+            lsp_server: RUST_ANALYZER,
+            project_kinds: Vec::new(),
+            lsp_unavailable: None,
+            config_path,
+            project_config_restore: toml::Table::new(),
+            unopened_diagnostics: HashMap::new(),
             completion: CompletionState {
                 open: false,
                 items: Vec::new(),
@@ -124,11 +256,48 @@ impl App {
             fs_full_refresh_pending: false,
             fs_changed_paths: HashSet::new(),
             last_fs_refresh: Instant::now(),
-            autosave_last_write: Instant::now(),
+            last_recovery_snapshot: Instant::now(),
+            autosave_dir: state_path.as_deref().and_then(autosave_dir_for),
+            state_path,
+            last_external_check: Instant::now(),
+            prompt_diff_scroll: 0,
+            prompt_diff_page: 0,
+            unsaved_diff_open: false,
+            unsaved_diff_lines: Vec::new(),
             replace_after_find: false,
+            find_origin: None,
             git_branch: None,
             enhanced_keys: false,
             word_wrap: false,
+            scroll_off: Self::DEFAULT_SCROLL_OFF,
+            scroll_lines: Self::DEFAULT_SCROLL_LINES,
+            scroll_acceleration: false,
+            wheel_streak: None,
+            line_numbers: true,
+            fold_arrows: true,
+            default_fold_level: 0,
+            highlight_trailing_whitespace: false,
+            bracket_colorization: true,
+            rainbow_indent: false,
+            dim_commented_code: false,
+            spell_check: false,
+            spell_checker: SpellChecker::load(),
+            lint_mixed_indent: false,
+            diagnostic_errors_only: false,
+            diagnostics_delay_ms: Self::DEFAULT_DIAGNOSTICS_DELAY_MS,
+            diagnostics_max_per_line: Self::DEFAULT_DIAGNOSTICS_MAX_PER_LINE,
+            reflow_width: Self::DEFAULT_REFLOW_WIDTH,
+            editor_esc: EscAction::Close,
+            confirm_key: ConfirmKey::Enter,
+            max_open_tabs: 0,
+            tree_sort: TreeSort::Name,
+            tree_sort_descending: false,
+            tree_dirs_first: true,
+            tree_show_details: false,
+            follow_symlinks: true,
+            auto_save: false,
+            auto_save_delay_ms: Self::DEFAULT_AUTO_SAVE_DELAY_MS,
+            default_indent: IndentSettings::default(),
             wrap_width_cache: usize::MAX,
             wrap_rebuild_deadline: None,
             keybinds: load_keybindings(),
@@ -146,14 +315,19 @@ impl App {
             git_refresh_in_flight: false,
             git_thread_handle: None,
             cached_file_list: Vec::new(),
+            file_index_stale: true,
+            file_index_rx: None,
         };
         app.git_branch = detect_git_branch(&app.root);
         app.git_file_statuses = compute_git_file_statuses(&app.root);
         app.git_change_summary = compute_git_change_summary(&app.root);
         app.restore_persisted_state();
+        app.detect_project();
+        let config_problem = app.load_config_files();
         app.rebuild_tree()?;
         app.start_fs_watcher();
-        let has_ra = resolve_rust_analyzer_bin().is_some();
+        app.record_recent_project();
+        let has_ra = app.lsp_server != RUST_ANALYZER || resolve_rust_analyzer_bin().is_some();
         let has_rg = Command::new("rg").arg("--version").output().is_ok();
         if !has_ra || !has_rg {
             let mut missing = Vec::new();
@@ -168,7 +342,10 @@ impl App {
                 missing.join(", ")
             );
         } else {
-            app.status = format!("Root: {}", app.root.display());
+            app.status = app.root_status();
+        }
+        if let Some(problem) = config_problem {
+            app.status = problem;
         }
         Ok(app)
     }
@@ -179,13 +356,21 @@ impl App {
             move |res: Result<notify::Event, notify::Error>| {
                 if let Ok(event) = res {
                     let full_refresh = matches!(event.kind, EventKind::Any | EventKind::Other);
+                    let files_changed = full_refresh
+                        || matches!(
+                            event.kind,
+                            EventKind::Create(_)
+                                | EventKind::Remove(_)
+                                | EventKind::Modify(ModifyKind::Name(_))
+                        );
                     let _ = tx.send(super::FsChangeEvent {
                         paths: event.paths,
                         full_refresh,
+                        files_changed,
                     });
                 }
             },
-            Config::default().with_poll_interval(Duration::from_millis(250)),
+            notify::Config::default().with_poll_interval(Duration::from_millis(250)),
         ) {
             Ok(w) => w,
             Err(err) => {
@@ -221,6 +406,8 @@ impl App {
                     };
                     if abs.starts_with(self.root.join(".git")) {
                         self.fs_full_refresh_pending = true;
+                    } else if change.files_changed {
+                        self.file_index_stale = true;
                     }
                     self.fs_changed_paths.insert(abs);
                 }
@@ -236,7 +423,8 @@ impl App {
             if self.file_picker_open {
                 self.refresh_file_picker_results();
             }
-            if let Some(path) = self.open_path().cloned() {
+            let untitled = self.active_tab().is_some_and(|t| t.untitled);
+            if let Some(path) = self.open_path().cloned().filter(|_| !untitled) {
                 if !path.exists() {
                     if self.is_dirty() {
                         self.set_status(
@@ -329,6 +517,42 @@ impl App {
         Ok(())
     }
 
+    /// Periodically look for tabs whose file changed on disk behind our back.
+    pub(crate) fn poll_external_changes(&mut self) -> io::Result<()> {
+        if self.last_external_check.elapsed()
+            < Duration::from_millis(Self::EXTERNAL_CHECK_INTERVAL_MS)
+        {
+            return Ok(());
+        }
+        self.last_external_check = Instant::now();
+        for idx in 0..self.tabs.len() {
+            self.check_external_change_at(idx)?;
+        }
+        Ok(())
+    }
+
+    /// If a tab's file has a new mtime, reload it when the tab is clean or
+    /// raise the conflict prompt when it has unsaved edits.
+    pub(crate) fn check_external_change_at(&mut self, idx: usize) -> io::Result<()> {
+        let Some(tab) = self.tabs.get(idx).filter(|t| !t.untitled) else {
+            return Ok(());
+        };
+        let mtime = fs::metadata(&tab.path).and_then(|m| m.modified()).ok();
+        if mtime.is_none() || mtime == tab.disk_mtime || tab.conflict_prompt_open {
+            return Ok(());
+        }
+        self.tabs[idx].disk_mtime = mtime;
+        let prev = self.active_tab;
+        self.active_tab = idx;
+        let result = if self.is_dirty() {
+            self.maybe_flag_external_conflict()
+        } else {
+            self.reload_open_file_from_disk_if_pristine()
+        };
+        self.active_tab = prev;
+        result
+    }
+
     pub(crate) fn active_theme(&self) -> &Theme {
         &self.themes[self.active_theme_index]
     }
@@ -353,30 +577,426 @@ impl App {
         self.tabs.iter().any(|t| t.dirty)
     }
 
+    pub(crate) fn dirty_tab_paths(&self) -> Vec<PathBuf> {
+        self.tabs
+            .iter()
+            .filter(|t| t.dirty)
+            .map(|t| t.path.clone())
+            .collect()
+    }
+
     pub(crate) fn mark_dirty(&mut self) {
-        if let Some(tab) = self.active_tab_mut() {
+        if let Some(tab) = self.active_tab_mut()
+            && !tab.read_only
+        {
             tab.dirty = true;
             tab.is_preview = false;
+            tab.last_edit = Some(Instant::now());
+            tab.auto_save_failed = false;
+            let cursor = tab.editor.cursor();
+            tab.last_edit_pos = Some(cursor);
+            let path = tab.path.clone();
+            self.last_edit_location = Some(NavLocation { path, cursor });
+        }
+    }
+
+    pub(crate) fn promote_preview_tab(&mut self) {
+        let Some(tab) = self.active_tab_mut() else {
+            self.set_status("No file open");
+            return;
+        };
+        if tab.is_preview {
+            tab.is_preview = false;
+            self.set_status("Tab kept open");
+        } else {
+            self.set_status("Tab is already kept open");
+        }
+    }
+
+    /// Pin the active tab so `max_open_tabs` never closes it, or unpin it.
+    pub(crate) fn toggle_pin_tab(&mut self) {
+        let Some(tab) = self.active_tab_mut() else {
+            self.set_status("No file open");
+            return;
+        };
+        tab.pinned = !tab.pinned;
+        if tab.pinned {
+            self.set_status("Tab pinned");
+        } else {
+            self.set_status("Tab unpinned");
         }
+        self.ensure_active_tab_visible();
     }
 
+    /// Focus tab `idx`. Its cursor and scroll offsets live on the tab, so
+    /// the view comes back as it was left; the horizontal offset only moves
+    /// if the editor got too narrow to show the cursor in the meantime.
     pub(crate) fn switch_to_tab(&mut self, idx: usize) {
         if idx < self.tabs.len() {
             self.active_tab = idx;
+            self.touch_tab_mru(idx);
             self.completion.reset();
             self.focus = Focus::Editor;
+            self.ensure_active_tab_visible();
+            self.sync_editor_scroll_col();
+        }
+    }
+
+    /// Move `idx` to the front of the most-recently-used tab order.
+    pub(crate) fn touch_tab_mru(&mut self, idx: usize) {
+        self.tab_mru.retain(|&i| i != idx);
+        self.tab_mru.insert(0, idx);
+    }
+
+    /// Jump to the tab that was focused before the active one.
+    pub(crate) fn last_used_tab(&mut self) {
+        let Some(idx) = self
+            .tab_mru
+            .iter()
+            .copied()
+            .find(|&i| i != self.active_tab && i < self.tabs.len())
+        else {
+            self.set_status("No previously used tab");
+            return;
+        };
+        // Keep the tab we are leaving next in line so repeated presses toggle.
+        self.touch_tab_mru(self.active_tab);
+        self.switch_to_tab(idx);
+    }
+
+    /// Current values of the settings `config.toml` can set.
+    pub(crate) fn config_snapshot(&self) -> Config {
+        // A theme only previewed in the browser is not a setting yet.
+        let theme = if self.theme_browser_open {
+            &self.themes[self.preview_revert_index]
+        } else {
+            self.active_theme()
+        };
+        Config {
+            theme: theme.name.clone(),
+            word_wrap: self.word_wrap,
+            scroll_off: self.scroll_off,
+            scroll_lines: self.scroll_lines,
+            scroll_acceleration: self.scroll_acceleration,
+            line_numbers: self.line_numbers,
+            fold_arrows: self.fold_arrows,
+            default_fold_level: self.default_fold_level,
+            indent_width: self.default_indent.width,
+            indent_hard_tabs: self.default_indent.hard_tabs,
+            render_tab_width: self.default_indent.render_tab_width,
+            highlight_trailing_whitespace: self.highlight_trailing_whitespace,
+            bracket_colorization: self.bracket_colorization,
+            rainbow_indent: self.rainbow_indent,
+            dim_commented_code: self.dim_commented_code,
+            spell_check: self.spell_check,
+            lint_mixed_indent: self.lint_mixed_indent,
+            auto_save: self.auto_save,
+            auto_save_delay_ms: self.auto_save_delay_ms,
+            follow_symlinks: self.follow_symlinks,
+            search_context_lines: self.search_results.context_lines,
+            diagnostics_delay_ms: self.diagnostics_delay_ms,
+            diagnostics_max_per_line: self.diagnostics_max_per_line,
+            reflow_width: self.reflow_width,
+            editor_esc: self.editor_esc,
+            confirm_key: self.confirm_key,
+            recent_projects_limit: self.recent_projects_limit,
+            max_open_tabs: self.max_open_tabs,
+            lsp_server: self.lsp_command.clone(),
+            formatters: self.formatters.clone(),
+            file_associations: self.file_associations.clone(),
+        }
+    }
+
+    pub(crate) fn apply_config(&mut self, config: &Config) {
+        if let Some(idx) = self
+            .themes
+            .iter()
+            .position(|t| t.name.eq_ignore_ascii_case(&config.theme))
+        {
+            self.active_theme_index = idx;
+            self.theme_index = idx;
+            self.preview_revert_index = idx;
+        }
+        self.scroll_off = config.scroll_off;
+        self.scroll_lines = config.scroll_lines.max(1);
+        self.scroll_acceleration = config.scroll_acceleration;
+        self.line_numbers = config.line_numbers;
+        self.fold_arrows = config.fold_arrows;
+        self.default_fold_level = config.default_fold_level;
+        self.default_indent = IndentSettings {
+            hard_tabs: config.indent_hard_tabs,
+            width: config.indent_width.max(1),
+            render_tab_width: config.render_tab_width,
+        };
+        self.highlight_trailing_whitespace = config.highlight_trailing_whitespace;
+        self.bracket_colorization = config.bracket_colorization;
+        self.rainbow_indent = config.rainbow_indent;
+        self.dim_commented_code = config.dim_commented_code;
+        self.spell_check = config.spell_check;
+        self.lint_mixed_indent = config.lint_mixed_indent;
+        self.auto_save = config.auto_save;
+        self.auto_save_delay_ms = config.auto_save_delay_ms;
+        self.search_results.context_lines = config.search_context_lines;
+        self.diagnostics_delay_ms = config.diagnostics_delay_ms;
+        self.diagnostics_max_per_line = config.diagnostics_max_per_line;
+        self.reflow_width = config.reflow_width.max(1);
+        self.editor_esc = config.editor_esc;
+        self.confirm_key = config.confirm_key;
+        self.recent_projects_limit = config.recent_projects_limit.max(1);
+        self.recent_projects.truncate(self.recent_projects_limit);
+        self.max_open_tabs = config.max_open_tabs;
+        self.evict_tabs_over_limit();
+        if self.word_wrap != config.word_wrap {
+            self.word_wrap = config.word_wrap;
+            if self.word_wrap {
+                for tab in &mut self.tabs {
+                    tab.editor_scroll_col = 0;
+                }
+            }
+            self.wrap_width_cache = self.editor_wrap_width_chars();
+            self.rebuild_all_visible_rows();
+        }
+        self.lsp_command = config.lsp_server.clone();
+        self.resolve_lsp_bin();
+        self.formatters = config.formatters.clone();
+        if self.file_associations != config.file_associations {
+            self.file_associations = config.file_associations.clone();
+            for tab in &mut self.tabs {
+                tab.detected_lang =
+                    syntax_lang_with_associations(&tab.path, &self.file_associations);
+            }
+            self.recompute_folds();
+        }
+        if self.follow_symlinks != config.follow_symlinks {
+            self.follow_symlinks = config.follow_symlinks;
+            self.file_index_stale = true;
+            let _ = self.rebuild_tree();
+        }
+    }
+
+    /// Look for project marker files at the root and pick the language
+    /// server to match.
+    pub(crate) fn detect_project(&mut self) {
+        self.project_kinds = detect_project_kinds(&self.root);
+        self.lsp_server = LspServerSpec::for_project(&self.project_kinds);
+        self.resolve_lsp_bin();
+    }
+
+    fn resolve_lsp_bin(&mut self) {
+        self.lsp_bin = if self.lsp_command.is_empty() {
+            self.lsp_server.resolve_bin()
+        } else {
+            PathBuf::from(&self.lsp_command)
+        };
+    }
+
+    /// The detected project kinds, e.g. "Rust + Node", or `None` when the
+    /// root has no known marker file.
+    pub(crate) fn project_label(&self) -> Option<String> {
+        if self.project_kinds.is_empty() {
+            return None;
+        }
+        let labels: Vec<&str> = self.project_kinds.iter().map(|k| k.label()).collect();
+        Some(labels.join(" + "))
+    }
+
+    /// Status shown when a dirty tab asks whether to save before closing.
+    pub(crate) fn close_prompt_status(&self) -> String {
+        let key = confirm_key_label(&PendingAction::ClosePrompt, self.confirm_key);
+        format!("Unsaved changes: {key} save+close | Esc discard | C cancel")
+    }
+
+    /// Start of the title bar: the project folder's name and, while any
+    /// tabs have unsaved changes, how many, e.g. "lazyide · app ●2".
+    pub(crate) fn title_label(&self) -> String {
+        let name = self.root.file_name().map_or_else(
+            || self.root.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        match self.tabs.iter().filter(|t| t.dirty).count() {
+            0 => format!("lazyide · {name}"),
+            dirty => format!("lazyide · {name} ●{dirty}"),
+        }
+    }
+
+    /// "Root: <path>", with the project kind when one was detected.
+    pub(crate) fn root_status(&self) -> String {
+        match self.project_label() {
+            Some(label) => format!("Root: {} ({label} project)", self.root.display()),
+            None => format!("Root: {}", self.root.display()),
+        }
+    }
+
+    /// Apply the config file at `path` over the current settings. Returns
+    /// the old values of the settings it replaced, and what was wrong with
+    /// the file, if anything; a missing file is not a problem.
+    fn apply_config_file(&mut self, path: &Path) -> (toml::Table, Option<String>) {
+        match load_config(path, &self.config_snapshot()) {
+            Ok(loaded) => {
+                self.apply_config(&loaded.config);
+                let problem = (!loaded.warnings.is_empty()).then(|| {
+                    format!(
+                        "Config warning in {}: {}",
+                        path.display(),
+                        loaded.warnings.join(", ")
+                    )
+                });
+                (loaded.replaced, problem)
+            }
+            Err(err) => (
+                toml::Table::new(),
+                Some(format!("Config error in {}: {}", path.display(), err)),
+            ),
+        }
+    }
+
+    /// Read `config.toml`, then the root's `.lazyide.toml` over it.
+    pub(crate) fn load_config_files(&mut self) -> Option<String> {
+        let global = match self.config_path.clone() {
+            Some(path) => self.apply_config_file(&path).1,
+            None => None,
+        };
+        let project = self.load_project_config();
+        global.or(project)
+    }
+
+    /// Apply the root's `.lazyide.toml` over the current settings.
+    pub(crate) fn load_project_config(&mut self) -> Option<String> {
+        let path = project_config_path(&self.root);
+        let (replaced, problem) = self.apply_config_file(&path);
+        self.project_config_restore = replaced;
+        problem
+    }
+
+    /// Put back the settings the root's `.lazyide.toml` overrode.
+    pub(crate) fn revert_project_config(&mut self) {
+        let replaced = std::mem::take(&mut self.project_config_restore);
+        if !replaced.is_empty() {
+            let config = restore_config(&self.config_snapshot(), replaced);
+            self.apply_config(&config);
+        }
+    }
+
+    pub(crate) fn reload_config(&mut self) {
+        let lsp_bin = self.lsp_bin.clone();
+        self.revert_project_config();
+        let problem = self.load_config_files();
+        if self.lsp_bin != lsp_bin {
+            self.restart_lsp();
+        }
+        match problem {
+            Some(problem) => self.set_status(problem),
+            None => self.set_status("Config reloaded"),
         }
     }
 
     pub(crate) fn restore_persisted_state(&mut self) {
-        let Some(saved) = load_persisted_state() else {
+        let Some(saved) = self.state_path.as_deref().and_then(load_persisted_state) else {
             return;
         };
         if let Some(word_wrap) = saved.word_wrap {
             self.word_wrap = word_wrap;
         }
-        if let Some(width) = saved.files_pane_width {
-            self.files_pane_width = width.max(Self::MIN_FILES_PANE_WIDTH);
+        if let Some(scroll_off) = saved.scroll_off {
+            self.scroll_off = scroll_off;
+        }
+        if let Some(lines) = saved.scroll_lines {
+            self.scroll_lines = lines.max(1);
+        }
+        if let Some(accelerate) = saved.scroll_acceleration {
+            self.scroll_acceleration = accelerate;
+        }
+        if let Some(numbers) = saved.line_numbers {
+            self.line_numbers = numbers;
+        }
+        if let Some(arrows) = saved.fold_arrows {
+            self.fold_arrows = arrows;
+        }
+        if let Some(highlight) = saved.highlight_trailing_whitespace {
+            self.highlight_trailing_whitespace = highlight;
+        }
+        if let Some(brackets) = saved.bracket_colorization {
+            self.bracket_colorization = brackets;
+        }
+        if let Some(rainbow) = saved.rainbow_indent {
+            self.rainbow_indent = rainbow;
+        }
+        if let Some(spell_check) = saved.spell_check {
+            self.spell_check = spell_check;
+        }
+        self.spell_checker
+            .set_user_words(saved.user_dictionary.clone());
+        if let Some(lint) = saved.lint_mixed_indent {
+            self.lint_mixed_indent = lint;
+        }
+        if let Some(errors_only) = saved.diagnostic_errors_only {
+            self.diagnostic_errors_only = errors_only;
+        }
+        if let Some(delay) = saved.diagnostics_delay_ms {
+            self.diagnostics_delay_ms = delay;
+        }
+        if let Some(max) = saved.diagnostics_max_per_line {
+            self.diagnostics_max_per_line = max;
+        }
+        if let Some(width) = saved.reflow_width {
+            self.reflow_width = width.max(1);
+        }
+        if let Some(limit) = saved.recent_projects_limit {
+            self.recent_projects_limit = limit.max(1);
+        }
+        self.recent_projects = saved.recent_projects.iter().map(PathBuf::from).collect();
+        self.prompt_history = saved.prompt_history.clone();
+        self.file_attributes = saved
+            .file_attributes
+            .iter()
+            .map(|(path, attrs)| (PathBuf::from(path), attrs.clone()))
+            .collect();
+        self.prune_file_attributes();
+        if let Some(sort) = saved.tree_sort {
+            self.tree_sort = sort;
+        }
+        if let Some(descending) = saved.tree_sort_descending {
+            self.tree_sort_descending = descending;
+        }
+        if let Some(dirs_first) = saved.tree_dirs_first {
+            self.tree_dirs_first = dirs_first;
+        }
+        if let Some(show) = saved.tree_show_details {
+            self.tree_show_details = show;
+        }
+        if let Some(follow) = saved.follow_symlinks {
+            self.follow_symlinks = follow;
+        }
+        if let Some(auto_save) = saved.auto_save {
+            self.auto_save = auto_save;
+        }
+        if let Some(delay) = saved.auto_save_delay_ms {
+            self.auto_save_delay_ms = delay;
+        }
+        if let Some(width) = saved.indent_width.filter(|w| *w > 0) {
+            self.default_indent.width = width;
+        }
+        if let Some(hard_tabs) = saved.indent_hard_tabs {
+            self.default_indent.hard_tabs = hard_tabs;
+        }
+        if let Some(width) = saved.render_tab_width {
+            self.default_indent.render_tab_width = width;
+        }
+        if let Some(context) = saved.search_context_lines {
+            self.search_results.context_lines = context;
+        }
+        if let Some(layout) = saved.project_layout(&self.root) {
+            self.files_pane_width = layout.files_pane_width.max(Self::MIN_FILES_PANE_WIDTH);
+            self.files_view_open = layout.files_view_open;
+        }
+        if let Some(side) = saved.files_pane_side {
+            self.files_pane_side = side;
+        }
+        if let Some(width) = saved.zen_width {
+            self.zen_width = width.max(Self::MIN_ZEN_WIDTH);
+        }
+        if let Some(spacing) = saved.line_spacing {
+            self.line_spacing = spacing.min(Self::MAX_LINE_SPACING);
         }
         if let Some(idx) = self
             .themes
@@ -390,20 +1010,253 @@ impl App {
     }
 
     pub(crate) fn persist_state(&mut self) {
-        let state = PersistedState {
-            theme_name: self.active_theme().name.clone(),
-            files_pane_width: Some(self.files_pane_width),
-            word_wrap: Some(self.word_wrap),
+        self.prune_file_attributes();
+        let Some(path) = self.state_path.clone() else {
+            return;
         };
-        if save_persisted_state(&state).is_err() {
+        // Keep the layouts saved by other projects.
+        let projects = load_persisted_state(&path)
+            .map(|saved| saved.projects)
+            .unwrap_or_default();
+        // A project's `.lazyide.toml` overrides stay out of the global state.
+        let settings = restore_config(&self.config_snapshot(), self.project_config_restore.clone());
+        let mut state = PersistedState {
+            theme_name: settings.theme,
+            projects,
+            word_wrap: Some(settings.word_wrap),
+            search_context_lines: Some(settings.search_context_lines),
+            follow_symlinks: Some(settings.follow_symlinks),
+            auto_save: Some(settings.auto_save),
+            auto_save_delay_ms: Some(settings.auto_save_delay_ms),
+            indent_width: Some(settings.indent_width),
+            indent_hard_tabs: Some(settings.indent_hard_tabs),
+            render_tab_width: Some(settings.render_tab_width),
+            highlight_trailing_whitespace: Some(settings.highlight_trailing_whitespace),
+            bracket_colorization: Some(settings.bracket_colorization),
+            rainbow_indent: Some(settings.rainbow_indent),
+            spell_check: Some(settings.spell_check),
+            user_dictionary: self.spell_checker.user_words().cloned().collect(),
+            scroll_off: Some(settings.scroll_off),
+            scroll_lines: Some(settings.scroll_lines),
+            scroll_acceleration: Some(settings.scroll_acceleration),
+            line_numbers: Some(settings.line_numbers),
+            fold_arrows: Some(settings.fold_arrows),
+            lint_mixed_indent: Some(settings.lint_mixed_indent),
+            diagnostic_errors_only: Some(self.diagnostic_errors_only),
+            diagnostics_delay_ms: Some(settings.diagnostics_delay_ms),
+            diagnostics_max_per_line: Some(settings.diagnostics_max_per_line),
+            reflow_width: Some(settings.reflow_width),
+            recent_projects_limit: Some(settings.recent_projects_limit),
+            recent_projects: self
+                .recent_projects
+                .iter()
+                .map(|p| p.to_string_lossy().into_owned())
+                .collect(),
+            prompt_history: self.prompt_history.clone(),
+            file_attributes: self
+                .file_attributes
+                .iter()
+                .map(|(path, attrs)| (path.to_string_lossy().into_owned(), attrs.clone()))
+                .collect(),
+            tree_sort: Some(self.tree_sort),
+            tree_sort_descending: Some(self.tree_sort_descending),
+            tree_dirs_first: Some(self.tree_dirs_first),
+            tree_show_details: Some(self.tree_show_details),
+            files_pane_side: Some(self.files_pane_side),
+            zen_width: Some(self.zen_width),
+            line_spacing: Some(self.line_spacing),
+        };
+        state.set_project_layout(
+            &self.root,
+            ProjectLayout {
+                files_pane_width: self.files_pane_width,
+                // Zen mode hides the pane only for the session.
+                files_view_open: self
+                    .zen_restore
+                    .map_or(self.files_view_open, |z| z.files_view_open),
+            },
+        );
+        if save_persisted_state(&path, &state).is_err() {
             self.set_status("Failed to persist app state");
         }
     }
 
+    /// Forget the attributes of files that no longer exist.
+    pub(crate) fn prune_file_attributes(&mut self) {
+        self.file_attributes.retain(|path, _| path.exists());
+    }
+
+    /// Make the previewed theme the one saved and reverted to.
     pub(crate) fn persist_theme_selection(&mut self) {
+        self.preview_revert_index = self.active_theme_index;
         self.persist_state();
     }
 
+    /// Close the theme browser and put back the theme it opened on.
+    pub(crate) fn cancel_theme_browser(&mut self) {
+        self.active_theme_index = self.preview_revert_index;
+        self.theme_index = self.preview_revert_index;
+        self.theme_browser_open = false;
+        self.menu_open = false;
+        self.set_status(format!("Theme reverted: {}", self.active_theme().name));
+    }
+
+    pub(crate) fn refresh_theme_results(&mut self) {
+        let query = self.theme_query.to_ascii_lowercase();
+        let mut scored: Vec<(usize, usize)> = self
+            .themes
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, t)| fuzzy_score(&query, &t.name).map(|score| (score, idx)))
+            .collect();
+        // Ties keep the browser's order, so an empty query lists every theme.
+        scored.sort();
+        self.theme_results = scored.into_iter().map(|(_, idx)| idx).collect();
+        // Preview the best match once the highlighted theme is filtered out.
+        if !self.theme_results.contains(&self.theme_index)
+            && let Some(&first) = self.theme_results.first()
+        {
+            self.preview_theme(first);
+        }
+    }
+
+    /// Highlight `themes[idx]` in the theme browser and show it live.
+    pub(crate) fn preview_theme(&mut self, idx: usize) {
+        self.theme_index = idx;
+        self.active_theme_index = idx;
+        self.set_status(format!("Preview: {}", self.active_theme().name));
+    }
+
+    /// Move the theme browser highlight one row through the filtered list.
+    pub(crate) fn step_theme_browser(&mut self, down: bool) {
+        let next = match self
+            .theme_results
+            .iter()
+            .position(|&idx| idx == self.theme_index)
+        {
+            Some(0) if !down => return,
+            Some(pos) if down => pos + 1,
+            Some(pos) => pos - 1,
+            None => 0,
+        };
+        if let Some(&idx) = self.theme_results.get(next) {
+            self.preview_theme(idx);
+        }
+    }
+
+    pub(crate) fn toggle_trailing_whitespace(&mut self) {
+        self.highlight_trailing_whitespace = !self.highlight_trailing_whitespace;
+        self.persist_state();
+        if self.highlight_trailing_whitespace {
+            self.set_status("Trailing whitespace highlighting enabled");
+        } else {
+            self.set_status("Trailing whitespace highlighting disabled");
+        }
+    }
+
+    pub(crate) fn toggle_line_numbers(&mut self) {
+        self.line_numbers = !self.line_numbers;
+        self.gutter_changed();
+        if self.line_numbers {
+            self.set_status("Line numbers shown");
+        } else {
+            self.set_status("Line numbers hidden");
+        }
+    }
+
+    pub(crate) fn toggle_fold_arrows(&mut self) {
+        self.fold_arrows = !self.fold_arrows;
+        self.gutter_changed();
+        if self.fold_arrows {
+            self.set_status("Fold arrows shown");
+        } else {
+            self.set_status("Fold arrows hidden");
+        }
+    }
+
+    /// Re-wrap and re-scroll for a new gutter width.
+    fn gutter_changed(&mut self) {
+        self.wrap_width_cache = self.editor_wrap_width_chars();
+        self.rebuild_all_visible_rows();
+        self.sync_editor_scroll_col();
+        self.persist_state();
+    }
+
+    pub(crate) fn toggle_bracket_colorization(&mut self) {
+        self.bracket_colorization = !self.bracket_colorization;
+        self.persist_state();
+        if self.bracket_colorization {
+            self.set_status("Bracket pair colorization enabled");
+        } else {
+            self.set_status("Bracket pair colorization disabled");
+        }
+    }
+
+    /// Colors `highlight_line` cycles brackets through; empty when
+    /// colorization is off.
+    pub(crate) fn bracket_colors(&self) -> &[Color] {
+        if self.bracket_colorization {
+            &self.active_theme().brackets
+        } else {
+            &[]
+        }
+    }
+
+    pub(crate) fn toggle_rainbow_indent(&mut self) {
+        self.rainbow_indent = !self.rainbow_indent;
+        self.persist_state();
+        if self.rainbow_indent {
+            self.set_status("Rainbow indentation enabled");
+        } else {
+            self.set_status("Rainbow indentation disabled");
+        }
+    }
+
+    pub(crate) fn toggle_spell_check(&mut self) {
+        self.spell_check = !self.spell_check;
+        self.persist_state();
+        if !self.spell_check {
+            self.set_status("Spell check disabled");
+        } else if self.spell_checker.has_dictionary() {
+            self.set_status("Spell check enabled");
+        } else if cfg!(feature = "spellcheck") {
+            self.set_status("Spell check enabled, but no en_US Hunspell dictionary was found");
+        } else {
+            self.set_status("Spell check needs a build with the `spellcheck` feature");
+        }
+    }
+
+    /// Backgrounds `apply_indent_tints` cycles indent levels through; empty
+    /// when rainbow indentation is off.
+    pub(crate) fn indent_tints(&self) -> &[Color] {
+        if self.rainbow_indent {
+            &self.active_theme().indent_tints
+        } else {
+            &[]
+        }
+    }
+
+    pub(crate) fn toggle_mixed_indent_lint(&mut self) {
+        self.lint_mixed_indent = !self.lint_mixed_indent;
+        self.persist_state();
+        if self.lint_mixed_indent {
+            self.set_status("Mixed indentation warnings enabled");
+            self.warn_mixed_indent();
+        } else {
+            self.set_status("Mixed indentation warnings disabled");
+        }
+    }
+
+    pub(crate) fn toggle_diagnostic_errors_only(&mut self) {
+        self.diagnostic_errors_only = !self.diagnostic_errors_only;
+        self.persist_state();
+        if self.diagnostic_errors_only {
+            self.set_status("Diagnostic navigation: errors only");
+        } else {
+            self.set_status("Diagnostic navigation: all severities");
+        }
+    }
+
     pub(crate) fn toggle_word_wrap(&mut self) {
         self.word_wrap = !self.word_wrap;
         if self.word_wrap {
@@ -423,18 +1276,37 @@ impl App {
         }
     }
 
+    pub(crate) fn toggle_auto_save(&mut self) {
+        self.auto_save = !self.auto_save;
+        self.persist_state();
+        if self.auto_save {
+            self.set_status(format!(
+                "Auto-save enabled ({} ms idle)",
+                self.auto_save_delay_ms
+            ));
+        } else {
+            self.set_status("Auto-save disabled");
+        }
+    }
+
     pub(crate) fn on_editor_content_changed(&mut self) {
+        if let Some(tab) = self.active_tab_mut() {
+            tab.drop_stale_typing_run();
+            tab.recount_chars();
+        }
         self.mark_dirty();
         self.notify_lsp_did_change();
         self.recompute_folds();
     }
 
     pub(crate) fn open_find_prompt(&mut self) {
+        self.find_origin = self.active_tab().map(|t| t.editor.cursor());
         self.prompt = Some(PromptState {
             title: "Find in file (regex)".to_string(),
             value: String::new(),
             cursor: 0,
             mode: PromptMode::FindInFile,
+            history: None,
         });
     }
 
@@ -444,6 +1316,7 @@ impl App {
             value: String::new(),
             cursor: 0,
             mode: PromptMode::FindInProject,
+            history: None,
         });
     }
 
@@ -453,18 +1326,76 @@ impl App {
             value: String::new(),
             cursor: 0,
             mode: PromptMode::GoToLine,
+            history: None,
+        });
+    }
+
+    pub(crate) fn open_zen_width_prompt(&mut self) {
+        let value = self.zen_width.to_string();
+        self.prompt = Some(PromptState {
+            title: "Zen mode text width".to_string(),
+            cursor: value.len(),
+            value,
+            mode: PromptMode::ZenWidth,
+            history: None,
         });
     }
 
     pub(crate) fn open_replace_prompt(&mut self) {
+        if self.refuse_read_only_edit() {
+            return;
+        }
         self.open_find_prompt();
         self.replace_after_find = true;
     }
 
+    /// Remember `value` as the newest input of a prompt in `mode`.
+    pub(crate) fn record_prompt_history(&mut self, mode: &PromptMode, value: &str) {
+        let Some(key) = mode.history_key() else {
+            return;
+        };
+        let history = self.prompt_history.entry(key.to_string()).or_default();
+        if history.first().is_some_and(|newest| newest == value) {
+            return;
+        }
+        push_prompt_history(history, value, Self::PROMPT_HISTORY_LIMIT);
+        self.persist_state();
+    }
+
+    /// Show the next older (or newer) history entry in the open prompt.
+    /// Stepping past the newest puts back what was typed before.
+    pub(crate) fn recall_prompt_history(&mut self, older: bool) {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+        let Some(history) = prompt
+            .mode
+            .history_key()
+            .and_then(|key| self.prompt_history.get(key))
+        else {
+            return;
+        };
+        let (index, draft) = match (prompt.history.take(), older) {
+            (None, true) if !history.is_empty() => (0, prompt.value.clone()),
+            (Some((i, draft)), true) => ((i + 1).min(history.len() - 1), draft),
+            (Some((0, draft)), false) => {
+                prompt.value = draft;
+                prompt.cursor = prompt.value.len();
+                return;
+            }
+            (Some((i, draft)), false) => (i - 1, draft),
+            (None, _) => return,
+        };
+        prompt.value = history[index].clone();
+        prompt.cursor = prompt.value.len();
+        prompt.history = Some((index, draft));
+    }
+
     pub(crate) fn open_command_palette(&mut self) {
+        // The highlighted command is kept from the last time, clamped by
+        // the refresh.
         self.menu_open = true;
         self.menu_query.clear();
-        self.menu_index = 0;
         self.refresh_menu_results();
     }
 
@@ -473,6 +1404,8 @@ impl App {
             CommandAction::Theme,
             CommandAction::Help,
             CommandAction::QuickOpen,
+            CommandAction::FindInFolder,
+            CommandAction::BatchRename,
             CommandAction::FindInFile,
             CommandAction::FindInProject,
             CommandAction::SaveFile,
@@ -483,6 +1416,49 @@ impl App {
             CommandAction::GoToLine,
             CommandAction::Keybinds,
             CommandAction::ToggleWordWrap,
+            CommandAction::KeepTabOpen,
+            CommandAction::ReopenClosedTab,
+            CommandAction::NewUntitled,
+            CommandAction::GoToSymbol,
+            CommandAction::GoToLastEdit,
+            CommandAction::GoToMatchingTag,
+            CommandAction::WordCount,
+            CommandAction::ReflowParagraph,
+            CommandAction::ToggleTaskCheckbox,
+            CommandAction::ToggleListItem,
+            CommandAction::FormatMarkdownTable,
+            CommandAction::FormatDocument,
+            CommandAction::SetLanguage,
+            CommandAction::ConvertIndentationToSpaces,
+            CommandAction::ConvertIndentationToTabs,
+            CommandAction::ToggleTrailingWhitespace,
+            CommandAction::ToggleBracketColorization,
+            CommandAction::ToggleRainbowIndent,
+            CommandAction::ToggleSpellCheck,
+            CommandAction::ToggleLineNumbers,
+            CommandAction::ToggleFoldArrows,
+            CommandAction::ToggleMixedIndentLint,
+            CommandAction::FixMixedIndentation,
+            CommandAction::ToggleAutoSave,
+            CommandAction::CycleTreeSort,
+            CommandAction::ToggleTreeSortDirection,
+            CommandAction::ToggleTreeDirsFirst,
+            CommandAction::ToggleTreeDetails,
+            CommandAction::RevealInFileManager,
+            CommandAction::ToggleFilesPaneSide,
+            CommandAction::ToggleZenMode,
+            CommandAction::SetZenWidth,
+            CommandAction::CycleLineSpacing,
+            CommandAction::ToggleReadOnly,
+            CommandAction::TogglePinTab,
+            CommandAction::DiffTabs,
+            CommandAction::ShowUnsavedDiff,
+            CommandAction::OpenFolder,
+            CommandAction::OpenRecentProject,
+            CommandAction::ToggleDiagnosticErrorsOnly,
+            CommandAction::ShowDiagnostics,
+            CommandAction::RestartLsp,
+            CommandAction::ReloadConfig,
         ];
         let q = self.menu_query.to_ascii_lowercase();
         self.menu_results = all
@@ -505,15 +1481,16 @@ impl App {
                 self.theme_browser_open = true;
                 self.theme_index = self.active_theme_index;
                 self.preview_revert_index = self.active_theme_index;
-                self.set_status("Theme browser: arrows preview, Enter keep, Esc revert");
+                self.theme_query.clear();
+                self.refresh_theme_results();
+                self.set_status(
+                    "Theme browser: type to filter, arrows preview, Enter keep, Esc revert",
+                );
             }
             CommandAction::Help => self.help_open = true,
-            CommandAction::QuickOpen => {
-                self.file_picker_open = true;
-                self.file_picker_query.clear();
-                self.file_picker_index = 0;
-                self.refresh_file_picker_results();
-            }
+            CommandAction::QuickOpen => self.open_file_picker(),
+            CommandAction::FindInFolder => self.open_folder_file_picker(),
+            CommandAction::BatchRename => self.start_batch_rename(),
             CommandAction::FindInFile => {
                 self.open_find_prompt();
             }
@@ -527,15 +1504,7 @@ impl App {
                 self.rebuild_tree()?;
                 self.set_status("Tree refreshed");
             }
-            CommandAction::ToggleFiles => {
-                self.files_view_open = !self.files_view_open;
-                if !self.files_view_open {
-                    self.focus = Focus::Editor;
-                    self.set_status("Files view hidden");
-                } else {
-                    self.set_status("Files view shown");
-                }
-            }
+            CommandAction::ToggleFiles => self.toggle_files_view(),
             CommandAction::GotoDefinition => self.request_lsp_definition(),
             CommandAction::ReplaceInFile => {
                 self.open_replace_prompt();
@@ -552,51 +1521,170 @@ impl App {
                 self.refresh_keybind_editor_actions();
             }
             CommandAction::ToggleWordWrap => self.toggle_word_wrap(),
+            CommandAction::KeepTabOpen => self.promote_preview_tab(),
+            CommandAction::ReopenClosedTab => self.reopen_closed_tab()?,
+            CommandAction::NewUntitled => self.new_untitled_tab(),
+            CommandAction::GoToSymbol => self.open_symbol_picker(),
+            CommandAction::GoToLastEdit => self.goto_last_edit()?,
+            CommandAction::GoToMatchingTag => self.goto_matching_tag(),
+            CommandAction::WordCount => self.show_word_count(),
+            CommandAction::ReflowParagraph => self.reflow_paragraph(),
+            CommandAction::ToggleTaskCheckbox => self.toggle_task_checkbox(),
+            CommandAction::ToggleListItem => self.toggle_list_item(),
+            CommandAction::FormatMarkdownTable => self.format_markdown_table(),
+            CommandAction::FormatDocument => self.format_document(),
+            CommandAction::SetLanguage => self.open_language_picker(),
+            CommandAction::ConvertIndentationToSpaces => self.convert_indentation(false),
+            CommandAction::ConvertIndentationToTabs => self.convert_indentation(true),
+            CommandAction::ToggleTrailingWhitespace => self.toggle_trailing_whitespace(),
+            CommandAction::ToggleBracketColorization => self.toggle_bracket_colorization(),
+            CommandAction::ToggleRainbowIndent => self.toggle_rainbow_indent(),
+            CommandAction::ToggleSpellCheck => self.toggle_spell_check(),
+            CommandAction::ToggleLineNumbers => self.toggle_line_numbers(),
+            CommandAction::ToggleFoldArrows => self.toggle_fold_arrows(),
+            CommandAction::ToggleMixedIndentLint => self.toggle_mixed_indent_lint(),
+            CommandAction::FixMixedIndentation => self.fix_mixed_indentation(),
+            CommandAction::ToggleAutoSave => self.toggle_auto_save(),
+            CommandAction::CycleTreeSort => self.cycle_tree_sort()?,
+            CommandAction::ToggleTreeSortDirection => self.toggle_tree_sort_direction()?,
+            CommandAction::ToggleTreeDirsFirst => self.toggle_tree_dirs_first()?,
+            CommandAction::ToggleTreeDetails => self.toggle_tree_details()?,
+            CommandAction::RevealInFileManager => self.reveal_current_in_os_file_manager(),
+            CommandAction::ToggleFilesPaneSide => self.toggle_files_pane_side(),
+            CommandAction::ToggleZenMode => self.toggle_zen_mode(),
+            CommandAction::SetZenWidth => self.open_zen_width_prompt(),
+            CommandAction::CycleLineSpacing => self.cycle_line_spacing(),
+            CommandAction::ToggleReadOnly => self.toggle_read_only(),
+            CommandAction::TogglePinTab => self.toggle_pin_tab(),
+            CommandAction::DiffTabs => self.open_diff_tab_picker(),
+            CommandAction::ShowUnsavedDiff => self.show_unsaved_diff(),
+            CommandAction::OpenFolder => self.open_folder_picker(),
+            CommandAction::OpenRecentProject => self.open_recent_project_picker(),
+            CommandAction::ToggleDiagnosticErrorsOnly => self.toggle_diagnostic_errors_only(),
+            CommandAction::ShowDiagnostics => self.open_diagnostics_panel(),
+            CommandAction::ReloadConfig => self.reload_config(),
+            CommandAction::RestartLsp => {
+                self.restart_lsp();
+                if self.lsp_unavailable.is_none() {
+                    self.set_status("LSP restarted");
+                }
+            }
         }
         Ok(())
     }
 
+    /// Stop the running project search or batch rename, keeping what it
+    /// finished so far. Returns whether anything was running.
+    pub(crate) fn cancel_operation(&mut self) -> bool {
+        let running = self.project_search.is_some() || self.rename_job.is_some();
+        self.cancel_project_search();
+        self.cancel_batch_rename();
+        running
+    }
+
     pub(crate) fn update_status_for_cursor(&mut self) {
         if self.focus == Focus::Editor {
             if let Some(tab) = self.active_tab() {
-                let cursor_row = tab.editor.cursor().0;
-                if let Some(diag) = tab.diagnostics.iter().find(|d| d.line == cursor_row + 1) {
-                    self.status = format!("[{}] {}", diag.severity, diag.message);
+                let (cursor_row, cursor_col) = tab.editor.cursor();
+                let visible = visible_diagnostics(&tab.diagnostics, self.diagnostics_max_per_line);
+                // Prefer the diagnostic under the cursor over the line's first
+                let on_row = |d: &&&LspDiagnostic| d.line == cursor_row + 1;
+                let under_cursor = visible
+                    .iter()
+                    .filter(on_row)
+                    .find(|d| (d.col..d.end_col.max(d.col + 1)).contains(&cursor_col));
+                if let Some(diag) = under_cursor.or_else(|| visible.iter().find(on_row)) {
+                    let on_line = tab
+                        .diagnostics
+                        .iter()
+                        .filter(|d| d.line == diag.line)
+                        .count();
+                    let hidden = on_line - visible.iter().filter(on_row).count();
+                    self.status = if hidden > 0 {
+                        format!("[{}] {} (+{} more)", diag.severity, diag.message, hidden)
+                    } else {
+                        format!("[{}] {}", diag.severity, diag.message)
+                    };
                 }
             }
         }
     }
 
-    pub(crate) fn poll_autosave(&mut self) -> io::Result<()> {
-        if self.autosave_last_write.elapsed() < Duration::from_millis(Self::AUTOSAVE_INTERVAL_MS) {
+    /// Copy dirty buffers to the recovery store every
+    /// `RECOVERY_SNAPSHOT_INTERVAL_MS`, leaving the files themselves alone.
+    pub(crate) fn poll_recovery_snapshot(&mut self) -> io::Result<()> {
+        let interval = Duration::from_millis(Self::RECOVERY_SNAPSHOT_INTERVAL_MS);
+        if self.last_recovery_snapshot.elapsed() < interval {
             return Ok(());
         }
+        let Some(dir) = &self.autosave_dir else {
+            return Ok(());
+        };
         for tab in &self.tabs {
-            if !tab.dirty {
+            if !tab.dirty || tab.untitled {
                 continue;
             }
-            let autosave = autosave_path_for(&tab.path);
-            if let Some(parent) = autosave.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            fs::write(&autosave, tab.editor.lines().join("\n"))?;
+            write_autosave(dir, &tab.path, &tab.editor.lines().join("\n"))?;
         }
-        self.autosave_last_write = Instant::now();
+        self.last_recovery_snapshot = Instant::now();
         Ok(())
     }
 
+    /// Save dirty tabs that have gone `auto_save_delay_ms` without an edit.
+    /// A tab that fails to save is flagged and left alone until it is edited
+    /// again, so its error is returned once while the other tabs still save.
+    pub(crate) fn poll_auto_save(&mut self) -> io::Result<()> {
+        if !self.auto_save {
+            return Ok(());
+        }
+        let delay = Duration::from_millis(self.auto_save_delay_ms);
+        let mut first_error = None;
+        for idx in 0..self.tabs.len() {
+            let tab = &self.tabs[idx];
+            let idle = tab.last_edit.is_none_or(|t| t.elapsed() >= delay);
+            if !tab.dirty
+                || !idle
+                || tab.untitled
+                || tab.read_only
+                || tab.auto_save_failed
+                || tab.conflict_prompt_open
+                || tab.recovery_prompt_open
+            {
+                continue;
+            }
+            let writable = fs::metadata(&tab.path).is_ok_and(|m| !m.permissions().readonly());
+            if !writable {
+                continue;
+            }
+            if let Err(err) = self.save_tab_at(idx) {
+                let tab = &mut self.tabs[idx];
+                tab.auto_save_failed = true;
+                let name = relative_path(&self.root, &tab.path);
+                let err = io::Error::new(err.kind(), format!("{}: {err}", name.display()));
+                first_error.get_or_insert(err);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Offer the recovery prompt when the open file has an autosave that is
+    /// newer than the file on disk and differs from it.
     pub(crate) fn check_recovery_for_open_file(&mut self) {
-        let Some(tab) = self.active_tab() else {
+        let (Some(tab), Some(dir)) = (self.active_tab(), &self.autosave_dir) else {
             return;
         };
-        let path = tab.path.clone();
-        let autosave = autosave_path_for(&path);
+        let autosave = autosave_path_for(dir, &tab.path);
+        let mtime = |p: &std::path::Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+        if mtime(&autosave) <= mtime(&tab.path) {
+            return;
+        }
         let Ok(recovered) = fs::read_to_string(autosave) else {
             return;
         };
         let current = self.tabs[self.active_tab].editor.lines().join("\n");
         if recovered != current {
             if let Some(tab) = self.active_tab_mut() {
+                tab.prompt_diff = unified_diff(tab.editor.lines(), &text_to_lines(&recovered), 3);
                 tab.recovery_prompt_open = true;
                 tab.recovery_text = Some(recovered);
             }
@@ -604,9 +1692,34 @@ impl App {
     }
 
     pub(crate) fn clear_autosave_for_open_file(&mut self) {
-        if let Some(tab) = self.active_tab() {
-            let _ = fs::remove_file(autosave_path_for(&tab.path));
+        if let (Some(tab), Some(dir)) = (self.active_tab(), &self.autosave_dir) {
+            remove_autosave(dir, &tab.path);
+        }
+    }
+
+    /// Reopen files under the project root that were left with an autosave
+    /// (e.g. after a crash), so each gets the recovery prompt.
+    pub(crate) fn restore_autosaved_buffers(&mut self) -> io::Result<()> {
+        let Some(Ok(entries)) = self.autosave_dir.as_ref().map(fs::read_dir) else {
+            return Ok(());
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "autosave"))
+            .filter_map(|p| fs::read_to_string(autosave_source_path(&p)).ok())
+            .map(PathBuf::from)
+            .filter(|p| p.starts_with(&self.root) && p.is_file())
+            .collect();
+        paths.sort();
+        for path in paths {
+            self.open_file(path)?;
         }
+        // Land on the first recovered tab so its prompt is what the user sees.
+        if let Some(idx) = self.tabs.iter().position(|t| t.recovery_prompt_open) {
+            self.switch_to_tab(idx);
+        }
+        Ok(())
     }
 
     pub(crate) fn maybe_flag_external_conflict(&mut self) -> io::Result<()> {
@@ -625,6 +1738,7 @@ impl App {
             .unwrap_or_default();
         if disk != snapshot && disk != current {
             if let Some(tab) = self.active_tab_mut() {
+                tab.prompt_diff = unified_diff(tab.editor.lines(), &text_to_lines(&disk), 3);
                 tab.conflict_prompt_open = true;
                 tab.conflict_disk_text = Some(disk);
             }
@@ -639,16 +1753,112 @@ impl App {
         self.files_pane_width = self.files_pane_width.clamp(min_files, max_files);
     }
 
+    /// Resize the files pane so the divider follows the mouse at `column`.
+    /// The pane grows away from the divider towards its own window edge.
+    pub(crate) fn drag_files_pane_divider(&mut self, column: u16) {
+        let desired = match self.files_pane_side {
+            PaneSide::Left => column.saturating_sub(self.tree_rect.x),
+            // The divider column sits just left of the pane.
+            PaneSide::Right => {
+                let pane_end = self.tree_rect.right();
+                pane_end.saturating_sub(column.saturating_add(1))
+            }
+        };
+        self.files_pane_width = desired.max(Self::MIN_FILES_PANE_WIDTH);
+        self.clamp_files_pane_width(
+            self.editor_rect.width + self.tree_rect.width + self.divider_rect.width,
+        );
+    }
+
+    /// Widen or narrow the files pane by `FILES_PANE_RESIZE_STEP`, within
+    /// the same bounds as dragging the divider.
+    pub(crate) fn resize_files_pane(&mut self, wider: bool) {
+        if !self.files_view_open {
+            self.set_status("Files pane is hidden");
+            return;
+        }
+        let width = if wider {
+            self.files_pane_width
+                .saturating_add(Self::FILES_PANE_RESIZE_STEP)
+        } else {
+            self.files_pane_width
+                .saturating_sub(Self::FILES_PANE_RESIZE_STEP)
+        };
+        self.files_pane_width = width.max(Self::MIN_FILES_PANE_WIDTH);
+        self.clamp_files_pane_width(
+            self.editor_rect.width + self.tree_rect.width + self.divider_rect.width,
+        );
+        self.persist_state();
+        self.set_status(format!("Files pane width: {}", self.files_pane_width));
+    }
+
+    pub(crate) fn toggle_files_view(&mut self) {
+        self.files_view_open = !self.files_view_open;
+        self.persist_state();
+        if !self.files_view_open {
+            self.focus = Focus::Editor;
+            self.set_status("Files view hidden");
+        } else {
+            self.set_status("Files view shown");
+        }
+    }
+
+    /// Hide the files pane, top bar, tab bar and key hints and center the
+    /// text, or put the layout back exactly as it was.
+    pub(crate) fn toggle_zen_mode(&mut self) {
+        if let Some(restore) = self.zen_restore.take() {
+            self.files_view_open = restore.files_view_open;
+            self.focus = restore.focus;
+            self.set_status("Zen mode off");
+            return;
+        }
+        self.zen_restore = Some(ZenRestore {
+            files_view_open: self.files_view_open,
+            focus: self.focus,
+        });
+        self.files_view_open = false;
+        self.focus = Focus::Editor;
+        self.set_status("Zen mode on");
+    }
+
+    /// Step the spacer rows through off, after every line, every 2nd and
+    /// every 3rd line.
+    pub(crate) fn cycle_line_spacing(&mut self) {
+        self.line_spacing = (self.line_spacing + 1) % (Self::MAX_LINE_SPACING + 1);
+        self.persist_state();
+        self.sync_editor_scroll_guess();
+        match self.line_spacing {
+            0 => self.set_status("Line spacing off"),
+            1 => self.set_status("Line spacing: blank row after every line"),
+            n => self.set_status(format!("Line spacing: blank row after every {n} lines")),
+        }
+    }
+
+    pub(crate) fn toggle_files_pane_side(&mut self) {
+        self.files_pane_side = self.files_pane_side.flipped();
+        self.persist_state();
+        self.set_status(format!(
+            "Files pane on the {}",
+            self.files_pane_side.label()
+        ));
+    }
+
     pub(crate) fn recompute_folds(&mut self) {
         let Some(tab) = self.active_tab() else {
             return;
         };
-        let lang = syntax_lang_for_path(Some(tab.path.as_path()));
+        let lang = tab.syntax_lang();
         let (fold_ranges, bracket_depths) =
             compute_fold_ranges(self.tabs[self.active_tab].editor.lines(), lang);
         let tab = &mut self.tabs[self.active_tab];
         tab.fold_ranges = fold_ranges;
         tab.bracket_depths = bracket_depths;
+        // Keep folds on the same blocks when lines above them came or went.
+        if !tab.folded_starts.is_empty() && !tab.fold_lines.is_empty() {
+            let lines = tab.editor.lines();
+            tab.folded_starts = shift_folded_starts(&tab.folded_starts, &tab.fold_lines, lines);
+            tab.fold_lines = lines.to_vec();
+        }
         tab.folded_starts
             .retain(|start| tab.fold_ranges.iter().any(|r| r.start_line == *start));
         self.rebuild_visible_rows();
@@ -703,6 +1913,12 @@ impl App {
         }
         let max_scroll = tab.visible_rows_map.len().saturating_sub(1);
         tab.editor_scroll_row = tab.editor_scroll_row.min(max_scroll);
+        // The text the folds sit on, kept only while something is folded.
+        if tab.folded_starts.is_empty() {
+            tab.fold_lines = Vec::new();
+        } else if tab.fold_lines.is_empty() {
+            tab.fold_lines = lines;
+        }
     }
 
     pub(crate) fn rebuild_all_visible_rows(&mut self) {
@@ -725,9 +1941,15 @@ impl App {
         }
     }
 
+    /// Gutter columns for the active tab, sized to its line count.
+    pub(crate) fn gutter(&self) -> GutterLayout {
+        let line_count = self.active_tab().map_or(1, |tab| tab.editor.lines().len());
+        GutterLayout::new(line_count, self.line_numbers, self.fold_arrows)
+    }
+
     fn editor_wrap_width_chars(&self) -> usize {
         let inner_width = self.editor_rect.width.saturating_sub(2);
-        let content_width = inner_width.saturating_sub(Self::EDITOR_GUTTER_WIDTH);
+        let content_width = inner_width.saturating_sub(self.gutter().width());
         if content_width == 0 {
             usize::MAX
         } else {
@@ -798,22 +2020,24 @@ impl App {
         }
     }
 
+    /// Smallest fold range containing `row`, only among folded ones with
+    /// `folded_only`.
+    fn innermost_fold_at(&self, row: usize, folded_only: bool) -> Option<(usize, usize)> {
+        let tab = self.active_tab()?;
+        tab.fold_ranges
+            .iter()
+            .filter(|fr| fr.start_line <= row && row <= fr.end_line)
+            .filter(|fr| !folded_only || tab.folded_starts.contains(&fr.start_line))
+            .min_by_key(|fr| fr.end_line - fr.start_line)
+            .map(|fr| (fr.start_line, fr.end_line))
+    }
+
     pub(crate) fn fold_current_block(&mut self) {
         let Some(tab) = self.active_tab() else {
             return;
         };
         let (cursor_row, _) = tab.editor.cursor();
-        let mut candidate: Option<(usize, usize)> = None;
-        for fr in &tab.fold_ranges {
-            if fr.start_line == cursor_row {
-                candidate = Some((fr.start_line, fr.end_line));
-                break;
-            }
-            if fr.start_line <= cursor_row && cursor_row <= fr.end_line {
-                candidate = Some((fr.start_line, fr.end_line));
-            }
-        }
-        if let Some((start_line, end_line)) = candidate {
+        if let Some((start_line, end_line)) = self.innermost_fold_at(cursor_row, false) {
             self.tabs[self.active_tab].folded_starts.insert(start_line);
             self.rebuild_visible_rows();
             self.sync_editor_scroll_guess();
@@ -828,20 +2052,8 @@ impl App {
             return;
         };
         let (cursor_row, _) = tab.editor.cursor();
-        let mut unfolded = false;
-        let starts: Vec<usize> = tab.folded_starts.iter().copied().collect();
-        for start in starts {
-            if let Some(fr) = tab.fold_ranges.iter().find(|fr| fr.start_line == start) {
-                if fr.start_line == cursor_row
-                    || (fr.start_line <= cursor_row && cursor_row <= fr.end_line)
-                {
-                    self.tabs[self.active_tab].folded_starts.remove(&start);
-                    unfolded = true;
-                    break;
-                }
-            }
-        }
-        if unfolded {
+        if let Some((start_line, _)) = self.innermost_fold_at(cursor_row, true) {
+            self.tabs[self.active_tab].folded_starts.remove(&start_line);
             self.rebuild_visible_rows();
             self.sync_editor_scroll_guess();
             self.set_status("Unfolded block");
@@ -850,6 +2062,48 @@ impl App {
         }
     }
 
+    /// Move the cursor to the start of the next fold range below it, or the
+    /// previous one above it, skipping ranges hidden inside a fold.
+    pub(crate) fn jump_to_fold(&mut self, forward: bool) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let (cursor_row, _) = tab.editor.cursor();
+        let hidden = |row: usize| {
+            tab.fold_ranges.iter().any(|fr| {
+                tab.folded_starts.contains(&fr.start_line)
+                    && fr.start_line < row
+                    && row <= fr.end_line
+            })
+        };
+        let starts = tab
+            .fold_ranges
+            .iter()
+            .map(|fr| fr.start_line)
+            .filter(|&row| !hidden(row));
+        let target = if forward {
+            starts.filter(|&row| row > cursor_row).min()
+        } else {
+            starts.filter(|&row| row < cursor_row).max()
+        };
+        let Some(row) = target else {
+            self.set_status(if forward {
+                "No next fold"
+            } else {
+                "No previous fold"
+            });
+            return;
+        };
+        let tab = &mut self.tabs[self.active_tab];
+        tab.editor.cancel_selection();
+        tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+            to_u16_saturating(row),
+            0,
+        ));
+        self.sync_editor_scroll_guess();
+        self.set_status(format!("Fold at line {}", row + 1));
+    }
+
     pub(crate) fn fold_all(&mut self) {
         let Some(tab) = self.active_tab() else {
             return;
@@ -884,19 +2138,7 @@ impl App {
             return;
         };
         let (cursor_row, _) = tab.editor.cursor();
-        // Check if cursor is on/in a folded block
-        let mut is_folded = false;
-        for &start in &tab.folded_starts {
-            if let Some(fr) = tab.fold_ranges.iter().find(|fr| fr.start_line == start) {
-                if fr.start_line == cursor_row
-                    || (fr.start_line <= cursor_row && cursor_row <= fr.end_line)
-                {
-                    is_folded = true;
-                    break;
-                }
-            }
-        }
-        if is_folded {
+        if self.innermost_fold_at(cursor_row, true).is_some() {
             self.unfold_current_block();
         } else {
             self.fold_current_block();
@@ -918,11 +2160,15 @@ impl App {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lsp_client::GOPLS;
+    use crate::tab::DiffLineKind;
+    use crate::types::ProjectKind;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
     use std::fs;
     use tempfile::tempdir;
 
     fn new_app(root: &std::path::Path) -> App {
-        App::new(root.to_path_buf()).expect("app should initialize")
+        App::with_paths(root.to_path_buf(), None, None).expect("app should initialize")
     }
 
     #[test]
@@ -1081,7 +2327,7 @@ mod tests {
         app.open_file(file).expect("open");
         app.word_wrap = true;
         // Simulate a narrow editor (wrap_width ~ 10 chars)
-        app.editor_rect = Rect::new(0, 0, 22, 20); // 22 - 2 border - 10 gutter = 10
+        app.editor_rect = Rect::new(0, 0, 22, 20); // 22 - 2 border - 9 gutter = 11
         app.rebuild_visible_rows();
         let tab = app.active_tab().expect("tab");
         // The long line should produce multiple segments (source row 0 appears more than once)
@@ -1095,6 +2341,29 @@ mod tests {
         assert_eq!(tab.visible_rows_map.len(), tab.visible_row_ends.len());
     }
 
+    #[test]
+    fn gutter_width_follows_the_line_count_digits() {
+        assert_eq!(GutterLayout::new(1, true, true).number_digits, 3);
+        assert_eq!(GutterLayout::new(999, true, true).number_digits, 3);
+        assert_eq!(GutterLayout::new(1000, true, true).number_digits, 4);
+        assert_eq!(GutterLayout::new(123_456, true, true).number_digits, 6);
+
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("long.txt");
+        let text: Vec<String> = (1..=12_345).map(|i| i.to_string()).collect();
+        fs::write(&file, text.join("\n")).expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        // Five digits and a space, two fold columns, three markers.
+        assert_eq!(app.gutter().width(), 6 + 2 + 3);
+        app.toggle_fold_arrows();
+        assert_eq!(app.gutter().width(), 6 + 3);
+        app.toggle_line_numbers();
+        assert_eq!(app.gutter().width(), 3);
+        assert!(!app.config_snapshot().line_numbers);
+    }
+
     #[test]
     fn rebuild_visible_rows_wrap_disabled_no_segments() {
         let tmp = tempdir().expect("tempdir");
@@ -1123,7 +2392,7 @@ mod tests {
         app.open_file(file1).expect("open");
         app.open_file(file2).expect("open");
         app.word_wrap = true;
-        app.editor_rect = Rect::new(0, 0, 22, 20); // wrap_width ~ 10
+        app.editor_rect = Rect::new(0, 0, 22, 20); // wrap_width ~ 11
         // Only active tab (tab 1) should have been rebuilt by previous opens
         // Now rebuild all:
         app.rebuild_all_visible_rows();
@@ -1151,7 +2420,7 @@ mod tests {
         let mut app = new_app(root);
         app.open_file(file).expect("open");
         app.word_wrap = true;
-        app.editor_rect = Rect::new(0, 0, 22, 20); // wrap_width ~ 10
+        app.editor_rect = Rect::new(0, 0, 22, 20); // wrap_width ~ 11
         // Fold the function body (lines 1-3 hidden, fold range 0..3)
         app.tabs[app.active_tab].folded_starts.insert(0);
         app.rebuild_visible_rows();
@@ -1202,4 +2471,681 @@ mod tests {
             "deadline should NOT be cleared yet"
         );
     }
+
+    #[test]
+    fn auto_save_writes_dirty_tab_after_idle_window() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "content\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file.clone()).expect("open");
+        app.auto_save = true;
+        app.auto_save_delay_ms = 500;
+        app.tabs[0].editor.insert_str("new ");
+        app.mark_dirty();
+
+        // Still inside the idle window: nothing is written yet.
+        app.poll_auto_save().expect("poll");
+        assert!(app.tabs[0].dirty);
+        assert_eq!(fs::read_to_string(&file).expect("read"), "content\n");
+
+        app.tabs[0].last_edit =
+            Some(std::time::Instant::now() - std::time::Duration::from_millis(600));
+        app.poll_auto_save().expect("poll");
+        let tab = &app.tabs[0];
+        assert!(!tab.dirty);
+        assert_eq!(fs::read_to_string(&file).expect("read"), "new content\n");
+        assert_eq!(tab.open_disk_snapshot.as_deref(), Some("new content\n"));
+    }
+
+    #[test]
+    fn auto_save_failure_is_reported_once_and_other_tabs_still_save() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let broken = root.join("a.txt");
+        let good = root.join("b.txt");
+        fs::write(&broken, "a\n").expect("write a");
+        fs::write(&good, "b\n").expect("write b");
+        let mut app = new_app(root);
+        app.auto_save = true;
+        app.auto_save_delay_ms = 0;
+        for path in [&broken, &good] {
+            app.open_file(path.clone()).expect("open");
+            app.active_tab_mut().expect("tab").editor.insert_str("x");
+            app.mark_dirty();
+        }
+        // A directory where the file was makes every write to it fail.
+        fs::remove_file(&broken).expect("remove a");
+        fs::create_dir(&broken).expect("dir a");
+
+        let err = app.poll_auto_save().expect_err("a fails");
+        assert!(err.to_string().starts_with("a.txt: "));
+        assert!(app.tabs[0].dirty);
+        assert!(!app.tabs[1].dirty);
+        assert_eq!(fs::read_to_string(&good).expect("read b"), "xb\n");
+
+        // The failed tab is not retried on every tick.
+        app.poll_auto_save().expect("no new error");
+        assert!(app.tabs[0].dirty);
+
+        // Until it is edited again.
+        app.switch_to_tab(0);
+        app.active_tab_mut().expect("tab").editor.insert_str("y");
+        app.mark_dirty();
+        assert!(app.poll_auto_save().is_err());
+    }
+
+    #[test]
+    fn auto_save_skips_clean_tabs() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "content").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file.clone()).expect("open");
+        app.auto_save = true;
+        app.auto_save_delay_ms = 0;
+
+        app.poll_auto_save().expect("poll");
+        assert!(!app.tabs[0].dirty);
+        // A save would have appended the trailing newline.
+        assert_eq!(fs::read_to_string(&file).expect("read"), "content");
+    }
+
+    fn write_externally(path: &std::path::Path, text: &str) {
+        fs::write(path, text).expect("write");
+        // Bump the mtime explicitly so coarse filesystem clocks can't hide it.
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .and_then(|f| f.set_modified(later))
+            .expect("set mtime");
+    }
+
+    #[test]
+    fn external_change_reloads_clean_tab() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "old\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file.clone()).expect("open");
+
+        write_externally(&file, "new from disk\n");
+        app.check_external_change_at(0).expect("check");
+
+        let tab = &app.tabs[0];
+        assert!(!tab.dirty);
+        assert!(!tab.conflict_prompt_open);
+        assert_eq!(tab.editor.lines()[0], "new from disk");
+        assert_eq!(tab.open_disk_snapshot.as_deref(), Some("new from disk\n"));
+    }
+
+    #[test]
+    fn external_change_on_dirty_tab_opens_conflict_prompt() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "old\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file.clone()).expect("open");
+        app.tabs[0].editor.insert_str("mine ");
+        app.mark_dirty();
+
+        write_externally(&file, "theirs\n");
+        app.check_external_change_at(0).expect("check");
+
+        let tab = &app.tabs[0];
+        assert!(tab.dirty);
+        assert!(tab.conflict_prompt_open);
+        assert_eq!(tab.conflict_disk_text.as_deref(), Some("theirs\n"));
+        assert_eq!(tab.editor.lines()[0], "mine old");
+        // The prompt's diff is worked out here, not by the renderer.
+        let diff: Vec<(DiffLineKind, &str)> = tab
+            .prompt_diff
+            .iter()
+            .map(|l| (l.kind, l.text.as_str()))
+            .collect();
+        assert_eq!(
+            diff,
+            vec![
+                (DiffLineKind::Hunk, "@@ -1 +1 @@"),
+                (DiffLineKind::Removed, "mine old"),
+                (DiffLineKind::Added, "theirs"),
+                (DiffLineKind::Context, ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn unchanged_mtime_skips_disk_read() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "old\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        app.tabs[0].editor.insert_str("mine ");
+        app.mark_dirty();
+
+        app.check_external_change_at(0).expect("check");
+        assert!(!app.tabs[0].conflict_prompt_open);
+    }
+
+    #[test]
+    fn poll_recovery_snapshot_writes_dirty_buffer_to_recovery_store() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let store = tempdir().expect("autosave dir");
+        let file = root.join("test.txt");
+        fs::write(&file, "content\n").expect("write");
+        let mut app = new_app(root);
+        app.autosave_dir = Some(store.path().to_path_buf());
+        app.open_file(file.clone()).expect("open");
+        app.tabs[0].editor.insert_str("unsaved ");
+        app.mark_dirty();
+        app.last_recovery_snapshot =
+            Instant::now() - Duration::from_millis(App::RECOVERY_SNAPSHOT_INTERVAL_MS);
+
+        app.poll_recovery_snapshot().expect("autosave");
+
+        let autosave = autosave_path_for(store.path(), &file);
+        let written = fs::read_to_string(&autosave).expect("autosave written");
+        let source = fs::read_to_string(autosave_source_path(&autosave)).expect("sidecar");
+        assert_eq!(written, "unsaved content\n");
+        assert_eq!(PathBuf::from(source), file);
+
+        app.save_file().expect("save");
+        assert!(!autosave.exists());
+    }
+
+    #[test]
+    fn startup_reopens_file_with_newer_autosave() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let store = tempdir().expect("autosave dir");
+        let file = root.join("test.txt");
+        fs::write(&file, "on disk\n").expect("write");
+        let earlier = std::time::SystemTime::now() - Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .and_then(|f| f.set_modified(earlier))
+            .expect("set mtime");
+        write_autosave(store.path(), &file, "recovered edit\n").expect("autosave");
+
+        let mut app = new_app(root);
+        app.autosave_dir = Some(store.path().to_path_buf());
+        app.restore_autosaved_buffers().expect("restore");
+
+        let tab = app.active_tab().expect("recovered tab");
+        assert_eq!(tab.path, file);
+        assert!(tab.recovery_prompt_open);
+        assert_eq!(tab.recovery_text.as_deref(), Some("recovered edit\n"));
+    }
+
+    #[test]
+    fn autosave_older_than_file_is_not_offered() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let store = tempdir().expect("autosave dir");
+        let file = root.join("test.txt");
+        fs::write(&file, "on disk\n").expect("write");
+        write_autosave(store.path(), &file, "old edit\n").expect("autosave");
+        let later = std::time::SystemTime::now() + Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .and_then(|f| f.set_modified(later))
+            .expect("set mtime");
+
+        let mut app = new_app(root);
+        app.autosave_dir = Some(store.path().to_path_buf());
+        app.open_file(file.clone()).expect("open");
+        assert!(!app.tabs[0].recovery_prompt_open);
+    }
+
+    #[test]
+    fn divider_drag_sizes_pane_on_either_side() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        app.files_pane_width = 32;
+
+        app.files_pane_side = PaneSide::Left;
+        app.tree_rect = Rect::new(0, 3, 32, 20);
+        app.divider_rect = Rect::new(32, 3, 1, 20);
+        app.editor_rect = Rect::new(33, 3, 67, 20);
+        app.drag_files_pane_divider(40);
+        assert_eq!(app.files_pane_width, 40);
+
+        // Pane docked right: the editor fills columns 0..67, then divider, then files.
+        app.files_pane_side = PaneSide::Right;
+        app.editor_rect = Rect::new(0, 3, 67, 20);
+        app.divider_rect = Rect::new(67, 3, 1, 20);
+        app.tree_rect = Rect::new(68, 3, 32, 20);
+        app.drag_files_pane_divider(59);
+        assert_eq!(app.files_pane_width, 40);
+        app.drag_files_pane_divider(95);
+        assert_eq!(app.files_pane_width, App::MIN_FILES_PANE_WIDTH);
+        app.drag_files_pane_divider(2);
+        assert_eq!(app.files_pane_width, 100 - App::MIN_EDITOR_PANE_WIDTH);
+    }
+
+    #[test]
+    fn palette_and_quick_open_reopen_on_the_last_row_within_the_list() {
+        let tmp = tempdir().expect("tempdir");
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(tmp.path().join(name), name).expect("write");
+        }
+        let mut app = new_app(tmp.path());
+        app.cached_file_list = ["a.txt", "b.txt", "c.txt"]
+            .iter()
+            .map(|name| tmp.path().join(name))
+            .collect();
+        app.file_index_stale = false;
+
+        app.open_command_palette();
+        app.menu_index = 4;
+        app.menu_open = false;
+        app.open_command_palette();
+        assert_eq!(app.menu_index, 4);
+
+        app.open_file_picker();
+        assert_eq!(app.file_picker_results.len(), 3);
+        app.file_picker_index = 2;
+        app.file_picker_open = false;
+        app.open_file_picker();
+        assert_eq!(app.file_picker_index, 2);
+
+        // A shorter list keeps the highlight on its last row.
+        app.file_picker_open = false;
+        app.cached_file_list.truncate(2);
+        app.open_file_picker();
+        assert_eq!(app.file_picker_index, 1);
+    }
+
+    #[test]
+    fn typing_filters_theme_browser_and_esc_restores_the_theme() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        let original = app.active_theme().name.clone();
+
+        app.run_command_action(CommandAction::Theme).expect("open");
+        assert_eq!(app.theme_results.len(), app.themes.len());
+        for c in "drac".chars() {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .expect("type");
+        }
+        let names: Vec<&str> = app
+            .theme_results
+            .iter()
+            .map(|&idx| app.themes[idx].name.as_str())
+            .collect();
+        assert_eq!(names.first(), Some(&"Dracula"));
+        assert!(names.iter().all(|name| fuzzy_score("drac", name).is_some()));
+        assert!(names.len() < app.themes.len());
+        assert_eq!(app.active_theme().name, "Dracula");
+
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("esc");
+        assert!(!app.theme_browser_open);
+        assert_eq!(app.active_theme().name, original);
+    }
+
+    #[test]
+    fn theme_browser_previews_live_and_saves_only_on_enter() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        app.active_theme_index = 0;
+        let first = app.themes[0].name.clone();
+        let second = app.themes[1].name.clone();
+        let down = KeyEvent::new(KeyCode::Down, KeyModifiers::NONE);
+
+        app.run_command_action(CommandAction::Theme).expect("open");
+        app.handle_key(down).expect("down");
+        assert_eq!(app.active_theme().name, second);
+        assert_eq!(app.config_snapshot().theme, first);
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("esc");
+        assert_eq!(app.active_theme().name, first);
+
+        app.run_command_action(CommandAction::Theme).expect("open");
+        app.handle_key(down).expect("down");
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("enter");
+        assert!(!app.theme_browser_open);
+        assert_eq!(app.active_theme().name, second);
+        assert_eq!(app.config_snapshot().theme, second);
+    }
+
+    #[test]
+    fn keyboard_resize_steps_files_pane_within_bounds() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        app.files_view_open = true;
+        app.files_pane_width = 32;
+        app.tree_rect = Rect::new(0, 3, 32, 20);
+        app.divider_rect = Rect::new(32, 3, 1, 20);
+        app.editor_rect = Rect::new(33, 3, 67, 20);
+        let step = App::FILES_PANE_RESIZE_STEP;
+
+        app.run_key_action(KeyAction::IncreaseFilesPane)
+            .expect("widen");
+        assert_eq!(app.files_pane_width, 32 + step);
+        app.run_key_action(KeyAction::DecreaseFilesPane)
+            .expect("narrow");
+        app.run_key_action(KeyAction::DecreaseFilesPane)
+            .expect("narrow");
+        assert_eq!(app.files_pane_width, 32 - step);
+
+        for _ in 0..20 {
+            app.resize_files_pane(false);
+        }
+        assert_eq!(app.files_pane_width, App::MIN_FILES_PANE_WIDTH);
+        for _ in 0..40 {
+            app.resize_files_pane(true);
+        }
+        assert_eq!(app.files_pane_width, 100 - App::MIN_EDITOR_PANE_WIDTH);
+    }
+
+    #[test]
+    fn zen_mode_hides_chrome_and_restores_prior_layout() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        app.files_view_open = true;
+        app.focus = Focus::Tree;
+
+        app.toggle_zen_mode();
+        assert!(!app.files_view_open);
+        assert_eq!(app.focus, Focus::Editor);
+        assert_eq!(
+            app.zen_restore,
+            Some(ZenRestore {
+                files_view_open: true,
+                focus: Focus::Tree,
+            })
+        );
+
+        app.toggle_zen_mode();
+        assert!(app.zen_restore.is_none());
+        assert!(app.files_view_open);
+        assert_eq!(app.focus, Focus::Tree);
+
+        // A hidden pane stays hidden once zen mode ends.
+        app.files_view_open = false;
+        app.focus = Focus::Editor;
+        app.toggle_zen_mode();
+        app.toggle_zen_mode();
+        assert!(!app.files_view_open);
+        assert_eq!(app.focus, Focus::Editor);
+    }
+
+    #[test]
+    fn config_file_overrides_named_settings_on_reload() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let config = root.join("config.toml");
+        let mut app = new_app(root);
+        app.config_path = Some(config.clone());
+        app.scroll_off = 1;
+
+        // No file: nothing changes.
+        app.reload_config();
+        assert_eq!(app.status, "Config reloaded");
+        assert_eq!(app.scroll_off, 1);
+
+        fs::write(&config, "word_wrap = true\nindent_width = 2\n").expect("write");
+        app.reload_config();
+        assert_eq!(app.status, "Config reloaded");
+        assert!(app.word_wrap);
+        assert_eq!(app.default_indent.width, 2);
+        // Settings the file leaves out keep their current value.
+        assert_eq!(app.scroll_off, 1);
+
+        fs::write(&config, "word_wrap = false\ncolour = \"red\"\n").expect("write");
+        app.reload_config();
+        assert!(!app.word_wrap);
+        let warning = format!("Config warning in {}: ", config.display());
+        assert_eq!(app.status, warning + "unknown key `colour`");
+
+        fs::write(&config, "scroll_off = -1\n").expect("write");
+        app.reload_config();
+        assert!(app.status.starts_with("Config error in "), "{}", app.status);
+        assert_eq!(app.scroll_off, 1);
+    }
+
+    #[test]
+    fn cargo_root_is_a_rust_project_served_by_rust_analyzer() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::write(root.join("Cargo.toml"), "[package]\n").expect("write");
+        let app = new_app(root);
+        assert_eq!(app.project_kinds, [ProjectKind::Rust]);
+        assert_eq!(app.lsp_server, RUST_ANALYZER);
+        assert_eq!(app.project_label().as_deref(), Some("Rust"));
+        let main = root.join("main.rs");
+        assert_eq!(app.lsp_server.language_id(&main), Some("rust"));
+
+        fs::write(root.join("go.mod"), "module x\n").expect("write");
+        fs::write(root.join("package.json"), "{}\n").expect("write");
+        let mut app = new_app(root);
+        assert_eq!(app.project_label().as_deref(), Some("Rust + Node + Go"));
+        assert_eq!(app.lsp_server, RUST_ANALYZER);
+
+        let go = root.join("go");
+        fs::create_dir(&go).expect("mkdir");
+        fs::write(go.join("go.mod"), "module y\n").expect("write");
+        app.set_root(go.clone()).expect("set root");
+        assert_eq!(app.project_kinds, [ProjectKind::Go]);
+        assert_eq!(app.lsp_server, GOPLS);
+        assert_eq!(app.lsp_bin, PathBuf::from("gopls"));
+        assert_eq!(app.status, format!("Root: {} (Go project)", go.display()));
+    }
+
+    #[test]
+    fn title_label_names_the_root_and_counts_dirty_tabs() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path().join("shop");
+        fs::create_dir(&root).expect("mkdir");
+        for name in ["a.rs", "b.rs", "c.rs"] {
+            fs::write(root.join(name), "").expect("write");
+        }
+        let mut app = new_app(&root);
+        for name in ["a.rs", "b.rs", "c.rs"] {
+            app.open_file(root.join(name)).expect("open");
+        }
+        assert_eq!(app.title_label(), "lazyide · shop");
+
+        app.tabs[0].dirty = true;
+        app.tabs[2].dirty = true;
+        assert_eq!(app.title_label(), "lazyide · shop ●2");
+
+        app.tabs[0].dirty = false;
+        assert_eq!(app.title_label(), "lazyide · shop ●1");
+    }
+
+    #[test]
+    fn project_config_applies_only_while_its_root_is_open() {
+        let tmp = tempdir().expect("tempdir");
+        let alpha = tmp.path().join("alpha");
+        let beta = tmp.path().join("beta");
+        fs::create_dir_all(&alpha).expect("mkdir");
+        fs::create_dir_all(&beta).expect("mkdir");
+        fs::write(
+            alpha.join(".lazyide.toml"),
+            "indent_width = 3\nlsp_server = \"/opt/ra/bin/rust-analyzer\"\n",
+        )
+        .expect("write");
+        let mut app = new_app(&beta);
+        let width = app.default_indent.width;
+        let lsp_bin = app.lsp_bin.clone();
+        let scroll_off = app.scroll_off;
+
+        app.set_root(alpha.clone()).expect("set root");
+        assert_eq!(app.default_indent.width, 3);
+        assert_eq!(app.lsp_bin, PathBuf::from("/opt/ra/bin/rust-analyzer"));
+        // A runtime change to a setting the project leaves alone is kept.
+        app.scroll_off = scroll_off + 1;
+
+        app.set_root(beta.clone()).expect("set root");
+        assert_eq!(app.default_indent.width, width);
+        assert_eq!(app.lsp_bin, lsp_bin);
+        assert_eq!(app.scroll_off, scroll_off + 1);
+
+        app.set_root(alpha).expect("set root");
+        assert_eq!(app.default_indent.width, 3);
+    }
+
+    const NESTED_FOLDS: &str =
+        "fn a() {\n    if x {\n        y();\n    }\n}\nfn b() {\n    z();\n}\n";
+
+    fn app_with_nested_folds(root: &std::path::Path) -> App {
+        let file = root.join("nested.rs");
+        fs::write(&file, NESTED_FOLDS).expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        app
+    }
+
+    fn put_cursor(app: &mut App, row: u16, col: u16) {
+        let tab = app.active_tab_mut().expect("tab");
+        tab.editor
+            .move_cursor(ratatui_textarea::CursorMove::Jump(row, col));
+    }
+
+    fn cursor_row(app: &App) -> usize {
+        app.active_tab().expect("tab").editor.cursor().0
+    }
+
+    #[test]
+    fn fold_under_cursor_folds_the_smallest_enclosing_range() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = app_with_nested_folds(tmp.path());
+        put_cursor(&mut app, 2, 8);
+
+        app.fold_current_block();
+        let folded = &app.active_tab().expect("tab").folded_starts;
+        assert_eq!(folded.iter().copied().collect::<Vec<_>>(), vec![1]);
+
+        app.toggle_fold_at_cursor();
+        assert!(app.active_tab().expect("tab").folded_starts.is_empty());
+
+        put_cursor(&mut app, 4, 0);
+        app.toggle_fold_at_cursor();
+        let folded = &app.active_tab().expect("tab").folded_starts;
+        assert_eq!(folded.iter().copied().collect::<Vec<_>>(), vec![0]);
+    }
+
+    #[test]
+    fn next_and_previous_fold_land_on_fold_starts() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = app_with_nested_folds(tmp.path());
+        put_cursor(&mut app, 0, 3);
+
+        app.jump_to_fold(true);
+        assert_eq!(cursor_row(&app), 1);
+        app.jump_to_fold(true);
+        assert_eq!(cursor_row(&app), 5);
+        app.jump_to_fold(true);
+        assert_eq!(cursor_row(&app), 5);
+        assert_eq!(app.status, "No next fold");
+        app.jump_to_fold(false);
+        assert_eq!(cursor_row(&app), 1);
+
+        // A fold start hidden inside a folded range is skipped.
+        put_cursor(&mut app, 0, 0);
+        app.fold_current_block();
+        app.jump_to_fold(true);
+        assert_eq!(cursor_row(&app), 5);
+        app.jump_to_fold(false);
+        assert_eq!(cursor_row(&app), 0);
+    }
+
+    fn folded_rows(app: &App) -> Vec<usize> {
+        let mut rows: Vec<usize> = app
+            .active_tab()
+            .expect("tab")
+            .folded_starts
+            .iter()
+            .copied()
+            .collect();
+        rows.sort_unstable();
+        rows
+    }
+
+    #[test]
+    fn inserting_a_line_above_a_fold_keeps_that_block_folded() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = app_with_nested_folds(tmp.path());
+        put_cursor(&mut app, 5, 0);
+        app.fold_current_block();
+        assert_eq!(folded_rows(&app), vec![5]);
+
+        put_cursor(&mut app, 0, 0);
+        app.tabs[0].editor.insert_str("use std::io;\n");
+        app.on_editor_content_changed();
+
+        assert_eq!(folded_rows(&app), vec![6]);
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[6], "fn b() {");
+        assert!(!tab.visible_rows_map.contains(&7));
+        assert!(tab.visible_rows_map.contains(&5));
+    }
+
+    #[test]
+    fn deleting_the_start_line_of_a_fold_drops_the_fold() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = app_with_nested_folds(tmp.path());
+        put_cursor(&mut app, 0, 0);
+        app.fold_current_block();
+        put_cursor(&mut app, 5, 0);
+        app.fold_current_block();
+        assert_eq!(folded_rows(&app), vec![0, 5]);
+
+        app.cut_line();
+
+        assert_eq!(app.active_tab().expect("tab").editor.lines()[5], "    z();");
+        assert_eq!(folded_rows(&app), vec![0]);
+    }
+
+    #[test]
+    fn fold_snapshot_is_kept_only_while_something_is_folded() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = app_with_nested_folds(tmp.path());
+        app.tabs[0].editor.insert_str("// edit\n");
+        app.on_editor_content_changed();
+        assert!(app.tabs[0].fold_lines.is_empty());
+
+        app.fold_all();
+        assert_eq!(app.tabs[0].fold_lines, app.tabs[0].editor.lines());
+        app.unfold_all();
+        assert!(app.tabs[0].fold_lines.is_empty());
+    }
+
+    #[test]
+    fn default_fold_level_folds_nested_blocks_on_open() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("impl.rs");
+        fs::write(
+            &file,
+            "impl S {\n    fn a() {\n        if x {\n            y();\n        }\n    }\n}\n",
+        )
+        .expect("write");
+        let mut app = new_app(tmp.path());
+        app.default_fold_level = 1;
+        app.open_file(file).expect("open");
+
+        assert_eq!(folded_rows(&app), vec![1, 2]);
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.visible_rows_map, vec![0, 1, 6, 7]);
+    }
+
+    #[test]
+    fn default_fold_level_zero_leaves_files_unfolded() {
+        let tmp = tempdir().expect("tempdir");
+        let app = app_with_nested_folds(tmp.path());
+        assert!(folded_rows(&app).is_empty());
+    }
 }
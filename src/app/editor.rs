@@ -1,7 +1,8 @@
-use super::App;
+use super::{App, TabDiffState};
 use std::collections::HashSet;
 use std::fs;
 use std::io;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
@@ -10,15 +11,29 @@ use ratatui::style::Style;
 use serde_json::json;
 use ratatui_textarea::TextArea;
 
+use crate::diff::{side_by_side_rows, unified_diff};
+use crate::emmet::{EmmetError, MAX_LINES, expand_abbreviation};
 use crate::keybinds::{KeyAction, KeyScope};
-use crate::persistence::autosave_path_for;
-use crate::syntax::syntax_lang_for_path;
-use crate::tab::Tab;
-use crate::types::{EditorContextAction, Focus};
+use crate::markdown::{
+    count_words, format_markdown_table, is_list_item, markdown_list_continuation,
+    reflow_paragraphs, set_list_item, split_markdown_prefix, toggle_task_checkbox,
+};
+use crate::persistence::{FileAttributes, remove_autosave};
+use crate::spellcheck::prose_words;
+use crate::syntax::{SyntaxLang, comment_start_for_lang, syntax_lang_with_associations};
+use crate::tab::{ClosedTab, DiffLine, DiffRowKind, NavLocation, Tab};
+use crate::types::{
+    EditorContextAction, Focus, LineContinuation, PendingAction, PromptMode, PromptState,
+};
 use crate::util::{
-    comment_prefix_for_path, compute_fold_ranges, compute_git_line_status, editor_context_actions,
-    inside, leading_indent_bytes, relative_path, text_to_lines, to_u16_saturating,
+    char_at_display_col, comment_continuation, comment_prefix_for_path, compute_fold_ranges,
+    compute_git_line_status, convert_indentation, display_col, editor_context_actions,
+    extract_symbols, fold_starts_at_level, fuzzy_score, indent_settings_for_path, inside,
+    leading_indent_bytes, looks_binary, matching_tag, mixed_indent_rows, pending_hint,
+    relative_path, run_formatter, spaced_screen_rows, spaced_text_rows, tab_label,
+    tab_scroll_offset_for, text_in_range, text_to_lines, to_u16_saturating,
 };
+use unicode_width::UnicodeWidthStr;
 
 impl App {
     pub(crate) fn duplicate_current_line(&mut self, above: bool) {
@@ -49,7 +64,11 @@ impl App {
             self.set_status("No file open");
             return;
         };
-        let Some(prefix) = comment_prefix_for_path(&tab.path) else {
+        let prefix = match tab.lang_override {
+            Some(lang) => comment_start_for_lang(lang),
+            None => comment_prefix_for_path(&tab.path),
+        };
+        let Some(prefix) = prefix else {
             self.set_status("No comment style for file type");
             return;
         };
@@ -102,6 +121,74 @@ impl App {
         self.set_status("Toggled comment");
     }
 
+    /// Replace the Emmet abbreviation ending at the cursor with the HTML it
+    /// expands to, as one undo step, leaving the cursor in the first empty
+    /// element.
+    pub(crate) fn expand_emmet(&mut self) {
+        let Some(tab) = self.active_tab_mut() else {
+            self.set_status("No file open");
+            return;
+        };
+        if tab.syntax_lang() != SyntaxLang::HtmlXml {
+            self.set_status("Emmet expansion works in HTML/XML files only");
+            return;
+        }
+        let (row, col) = tab.editor.cursor();
+        let line = tab.editor.lines()[row].clone();
+        let before: String = line.chars().take(col).collect();
+        let abbr = before
+            .rsplit(char::is_whitespace)
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let expanded = match expand_abbreviation(&abbr, &tab.indent.unit()) {
+            Ok(expanded) => expanded,
+            Err(_) if abbr.is_empty() => {
+                self.set_status("No Emmet abbreviation before the cursor");
+                return;
+            }
+            Err(EmmetError::Malformed) => {
+                self.set_status(format!("Not an Emmet abbreviation: {abbr}"));
+                return;
+            }
+            Err(EmmetError::TooLarge) => {
+                self.set_status(format!(
+                    "{abbr} expands to more than {MAX_LINES} lines; not expanded"
+                ));
+                return;
+            }
+        };
+        let base = line[..leading_indent_bytes(&line)].to_string();
+        let caret = expanded
+            .iter()
+            .enumerate()
+            .find_map(|(i, l)| l.find("></").map(|end| (i, l[..=end].chars().count())));
+        let start_col = col - abbr.chars().count();
+
+        tab.editor.cancel_selection();
+        tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+            to_u16_saturating(row),
+            to_u16_saturating(start_col),
+        ));
+        let mut steps = usize::from(tab.editor.delete_str(abbr.chars().count()));
+        steps += usize::from(tab.editor.insert_str(expanded.join(&format!("\n{base}"))));
+        if let Some((i, c)) = caret {
+            let caret_col = if i == 0 {
+                start_col + c
+            } else {
+                base.chars().count() + c
+            };
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(row + i),
+                to_u16_saturating(caret_col),
+            ));
+        }
+        tab.group_last_edits(steps);
+        self.on_editor_content_changed();
+        self.sync_editor_scroll_guess();
+        self.set_status(format!("Expanded {abbr}"));
+    }
+
     pub(crate) fn dedent_lines(&mut self) {
         let Some(tab) = self.active_tab() else {
             return;
@@ -118,16 +205,17 @@ impl App {
             return;
         }
         let end_row = end_row.min(lines.len().saturating_sub(1));
+        let width = tab.indent.width as usize;
         let mut changed = false;
         for line in lines.iter_mut().take(end_row + 1).skip(start_row) {
-            if line.starts_with("    ") {
-                *line = line[4..].to_string();
+            if line.starts_with(&" ".repeat(width)) {
+                *line = line[width..].to_string();
                 changed = true;
             } else if line.starts_with('\t') {
                 *line = line[1..].to_string();
                 changed = true;
             } else {
-                // Remove any leading spaces (less than 4)
+                // Remove any leading spaces (less than one level)
                 let spaces = line.len() - line.trim_start_matches(' ').len();
                 if spaces > 0 {
                     *line = line[spaces..].to_string();
@@ -137,13 +225,188 @@ impl App {
         }
         if changed {
             let (row, col) = self.tabs[self.active_tab].editor.cursor();
-            let new_col = col.saturating_sub(4);
+            let new_col = col.saturating_sub(width);
             self.replace_editor_text(lines, (row, new_col));
             self.on_editor_content_changed();
             self.set_status("Dedented");
         }
     }
 
+    /// Break the line at the cursor, carrying its indentation over and adding
+    /// one level after an opening bracket.
+    pub(crate) fn insert_newline_with_indent(&mut self) {
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        let (row, col) = tab.editor.cursor();
+        let line = tab.editor.lines()[row].clone();
+        let base = line[..leading_indent_bytes(&line).min(col)].to_string();
+        let before: String = line.chars().take(col).collect();
+        let opener = before.trim_end().chars().last();
+        let closer = line.chars().nth(col);
+        let mut indent = base.clone();
+        if matches!(opener, Some('{' | '(' | '[')) {
+            indent.push_str(&tab.indent.unit());
+        }
+        tab.editor.insert_newline();
+        tab.editor.insert_str(&indent);
+        if matches!(
+            (opener, closer),
+            (Some('{'), Some('}')) | (Some('('), Some(')')) | (Some('['), Some(']'))
+        ) {
+            // Put the closing bracket on its own line below the cursor.
+            tab.editor.insert_newline();
+            tab.editor.insert_str(&base);
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Up);
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::End);
+        }
+        self.on_editor_content_changed();
+        self.sync_editor_scroll_guess();
+    }
+
+    /// Enter on a Markdown list item starts the next item and on an empty
+    /// item ends the list; on a line comment it carries the comment marker
+    /// over. Returns false when a plain newline should be inserted.
+    pub(crate) fn continue_list_or_comment(&mut self) -> bool {
+        let Some(tab) = self.active_tab_mut() else {
+            return false;
+        };
+        if tab.editor.selection_range().is_some() {
+            return false;
+        }
+        let (row, col) = tab.editor.cursor();
+        let line = &tab.editor.lines()[row];
+        let lang = tab.syntax_lang();
+        let continuation = if lang == SyntaxLang::Markdown {
+            markdown_list_continuation(line)
+        } else {
+            let prefix = match tab.lang_override {
+                Some(lang) => comment_start_for_lang(lang),
+                None => comment_prefix_for_path(&tab.path),
+            };
+            prefix
+                .filter(|p| *p != "/*")
+                .and_then(|p| comment_continuation(line, p))
+                .map(LineContinuation::Prefix)
+        };
+        match continuation {
+            Some(LineContinuation::Prefix(prefix)) if col >= prefix.trim_end().chars().count() => {
+                if tab.editor.insert_str(format!("\n{prefix}")) {
+                    self.on_editor_content_changed();
+                    self.sync_editor_scroll_guess();
+                }
+                true
+            }
+            Some(LineContinuation::Exit(text)) if col == line.chars().count() => {
+                let end = text.chars().count();
+                self.replace_rows(row..=row, &[text], (row, end));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Backspace within space indentation deletes back to the previous indent
+    /// stop. Returns false when a plain one-character backspace should run.
+    pub(crate) fn soft_tab_backspace(&mut self) -> bool {
+        let Some(tab) = self.active_tab_mut() else {
+            return false;
+        };
+        if tab.indent.hard_tabs || tab.editor.selection_range().is_some() {
+            return false;
+        }
+        let (row, col) = tab.editor.cursor();
+        let in_indent = tab.editor.lines()[row].chars().take(col).all(|c| c == ' ');
+        if col == 0 || !in_indent {
+            return false;
+        }
+        let count = (col - 1) % tab.indent.width.max(1) as usize + 1;
+        tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+            to_u16_saturating(row),
+            to_u16_saturating(col - count),
+        ));
+        tab.editor.delete_str(count);
+        self.on_editor_content_changed();
+        self.sync_editor_scroll_guess();
+        true
+    }
+
+    /// Rewrite the active buffer's leading whitespace as tabs or spaces and
+    /// switch the tab's indent style to match.
+    pub(crate) fn convert_indentation(&mut self, to_tabs: bool) {
+        if self.refuse_read_only_edit() {
+            return;
+        }
+        let Some(tab) = self.active_tab_mut() else {
+            self.set_status("No file open");
+            return;
+        };
+        tab.indent.hard_tabs = to_tabs;
+        let label = tab.indent.label();
+        let lines = convert_indentation(tab.editor.lines(), to_tabs, tab.indent.width as usize);
+        let changed = lines != tab.editor.lines();
+        let cursor = tab.editor.cursor();
+        self.replace_editor_text(lines, cursor);
+        if changed {
+            self.on_editor_content_changed();
+        }
+        self.set_status(format!("Indentation: {label}"));
+    }
+
+    /// Report lines of the active tab whose indentation mixes tabs and
+    /// spaces, when that lint is enabled.
+    pub(crate) fn warn_mixed_indent(&mut self) {
+        if !self.lint_mixed_indent {
+            return;
+        }
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let rows = mixed_indent_rows(tab.editor.lines());
+        let Some(first) = rows.first() else {
+            return;
+        };
+        self.set_status(format!(
+            "Mixed tabs and spaces in indentation on {} line(s), first at line {}",
+            rows.len(),
+            first + 1
+        ));
+    }
+
+    /// Rewrite the indentation of lines mixing tabs and spaces in the tab's
+    /// configured style, leaving consistently indented lines alone.
+    pub(crate) fn fix_mixed_indentation(&mut self) {
+        if self.refuse_read_only_edit() {
+            return;
+        }
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return;
+        };
+        let rows = mixed_indent_rows(tab.editor.lines());
+        if rows.is_empty() {
+            self.set_status("No mixed indentation");
+            return;
+        }
+        let converted = convert_indentation(
+            tab.editor.lines(),
+            tab.indent.hard_tabs,
+            tab.indent.width as usize,
+        );
+        let mut lines = tab.editor.lines().to_vec();
+        for &row in &rows {
+            lines[row] = converted[row].clone();
+        }
+        let cursor = tab.editor.cursor();
+        let label = tab.indent.label();
+        self.replace_editor_text(lines, cursor);
+        self.on_editor_content_changed();
+        self.set_status(format!(
+            "Fixed indentation on {} line(s): {label}",
+            rows.len()
+        ));
+    }
+
     pub(crate) fn replace_editor_text(&mut self, lines: Vec<String>, cursor: (usize, usize)) {
         let mut ta = TextArea::from(lines);
         ta.set_cursor_line_style(Style::default().bg(self.active_theme().bg_alt));
@@ -153,7 +416,10 @@ impl App {
             to_u16_saturating(cursor.1),
         ));
         if let Some(tab) = self.active_tab_mut() {
+            tab.indent.apply_to(&mut ta);
             tab.editor = ta;
+            tab.recount_chars();
+            tab.clear_undo_groups();
         }
         self.recompute_folds();
         self.sync_editor_scroll_guess();
@@ -269,9 +535,12 @@ impl App {
         if text.is_empty() {
             return;
         }
-        if self.active_tab_mut().is_none() {
+        if self.active_tab_mut().is_none() || self.refuse_read_only_edit() {
             return;
         }
+        if let Some(tab) = self.active_tab_mut() {
+            tab.end_typing_run();
+        }
         let inserted = self
             .active_tab_mut()
             .is_some_and(|t| t.editor.insert_str(&text));
@@ -282,6 +551,9 @@ impl App {
     }
 
     pub(crate) fn paste_from_clipboard(&mut self) {
+        if self.refuse_read_only_edit() {
+            return;
+        }
         let mut from_system = false;
         if let Some(clipboard) = self.clipboard.as_mut() {
             if let Ok(text) = clipboard.get_text() {
@@ -305,6 +577,54 @@ impl App {
         }
     }
 
+    /// Text for a middle-click paste: the primary selection where the
+    /// platform has one, else the system clipboard, else the yank buffer.
+    pub(crate) fn middle_click_text(&mut self) -> Option<String> {
+        if let Some(clipboard) = self.clipboard.as_mut() {
+            #[cfg(all(
+                unix,
+                not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+            ))]
+            {
+                use arboard::{GetExtLinux, LinuxClipboardKind};
+                if let Ok(text) = clipboard
+                    .get()
+                    .clipboard(LinuxClipboardKind::Primary)
+                    .text()
+                    && !text.is_empty()
+                {
+                    return Some(text);
+                }
+            }
+            if let Ok(text) = clipboard.get_text()
+                && !text.is_empty()
+            {
+                return Some(text);
+            }
+        }
+        let yanked = self.active_tab()?.editor.yank_text();
+        (!yanked.is_empty()).then_some(yanked)
+    }
+
+    /// Insert `text` at (`row`, `col`), leaving the cursor after it.
+    pub(crate) fn paste_at(&mut self, row: usize, col: usize, text: &str) {
+        if self.active_tab_mut().is_none() || self.refuse_read_only_edit() {
+            return;
+        }
+        let inserted = self.active_tab_mut().is_some_and(|tab| {
+            tab.editor.cancel_selection();
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(row),
+                to_u16_saturating(col),
+            ));
+            tab.editor.insert_str(text)
+        });
+        if inserted {
+            self.on_editor_content_changed();
+            self.set_status("Pasted");
+        }
+    }
+
     pub(crate) fn open_file(&mut self, path: PathBuf) -> io::Result<()> {
         self.open_file_as(path, false)
     }
@@ -324,19 +644,194 @@ impl App {
         }
 
         let bytes = fs::read(&path)?;
-        if bytes.iter().take(8192).any(|&b| b == 0) {
+        if looks_binary(&bytes) {
+            self.set_status(pending_hint(
+                &PendingAction::OpenWith {
+                    path: path.clone(),
+                    as_preview,
+                },
+                self.confirm_key,
+            ));
+            self.pending = PendingAction::OpenWith { path, as_preview };
+            return Ok(());
+        }
+        self.open_bytes_in_tab(path, &bytes, as_preview, false);
+        Ok(())
+    }
+
+    /// Answer to the open-with prompt: load the binary file into a
+    /// read-only tab anyway.
+    pub(crate) fn force_open_as_text(&mut self, path: PathBuf, as_preview: bool) -> io::Result<()> {
+        let bytes = fs::read(&path)?;
+        self.open_bytes_in_tab(path, &bytes, as_preview, true);
+        Ok(())
+    }
+
+    fn open_bytes_in_tab(
+        &mut self,
+        path: PathBuf,
+        bytes: &[u8],
+        as_preview: bool,
+        binary_text: bool,
+    ) {
+        let text = String::from_utf8_lossy(bytes).to_string();
+        let mut tab = self.build_tab(path.clone(), text);
+        let meta = fs::metadata(&path).ok();
+        let write_protected = meta.as_ref().is_some_and(|m| m.permissions().readonly());
+        let read_only =
+            write_protected || self.file_attributes.get(&path).is_some_and(|a| a.read_only);
+        tab.is_preview = as_preview;
+        tab.binary_text = binary_text;
+        tab.read_only = binary_text || read_only;
+        tab.disk_mtime = meta.and_then(|m| m.modified().ok());
+        tab.git_line_status = compute_git_line_status(&self.root, &path, tab.editor.lines().len());
+        if self.default_fold_level > 0 {
+            tab.folded_starts = fold_starts_at_level(&tab.fold_ranges, self.default_fold_level)
+                .into_iter()
+                .collect();
+        }
+
+        // If opening as preview, replace existing preview tab
+        if as_preview {
+            if let Some(idx) = self.tabs.iter().position(|t| t.is_preview && !t.dirty) {
+                self.remove_tab_at(idx);
+                // Insert new tab at the same position
+                self.tabs.insert(idx, tab);
+                for i in &mut self.tab_mru {
+                    if *i >= idx {
+                        *i += 1;
+                    }
+                }
+                self.active_tab = idx;
+            } else {
+                self.tabs.push(tab);
+                self.active_tab = self.tabs.len() - 1;
+            }
+        } else {
+            self.tabs.push(tab);
+            self.active_tab = self.tabs.len() - 1;
+        }
+        self.touch_tab_mru(self.active_tab);
+        self.evict_tabs_over_limit();
+        if self.default_fold_level > 0 {
+            self.rebuild_visible_rows();
+        }
+
+        self.focus = Focus::Editor;
+        self.completion.reset();
+        self.ensure_active_tab_visible();
+        let lsp_was_unavailable = self.lsp_unavailable.is_some();
+        self.ensure_lsp_for_path(&path);
+        self.check_recovery_for_open_file();
+        if binary_text {
             self.set_status(format!(
-                "Cannot open binary file: {}",
+                "Opened {} as text (read-only)",
                 relative_path(&self.root, &path).display()
             ));
-            return Ok(());
+        } else if read_only {
+            self.set_status(format!(
+                "Opened {} (read-only)",
+                relative_path(&self.root, &path).display()
+            ));
+        } else {
+            self.set_status(format!(
+                "Opened {}",
+                relative_path(&self.root, &path).display()
+            ));
+            self.warn_mixed_indent();
+        }
+        // A server that just failed to start is reported over the open message.
+        if !lsp_was_unavailable && let Some(reason) = self.lsp_unavailable.clone() {
+            self.set_status(reason);
+        }
+    }
+
+    /// Refuse an edit to a read-only tab with a status message. Returns
+    /// whether the edit was refused.
+    pub(crate) fn refuse_read_only_edit(&mut self) -> bool {
+        match self.active_tab() {
+            Some(t) if t.binary_text => {
+                self.set_status("Read-only: file was opened as text from a binary");
+            }
+            Some(t) if t.read_only => {
+                self.set_status("Read-only: use Toggle Read-Only to edit");
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Drop the selection and search highlight in the active tab.
+    pub(crate) fn clear_editor_highlights(&mut self) {
+        if let Some(tab) = self.active_tab_mut() {
+            tab.editor.cancel_selection();
+            let _ = tab.editor.set_search_pattern("");
+        }
+    }
+
+    /// Flip the active tab between read-only and editable. Binaries opened
+    /// as text stay read-only.
+    pub(crate) fn toggle_read_only(&mut self) {
+        let Some(tab) = self.active_tab_mut() else {
+            self.set_status("No file open");
+            return;
+        };
+        if tab.binary_text {
+            self.set_status("Read-only: file was opened as text from a binary");
+            return;
+        }
+        // A read-only tab is never saved, so its unsaved edits would be stuck.
+        if !tab.read_only && tab.dirty {
+            self.set_status("Save or revert changes before making the tab read-only");
+            return;
+        }
+        tab.read_only = !tab.read_only;
+        if tab.read_only {
+            self.set_status("Read-only mode enabled");
+        } else {
+            self.set_status("Read-only mode disabled");
+        }
+        self.remember_file_attributes();
+    }
+
+    /// Record the active tab's language override and read-only flag so they
+    /// come back the next time its file is opened.
+    fn remember_file_attributes(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        if tab.untitled || tab.binary_text {
+            return;
+        }
+        let path = tab.path.clone();
+        let attrs = FileAttributes {
+            lang: tab.lang_override.map(|lang| lang.config_key().to_string()),
+            read_only: tab.read_only,
+        };
+        if attrs == FileAttributes::default() {
+            self.file_attributes.remove(&path);
+        } else {
+            self.file_attributes.insert(path, attrs);
         }
-        let text = String::from_utf8_lossy(&bytes).to_string();
+        self.persist_state();
+    }
+
+    /// Fresh tab holding `text`, with folds and visible rows computed for
+    /// `path`'s language.
+    fn build_tab(&self, path: PathBuf, text: String) -> Tab {
         let mut ta = TextArea::from(text_to_lines(&text));
         ta.set_cursor_line_style(Style::default().bg(self.active_theme().bg_alt));
         ta.set_selection_style(Style::default().bg(self.active_theme().selection));
+        let indent = indent_settings_for_path(&path, &self.root, self.default_indent);
+        indent.apply_to(&mut ta);
 
-        let lang = syntax_lang_for_path(Some(path.as_path()));
+        let detected_lang = syntax_lang_with_associations(&path, &self.file_associations);
+        let lang_override = self
+            .file_attributes
+            .get(&path)
+            .and_then(|attrs| attrs.lang.as_deref())
+            .and_then(SyntaxLang::from_config_key);
+        let lang = lang_override.unwrap_or(detected_lang);
         let (fold_ranges, bracket_depths) = compute_fold_ranges(ta.lines(), lang);
         let mut visible_rows_map = Vec::new();
         let mut visible_row_starts = Vec::new();
@@ -352,97 +847,486 @@ impl App {
             visible_row_ends.push(0);
         }
 
-        let git_line_status = compute_git_line_status(&self.root, &path, ta.lines().len());
-
-        let tab = Tab {
-            path: path.clone(),
-            is_preview: as_preview,
+        let char_count = ta.lines().iter().map(|l| l.chars().count()).sum();
+        Tab {
+            path,
+            untitled: false,
+            lang_override,
+            detected_lang,
+            indent,
+            is_preview: false,
+            read_only: false,
+            pinned: false,
+            binary_text: false,
             editor: ta,
+            char_count,
             dirty: false,
             open_disk_snapshot: Some(text),
+            disk_mtime: None,
             editor_scroll_row: 0,
             editor_scroll_col: 0,
             fold_ranges,
             bracket_depths,
             folded_starts: HashSet::new(),
+            fold_lines: Vec::new(),
             visible_rows_map,
             visible_row_starts,
             visible_row_ends,
             open_doc_uri: None,
             open_doc_version: 0,
+            lsp_synced_text: String::new(),
             diagnostics: Vec::new(),
+            pending_diagnostics: None,
             conflict_prompt_open: false,
             conflict_disk_text: None,
             recovery_prompt_open: false,
             recovery_text: None,
-            git_line_status,
-        };
-
-        // If opening as preview, replace existing preview tab
-        if as_preview {
-            if let Some(idx) = self.tabs.iter().position(|t| t.is_preview) {
-                self.close_tab_at(idx);
-                // Insert new tab at the same position
-                self.tabs.insert(idx, tab);
-                self.active_tab = idx;
-            } else {
-                self.tabs.push(tab);
-                self.active_tab = self.tabs.len() - 1;
-            }
-        } else {
-            self.tabs.push(tab);
-            self.active_tab = self.tabs.len() - 1;
+            prompt_diff: Vec::new(),
+            git_line_status: Vec::new(),
+            last_edit: None,
+            auto_save_failed: false,
+            last_edit_pos: None,
+            undo_groups: Vec::new(),
+            redo_groups: Vec::new(),
+            typing_run: None,
         }
+    }
 
+    /// Open an empty scratch tab that isn't backed by a file until saved.
+    pub(crate) fn new_untitled_tab(&mut self) {
+        self.untitled_count += 1;
+        let name = format!("untitled-{}", self.untitled_count);
+        let mut tab = self.build_tab(PathBuf::from(&name), String::new());
+        tab.untitled = true;
+        tab.open_disk_snapshot = None;
+        self.tabs.push(tab);
+        self.active_tab = self.tabs.len() - 1;
+        self.touch_tab_mru(self.active_tab);
+        self.evict_tabs_over_limit();
         self.focus = Focus::Editor;
         self.completion.reset();
-        self.ensure_lsp_for_path(&path);
-        self.check_recovery_for_open_file();
-        self.set_status(format!(
-            "Opened {}",
-            relative_path(&self.root, &path).display()
-        ));
-        Ok(())
+        self.ensure_active_tab_visible();
+        self.set_status(format!("Opened {name}"));
     }
 
-    pub(crate) fn save_file(&mut self) -> io::Result<()> {
-        let Some(tab) = self.active_tab_mut() else {
+    pub(crate) fn open_language_picker(&mut self) {
+        let Some(tab) = self.active_tab() else {
             self.set_status("No file open");
-            return Ok(());
+            return;
         };
-        let path = tab.path.clone();
-        let mut content = tab.editor.lines().join("\n");
-        // Ensure file ends with a trailing newline (POSIX convention)
-        if !content.ends_with('\n') {
-            content.push('\n');
-        }
-        fs::write(&path, &content)?;
-        tab.dirty = false;
-        tab.open_disk_snapshot = Some(content);
-        tab.conflict_prompt_open = false;
-        tab.conflict_disk_text = None;
-        self.clear_autosave_for_open_file();
-        // Trigger an immediate async git refresh so the gutter updates promptly
-        self.fs_refresh_pending = true;
-        self.fs_full_refresh_pending = true;
-        self.last_fs_refresh = Instant::now()
-            .checked_sub(Duration::from_millis(Self::FS_REFRESH_DEBOUNCE_MS + 1))
-            .unwrap_or_else(Instant::now);
-        self.set_status(format!(
-            "Saved {}",
-            relative_path(&self.root, &path).display()
-        ));
-        Ok(())
+        self.language_picker.index = tab
+            .lang_override
+            .and_then(|lang| SyntaxLang::ALL.iter().position(|l| *l == lang))
+            .map_or(0, |pos| pos + 1);
+        self.language_picker.open = true;
     }
 
-    pub(crate) fn close_file(&mut self) {
-        if self.tabs.is_empty() {
+    pub(crate) fn apply_language_picker(&mut self) {
+        self.language_picker.open = false;
+        let lang = match self.language_picker.index {
+            0 => None,
+            idx => SyntaxLang::ALL.get(idx - 1).copied(),
+        };
+        self.set_language(lang);
+    }
+
+    /// Override the active tab's language, or go back to detecting it from
+    /// the file extension with `None`.
+    pub(crate) fn set_language(&mut self, lang: Option<SyntaxLang>) {
+        let Some(tab) = self.active_tab_mut() else {
             return;
+        };
+        tab.lang_override = lang;
+        let label = tab.syntax_lang().label();
+        self.recompute_folds();
+        match lang {
+            Some(_) => self.set_status(format!("Language: {label}")),
+            None => self.set_status(format!("Language: {label} (auto-detected)")),
         }
-        self.close_tab_at(self.active_tab);
+        self.remember_file_attributes();
     }
 
+    /// Unified diff from the active tab's text as last read from or written
+    /// to disk to its current buffer.
+    pub(crate) fn unsaved_diff(&self) -> Vec<DiffLine> {
+        let Some(tab) = self.active_tab() else {
+            return Vec::new();
+        };
+        let disk = tab
+            .open_disk_snapshot
+            .as_deref()
+            .map(text_to_lines)
+            .unwrap_or_default();
+        unified_diff(&disk, tab.editor.lines(), 3)
+    }
+
+    pub(crate) fn show_unsaved_diff(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return;
+        };
+        if !tab.dirty {
+            self.set_status("No unsaved changes");
+            return;
+        }
+        self.prompt_diff_scroll = 0;
+        self.unsaved_diff_lines = self.unsaved_diff();
+        self.unsaved_diff_open = true;
+    }
+
+    /// Pick the tab to diff the active one against; with only one other tab
+    /// open the diff is shown straight away.
+    pub(crate) fn open_diff_tab_picker(&mut self) {
+        let others: Vec<usize> = (0..self.tabs.len())
+            .filter(|&i| i != self.active_tab)
+            .collect();
+        match others.as_slice() {
+            [] => self.set_status("Open another tab to diff against"),
+            [other] => self.diff_tabs(self.active_tab, *other),
+            _ => {
+                self.diff_tab_picker.index = self.diff_tab_picker.index.min(others.len() - 1);
+                self.diff_tab_picker.tabs = others;
+                self.diff_tab_picker.open = true;
+            }
+        }
+    }
+
+    pub(crate) fn apply_diff_tab_picker(&mut self) {
+        self.diff_tab_picker.open = false;
+        let picked = self
+            .diff_tab_picker
+            .tabs
+            .get(self.diff_tab_picker.index)
+            .copied();
+        if let Some(other) = picked {
+            self.diff_tabs(self.active_tab, other);
+        }
+    }
+
+    /// Show tab `left` and tab `right` side by side with their differing
+    /// lines aligned.
+    pub(crate) fn diff_tabs(&mut self, left: usize, right: usize) {
+        let (Some(l), Some(r)) = (self.tabs.get(left), self.tabs.get(right)) else {
+            return;
+        };
+        let rows = side_by_side_rows(l.editor.lines(), r.editor.lines());
+        let changed = rows.iter().filter(|r| r.kind != DiffRowKind::Same).count();
+        self.tab_diff = Some(TabDiffState {
+            left: l.path.clone(),
+            right: r.path.clone(),
+            rows,
+            scroll: 0,
+            page: 0,
+        });
+        if changed == 0 {
+            self.set_status("Tabs are identical");
+        } else {
+            self.set_status(format!("{changed} differing row(s)"));
+        }
+    }
+
+    pub(crate) fn open_symbol_picker(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return;
+        };
+        let symbols = extract_symbols(tab.editor.lines(), tab.syntax_lang());
+        if symbols.is_empty() {
+            self.set_status("No symbols found in this file");
+            return;
+        }
+        // The refresh clamps the index kept from the last time.
+        self.symbol_picker.symbols = symbols;
+        self.symbol_picker.query.clear();
+        self.symbol_picker.open = true;
+        self.refresh_symbol_picker_results();
+    }
+
+    pub(crate) fn refresh_symbol_picker_results(&mut self) {
+        let query = self.symbol_picker.query.to_ascii_lowercase();
+        let mut scored: Vec<(usize, usize)> = self
+            .symbol_picker
+            .symbols
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, sym)| fuzzy_score(&query, &sym.label).map(|score| (score, idx)))
+            .collect();
+        // Ties keep file order, so an empty query lists symbols top to bottom.
+        scored.sort();
+        self.symbol_picker.results = scored.into_iter().map(|(_, idx)| idx).collect();
+        self.symbol_picker.index = self
+            .symbol_picker
+            .index
+            .min(self.symbol_picker.results.len().saturating_sub(1));
+    }
+
+    pub(crate) fn apply_symbol_picker(&mut self) {
+        self.symbol_picker.open = false;
+        let Some(symbol) = self
+            .symbol_picker
+            .results
+            .get(self.symbol_picker.index)
+            .and_then(|&idx| self.symbol_picker.symbols.get(idx))
+            .cloned()
+        else {
+            return;
+        };
+        self.push_nav_history();
+        if let Some(tab) = self.active_tab_mut() {
+            let max_line = tab.editor.lines().len().saturating_sub(1);
+            let row = symbol.line.min(max_line);
+            let col = tab.editor.lines()[row]
+                .chars()
+                .take_while(|c| c.is_whitespace())
+                .count();
+            tab.editor.cancel_selection();
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(row),
+                to_u16_saturating(col),
+            ));
+        }
+        self.sync_editor_scroll_guess();
+        self.center_cursor_line();
+        self.set_status(format!("Jumped to {}", symbol.label));
+    }
+
+    pub(crate) fn open_save_as_prompt(&mut self) {
+        self.prompt = Some(PromptState {
+            title: "Save as (relative to project root)".to_string(),
+            value: String::new(),
+            cursor: 0,
+            mode: PromptMode::SaveAs,
+            history: None,
+        });
+    }
+
+    /// Give the active untitled tab a real file at `value` and save it there.
+    pub(crate) fn save_untitled_as(&mut self, value: &str) -> io::Result<()> {
+        let value = value.trim();
+        if value.is_empty() {
+            self.set_status("Path cannot be empty");
+            return Ok(());
+        }
+        if !self.active_tab().is_some_and(|t| t.untitled) {
+            self.set_status("No untitled buffer to save");
+            return Ok(());
+        }
+        let target = self.root.join(value);
+        if target.exists() {
+            self.set_status("File already exists");
+            return Ok(());
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let idx = self.active_tab;
+        self.tabs[idx].path = target.clone();
+        self.tabs[idx].untitled = false;
+        self.tabs[idx].detected_lang =
+            syntax_lang_with_associations(&target, &self.file_associations);
+        self.save_tab_at(idx)?;
+        let line_count = self.tabs[idx].editor.lines().len();
+        self.tabs[idx].git_line_status = compute_git_line_status(&self.root, &target, line_count);
+        self.recompute_folds();
+        self.ensure_active_tab_visible();
+        self.ensure_lsp_for_path(&target);
+        self.rebuild_tree()?;
+        self.set_status(format!(
+            "Saved {}",
+            relative_path(&self.root, &target).display()
+        ));
+        Ok(())
+    }
+
+    pub(crate) fn save_file(&mut self) -> io::Result<()> {
+        if self.refuse_read_only_edit() {
+            return Ok(());
+        }
+        if self.active_tab().is_some_and(|t| t.untitled) {
+            self.open_save_as_prompt();
+            return Ok(());
+        }
+        let Some(path) = self.open_path().cloned() else {
+            self.set_status("No file open");
+            return Ok(());
+        };
+        self.save_tab_at(self.active_tab)?;
+        self.set_status(format!(
+            "Saved {}",
+            relative_path(&self.root, &path).display()
+        ));
+        self.warn_mixed_indent();
+        Ok(())
+    }
+
+    /// Write a tab's buffer to disk and mark it clean, without touching the
+    /// status line.
+    pub(crate) fn save_tab_at(&mut self, idx: usize) -> io::Result<()> {
+        let Some(tab) = self.tabs.get_mut(idx).filter(|t| !t.untitled) else {
+            return Ok(());
+        };
+        let mut content = tab.editor.lines().join("\n");
+        // Ensure file ends with a trailing newline (POSIX convention)
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        fs::write(&tab.path, &content)?;
+        tab.dirty = false;
+        tab.open_disk_snapshot = Some(content);
+        tab.disk_mtime = fs::metadata(&tab.path).and_then(|m| m.modified()).ok();
+        tab.conflict_prompt_open = false;
+        tab.conflict_disk_text = None;
+        if let Some(dir) = &self.autosave_dir {
+            remove_autosave(dir, &tab.path);
+        }
+        // Trigger an immediate async git refresh so the gutter updates promptly
+        self.fs_refresh_pending = true;
+        self.fs_full_refresh_pending = true;
+        self.last_fs_refresh = Instant::now()
+            .checked_sub(Duration::from_millis(Self::FS_REFRESH_DEBOUNCE_MS + 1))
+            .unwrap_or_else(Instant::now);
+        Ok(())
+    }
+
+    /// Save every dirty tab, then quit. Stays open if any save fails.
+    /// Read-only tabs are never written.
+    pub(crate) fn save_all_and_quit(&mut self) -> io::Result<()> {
+        for i in 0..self.tabs.len() {
+            if self.tabs[i].dirty && !self.tabs[i].read_only {
+                self.save_tab_at(i)?;
+            }
+        }
+        // Untitled buffers need a location first; stay open and ask for one.
+        if let Some(idx) = self.tabs.iter().position(|t| t.dirty && t.untitled) {
+            self.switch_to_tab(idx);
+            self.open_save_as_prompt();
+            return Ok(());
+        }
+        self.quit = true;
+        Ok(())
+    }
+
+    /// Drop unsaved edits (and their autosave copies), then quit.
+    pub(crate) fn discard_all_and_quit(&mut self) {
+        if let Some(dir) = &self.autosave_dir {
+            for tab in self.tabs.iter().filter(|t| t.dirty) {
+                remove_autosave(dir, &tab.path);
+            }
+        }
+        self.quit = true;
+    }
+
+    pub(crate) fn close_file(&mut self) {
+        if self.tabs.is_empty() {
+            return;
+        }
+        self.close_tab_at(self.active_tab);
+    }
+
+    /// Index of the tab under a tab-bar column. Separators map to the tab on
+    /// their right and anything past the last visible tab maps to it.
+    pub(crate) fn tab_drop_index(&self, column: u16) -> Option<usize> {
+        if self.tab_rects.is_empty() {
+            return None;
+        }
+        let offset = self.tab_scroll_offset;
+        for (i, (name_rect, close_rect)) in self.tab_rects.iter().enumerate() {
+            if column < name_rect.x || column < close_rect.x + close_rect.width {
+                return Some(offset + i);
+            }
+        }
+        Some(offset + self.tab_rects.len() - 1)
+    }
+
+    /// Move a tab to a new position, keeping it active.
+    pub(crate) fn move_tab(&mut self, from: usize, to: usize) {
+        if from >= self.tabs.len() || to >= self.tabs.len() || from == to {
+            return;
+        }
+        let tab = self.tabs.remove(from);
+        self.tabs.insert(to, tab);
+        for i in &mut self.tab_mru {
+            if *i == from {
+                *i = to;
+            } else if (from + 1..=to).contains(i) {
+                *i -= 1;
+            } else if (to..from).contains(i) {
+                *i += 1;
+            }
+        }
+        self.active_tab = to;
+        self.ensure_active_tab_visible();
+        self.set_status(format!("Moved tab to position {}", to + 1));
+    }
+
+    /// Scroll the tab bar so the active tab is fully visible.
+    pub(crate) fn ensure_active_tab_visible(&mut self) {
+        if self.tabs.is_empty() {
+            self.tab_scroll_offset = 0;
+            return;
+        }
+        if self.tab_bar_width == 0 {
+            return;
+        }
+        let widths: Vec<usize> = self.tabs.iter().map(|t| tab_label(t).width()).collect();
+        self.tab_scroll_offset = tab_scroll_offset_for(
+            &widths,
+            self.tab_scroll_offset,
+            self.active_tab,
+            self.tab_bar_width,
+        );
+    }
+
+    pub(crate) fn scroll_tab_bar(&mut self, forward: bool) {
+        if forward {
+            if self.tab_scroll_offset + 1 < self.tabs.len() {
+                self.tab_scroll_offset += 1;
+            }
+        } else {
+            self.tab_scroll_offset = self.tab_scroll_offset.saturating_sub(1);
+        }
+    }
+
+    /// Close a tab, remembering it so it can be reopened. Tabs whose file no
+    /// longer exists on disk are not remembered.
     pub(crate) fn close_tab_at(&mut self, idx: usize) {
+        let Some(tab) = self.tabs.get(idx) else {
+            return;
+        };
+        if !tab.untitled && tab.path.is_file() {
+            let closed = ClosedTab {
+                path: tab.path.clone(),
+                cursor: tab.editor.cursor(),
+                scroll_row: tab.editor_scroll_row,
+                scroll_col: tab.editor_scroll_col,
+            };
+            self.closed_tabs.retain(|c| c.path != closed.path);
+            self.closed_tabs.push(closed);
+            if self.closed_tabs.len() > Self::MAX_CLOSED_TABS {
+                self.closed_tabs.remove(0);
+            }
+        }
+        self.remove_tab_at(idx);
+    }
+
+    /// Close least recently used tabs until no more than `max_open_tabs`
+    /// remain. Dirty, pinned and active tabs are never closed, so the limit
+    /// is exceeded when nothing else can go.
+    pub(crate) fn evict_tabs_over_limit(&mut self) {
+        if self.max_open_tabs == 0 {
+            return;
+        }
+        while self.tabs.len() > self.max_open_tabs {
+            let Some(idx) = self.tab_mru.iter().rev().copied().find(|&i| {
+                i != self.active_tab && self.tabs.get(i).is_some_and(|t| !t.dirty && !t.pinned)
+            }) else {
+                break;
+            };
+            self.close_tab_at(idx);
+        }
+    }
+
+    fn remove_tab_at(&mut self, idx: usize) {
         if idx >= self.tabs.len() {
             return;
         }
@@ -457,8 +1341,16 @@ impl App {
             );
         }
         // Clear autosave
-        let _ = fs::remove_file(autosave_path_for(&self.tabs[idx].path));
+        if let Some(dir) = &self.autosave_dir {
+            remove_autosave(dir, &self.tabs[idx].path);
+        }
         self.tabs.remove(idx);
+        self.tab_mru.retain(|&i| i != idx);
+        for i in &mut self.tab_mru {
+            if *i > idx {
+                *i -= 1;
+            }
+        }
         if self.tabs.is_empty() {
             self.active_tab = 0;
             self.focus = Focus::Tree;
@@ -469,122 +1361,596 @@ impl App {
         } else if self.active_tab > idx {
             self.active_tab -= 1;
         }
+        self.ensure_active_tab_visible();
     }
-    pub(crate) fn handle_help_key(&mut self, key: KeyEvent) -> io::Result<()> {
-        let is_help_key = self.keybinds.lookup(&key, KeyScope::Global) == Some(KeyAction::Help);
-        match (key.modifiers, key.code) {
-            (_, KeyCode::Esc) => {
-                self.help_open = false;
-            }
-            _ if is_help_key => {
-                self.help_open = false;
-            }
-            _ => {}
+
+    fn current_nav_location(&self) -> Option<NavLocation> {
+        self.active_tab().map(|tab| NavLocation {
+            path: tab.path.clone(),
+            cursor: tab.editor.cursor(),
+        })
+    }
+
+    /// Remember where the cursor is before a big jump, so `navigate_back`
+    /// can return to it. A new jump drops the forward history.
+    pub(crate) fn push_nav_history(&mut self) {
+        let Some(here) = self.current_nav_location() else {
+            return;
+        };
+        self.nav_forward.clear();
+        if self.nav_back.last() == Some(&here) {
+            return;
+        }
+        self.nav_back.push(here);
+        if self.nav_back.len() > Self::MAX_NAV_HISTORY {
+            self.nav_back.remove(0);
         }
-        Ok(())
     }
 
-    pub(crate) fn handle_editor_context_menu_key(&mut self, key: KeyEvent) -> io::Result<()> {
-        match (key.modifiers, key.code) {
-            (_, KeyCode::Esc) => {
-                self.editor_context_menu_open = false;
-            }
-            (_, KeyCode::Down) | (_, KeyCode::Char('j')) => {
-                if self.editor_context_menu_index < editor_context_actions().len().saturating_sub(1)
-                {
-                    self.editor_context_menu_index += 1;
-                }
+    pub(crate) fn navigate_back(&mut self) -> io::Result<()> {
+        self.step_nav_history(false)
+    }
+
+    pub(crate) fn navigate_forward(&mut self) -> io::Result<()> {
+        self.step_nav_history(true)
+    }
+
+    /// Move to the next entry of the back or forward history, pushing the
+    /// current position onto the other one. Entries that point at the current
+    /// position or at a file that is gone are skipped.
+    fn step_nav_history(&mut self, forward: bool) -> io::Result<()> {
+        let here = self.current_nav_location();
+        loop {
+            let next = if forward {
+                self.nav_forward.pop()
+            } else {
+                self.nav_back.pop()
+            };
+            let Some(target) = next else {
+                self.set_status(if forward {
+                    "No forward history"
+                } else {
+                    "No back history"
+                });
+                return Ok(());
+            };
+            if here.as_ref() == Some(&target) {
+                continue;
             }
-            (_, KeyCode::Up) | (_, KeyCode::Char('k')) => {
-                if self.editor_context_menu_index > 0 {
-                    self.editor_context_menu_index -= 1;
+            if self.go_to_nav_location(&target)? {
+                if let Some(here) = here {
+                    let other = if forward {
+                        &mut self.nav_back
+                    } else {
+                        &mut self.nav_forward
+                    };
+                    other.push(here);
                 }
+                self.set_status(format!(
+                    "{} to {}:{}",
+                    if forward { "Forward" } else { "Back" },
+                    relative_path(&self.root, &target.path).display(),
+                    target.cursor.0 + 1
+                ));
+                return Ok(());
             }
-            (_, KeyCode::Enter) => {
-                let action = editor_context_actions()[self.editor_context_menu_index];
-                self.apply_editor_context_action(action);
-            }
-            _ => {}
         }
-        Ok(())
     }
 
-    pub(crate) fn apply_editor_context_action(&mut self, action: EditorContextAction) {
-        self.editor_context_menu_open = false;
-        self.focus = Focus::Editor;
-        match action {
-            EditorContextAction::Copy => self.copy_selection_to_clipboard(),
-            EditorContextAction::Cut => self.cut_selection_to_clipboard(),
-            EditorContextAction::Paste => self.paste_from_clipboard(),
-            EditorContextAction::SelectAll => {
-                if let Some(tab) = self.active_tab_mut() {
-                    tab.editor.select_all();
-                }
-                self.set_status("Selected all");
-            }
-            EditorContextAction::Cancel => {}
+    /// Jump to where the most recent edit left the cursor, switching to its
+    /// tab or reopening its file.
+    pub(crate) fn goto_last_edit(&mut self) -> io::Result<()> {
+        let Some(target) = self.last_edit_location.clone() else {
+            self.set_status("No edits yet");
+            return Ok(());
+        };
+        self.push_nav_history();
+        if self.go_to_nav_location(&target)? {
+            self.set_status(format!(
+                "Last edit: {}:{}",
+                relative_path(&self.root, &target.path).display(),
+                target.cursor.0 + 1
+            ));
+        } else {
+            self.set_status("File of the last edit is no longer available");
         }
+        Ok(())
     }
 
-    pub(crate) fn sync_editor_scroll_guess(&mut self) {
+    /// Move the cursor to the partner of the HTML/XML tag under it.
+    pub(crate) fn goto_matching_tag(&mut self) {
         let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
             return;
         };
-        let (cursor_row, cursor_col) = tab.editor.cursor();
-        let inner_height = self.editor_rect.height.saturating_sub(2) as usize;
-        if inner_height == 0 {
-            if let Some(tab) = self.active_tab_mut() {
-                tab.editor_scroll_row = 0;
-            }
+        if tab.syntax_lang() != SyntaxLang::HtmlXml {
+            self.set_status("Tag matching works in HTML/XML files only");
             return;
         }
-        if self
-            .active_tab()
-            .is_some_and(|t| t.visible_rows_map.is_empty())
-        {
-            self.rebuild_visible_rows();
-        }
-        let cursor_visible = self.visible_index_of_source_position(cursor_row, cursor_col);
-        let Some(tab) = self.active_tab_mut() else {
+        let Some([_, (row, col, _)]) = matching_tag(tab.editor.lines(), tab.editor.cursor()) else {
+            self.set_status("No matching tag at the cursor");
             return;
         };
-        if cursor_visible < tab.editor_scroll_row {
-            tab.editor_scroll_row = cursor_visible;
-        } else if cursor_visible >= tab.editor_scroll_row + inner_height {
-            tab.editor_scroll_row = cursor_visible.saturating_sub(inner_height.saturating_sub(1));
+        self.push_nav_history();
+        if let Some(tab) = self.active_tab_mut() {
+            tab.editor.cancel_selection();
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(row),
+                to_u16_saturating(col),
+            ));
         }
-        self.sync_editor_scroll_col();
+        self.sync_editor_scroll_guess();
+        self.set_status(format!("Matching tag on line {}", row + 1));
     }
 
-    pub(crate) fn sync_editor_scroll_col(&mut self) {
-        if self.word_wrap {
-            return;
+    /// Show `target`, switching to its tab or reopening its file. Returns
+    /// false when neither is possible.
+    fn go_to_nav_location(&mut self, target: &NavLocation) -> io::Result<bool> {
+        if let Some(idx) = self.tabs.iter().position(|t| t.path == target.path) {
+            self.switch_to_tab(idx);
+        } else if target.path.is_file() {
+            self.open_file(target.path.clone())?;
+            if self.open_path() != Some(&target.path) {
+                return Ok(false);
+            }
+        } else {
+            return Ok(false);
         }
-        let Some(tab) = self.active_tab() else {
-            return;
-        };
+        if let Some(tab) = self.active_tab_mut() {
+            let (row, col) = target.cursor;
+            let row = row.min(tab.editor.lines().len().saturating_sub(1));
+            let col = col.min(tab.editor.lines()[row].chars().count());
+            tab.editor.cancel_selection();
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(row),
+                to_u16_saturating(col),
+            ));
+        }
+        self.sync_editor_scroll_guess();
+        Ok(true)
+    }
+
+    /// Reopen the most recently closed tab, restoring its cursor and scroll.
+    pub(crate) fn reopen_closed_tab(&mut self) -> io::Result<()> {
+        while let Some(closed) = self.closed_tabs.pop() {
+            if !closed.path.is_file() {
+                continue;
+            }
+            self.open_file(closed.path.clone())?;
+            if let Some(tab) = self.active_tab_mut() {
+                if tab.path != closed.path {
+                    return Ok(());
+                }
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(closed.cursor.0),
+                    to_u16_saturating(closed.cursor.1),
+                ));
+                tab.editor_scroll_row = closed.scroll_row;
+                tab.editor_scroll_col = closed.scroll_col;
+            }
+            self.set_status(format!(
+                "Reopened {}",
+                relative_path(&self.root, &closed.path).display()
+            ));
+            return Ok(());
+        }
+        self.set_status("No closed tabs to reopen");
+        Ok(())
+    }
+
+    /// Show the word and character count and reading time of a Markdown or
+    /// plain text tab, or of its selection, in the status bar.
+    pub(crate) fn show_word_count(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return;
+        };
+        let lang = tab.syntax_lang();
+        if !matches!(lang, SyntaxLang::Markdown | SyntaxLang::Plain) {
+            self.set_status("Word count is for Markdown and plain text files");
+            return;
+        }
+        let lines = tab.editor.lines();
+        let (scope, text) = match tab.editor.selection_range() {
+            Some((start, end)) => ("Selection", text_in_range(lines, start, end)),
+            None => ("Document", lines.join("\n")),
+        };
+        let words = count_words(&text, lang == SyntaxLang::Markdown);
+        let chars = text.chars().filter(|&c| c != '\n').count();
+        let minutes = words.div_ceil(Self::READING_WORDS_PER_MINUTE);
+        self.set_status(format!(
+            "{scope}: {words} words, {chars} characters, ~{minutes} min read"
+        ));
+    }
+
+    /// Swap `rows` of the active buffer for `new_lines` as one undo step and
+    /// put the cursor at `cursor`.
+    pub(crate) fn replace_rows(
+        &mut self,
+        rows: RangeInclusive<usize>,
+        new_lines: &[String],
+        cursor: (usize, usize),
+    ) {
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        let old_chars = tab.editor.lines()[rows.clone()]
+            .iter()
+            .map(|l| l.chars().count() + 1)
+            .sum::<usize>()
+            - 1;
+        tab.editor.cancel_selection();
+        tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+            to_u16_saturating(*rows.start()),
+            0,
+        ));
+        let mut steps = usize::from(tab.editor.delete_str(old_chars));
+        steps += usize::from(tab.editor.insert_str(new_lines.join("\n")));
+        tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+            to_u16_saturating(cursor.0),
+            to_u16_saturating(cursor.1),
+        ));
+        tab.group_last_edits(steps);
+        self.on_editor_content_changed();
+        self.sync_editor_scroll_guess();
+    }
+
+    /// The rows of the selection, or the cursor row, in a Markdown tab that
+    /// can be edited. Sets the status and returns `None` otherwise.
+    fn markdown_edit_rows(&mut self) -> Option<RangeInclusive<usize>> {
+        if self.refuse_read_only_edit() {
+            return None;
+        }
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return None;
+        };
+        if tab.syntax_lang() != SyntaxLang::Markdown {
+            self.set_status("List toggles work in Markdown files only");
+            return None;
+        }
+        Some(match tab.editor.selection_range() {
+            Some(((start, _), (end, _))) => start..=end,
+            None => {
+                let (row, _) = tab.editor.cursor();
+                row..=row
+            }
+        })
+    }
+
+    /// Apply `edit` to each line in `rows`, keeping the cursor on its text.
+    fn edit_markdown_rows(
+        &mut self,
+        rows: RangeInclusive<usize>,
+        edit: impl Fn(&str) -> String,
+    ) -> bool {
+        let tab = &self.tabs[self.active_tab];
+        let (row, col) = tab.editor.cursor();
+        let old = &tab.editor.lines()[rows.clone()];
+        let new: Vec<String> = old.iter().map(|l| edit(l)).collect();
+        if new == old {
+            return false;
+        }
+        let col = match rows.clone().position(|r| r == row) {
+            Some(i) => {
+                let (before, after) = (old[i].chars().count(), new[i].chars().count());
+                (col + after).saturating_sub(before)
+            }
+            None => col,
+        };
+        self.replace_rows(rows, &new, (row, col));
+        true
+    }
+
+    /// Align the Markdown table around the cursor: the run of adjacent
+    /// lines that contain a `|`.
+    pub(crate) fn format_markdown_table(&mut self) {
+        if self.refuse_read_only_edit() {
+            return;
+        }
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return;
+        };
+        if tab.syntax_lang() != SyntaxLang::Markdown {
+            self.set_status("Table formatting works in Markdown files only");
+            return;
+        }
+        let lines = tab.editor.lines();
+        let (row, col) = tab.editor.cursor();
+        let in_table = |r: usize| lines[r].contains('|');
+        if !in_table(row) {
+            self.set_status("No table at the cursor");
+            return;
+        }
+        let (mut first, mut last) = (row, row);
+        while first > 0 && in_table(first - 1) {
+            first -= 1;
+        }
+        while last + 1 < lines.len() && in_table(last + 1) {
+            last += 1;
+        }
+        let table = format_markdown_table(&lines[first..=last]);
+        if table == lines[first..=last] {
+            self.set_status("Table is already aligned");
+            return;
+        }
+        let col = col.min(table[row - first].chars().count());
+        self.replace_rows(first..=last, &table, (row, col));
+        self.set_status(format!("Formatted table ({} rows)", table.len()));
+    }
+
+    /// Pipe the buffer through the formatter configured for its language and
+    /// put the output in its place as one undo step. On failure the buffer
+    /// is left alone and the formatter's stderr goes to the status bar.
+    pub(crate) fn format_document(&mut self) {
+        if self.refuse_read_only_edit() {
+            return;
+        }
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return;
+        };
+        let lang = tab.syntax_lang();
+        let Some(command) = self.formatters.get(lang.config_key()) else {
+            self.set_status(format!(
+                "No formatter for {} (set formatters.{})",
+                lang.label(),
+                lang.config_key()
+            ));
+            return;
+        };
+        let lines = tab.editor.lines();
+        let mut text = lines.join("\n");
+        // Formatters expect a file as it is saved, newline-terminated.
+        let ends_with_newline = text.ends_with('\n');
+        if !ends_with_newline {
+            text.push('\n');
+        }
+        let file = (!tab.untitled).then_some(tab.path.as_path());
+        let timeout = Duration::from_millis(self.formatter_timeout_ms);
+        let mut formatted = match run_formatter(command, &self.root, file, &text, timeout) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                self.set_status(format!("Formatter failed: {e}"));
+                return;
+            }
+        };
+        if !ends_with_newline && formatted.ends_with('\n') {
+            formatted.pop();
+        }
+        let new_lines = text_to_lines(&formatted);
+        if new_lines == lines {
+            self.set_status("Already formatted");
+            return;
+        }
+        let last = lines.len() - 1;
+        let (row, col) = tab.editor.cursor();
+        let row = row.min(new_lines.len() - 1);
+        let col = col.min(new_lines[row].chars().count());
+        self.replace_rows(0..=last, &new_lines, (row, col));
+        self.set_status(format!("Formatted {}", lang.label()));
+    }
+
+    /// Flip the task checkbox on the cursor line or each selected line.
+    pub(crate) fn toggle_task_checkbox(&mut self) {
+        let Some(rows) = self.markdown_edit_rows() else {
+            return;
+        };
+        self.edit_markdown_rows(rows, toggle_task_checkbox);
+        self.set_status("Toggled task checkbox");
+    }
+
+    /// Turn the cursor line or selected lines into `- ` list items, or back
+    /// into plain text when they all are list items already.
+    pub(crate) fn toggle_list_item(&mut self) {
+        let Some(rows) = self.markdown_edit_rows() else {
+            return;
+        };
+        let lines = &self.tabs[self.active_tab].editor.lines()[rows.clone()];
+        let bullet = !lines
+            .iter()
+            .filter(|l| !l.trim().is_empty())
+            .all(|l| is_list_item(l));
+        if !self.edit_markdown_rows(rows, |l| set_list_item(l, bullet)) {
+            self.set_status("No lines to toggle");
+        } else if bullet {
+            self.set_status("Made list items");
+        } else {
+            self.set_status("Removed list markers");
+        }
+    }
+
+    /// Hard-wrap the paragraph under the cursor, or every paragraph the
+    /// selection touches, to `reflow_width` columns.
+    pub(crate) fn reflow_paragraph(&mut self) {
+        if self.refuse_read_only_edit() {
+            return;
+        }
+        let width = self.reflow_width;
+        let Some(tab) = self.active_tab_mut() else {
+            self.set_status("No file open");
+            return;
+        };
+        if !matches!(tab.syntax_lang(), SyntaxLang::Markdown | SyntaxLang::Plain) {
+            self.set_status("Reflow is for Markdown and plain text files");
+            return;
+        }
+        let lines = tab.editor.lines();
+        let is_blank = |row: usize| split_markdown_prefix(&lines[row]).2.is_empty();
+        let (mut first, mut last) = match tab.editor.selection_range() {
+            Some(((start, _), (end, _))) => (start, end),
+            None => {
+                let (row, _) = tab.editor.cursor();
+                (row, row)
+            }
+        };
+        if first == last && is_blank(first) {
+            self.set_status("No paragraph at the cursor");
+            return;
+        }
+        while first > 0 && !is_blank(first - 1) && !is_blank(first) {
+            first -= 1;
+        }
+        while last + 1 < lines.len() && !is_blank(last + 1) && !is_blank(last) {
+            last += 1;
+        }
+        let reflowed = reflow_paragraphs(&lines[first..=last], width);
+        if reflowed == lines[first..=last] {
+            self.set_status(format!("Already fits in {width} columns"));
+            return;
+        }
+        let end_row = first + reflowed.len() - 1;
+        let end_col = reflowed[reflowed.len() - 1].chars().count();
+        self.replace_rows(first..=last, &reflowed, (end_row, end_col));
+        self.set_status(format!(
+            "Reflowed {} line(s) to {width} columns",
+            reflowed.len()
+        ));
+    }
+
+    pub(crate) fn handle_help_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let is_help_key = self.keybinds.lookup(&key, KeyScope::Global) == Some(KeyAction::Help);
+        match (key.modifiers, key.code) {
+            (_, KeyCode::Esc) => {
+                self.help_open = false;
+            }
+            _ if is_help_key => {
+                self.help_open = false;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_editor_context_menu_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        match (key.modifiers, key.code) {
+            (_, KeyCode::Esc) => {
+                self.editor_context_menu_open = false;
+            }
+            (_, KeyCode::Down) | (_, KeyCode::Char('j')) => {
+                if self.editor_context_menu_index < editor_context_actions().len().saturating_sub(1)
+                {
+                    self.editor_context_menu_index += 1;
+                }
+            }
+            (_, KeyCode::Up) | (_, KeyCode::Char('k')) => {
+                if self.editor_context_menu_index > 0 {
+                    self.editor_context_menu_index -= 1;
+                }
+            }
+            (_, KeyCode::Enter) => {
+                let action = editor_context_actions()[self.editor_context_menu_index];
+                self.apply_editor_context_action(action);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn apply_editor_context_action(&mut self, action: EditorContextAction) {
+        self.editor_context_menu_open = false;
+        self.focus = Focus::Editor;
+        match action {
+            EditorContextAction::Copy => self.copy_selection_to_clipboard(),
+            EditorContextAction::Cut if self.refuse_read_only_edit() => {}
+            EditorContextAction::Cut => self.cut_selection_to_clipboard(),
+            EditorContextAction::Paste => self.paste_from_clipboard(),
+            EditorContextAction::SelectAll => {
+                if let Some(tab) = self.active_tab_mut() {
+                    tab.editor.select_all();
+                }
+                self.set_status("Selected all");
+            }
+            EditorContextAction::AddToDictionary => self.add_word_to_dictionary(),
+            EditorContextAction::Cancel => {}
+        }
+    }
+
+    /// Add the right-clicked word, or the one at the cursor, to the user
+    /// dictionary so spell check stops flagging it.
+    pub(crate) fn add_word_to_dictionary(&mut self) {
+        let target = self.editor_context_menu_target.take();
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let (row, col) = target.unwrap_or_else(|| tab.editor.cursor());
+        let word: Option<String> = tab.editor.lines().get(row).and_then(|line| {
+            let (start, end) = prose_words(line, 0, line.chars().count())
+                .into_iter()
+                .find(|&(start, end)| start <= col && col <= end)?;
+            Some(line.chars().skip(start).take(end - start).collect())
+        });
+        let Some(word) = word else {
+            self.set_status("No word here to add to the dictionary");
+            return;
+        };
+        self.spell_checker.add_word(&word);
+        self.persist_state();
+        self.set_status(format!("Added \"{word}\" to the dictionary"));
+    }
+
+    pub(crate) fn sync_editor_scroll_guess(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let (cursor_row, cursor_col) = tab.editor.cursor();
+        let inner_height = self.editor_text_height();
+        if inner_height == 0 {
+            if let Some(tab) = self.active_tab_mut() {
+                tab.editor_scroll_row = 0;
+            }
+            return;
+        }
+        if self
+            .active_tab()
+            .is_some_and(|t| t.visible_rows_map.is_empty())
+        {
+            self.rebuild_visible_rows();
+        }
+        let cursor_visible = self.visible_index_of_source_position(cursor_row, cursor_col);
+        let margin = self.scroll_margin(inner_height);
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        // Keep `margin` rows around the cursor, except where the file ends.
+        let last_visible = tab.visible_rows_map.len().saturating_sub(1);
+        let top = cursor_visible.saturating_sub(margin);
+        let bottom = (cursor_visible + margin).min(last_visible.max(cursor_visible));
+        if top < tab.editor_scroll_row {
+            tab.editor_scroll_row = top;
+        } else if bottom >= tab.editor_scroll_row + inner_height {
+            tab.editor_scroll_row = bottom + 1 - inner_height;
+        }
+        self.sync_editor_scroll_col();
+    }
+
+    /// Rows kept visible above and below the cursor: `scroll_off`, capped so
+    /// both margins fit in a viewport `inner_height` rows tall.
+    pub(crate) fn scroll_margin(&self, inner_height: usize) -> usize {
+        self.scroll_off.min(inner_height.saturating_sub(1) / 2)
+    }
+
+    pub(crate) fn sync_editor_scroll_col(&mut self) {
+        if self.word_wrap {
+            return;
+        }
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
         let (cursor_row, cursor_col) = tab.editor.cursor();
         let content_width = self
             .editor_rect
             .width
             .saturating_sub(2)
-            .saturating_sub(Self::EDITOR_GUTTER_WIDTH) as usize;
+            .saturating_sub(self.gutter().width()) as usize;
         if content_width == 0 {
             return;
         }
         // Compute cursor's display-width offset from start of line
-        let line = tab
-            .editor
-            .lines()
-            .get(cursor_row)
-            .map(|l| l.replace('\t', "    "))
-            .unwrap_or_default();
-        let chars: Vec<char> = line.chars().collect();
-        let mut cursor_display_col = 0usize;
-        for i in 0..cursor_col.min(chars.len()) {
-            cursor_display_col +=
-                unicode_width::UnicodeWidthChar::width(chars[i]).unwrap_or(0);
-        }
+        let cursor_display_col = tab.editor.lines().get(cursor_row).map_or(0, |line| {
+            display_col(line, cursor_col, tab.indent.tab_stop())
+        });
         let scroll_col = tab.editor_scroll_col;
         if cursor_display_col < scroll_col {
             if let Some(tab) = self.active_tab_mut() {
@@ -601,7 +1967,7 @@ impl App {
     /// viewport. This prevents `sync_editor_scroll_guess` from snapping
     /// the viewport back to the old cursor position on the next action.
     pub(crate) fn clamp_cursor_to_viewport(&mut self) {
-        let inner_height = self.editor_rect.height.saturating_sub(2) as usize;
+        let inner_height = self.editor_text_height();
         if inner_height == 0 {
             return;
         }
@@ -615,18 +1981,23 @@ impl App {
         };
         let scroll = tab.editor_scroll_row;
         let viewport_end = scroll + inner_height;
+        let margin = self.scroll_margin(inner_height);
+        // Rows the cursor may occupy without the scroll-off margin pulling the
+        // view back; the margin doesn't apply at the start or end of the file.
+        let low = if scroll == 0 { 0 } else { scroll + margin };
+        let high = if viewport_end >= tab.visible_rows_map.len() {
+            viewport_end.saturating_sub(1)
+        } else {
+            viewport_end.saturating_sub(1 + margin)
+        };
 
-        // Cursor is already in the viewport — nothing to do.
-        if cursor_vis >= scroll && cursor_vis < viewport_end {
+        // Cursor is already inside the margins — nothing to do.
+        if cursor_vis >= low && cursor_vis <= high {
             return;
         }
 
-        // Pick the closest viewport edge.
-        let target_vis = if cursor_vis < scroll {
-            scroll
-        } else {
-            viewport_end.saturating_sub(1)
-        };
+        // Pick the closest allowed row.
+        let target_vis = if cursor_vis < low { low } else { high };
 
         let target_row = tab
             .visible_rows_map
@@ -646,7 +2017,7 @@ impl App {
         let Some(tab) = self.active_tab() else {
             return;
         };
-        let inner_height = self.editor_rect.height.saturating_sub(2) as usize;
+        let inner_height = self.editor_text_height();
         if inner_height == 0 {
             return;
         }
@@ -727,258 +2098,2249 @@ impl App {
         self.sync_editor_scroll_guess();
     }
 
-    pub(crate) fn page_down(&mut self) {
-        self.page_move(true);
+    pub(crate) fn page_down(&mut self) {
+        self.page_move(true);
+    }
+
+    pub(crate) fn page_up(&mut self) {
+        self.page_move(false);
+    }
+
+    pub(crate) fn center_cursor_line(&mut self) {
+        self.place_cursor_line(|height, _| height.saturating_sub(1) / 2);
+    }
+
+    pub(crate) fn scroll_cursor_to_top(&mut self) {
+        self.place_cursor_line(|_, margin| margin);
+    }
+
+    pub(crate) fn scroll_cursor_to_bottom(&mut self) {
+        self.place_cursor_line(|height, margin| height.saturating_sub(1 + margin));
+    }
+
+    /// Scroll so the cursor line sits `rows_above(inner_height, margin)` rows
+    /// below the top of the viewport, without scrolling before the first line
+    /// or past the point where the last line reaches the bottom.
+    fn place_cursor_line(&mut self, rows_above: impl FnOnce(usize, usize) -> usize) {
+        let inner_height = self.editor_text_height();
+        if inner_height == 0 {
+            return;
+        }
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let (cursor_row, cursor_col) = tab.editor.cursor();
+        if tab.visible_rows_map.is_empty() {
+            self.rebuild_visible_rows();
+        }
+        let cursor_vis = self.visible_index_of_source_position(cursor_row, cursor_col);
+        let offset = rows_above(inner_height, self.scroll_margin(inner_height));
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        let max_scroll = tab.visible_rows_map.len().saturating_sub(inner_height);
+        tab.editor_scroll_row = cursor_vis.saturating_sub(offset).min(max_scroll);
+    }
+
+    /// Editor rows available to text, less the spacer rows line spacing
+    /// may take.
+    pub(crate) fn editor_text_height(&self) -> usize {
+        let inner_height = self.editor_rect.height.saturating_sub(2) as usize;
+        spaced_text_rows(inner_height, self.line_spacing)
+    }
+
+    /// Visible-row index drawn on editor screen row `inner_y`. A spacer row
+    /// belongs to the line above it; rows past the end of the buffer give an
+    /// index past the end of `visible_rows_map`.
+    pub(crate) fn visible_index_at_screen_row(&self, inner_y: usize) -> usize {
+        let Some(tab) = self.active_tab() else {
+            return inner_y;
+        };
+        if self.line_spacing == 0 {
+            return tab.editor_scroll_row + inner_y;
+        }
+        let rows = spaced_screen_rows(
+            &tab.visible_rows_map,
+            tab.editor_scroll_row,
+            inner_y + 1,
+            self.line_spacing,
+        );
+        if rows.len() <= inner_y {
+            return tab.visible_rows_map.len();
+        }
+        rows.iter()
+            .rev()
+            .find_map(|r| *r)
+            .unwrap_or(tab.editor_scroll_row)
+    }
+
+    pub(crate) fn editor_pos_from_mouse(&self, x: u16, y: u16) -> Option<(usize, usize)> {
+        if !inside(x, y, self.editor_rect) {
+            return None;
+        }
+        let tab = self.active_tab()?;
+        let inner_x = x.saturating_sub(self.editor_rect.x.saturating_add(1)) as usize;
+        let inner_y = y.saturating_sub(self.editor_rect.y.saturating_add(1)) as usize;
+        let lines = tab.editor.lines();
+        if lines.is_empty() {
+            return Some((0, 0));
+        }
+        let visible_idx = self.visible_index_at_screen_row(inner_y);
+        let row = tab
+            .visible_rows_map
+            .get(visible_idx)
+            .copied()
+            .unwrap_or_else(|| *tab.visible_rows_map.last().unwrap_or(&0));
+        let seg_start = tab
+            .visible_row_starts
+            .get(visible_idx)
+            .copied()
+            .unwrap_or(0);
+        let seg_end = tab
+            .visible_row_ends
+            .get(visible_idx)
+            .copied()
+            .unwrap_or(seg_start);
+        let text_x = inner_x.saturating_sub(self.gutter().width() as usize);
+        let max_col = lines[row].chars().count();
+        // text_x is in screen columns; map to char index within the segment,
+        // whose tabs are drawn out to the next tab stop.
+        let segment: String = lines[row]
+            .chars()
+            .skip(seg_start)
+            .take(seg_end.saturating_sub(seg_start))
+            .collect();
+        // When not wrapping, offset text_x by editor_scroll_col so clicks
+        // land on the correct character in the horizontally-scrolled view.
+        let effective_text_x = if !self.word_wrap {
+            text_x + tab.editor_scroll_col
+        } else {
+            text_x
+        };
+        let tab_width = tab.indent.tab_stop();
+        let col = seg_start + char_at_display_col(&segment, effective_text_x, tab_width);
+        let col = col.min(seg_end).min(max_col);
+        Some((row, col))
+    }
+    pub(crate) fn select_line(&mut self, row: usize) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let lines = tab.editor.lines();
+        let total = lines.len();
+        if total == 0 || row >= total {
+            return;
+        }
+        let line_len = lines[row].chars().count();
+        // Start selection from end and move cursor to start, so the cursor
+        // ends up at the beginning of the line rather than the next line.
+        if let Some(tab) = self.active_tab_mut() {
+            if row + 1 < total {
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(row + 1),
+                    0,
+                ));
+            } else {
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(row),
+                    to_u16_saturating(line_len),
+                ));
+            }
+            tab.editor.start_selection();
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(row),
+                0,
+            ));
+        }
+        self.sync_editor_scroll_guess();
+    }
+
+    pub(crate) fn select_line_range(&mut self, from: usize, to: usize) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let lines = tab.editor.lines();
+        let total = lines.len();
+        if total == 0 {
+            return;
+        }
+        let start = from.min(to).min(total.saturating_sub(1));
+        let end = from.max(to).min(total.saturating_sub(1));
+        // Select from start of first line to start of line after last (or end of last line)
+        if let Some(tab) = self.active_tab_mut() {
+            if end + 1 < total {
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(end + 1),
+                    0,
+                ));
+            } else {
+                let line_len = tab
+                    .editor
+                    .lines()
+                    .get(end)
+                    .map_or(0, |l| l.chars().count());
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(end),
+                    to_u16_saturating(line_len),
+                ));
+            }
+            tab.editor.start_selection();
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(start),
+                0,
+            ));
+        }
+        self.sync_editor_scroll_guess();
+    }
+
+    pub(crate) fn gutter_row_from_mouse(&self, y: u16) -> Option<usize> {
+        let tab = self.active_tab()?;
+        let inner_y = y.saturating_sub(self.editor_rect.y.saturating_add(1)) as usize;
+        let visible_idx = self.visible_index_at_screen_row(inner_y);
+        tab.visible_rows_map.get(visible_idx).copied()
+    }
+
+    pub(crate) fn extend_mouse_selection(&mut self, x: u16, y: u16) {
+        if let (Some((anchor_row, anchor_col)), Some((row, col))) =
+            (self.editor_drag_anchor, self.editor_pos_from_mouse(x, y))
+        {
+            if let Some(tab) = self.active_tab_mut() {
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(anchor_row),
+                    to_u16_saturating(anchor_col),
+                ));
+                tab.editor.start_selection();
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(row),
+                    to_u16_saturating(col),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tab::{DiffLineKind, IndentSettings};
+    use crate::types::{CommandAction, ConfirmKey, EscAction, PendingAction};
+    use crate::util::{expand_tabs, last_visible_tab};
+    use ratatui::crossterm::event::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+    use ratatui::layout::Rect;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn new_app(root: &std::path::Path) -> App {
+        App::with_paths(root.to_path_buf(), None, None).expect("app should initialize")
+    }
+
+    #[test]
+    fn click_line_number_selects_line() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "line 0\nline 1\nline 2\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+
+        app.select_line(1);
+
+        let tab = app.active_tab().expect("tab");
+        let sel = tab.editor.selection_range().expect("should have selection");
+        assert_eq!(sel, ((1, 0), (2, 0)));
+        // Cursor should be at the start of the selected line
+        assert_eq!(tab.editor.cursor(), (1, 0));
+    }
+
+    #[test]
+    fn click_line_number_selects_last_line() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "line 0\nline 1\nlast line").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+
+        app.select_line(2);
+
+        let tab = app.active_tab().expect("tab");
+        let sel = tab.editor.selection_range().expect("should have selection");
+        assert_eq!(sel, ((2, 0), (2, 9)));
+    }
+
+    #[test]
+    fn click_line_number_selects_first_line() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "first\nsecond\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+
+        app.select_line(0);
+
+        let tab = app.active_tab().expect("tab");
+        let sel = tab.editor.selection_range().expect("should have selection");
+        assert_eq!(sel, ((0, 0), (1, 0)));
+    }
+
+    #[test]
+    fn cut_line_removes_middle_line() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "aaa\nbbb\nccc\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        // Move cursor to line 1 (bbb)
+        app.tabs[app.active_tab]
+            .editor
+            .move_cursor(ratatui_textarea::CursorMove::Jump(1, 0));
+        app.cut_line();
+        let lines = app.tabs[app.active_tab].editor.lines().to_vec();
+        assert_eq!(lines, vec!["aaa", "ccc", ""]);
+    }
+
+    #[test]
+    fn cut_line_removes_last_line() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "aaa\nbbb\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        // Move cursor to last content line (index 2 is the empty trailing line)
+        app.tabs[app.active_tab]
+            .editor
+            .move_cursor(ratatui_textarea::CursorMove::Jump(2, 0));
+        app.cut_line();
+        let lines = app.tabs[app.active_tab].editor.lines().to_vec();
+        assert_eq!(lines, vec!["aaa", "bbb"]);
+        // Cursor should be clamped, not out of bounds
+        let (row, _) = app.tabs[app.active_tab].editor.cursor();
+        assert!(row < lines.len());
+    }
+
+    #[test]
+    fn editing_preview_tab_promotes_it() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("a.txt");
+        fs::write(&file, "hello\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file_as(file, true).expect("open preview");
+        assert!(app.active_tab().expect("tab").is_preview);
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+            .expect("type");
+
+        let tab = app.active_tab().expect("tab");
+        assert!(tab.dirty);
+        assert!(!tab.is_preview, "first edit should promote the preview tab");
+    }
+
+    #[test]
+    fn typed_word_undoes_in_one_step_and_paste_separately() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("u.txt");
+        fs::write(&file, "").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        let type_str = |app: &mut App, text: &str| {
+            for c in text.chars() {
+                app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                    .expect("type");
+            }
+        };
+        let first_line = |app: &App| app.active_tab().expect("tab").editor.lines()[0].clone();
+
+        type_str(&mut app, "hello");
+        app.handle_paste("XY".to_string());
+        type_str(&mut app, "z");
+        assert_eq!(first_line(&app), "helloXYz");
+
+        app.run_key_action(KeyAction::Undo).expect("undo");
+        assert_eq!(first_line(&app), "helloXY");
+        app.run_key_action(KeyAction::Undo).expect("undo");
+        assert_eq!(first_line(&app), "hello", "paste is its own undo unit");
+        app.run_key_action(KeyAction::Undo).expect("undo");
+        assert_eq!(first_line(&app), "", "the typed word undoes in one step");
+        app.run_key_action(KeyAction::Redo).expect("redo");
+        assert_eq!(first_line(&app), "hello");
+    }
+
+    #[test]
+    fn long_typing_run_groups_no_more_than_the_undo_history() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("w.txt");
+        fs::write(&file, "").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor.set_max_histories(4);
+        }
+        for c in "abcdef".chars() {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .expect("type");
+        }
+
+        app.run_key_action(KeyAction::Undo).expect("undo");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[0], "ab");
+        assert_eq!(tab.redo_groups.last().map(|g| g.steps), Some(4));
+    }
+
+    #[test]
+    fn cached_char_count_follows_edits() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("c.txt");
+        fs::write(&file, "ab\ncd\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        assert_eq!(app.active_tab().expect("tab").char_count, 4);
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+            .expect("type");
+        app.handle_paste("yz".to_string());
+        assert_eq!(app.active_tab().expect("tab").char_count, 7);
+        app.run_key_action(KeyAction::Undo).expect("undo");
+        assert_eq!(app.active_tab().expect("tab").char_count, 5);
+    }
+
+    #[test]
+    fn typing_runs_split_at_word_starts() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("w.txt");
+        fs::write(&file, "").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        for c in "one two".chars() {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .expect("type");
+        }
+        app.handle_key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE))
+            .expect("backspace");
+
+        app.run_key_action(KeyAction::Undo).expect("undo");
+        assert_eq!(app.active_tab().expect("tab").editor.lines()[0], "one two");
+        app.run_key_action(KeyAction::Undo).expect("undo");
+        assert_eq!(app.active_tab().expect("tab").editor.lines()[0], "one ");
+        app.run_key_action(KeyAction::Undo).expect("undo");
+        assert_eq!(app.active_tab().expect("tab").editor.lines()[0], "");
+    }
+
+    #[test]
+    fn add_to_dictionary_takes_the_clicked_word() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("main.rs");
+        fs::write(&file, "// lazyide tabbar `snake_case`\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+
+        app.editor_context_menu_target = Some((0, 12));
+        app.apply_editor_context_action(EditorContextAction::AddToDictionary);
+        assert!(app.spell_checker.is_known("tabbar"));
+        assert!(app.editor_context_menu_target.is_none());
+
+        app.editor_context_menu_target = Some((0, 22));
+        app.apply_editor_context_action(EditorContextAction::AddToDictionary);
+        assert_eq!(app.status, "No word here to add to the dictionary");
+    }
+
+    #[test]
+    fn enter_continues_list_items_and_line_comments() {
+        let tmp = tempdir().expect("tempdir");
+        let md = tmp.path().join("list.md");
+        fs::write(&md, "- a").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(md).expect("open");
+        let enter_at_end = |app: &mut App| {
+            if let Some(tab) = app.active_tab_mut() {
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Bottom);
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::End);
+            }
+            app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+                .expect("enter");
+        };
+        let lines = |app: &App| app.active_tab().expect("tab").editor.lines().to_vec();
+
+        enter_at_end(&mut app);
+        assert_eq!(lines(&app), ["- a", "- "]);
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (1, 2));
+        enter_at_end(&mut app);
+        assert_eq!(lines(&app), ["- a", ""], "an empty item ends the list");
+
+        let rs = tmp.path().join("main.rs");
+        fs::write(&rs, "fn main() {}\n    // one").expect("write");
+        app.open_file(rs).expect("open");
+        enter_at_end(&mut app);
+        assert_eq!(lines(&app), ["fn main() {}", "    // one", "    // "]);
+    }
+
+    #[test]
+    fn emmet_expands_in_html_at_the_line_indent() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("page.html");
+        fs::write(&file, "<body>\n  ul>li*2\n</body>\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        if let Some(tab) = app.active_tab_mut() {
+            tab.indent = IndentSettings {
+                hard_tabs: false,
+                width: 2,
+                render_tab_width: 0,
+            };
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(1, 9));
+        }
+
+        app.run_key_action(KeyAction::ExpandEmmet).expect("expand");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(
+            tab.editor.lines(),
+            [
+                "<body>",
+                "  <ul>",
+                "    <li></li>",
+                "    <li></li>",
+                "  </ul>",
+                "</body>",
+                "",
+            ]
+        );
+        assert_eq!(tab.editor.cursor(), (2, 8));
+
+        app.run_key_action(KeyAction::Undo).expect("undo");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[1], "  ul>li*2");
+    }
+
+    #[test]
+    fn oversized_emmet_expansion_is_refused() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("page.html");
+        let abbr = "div*100>div*100>div*100";
+        fs::write(&file, format!("{abbr}\n")).expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::End);
+        }
+
+        app.run_key_action(KeyAction::ExpandEmmet).expect("expand");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines(), [abbr, ""]);
+        assert!(!tab.dirty);
+        assert!(app.status.contains("more than 1000 lines"));
+    }
+
+    #[test]
+    fn emmet_is_refused_outside_html() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("main.rs");
+        fs::write(&file, "ul>li\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        app.run_key_action(KeyAction::ExpandEmmet).expect("expand");
+        assert_eq!(app.active_tab().expect("tab").editor.lines()[0], "ul>li");
+        assert!(app.status.contains("HTML/XML"));
+    }
+
+    #[test]
+    fn opening_two_previews_reuses_single_slot() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let a = root.join("a.txt");
+        let b = root.join("b.txt");
+        let c = root.join("c.txt");
+        fs::write(&a, "a\n").expect("write a");
+        fs::write(&b, "b\n").expect("write b");
+        fs::write(&c, "c\n").expect("write c");
+        let mut app = new_app(root);
+        app.open_file(a).expect("open sticky");
+        app.open_file_as(b, true).expect("preview b");
+        app.open_file_as(c.clone(), true).expect("preview c");
+
+        assert_eq!(app.tabs.len(), 2);
+        assert_eq!(app.tabs.iter().filter(|t| t.is_preview).count(), 1);
+        assert_eq!(app.open_path(), Some(&c));
+        assert_eq!(app.active_tab, 1);
+    }
+
+    #[test]
+    fn keep_tab_open_promotes_preview() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("a.txt");
+        fs::write(&file, "a\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file_as(file, true).expect("open preview");
+        app.run_key_action(KeyAction::KeepTabOpen).expect("keep");
+        assert!(!app.active_tab().expect("tab").is_preview);
+        assert!(!app.active_tab().expect("tab").dirty);
+    }
+
+    fn open_three_tabs(root: &std::path::Path) -> App {
+        let mut app = new_app(root);
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            let path = root.join(name);
+            fs::write(&path, name).expect("write");
+            app.open_file(path).expect("open");
+        }
+        app.editor_rect = Rect::new(20, 0, 60, 20);
+        app.tab_rects = (0..3u16)
+            .map(|i| {
+                let x = 21 + i * 11;
+                (Rect::new(x, 0, 6, 1), Rect::new(x + 6, 0, 4, 1))
+            })
+            .collect();
+        app
+    }
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16) -> MouseEvent {
+        MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    fn open_two_lines(root: &std::path::Path) -> App {
+        let file = root.join("words.txt");
+        fs::write(&file, "alpha beta\ngamma\n").expect("write");
+        let mut app = new_app(root);
+        app.clipboard = None;
+        app.open_file(file).expect("open");
+        app.editor_rect = Rect::new(0, 0, 60, 12);
+        app.rebuild_visible_rows();
+        app
+    }
+
+    #[test]
+    fn paste_at_clicked_position_inserts_text_there() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_two_lines(tmp.path());
+        // Border, then the gutter, then three columns into "gamma".
+        let column = 1 + app.gutter().width() + 3;
+        let (row, col) = app.editor_pos_from_mouse(column, 2).expect("in editor");
+        assert_eq!((row, col), (1, 3));
+
+        app.paste_at(row, col, "XY");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[1], "gamXYma");
+        assert_eq!(tab.editor.cursor(), (1, 5));
+        assert!(tab.dirty);
+    }
+
+    #[test]
+    fn middle_click_pastes_at_the_click_not_the_cursor() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_two_lines(tmp.path());
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor.set_yank_text("new ".to_string());
+        }
+        let column = 1 + app.gutter().width() + 6;
+
+        app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Middle), column, 1))
+            .expect("middle click");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[0], "alpha new beta");
+        assert_eq!(tab.editor.lines()[1], "gamma");
+        assert_eq!(tab.editor.cursor(), (0, 10));
+    }
+
+    fn tab_names(app: &App) -> Vec<String> {
+        app.tabs
+            .iter()
+            .map(|t| t.path.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn dragging_first_tab_onto_third_reorders_tabs() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_three_tabs(tmp.path());
+
+        app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 22, 0))
+            .expect("down");
+        app.handle_mouse(mouse(MouseEventKind::Drag(MouseButton::Left), 35, 0))
+            .expect("drag");
+        app.handle_mouse(mouse(MouseEventKind::Up(MouseButton::Left), 45, 0))
+            .expect("up");
+
+        assert_eq!(tab_names(&app), vec!["b.txt", "c.txt", "a.txt"]);
+        assert_eq!(app.active_tab, 2);
+        assert!(app.tab_drag.is_none());
+    }
+
+    #[test]
+    fn dropping_tab_outside_tab_bar_cancels_reorder() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_three_tabs(tmp.path());
+
+        app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 22, 0))
+            .expect("down");
+        app.handle_mouse(mouse(MouseEventKind::Drag(MouseButton::Left), 45, 5))
+            .expect("drag");
+        app.handle_mouse(mouse(MouseEventKind::Up(MouseButton::Left), 45, 5))
+            .expect("up");
+
+        assert_eq!(tab_names(&app), vec!["a.txt", "b.txt", "c.txt"]);
+        assert_eq!(app.active_tab, 0);
+        assert_eq!(app.status, "Tab move canceled");
+    }
+
+    #[test]
+    fn switching_to_offscreen_tab_scrolls_tab_bar() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let mut app = new_app(root);
+        for i in 0..8 {
+            let path = root.join(format!("file_{i}.txt"));
+            fs::write(&path, "x").expect("write");
+            app.open_file(path).expect("open");
+        }
+        app.tab_bar_width = 40;
+        app.switch_to_tab(0);
+        assert_eq!(app.tab_scroll_offset, 0);
+
+        app.switch_to_tab(7);
+        let widths: Vec<usize> = app.tabs.iter().map(|t| tab_label(t).width()).collect();
+        assert!(app.tab_scroll_offset > 0, "offset should move right");
+        assert!(last_visible_tab(&widths, app.tab_scroll_offset, 40) >= 7);
+
+        app.switch_to_tab(1);
+        assert!(app.tab_scroll_offset <= 1, "offset should move back left");
+    }
+
+    #[test]
+    fn tab_mru_tracks_switches_and_closes() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_three_tabs(tmp.path());
+        assert_eq!(app.tab_mru, vec![2, 1, 0]);
+
+        app.switch_to_tab(0);
+        assert_eq!(app.tab_mru, vec![0, 2, 1]);
+
+        // Closing a tab drops it and shifts later indices down.
+        app.close_tab_at(1);
+        assert_eq!(tab_names(&app), vec!["a.txt", "c.txt"]);
+        assert_eq!(app.tab_mru, vec![0, 1]);
+    }
+
+    fn open_named(app: &mut App, root: &std::path::Path, name: &str) {
+        let path = root.join(name);
+        fs::write(&path, name).expect("write");
+        app.open_file(path).expect("open");
+    }
+
+    #[test]
+    fn opening_past_max_open_tabs_closes_the_least_recently_used_tab() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_three_tabs(tmp.path());
+        app.max_open_tabs = 3;
+        app.switch_to_tab(0);
+
+        open_named(&mut app, tmp.path(), "d.txt");
+        assert_eq!(tab_names(&app), vec!["a.txt", "c.txt", "d.txt"]);
+        assert_eq!(app.active_tab, 2);
+        assert_eq!(app.tab_mru, vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn dirty_tab_is_skipped_for_eviction() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_three_tabs(tmp.path());
+        app.max_open_tabs = 3;
+        app.tabs[0].dirty = true;
+
+        open_named(&mut app, tmp.path(), "d.txt");
+        assert_eq!(tab_names(&app), vec!["a.txt", "c.txt", "d.txt"]);
+        assert!(app.tabs[0].dirty);
+    }
+
+    #[test]
+    fn pinned_tab_is_never_evicted() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_three_tabs(tmp.path());
+        app.switch_to_tab(0);
+        app.run_command_action(CommandAction::TogglePinTab)
+            .expect("pin");
+        app.switch_to_tab(2);
+        app.max_open_tabs = 1;
+
+        open_named(&mut app, tmp.path(), "d.txt");
+        assert_eq!(tab_names(&app), vec!["a.txt", "d.txt"]);
+        assert!(app.tabs[0].pinned);
+        assert_eq!(app.active_tab, 1);
+    }
+
+    #[test]
+    fn last_used_tab_toggles_between_two_most_recent() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_three_tabs(tmp.path());
+        app.switch_to_tab(0);
+
+        app.last_used_tab();
+        assert_eq!(app.active_tab, 2);
+        app.last_used_tab();
+        assert_eq!(app.active_tab, 0);
+        app.last_used_tab();
+        assert_eq!(app.active_tab, 2);
+    }
+
+    #[test]
+    fn reopen_closed_tab_restores_file_and_cursor() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("a.txt");
+        fs::write(&file, "zero\none\ntwo\nthree\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file.clone()).expect("open");
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(2, 1));
+            tab.editor_scroll_row = 1;
+        }
+
+        app.close_file();
+        assert!(app.tabs.is_empty());
+        app.run_key_action(KeyAction::ReopenClosedTab)
+            .expect("reopen");
+
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.path, file);
+        assert_eq!(tab.editor.cursor(), (2, 1));
+        assert_eq!(tab.editor_scroll_row, 1);
+        assert!(app.closed_tabs.is_empty());
+    }
+
+    #[test]
+    fn closed_tab_stack_is_lifo_and_capped() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let mut app = new_app(root);
+        let count = App::MAX_CLOSED_TABS + 2;
+        for i in 0..count {
+            let path = root.join(format!("file_{i}.txt"));
+            fs::write(&path, "x").expect("write");
+            app.open_file(path).expect("open");
+        }
+        for _ in 0..count {
+            app.close_tab_at(0);
+        }
+        assert_eq!(app.closed_tabs.len(), App::MAX_CLOSED_TABS);
+
+        app.reopen_closed_tab().expect("reopen");
+        let expected = root.join(format!("file_{}.txt", count - 1));
+        assert_eq!(app.open_path(), Some(&expected));
+        app.reopen_closed_tab().expect("reopen");
+        let expected = root.join(format!("file_{}.txt", count - 2));
+        assert_eq!(app.open_path(), Some(&expected));
+    }
+
+    #[test]
+    fn deleted_file_is_not_remembered_as_closed() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("gone.txt");
+        fs::write(&file, "x").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file.clone()).expect("open");
+
+        fs::remove_file(&file).expect("remove");
+        app.close_file();
+        assert!(app.closed_tabs.is_empty());
+
+        app.reopen_closed_tab().expect("reopen");
+        assert!(app.tabs.is_empty());
+        assert_eq!(app.status, "No closed tabs to reopen");
+    }
+
+    fn open_two_dirty_tabs(app: &mut App, root: &std::path::Path) -> (PathBuf, PathBuf) {
+        let a = root.join("a.txt");
+        let b = root.join("b.txt");
+        fs::write(&a, "aaa\n").expect("write");
+        fs::write(&b, "bbb\n").expect("write");
+        for path in [&a, &b] {
+            app.open_file(path.clone()).expect("open");
+            app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+                .expect("type");
+        }
+        assert_eq!(app.dirty_tab_paths(), vec![a.clone(), b.clone()]);
+        (a, b)
+    }
+
+    #[test]
+    fn quit_with_dirty_tabs_waits_for_resolution() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let mut app = new_app(root);
+        open_two_dirty_tabs(&mut app, root);
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL))
+            .expect("quit");
+        assert!(!app.quit);
+        assert!(matches!(app.pending, PendingAction::QuitPrompt));
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('z'), KeyModifiers::NONE))
+            .expect("other key");
+        assert!(!app.quit);
+        assert!(matches!(app.pending, PendingAction::QuitPrompt));
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::NONE))
+            .expect("cancel");
+        assert!(!app.quit);
+        assert!(matches!(app.pending, PendingAction::None));
+        assert_eq!(app.status, "Quit canceled");
+        assert!(app.any_tab_dirty());
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL))
+            .expect("quit");
+        app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 0, 0))
+            .expect("click outside");
+        assert!(!app.quit);
+        assert!(matches!(app.pending, PendingAction::None));
+        assert_eq!(app.status, "Quit canceled");
+    }
+
+    #[test]
+    fn quit_prompt_save_all_writes_every_dirty_tab() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let mut app = new_app(root);
+        let (a, b) = open_two_dirty_tabs(&mut app, root);
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL))
+            .expect("quit");
+        app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::NONE))
+            .expect("save all");
+
+        assert!(app.quit);
+        assert!(!app.any_tab_dirty());
+        assert_eq!(fs::read_to_string(&a).expect("read a"), "xaaa\n");
+        assert_eq!(fs::read_to_string(&b).expect("read b"), "xbbb\n");
+    }
+
+    #[test]
+    fn chord_confirm_key_ignores_enter_in_quit_and_close_prompts() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let mut app = new_app(root);
+        app.confirm_key = ConfirmKey::Chord;
+        let (a, b) = open_two_dirty_tabs(&mut app, root);
+
+        app.run_key_action(KeyAction::CloseTab).expect("close");
+        assert_eq!(
+            app.status,
+            "Unsaved changes: Ctrl+S save+close | Esc discard | C cancel"
+        );
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("enter");
+        assert!(matches!(app.pending, PendingAction::ClosePrompt));
+        assert_eq!(app.tabs.len(), 2);
+        app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL))
+            .expect("save and close");
+        assert_eq!(app.tabs.len(), 1);
+        assert_eq!(fs::read_to_string(&b).expect("read b"), "xbbb\n");
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL))
+            .expect("quit");
+        assert!(app.status.contains("Ctrl+S save all"));
+        for code in [KeyCode::Enter, KeyCode::Char('s')] {
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+                .expect("not a confirm");
+        }
+        assert!(!app.quit);
+        assert!(matches!(app.pending, PendingAction::QuitPrompt));
+        app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL))
+            .expect("save all");
+        assert!(app.quit);
+        assert_eq!(fs::read_to_string(&a).expect("read a"), "xaaa\n");
+    }
+
+    #[test]
+    fn quit_prompt_discard_all_leaves_files_untouched() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let mut app = new_app(root);
+        let (a, _) = open_two_dirty_tabs(&mut app, root);
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL))
+            .expect("quit");
+        app.handle_key(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE))
+            .expect("discard all");
+
+        assert!(app.quit);
+        assert_eq!(fs::read_to_string(&a).expect("read a"), "aaa\n");
+    }
+
+    #[test]
+    fn untitled_tab_saves_to_chosen_path() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let mut app = new_app(root);
+
+        app.run_key_action(KeyAction::NewUntitled)
+            .expect("new untitled");
+        let tab = app.active_tab().expect("tab");
+        assert!(tab.untitled);
+        assert_eq!(tab.path, PathBuf::from("untitled-1"));
+        assert_eq!(tab.syntax_lang(), crate::syntax::SyntaxLang::Plain);
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+            .expect("type");
+        app.save_file().expect("save");
+        let prompt = app.prompt.take().expect("save-as prompt");
+        assert!(matches!(prompt.mode, PromptMode::SaveAs));
+
+        app.apply_prompt(prompt.mode, "notes/scratch.rs".to_string())
+            .expect("save as");
+
+        let target = root.join("notes/scratch.rs");
+        assert_eq!(fs::read_to_string(&target).expect("read"), "x\n");
+        let tab = app.active_tab().expect("tab");
+        assert!(!tab.untitled);
+        assert!(!tab.dirty);
+        assert_eq!(tab.path, target);
+        assert_eq!(tab.syntax_lang(), crate::syntax::SyntaxLang::Rust);
+    }
+
+    #[test]
+    fn untitled_tabs_are_numbered_and_never_auto_saved() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let mut app = new_app(root);
+        app.auto_save = true;
+        app.auto_save_delay_ms = 0;
+
+        app.new_untitled_tab();
+        app.new_untitled_tab();
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+            .expect("type");
+        app.poll_auto_save().expect("auto save");
+
+        assert_eq!(app.tabs[1].path, PathBuf::from("untitled-2"));
+        assert!(app.tabs[1].dirty);
+        assert!(app.tabs[1].untitled);
+    }
+
+    #[test]
+    fn save_all_and_quit_asks_where_to_save_untitled() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let mut app = new_app(root);
+        app.new_untitled_tab();
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+            .expect("type");
+
+        app.save_all_and_quit().expect("save all");
+
+        assert!(!app.quit);
+        assert!(matches!(
+            app.prompt.as_ref().map(|p| &p.mode),
+            Some(PromptMode::SaveAs)
+        ));
+    }
+
+    #[test]
+    fn language_override_highlights_txt_as_rust() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("snippet.txt");
+        fs::write(&file, "let x = 1;\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file.clone()).expect("open");
+        let is_keyword = |app: &App| {
+            let theme = app.active_theme();
+            let tab = app.active_tab().expect("tab");
+            let line = crate::syntax::highlight_line(
+                &tab.editor.lines()[0],
+                tab.syntax_lang(),
+                theme,
+                0,
+                &theme.brackets,
+            );
+            line.spans
+                .iter()
+                .any(|s| s.content == "let" && s.style.fg == Some(theme.accent))
+        };
+        assert!(!is_keyword(&app));
+
+        app.run_command_action(CommandAction::SetLanguage)
+            .expect("open picker");
+        assert!(app.language_picker.open);
+        app.language_picker.index = 2; // Auto Detect, Plain Text, Rust
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("pick");
+
+        assert!(!app.language_picker.open);
+        assert_eq!(
+            app.active_tab().expect("tab").lang_override,
+            Some(SyntaxLang::Rust)
+        );
+        assert!(is_keyword(&app));
+        app.toggle_comment();
+        assert_eq!(
+            app.active_tab().expect("tab").editor.lines()[0],
+            "// let x = 1;"
+        );
+    }
+
+    #[test]
+    fn tab_key_inserts_spaces_from_indent_settings() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("main.rs");
+        fs::write(&file, "x\n").expect("write");
+        let mut app = new_app(root);
+        app.default_indent = IndentSettings {
+            hard_tabs: false,
+            width: 4,
+            render_tab_width: 0,
+        };
+        app.open_file(file).expect("open");
+
+        app.handle_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+            .expect("tab");
+
+        assert_eq!(app.active_tab().expect("tab").editor.lines()[0], "    x");
+    }
+
+    #[test]
+    fn tab_key_inserts_hard_tab_from_editorconfig() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::write(root.join(".editorconfig"), "[*.go]\nindent_style = tab\n").expect("write");
+        let file = root.join("main.go");
+        fs::write(&file, "x\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+
+        app.handle_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+            .expect("tab");
+
+        assert_eq!(app.active_tab().expect("tab").editor.lines()[0], "\tx");
+    }
+
+    #[test]
+    fn enter_keeps_indent_and_indents_after_open_brace() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("main.rs");
+        fs::write(&file, "  fn f() {}\n").expect("write");
+        let mut app = new_app(root);
+        app.default_indent = IndentSettings {
+            hard_tabs: false,
+            width: 2,
+            render_tab_width: 0,
+        };
+        app.open_file(file).expect("open");
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(0, 10));
+        }
+
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("enter");
+
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[..3], ["  fn f() {", "    ", "  }"]);
+        assert_eq!(tab.editor.cursor(), (1, 4));
+    }
+
+    #[test]
+    fn convert_indentation_to_spaces_rewrites_tab_indented_file() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("main.rs");
+        fs::write(&file, "fn main() {\n\tif x {\n\t\ty();\n\t}\n}\n").expect("write");
+        let mut app = new_app(root);
+        app.default_indent = IndentSettings {
+            hard_tabs: true,
+            width: 4,
+            render_tab_width: 0,
+        };
+        app.open_file(file).expect("open");
+
+        app.run_command_action(CommandAction::ConvertIndentationToSpaces)
+            .expect("convert");
+
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(
+            tab.editor.lines().join("\n"),
+            "fn main() {\n    if x {\n        y();\n    }\n}\n"
+        );
+        assert!(tab.dirty);
+        assert!(!tab.indent.hard_tabs);
+    }
+
+    #[test]
+    fn backspace_in_space_indent_removes_full_indent_level() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("main.rs");
+        fs::write(&file, "        x\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(0, 4));
+        }
+
+        app.handle_key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE))
+            .expect("backspace");
+
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[0], "    x");
+        assert_eq!(tab.editor.cursor(), (0, 0));
+        assert!(tab.dirty);
+    }
+
+    #[test]
+    fn backspace_mid_line_removes_one_char() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("main.rs");
+        fs::write(&file, "    let  x\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(0, 9));
+        }
+
+        app.handle_key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE))
+            .expect("backspace");
+
+        assert_eq!(
+            app.active_tab().expect("tab").editor.lines()[0],
+            "    let x"
+        );
+    }
+
+    fn open_numbered_lines(root: &std::path::Path, count: usize) -> App {
+        let file = root.join("lines.txt");
+        let text: Vec<String> = (0..count).map(|i| format!("line {i}")).collect();
+        fs::write(&file, text.join("\n")).expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        app.editor_rect = Rect::new(0, 0, 60, 12); // 10 visible rows
+        app
+    }
+
+    fn move_cursor_to_row(app: &mut App, row: u16) {
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(row, 0));
+        }
+        app.sync_editor_scroll_guess();
+    }
+
+    #[test]
+    fn scroll_off_keeps_margin_below_cursor() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_numbered_lines(tmp.path(), 50);
+        assert_eq!(app.scroll_off, 3);
+
+        move_cursor_to_row(&mut app, 6);
+        assert_eq!(app.active_tab().expect("tab").editor_scroll_row, 0);
+        move_cursor_to_row(&mut app, 7);
+        assert_eq!(app.active_tab().expect("tab").editor_scroll_row, 1);
+        move_cursor_to_row(&mut app, 20);
+        assert_eq!(app.active_tab().expect("tab").editor_scroll_row, 14);
+
+        // Moving back up keeps three rows above the cursor.
+        move_cursor_to_row(&mut app, 16);
+        assert_eq!(app.active_tab().expect("tab").editor_scroll_row, 13);
+    }
+
+    #[test]
+    fn wheel_scrolls_the_configured_number_of_lines() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_numbered_lines(tmp.path(), 100);
+        app.scroll_lines = 5;
+
+        for expected in [5, 10, 15] {
+            app.handle_mouse(mouse(MouseEventKind::ScrollDown, 30, 5))
+                .expect("scroll");
+            assert_eq!(app.active_tab().expect("tab").editor_scroll_row, expected);
+        }
+        app.handle_mouse(mouse(MouseEventKind::ScrollUp, 30, 5))
+            .expect("scroll");
+        assert_eq!(app.active_tab().expect("tab").editor_scroll_row, 10);
+    }
+
+    #[test]
+    fn rapid_wheel_notches_accelerate_and_reset_after_a_pause() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        app.scroll_lines = 2;
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let steady: Vec<usize> = (0..4).map(|i| app.wheel_step(true, at(i * 10))).collect();
+        assert_eq!(steady, [2, 2, 2, 2]);
+
+        app.scroll_acceleration = true;
+        let fast: Vec<usize> = (0..10)
+            .map(|i| app.wheel_step(true, at(1000 + i * 10)))
+            .collect();
+        assert_eq!(fast, [2, 2, 4, 4, 6, 6, 8, 8, 8, 8]);
+
+        // Reversing direction or pausing starts over at the base step.
+        assert_eq!(app.wheel_step(false, at(1100)), 2);
+        assert_eq!(app.wheel_step(false, at(1110)), 2);
+        assert_eq!(app.wheel_step(false, at(1120)), 4);
+        assert_eq!(app.wheel_step(false, at(2000)), 2);
+    }
+
+    #[test]
+    fn switching_tabs_restores_far_right_cursor_and_view() {
+        let tmp = tempdir().expect("tempdir");
+        let long = tmp.path().join("long.txt");
+        let short = tmp.path().join("short.txt");
+        fs::write(&long, format!("{}\nnext\n", "x".repeat(300))).expect("write");
+        fs::write(&short, "short\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.word_wrap = false;
+        app.editor_rect = Rect::new(0, 0, 60, 12);
+        app.open_file(long).expect("open long");
+        app.handle_key(KeyEvent::new(KeyCode::End, KeyModifiers::NONE))
+            .expect("end");
+        let (cursor, scroll_col) = {
+            let tab = app.active_tab().expect("tab");
+            (tab.editor.cursor(), tab.editor_scroll_col)
+        };
+        assert_eq!(cursor, (0, 300));
+        assert!(scroll_col > 0);
+
+        app.open_file(short).expect("open short");
+        assert_eq!(app.active_tab().expect("tab").editor_scroll_col, 0);
+        app.switch_to_tab(0);
+
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.cursor(), cursor);
+        assert_eq!(tab.editor_scroll_col, scroll_col);
+        assert_eq!(tab.editor_scroll_row, 0);
+    }
+
+    #[test]
+    fn scroll_off_margin_shrinks_at_file_edges() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_numbered_lines(tmp.path(), 50);
+
+        move_cursor_to_row(&mut app, 49);
+        assert_eq!(app.active_tab().expect("tab").editor_scroll_row, 40);
+        move_cursor_to_row(&mut app, 1);
+        assert_eq!(app.active_tab().expect("tab").editor_scroll_row, 0);
+    }
+
+    #[test]
+    fn center_cursor_line_puts_cursor_mid_viewport() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_numbered_lines(tmp.path(), 50);
+        move_cursor_to_row(&mut app, 25);
+
+        app.center_cursor_line();
+        let scroll = app.active_tab().expect("tab").editor_scroll_row;
+        assert_eq!(scroll, 21);
+        assert_eq!(25 - scroll, 4); // row 4 of a 10-row viewport
+
+        app.scroll_cursor_to_top();
+        assert_eq!(app.active_tab().expect("tab").editor_scroll_row, 22);
+        app.scroll_cursor_to_bottom();
+        assert_eq!(app.active_tab().expect("tab").editor_scroll_row, 19);
+    }
+
+    #[test]
+    fn cursor_line_placement_clamps_at_file_edges() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_numbered_lines(tmp.path(), 50);
+
+        move_cursor_to_row(&mut app, 2);
+        app.center_cursor_line();
+        assert_eq!(app.active_tab().expect("tab").editor_scroll_row, 0);
+        app.scroll_cursor_to_bottom();
+        assert_eq!(app.active_tab().expect("tab").editor_scroll_row, 0);
+
+        move_cursor_to_row(&mut app, 48);
+        app.scroll_cursor_to_top();
+        assert_eq!(app.active_tab().expect("tab").editor_scroll_row, 40);
+    }
+
+    #[test]
+    fn mouse_hit_test_accounts_for_tab_stops() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("tabs.go");
+        fs::write(&file, "\tx\nab\tcd\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        app.editor_rect = Rect::new(0, 0, 60, 12);
+        app.rebuild_visible_rows();
+        assert_eq!(app.active_tab().expect("tab").indent.width, 4);
+        // Screen x of display column `col` on editor row `row`.
+        let gutter = app.gutter().width();
+        let at = |col: u16| 1 + gutter + col;
+
+        assert_eq!(app.editor_pos_from_mouse(at(2), 1), Some((0, 0)));
+        assert_eq!(app.editor_pos_from_mouse(at(4), 1), Some((0, 1)));
+        assert_eq!(app.editor_pos_from_mouse(at(5), 1), Some((0, 2)));
+        assert_eq!(app.editor_pos_from_mouse(at(4), 2), Some((1, 3)));
+
+        if let Some(tab) = app.active_tab_mut() {
+            tab.indent.width = 8;
+        }
+        assert_eq!(app.editor_pos_from_mouse(at(4), 1), Some((0, 0)));
+        assert_eq!(app.editor_pos_from_mouse(at(8), 1), Some((0, 1)));
+    }
+
+    #[test]
+    fn render_tab_width_is_separate_from_indent_width() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("main.rs");
+        fs::write(&file, "\tx\ny\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.default_indent = IndentSettings {
+            hard_tabs: false,
+            width: 4,
+            render_tab_width: 2,
+        };
+        app.open_file(file).expect("open");
+        app.editor_rect = Rect::new(0, 0, 60, 12);
+        app.rebuild_visible_rows();
+        let gutter = app.gutter().width();
+        let at = |col: u16| 1 + gutter + col;
+
+        let tab = app.active_tab().expect("tab");
+        let drawn = expand_tabs(&tab.editor.lines()[0], tab.indent.tab_stop());
+        assert_eq!(drawn, "  x");
+        assert_eq!(app.editor_pos_from_mouse(at(1), 1), Some((0, 0)));
+        assert_eq!(app.editor_pos_from_mouse(at(2), 1), Some((0, 1)));
+
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(1, 0));
+        }
+        app.handle_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+            .expect("tab");
+        assert_eq!(app.active_tab().expect("tab").editor.lines()[1], "    y");
+    }
+
+    #[test]
+    fn line_spacing_keeps_mouse_rows_on_source_lines() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = open_numbered_lines(tmp.path(), 50);
+        app.rebuild_visible_rows();
+        let map_before = app.active_tab().expect("tab").visible_rows_map.clone();
+        app.line_spacing = 1;
+        app.rebuild_visible_rows();
+        assert_eq!(app.active_tab().expect("tab").visible_rows_map, map_before);
+        let x = 1 + app.gutter().width();
+        let top = app.editor_rect.y + 1;
+
+        // Row 1 below each line is its spacer and resolves to the line above.
+        assert_eq!(app.editor_pos_from_mouse(x, top), Some((0, 0)));
+        assert_eq!(app.editor_pos_from_mouse(x, top + 1), Some((0, 0)));
+        assert_eq!(app.editor_pos_from_mouse(x, top + 2), Some((1, 0)));
+        assert_eq!(app.editor_pos_from_mouse(x, top + 6), Some((3, 0)));
+        assert_eq!(app.gutter_row_from_mouse(top + 5), Some(2));
+
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor_scroll_row = 10;
+        }
+        assert_eq!(app.editor_pos_from_mouse(x, top + 2), Some((11, 0)));
+        assert_eq!(app.gutter_row_from_mouse(top + 4), Some(12));
+    }
+
+    #[test]
+    fn gutter_clicks_toggle_folds_only_in_the_fold_column() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("fold.rs");
+        fs::write(&file, "fn main() {\n    a();\n    b();\n}\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        app.editor_rect = Rect::new(0, 0, 60, 12);
+        app.rebuild_visible_rows();
+        let click = |app: &mut App, x: u16| {
+            app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 1 + x, 1))
+                .expect("click");
+            app.handle_mouse(mouse(MouseEventKind::Up(MouseButton::Left), 1 + x, 1))
+                .expect("release");
+        };
+        let folded = |app: &App| app.active_tab().expect("tab").folded_starts.contains(&0);
+
+        // Numbers take columns 0-3, so the arrow sits at column 4.
+        click(&mut app, 3);
+        assert!(!folded(&app));
+        click(&mut app, 4);
+        assert!(folded(&app));
+        click(&mut app, 4);
+        assert!(!folded(&app));
+
+        // Without numbers the arrow moves to the left edge.
+        app.toggle_line_numbers();
+        click(&mut app, 0);
+        assert!(folded(&app));
+        click(&mut app, 0);
+
+        // Without the arrow column the gutter only selects lines.
+        app.toggle_fold_arrows();
+        for x in 0..app.gutter().width() {
+            click(&mut app, x);
+            assert!(!folded(&app));
+        }
+        let tab = app.active_tab().expect("tab");
+        assert!(tab.editor.selection_range().is_some());
+    }
+
+    #[test]
+    fn mixed_indentation_is_flagged_on_open_and_fixed() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("mixed.txt");
+        fs::write(&file, "fn f() {\n\t    let x = 1;\n    x\n}\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.lint_mixed_indent = true;
+        app.open_file(file).expect("open");
+        assert!(app.status.contains("first at line 2"), "{}", app.status);
+
+        app.fix_mixed_indentation();
+        let lines = app.active_tab().expect("tab").editor.lines().to_vec();
+        assert_eq!(lines[1], "        let x = 1;");
+        assert_eq!(lines[2], "    x");
+        assert!(app.active_tab().expect("tab").dirty);
+
+        app.save_file().expect("save");
+        assert!(app.status.starts_with("Saved"), "{}", app.status);
+    }
+
+    #[test]
+    fn fix_mixed_indentation_uses_hard_tabs_when_configured() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("mixed.go");
+        fs::write(&file, "func f() {\n  \tx\n}\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        if let Some(tab) = app.active_tab_mut() {
+            tab.indent.hard_tabs = true;
+        }
+
+        app.fix_mixed_indentation();
+        assert_eq!(app.active_tab().expect("tab").editor.lines()[1], "\tx");
+    }
+
+    #[test]
+    fn symbol_picker_filters_and_jumps_to_symbol() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("lib.rs");
+        fs::write(
+            &file,
+            "struct Config {}\n\nimpl Config {\n    fn load() {}\n    fn save() {}\n}\n",
+        )
+        .expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+
+        app.open_symbol_picker();
+        assert!(app.symbol_picker.open);
+        assert_eq!(app.symbol_picker.results.len(), 4);
+
+        for c in "save".chars() {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .expect("type");
+        }
+        let first = app.symbol_picker.results[0];
+        assert_eq!(app.symbol_picker.symbols[first].label, "fn save");
+
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("enter");
+        assert!(!app.symbol_picker.open);
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (4, 4));
+    }
+
+    #[test]
+    fn symbol_picker_reopens_on_the_last_row_within_the_outline() {
+        let tmp = tempdir().expect("tempdir");
+        let big = tmp.path().join("lib.rs");
+        let small = tmp.path().join("main.rs");
+        fs::write(
+            &big,
+            "struct Config {}\n\nimpl Config {\n    fn load() {}\n    fn save() {}\n}\n",
+        )
+        .expect("write");
+        fs::write(&small, "fn main() {}\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(big).expect("open");
+
+        app.open_symbol_picker();
+        app.symbol_picker.index = 3;
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("close");
+        app.open_symbol_picker();
+        assert_eq!(app.symbol_picker.index, 3);
+
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("close");
+        app.open_file(small).expect("open");
+        app.open_symbol_picker();
+        assert_eq!(app.symbol_picker.results.len(), 1);
+        assert_eq!(app.symbol_picker.index, 0);
+    }
+
+    #[test]
+    fn binary_file_asks_how_to_open_instead_of_loading() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("logo.png");
+        fs::write(&file, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").expect("write");
+        let mut app = new_app(tmp.path());
+
+        app.open_file_as(file.clone(), true).expect("open");
+
+        assert!(app.tabs.is_empty());
+        match &app.pending {
+            PendingAction::OpenWith { path, as_preview } => {
+                assert_eq!(path, &file);
+                assert!(as_preview);
+            }
+            other => panic!("expected open-with prompt, got {other:?}"),
+        }
+
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("esc");
+        assert!(matches!(app.pending, PendingAction::None));
+        assert!(app.tabs.is_empty());
+    }
+
+    #[test]
+    fn force_open_as_text_loads_binary_read_only() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("data.bin");
+        fs::write(&file, b"head\0tail\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file.clone()).expect("open");
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('t'), KeyModifiers::NONE))
+            .expect("choose text");
+
+        assert!(matches!(app.pending, PendingAction::None));
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.path, file);
+        assert!(tab.read_only);
+        assert_eq!(tab.editor.lines()[0], "head\0tail");
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+            .expect("type");
+        app.handle_paste("pasted".to_string());
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[0], "head\0tail");
+        assert!(!tab.dirty);
+        assert!(app.status.starts_with("Read-only"));
+
+        app.run_command_action(CommandAction::ToggleReadOnly)
+            .expect("toggle");
+        assert!(app.active_tab().expect("tab").read_only);
+    }
+
+    #[test]
+    fn toggle_read_only_suppresses_edits_until_turned_off() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("notes.txt");
+        fs::write(&file, "abc\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+
+        app.run_command_action(CommandAction::ToggleReadOnly)
+            .expect("toggle on");
+        assert!(tab_label(app.active_tab().expect("tab")).contains("[ro]"));
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+            .expect("type");
+        app.handle_key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE))
+            .expect("backspace");
+        app.handle_paste("pasted".to_string());
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[0], "abc");
+        assert!(!tab.dirty);
+        assert!(app.status.starts_with("Read-only"));
+
+        app.run_command_action(CommandAction::ToggleReadOnly)
+            .expect("toggle off");
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+            .expect("type");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[0], "xabc");
+        assert!(tab.dirty);
+        assert!(!tab_label(tab).contains("[ro]"));
+    }
+
+    #[test]
+    fn read_only_tab_refuses_palette_edits_and_find_replace() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("mixed.go");
+        fs::write(&file, "func f() {\n  \tx\n}\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        app.toggle_read_only();
+
+        app.run_command_action(CommandAction::ConvertIndentationToSpaces)
+            .expect("convert");
+        app.run_command_action(CommandAction::FixMixedIndentation)
+            .expect("fix");
+        app.replace_in_open_file("x", "y");
+        app.handle_key(KeyEvent::new(KeyCode::Char('h'), KeyModifiers::CONTROL))
+            .expect("find & replace");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[1], "  \tx");
+        assert!(!tab.dirty);
+        assert!(app.prompt.is_none());
+        assert!(app.status.starts_with("Read-only"));
+    }
+
+    #[test]
+    fn dirty_tab_cannot_be_made_read_only() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("notes.txt");
+        fs::write(&file, "abc\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file.clone()).expect("open");
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+            .expect("type");
+
+        app.toggle_read_only();
+        assert!(!app.active_tab().expect("tab").read_only);
+        assert!(app.status.contains("before making the tab read-only"));
+
+        // Even if a read-only tab ends up dirty, quitting never writes it.
+        app.tabs[app.active_tab].read_only = true;
+        app.save_all_and_quit().expect("quit");
+        assert_eq!(fs::read_to_string(&file).expect("read"), "abc\n");
+    }
+
+    #[test]
+    fn diff_tabs_pairs_rows_and_scrolls_in_lockstep() {
+        let tmp = tempdir().expect("tempdir");
+        let a = tmp.path().join("a.txt");
+        let b = tmp.path().join("b.txt");
+        let c = tmp.path().join("c.txt");
+        fs::write(&a, "same\nold\nend\n").expect("write");
+        fs::write(&b, "same\nnew\nmore\nend\n").expect("write");
+        fs::write(&c, "other\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(a.clone()).expect("open a");
+        app.run_command_action(CommandAction::DiffTabs)
+            .expect("diff");
+        assert!(app.tab_diff.is_none());
+        assert!(!app.diff_tab_picker.open);
+
+        app.open_file(b.clone()).expect("open b");
+        app.open_file(c).expect("open c");
+        app.switch_to_tab(0);
+        app.run_command_action(CommandAction::DiffTabs)
+            .expect("diff");
+        assert!(app.diff_tab_picker.open);
+        assert_eq!(app.diff_tab_picker.tabs, vec![1, 2]);
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("pick b");
+
+        let diff = app.tab_diff.as_ref().expect("diff open");
+        assert_eq!((diff.left.clone(), diff.right.clone()), (a, b));
+        let kinds: Vec<DiffRowKind> = diff.rows.iter().map(|r| r.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DiffRowKind::Same,
+                DiffRowKind::Changed,
+                DiffRowKind::Added,
+                DiffRowKind::Same,
+                DiffRowKind::Same,
+            ]
+        );
+
+        app.tab_diff.as_mut().expect("diff").page = 2;
+        app.handle_key(KeyEvent::new(KeyCode::End, KeyModifiers::NONE))
+            .expect("end");
+        assert_eq!(app.tab_diff.as_ref().expect("diff").scroll, 3);
+        app.handle_key(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE))
+            .expect("up");
+        assert_eq!(app.tab_diff.as_ref().expect("diff").scroll, 2);
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("close");
+        assert!(app.tab_diff.is_none());
+    }
+
+    #[test]
+    fn unsaved_diff_classifies_edited_lines_against_disk() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("notes.txt");
+        fs::write(&file, "a\nb\nc\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+
+        app.run_command_action(CommandAction::ShowUnsavedDiff)
+            .expect("show");
+        assert!(!app.unsaved_diff_open);
+        assert_eq!(app.status, "No unsaved changes");
+
+        app.handle_key(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE))
+            .expect("down");
+        app.handle_key(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE))
+            .expect("type");
+        let diff: Vec<(DiffLineKind, String)> = app
+            .unsaved_diff()
+            .into_iter()
+            .map(|l| (l.kind, l.text))
+            .collect();
+        assert_eq!(
+            diff,
+            vec![
+                (DiffLineKind::Hunk, "@@ -1 +1 @@".to_string()),
+                (DiffLineKind::Context, "a".to_string()),
+                (DiffLineKind::Removed, "b".to_string()),
+                (DiffLineKind::Added, "Xb".to_string()),
+                (DiffLineKind::Context, "c".to_string()),
+                (DiffLineKind::Context, String::new()),
+            ]
+        );
+
+        app.run_command_action(CommandAction::ShowUnsavedDiff)
+            .expect("show");
+        assert!(app.unsaved_diff_open);
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("close");
+        assert!(!app.unsaved_diff_open);
+    }
+
+    #[test]
+    fn reopened_file_keeps_its_language_override_and_read_only_flag() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("build.txt");
+        fs::write(&file, "echo hi\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file.clone()).expect("open");
+        app.set_language(Some(SyntaxLang::Shell));
+        app.toggle_read_only();
+        app.close_tab_at(app.active_tab);
+
+        app.open_file(file).expect("reopen");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.lang_override, Some(SyntaxLang::Shell));
+        assert_eq!(tab.detected_lang, SyntaxLang::Plain);
+        assert!(tab.read_only);
+    }
+
+    #[test]
+    fn deleted_file_drops_its_remembered_attributes() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("gone.txt");
+        fs::write(&file, "text\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file.clone()).expect("open");
+        app.toggle_read_only();
+        assert!(app.file_attributes.contains_key(&file));
+
+        fs::remove_file(&file).expect("remove");
+        app.prune_file_attributes();
+        assert!(!app.file_attributes.contains_key(&file));
+    }
+
+    #[test]
+    fn write_protected_file_opens_read_only() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("locked.txt");
+        fs::write(&file, "abc\n").expect("write");
+        let mut perms = fs::metadata(&file).expect("metadata").permissions();
+        perms.set_readonly(true);
+        fs::set_permissions(&file, perms).expect("set readonly");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+
+        assert!(app.active_tab().expect("tab").read_only);
+        app.run_command_action(CommandAction::ToggleReadOnly)
+            .expect("toggle off");
+        assert!(!app.active_tab().expect("tab").read_only);
+    }
+    #[test]
+    fn navigate_back_and_forward_across_go_to_line_and_files() {
+        let tmp = tempdir().expect("tempdir");
+        let a = tmp.path().join("a.txt");
+        let b = tmp.path().join("b.txt");
+        fs::write(&a, "one\ntwo\nthree\nfour\n").expect("write a");
+        fs::write(&b, "x\ny\n").expect("write b");
+        let mut app = new_app(tmp.path());
+        app.open_file(a.clone()).expect("open a");
+
+        app.apply_prompt(PromptMode::GoToLine, "3".to_string())
+            .expect("goto");
+        app.push_nav_history();
+        app.open_file(b.clone()).expect("open b");
+        app.apply_prompt(PromptMode::GoToLine, "2".to_string())
+            .expect("goto");
+        app.close_file();
+        assert_eq!(app.open_path(), Some(&a));
+
+        app.navigate_back().expect("back");
+        assert_eq!(app.open_path(), Some(&b));
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (0, 0));
+        app.navigate_back().expect("back");
+        assert_eq!(app.open_path(), Some(&a));
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (2, 0));
+        app.navigate_back().expect("back");
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (0, 0));
+        app.navigate_back().expect("back");
+        assert_eq!(app.status, "No back history");
+
+        app.navigate_forward().expect("forward");
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (2, 0));
+        app.navigate_forward().expect("forward");
+        assert_eq!(app.open_path(), Some(&b));
+
+        app.apply_prompt(PromptMode::GoToLine, "2".to_string())
+            .expect("goto");
+        assert!(app.nav_forward.is_empty());
     }
 
-    pub(crate) fn page_up(&mut self) {
-        self.page_move(false);
+    #[test]
+    fn goto_last_edit_returns_to_the_edited_tab_and_position() {
+        let tmp = tempdir().expect("tempdir");
+        let a = tmp.path().join("a.txt");
+        let b = tmp.path().join("b.txt");
+        fs::write(&a, "one\ntwo\nthree\n").expect("write a");
+        fs::write(&b, "x\ny\n").expect("write b");
+        let mut app = new_app(tmp.path());
+        app.open_file(a.clone()).expect("open a");
+        app.goto_last_edit().expect("no edits");
+        assert_eq!(app.status, "No edits yet");
+
+        app.apply_prompt(PromptMode::GoToLine, "3".to_string())
+            .expect("goto");
+        app.handle_key(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE))
+            .expect("type");
+        assert_eq!(app.active_tab().expect("tab").last_edit_pos, Some((2, 1)));
+        app.apply_prompt(PromptMode::GoToLine, "1".to_string())
+            .expect("goto");
+        app.open_file(b.clone()).expect("open b");
+
+        app.goto_last_edit().expect("jump");
+        assert_eq!(app.open_path(), Some(&a));
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (2, 1));
+        app.navigate_back().expect("back");
+        assert_eq!(app.open_path(), Some(&b));
     }
 
-    pub(crate) fn editor_pos_from_mouse(&self, x: u16, y: u16) -> Option<(usize, usize)> {
-        if !inside(x, y, self.editor_rect) {
-            return None;
-        }
-        let tab = self.active_tab()?;
-        let inner_x = x.saturating_sub(self.editor_rect.x.saturating_add(1)) as usize;
-        let inner_y = y.saturating_sub(self.editor_rect.y.saturating_add(1)) as usize;
-        let lines = tab.editor.lines();
-        if lines.is_empty() {
-            return Some((0, 0));
-        }
-        let visible_idx = tab.editor_scroll_row + inner_y;
-        let row = tab
-            .visible_rows_map
-            .get(visible_idx)
-            .copied()
-            .unwrap_or_else(|| *tab.visible_rows_map.last().unwrap_or(&0));
-        let seg_start = tab
-            .visible_row_starts
-            .get(visible_idx)
-            .copied()
-            .unwrap_or(0);
-        let seg_end = tab
-            .visible_row_ends
-            .get(visible_idx)
-            .copied()
-            .unwrap_or(seg_start);
-        let text_x = inner_x.saturating_sub(Self::EDITOR_GUTTER_WIDTH as usize);
-        let max_col = lines[row].chars().count();
-        // text_x is in screen columns; map to char index within the segment
-        // by walking chars and accumulating display width.
-        let display_line = lines[row].replace('\t', "    ");
-        let chars: Vec<char> = display_line.chars().collect();
-        // When not wrapping, offset text_x by editor_scroll_col so clicks
-        // land on the correct character in the horizontally-scrolled view.
-        let effective_text_x = if !self.word_wrap {
-            text_x + tab.editor_scroll_col
-        } else {
-            text_x
-        };
-        let mut col = seg_start;
-        let mut width_acc = 0usize;
-        for i in seg_start..seg_end.min(chars.len()) {
-            let cw = unicode_width::UnicodeWidthChar::width(chars[i]).unwrap_or(0);
-            if width_acc + cw > effective_text_x {
-                break;
-            }
-            width_acc += cw;
-            col = i + 1;
+    #[test]
+    fn word_count_covers_the_selection_when_there_is_one() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("notes.md");
+        fs::write(&file, "## One two three\nfour five\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+
+        app.show_word_count();
+        assert_eq!(app.status, "Document: 5 words, 25 characters, ~1 min read");
+
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(0, 7));
+            tab.editor.start_selection();
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(1, 4));
         }
-        let col = col.min(seg_end).min(max_col);
-        Some((row, col))
+        app.show_word_count();
+        assert_eq!(app.status, "Selection: 3 words, 13 characters, ~1 min read");
+
+        let rs = tmp.path().join("main.rs");
+        fs::write(&rs, "fn main() {}\n").expect("write");
+        app.open_file(rs).expect("open");
+        app.show_word_count();
+        assert_eq!(
+            app.status,
+            "Word count is for Markdown and plain text files"
+        );
     }
-    pub(crate) fn select_line(&mut self, row: usize) {
-        let Some(tab) = self.active_tab() else {
-            return;
-        };
-        let lines = tab.editor.lines();
-        let total = lines.len();
-        if total == 0 || row >= total {
-            return;
-        }
-        let line_len = lines[row].chars().count();
-        // Start selection from end and move cursor to start, so the cursor
-        // ends up at the beginning of the line rather than the next line.
-        if let Some(tab) = self.active_tab_mut() {
-            if row + 1 < total {
-                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                    to_u16_saturating(row + 1),
-                    0,
-                ));
-            } else {
-                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                    to_u16_saturating(row),
-                    to_u16_saturating(line_len),
-                ));
-            }
-            tab.editor.start_selection();
-            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                to_u16_saturating(row),
-                0,
-            ));
+
+    #[test]
+    fn reflow_wraps_the_cursor_paragraph_as_one_undo_step() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("notes.md");
+        let text = "# Title\n\nOne two three\nfour five six seven eight\n\nkeep\n";
+        fs::write(&file, text).expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        app.reflow_width = 14;
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(3, 2));
         }
-        self.sync_editor_scroll_guess();
+
+        app.run_key_action(KeyAction::ReflowParagraph)
+            .expect("reflow");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(
+            tab.editor.lines(),
+            [
+                "# Title",
+                "",
+                "One two three",
+                "four five six",
+                "seven eight",
+                "",
+                "keep",
+                "",
+            ]
+        );
+        assert_eq!(app.status, "Reflowed 3 line(s) to 14 columns");
+
+        app.run_key_action(KeyAction::Undo).expect("undo");
+        let lines = app.active_tab().expect("tab").editor.lines().join("\n");
+        assert_eq!(lines, text);
     }
 
-    pub(crate) fn select_line_range(&mut self, from: usize, to: usize) {
-        let Some(tab) = self.active_tab() else {
-            return;
-        };
-        let lines = tab.editor.lines();
-        let total = lines.len();
-        if total == 0 {
-            return;
+    #[test]
+    fn markdown_toggles_flip_checkboxes_and_list_markers() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("todo.md");
+        fs::write(&file, "  - [ ] write docs\nplain line\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        let line = |app: &App, row: usize| app.tabs[app.active_tab].editor.lines()[row].clone();
+
+        app.run_key_action(KeyAction::ToggleTaskCheckbox)
+            .expect("check");
+        assert_eq!(line(&app, 0), "  - [x] write docs");
+        app.run_key_action(KeyAction::ToggleTaskCheckbox)
+            .expect("uncheck");
+        assert_eq!(line(&app, 0), "  - [ ] write docs");
+
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(1, 5));
         }
-        let start = from.min(to).min(total.saturating_sub(1));
-        let end = from.max(to).min(total.saturating_sub(1));
-        // Select from start of first line to start of line after last (or end of last line)
-        if let Some(tab) = self.active_tab_mut() {
-            if end + 1 < total {
-                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                    to_u16_saturating(end + 1),
-                    0,
-                ));
-            } else {
-                let line_len = tab
-                    .editor
-                    .lines()
-                    .get(end)
-                    .map_or(0, |l| l.chars().count());
-                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                    to_u16_saturating(end),
-                    to_u16_saturating(line_len),
-                ));
-            }
-            tab.editor.start_selection();
-            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                to_u16_saturating(start),
-                0,
-            ));
+        app.run_key_action(KeyAction::ToggleListItem)
+            .expect("bullet");
+        assert_eq!(line(&app, 1), "- plain line");
+        assert_eq!(app.tabs[app.active_tab].editor.cursor(), (1, 7));
+        app.run_key_action(KeyAction::ToggleListItem)
+            .expect("unbullet");
+        assert_eq!(line(&app, 1), "plain line");
+
+        let rs = tmp.path().join("main.rs");
+        fs::write(&rs, "fn main() {}\n").expect("write");
+        app.open_file(rs).expect("open");
+        app.toggle_list_item();
+        assert_eq!(app.status, "List toggles work in Markdown files only");
+    }
+
+    #[test]
+    fn format_table_aligns_the_table_under_the_cursor() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("table.md");
+        fs::write(&file, "Intro\n|a|bb|\n|-|-:|\n|ccc|d|\nOutro\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(3, 1));
         }
-        self.sync_editor_scroll_guess();
+
+        app.run_command_action(CommandAction::FormatMarkdownTable)
+            .expect("format");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(
+            tab.editor.lines()[..5],
+            [
+                "Intro",
+                "| a   |  bb |",
+                "| --- | --: |",
+                "| ccc |   d |",
+                "Outro",
+            ]
+        );
+        assert_eq!(app.status, "Formatted table (3 rows)");
     }
 
-    pub(crate) fn gutter_row_from_mouse(&self, y: u16) -> Option<usize> {
-        let tab = self.active_tab()?;
-        let inner_y = y.saturating_sub(self.editor_rect.y.saturating_add(1)) as usize;
-        let visible_idx = tab.editor_scroll_row + inner_y;
-        tab.visible_rows_map.get(visible_idx).copied()
+    fn app_for_esc(root: &std::path::Path, esc: EscAction, dirty: bool) -> App {
+        let file = root.join("a.txt");
+        fs::write(&file, "alpha beta\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        app.editor_esc = esc;
+        app.focus = Focus::Editor;
+        let tab = app.active_tab_mut().expect("tab");
+        tab.dirty = dirty;
+        tab.editor.set_search_pattern("beta").expect("pattern");
+        tab.editor.start_selection();
+        tab.editor.move_cursor(ratatui_textarea::CursorMove::End);
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("esc");
+        app
     }
 
-    pub(crate) fn extend_mouse_selection(&mut self, x: u16, y: u16) {
-        if let (Some((anchor_row, anchor_col)), Some((row, col))) =
-            (self.editor_drag_anchor, self.editor_pos_from_mouse(x, y))
-        {
-            if let Some(tab) = self.active_tab_mut() {
-                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                    to_u16_saturating(anchor_row),
-                    to_u16_saturating(anchor_col),
-                ));
-                tab.editor.start_selection();
-                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                    to_u16_saturating(row),
-                    to_u16_saturating(col),
-                ));
-            }
-        }
+    #[test]
+    fn esc_close_closes_clean_files_and_asks_for_dirty_ones() {
+        let tmp = tempdir().expect("tempdir");
+        let app = app_for_esc(tmp.path(), EscAction::Close, false);
+        assert!(app.tabs.is_empty());
+
+        let app = app_for_esc(tmp.path(), EscAction::Close, true);
+        assert_eq!(app.tabs.len(), 1);
+        assert!(matches!(app.pending, PendingAction::ClosePrompt));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use tempfile::tempdir;
+    #[test]
+    fn esc_clear_only_drops_selection_and_search_highlight() {
+        let tmp = tempdir().expect("tempdir");
+        for dirty in [false, true] {
+            let app = app_for_esc(tmp.path(), EscAction::Clear, dirty);
+            assert!(matches!(app.pending, PendingAction::None));
+            let tab = app.active_tab().expect("tab stays open");
+            assert_eq!(tab.dirty, dirty);
+            assert!(tab.editor.selection_range().is_none());
+            assert!(tab.editor.search_pattern().is_none());
+        }
+    }
 
-    fn new_app(root: &std::path::Path) -> App {
-        App::new(root.to_path_buf()).expect("app should initialize")
+    #[test]
+    fn esc_nothing_leaves_the_editor_alone() {
+        let tmp = tempdir().expect("tempdir");
+        for dirty in [false, true] {
+            let app = app_for_esc(tmp.path(), EscAction::Nothing, dirty);
+            assert!(matches!(app.pending, PendingAction::None));
+            let tab = app.active_tab().expect("tab stays open");
+            assert!(tab.editor.selection_range().is_some());
+            assert!(tab.editor.search_pattern().is_some());
+        }
     }
 
+    #[cfg(unix)]
     #[test]
-    fn click_line_number_selects_line() {
+    fn file_associations_set_the_language_of_open_and_new_tabs() {
         let tmp = tempdir().expect("tempdir");
-        let root = tmp.path();
-        let file = root.join("test.txt");
-        fs::write(&file, "line 0\nline 1\nline 2\n").expect("write");
-        let mut app = new_app(root);
-        app.open_file(file).expect("open");
+        let file = tmp.path().join("app.conf");
+        fs::write(&file, "# comment\n").expect("write");
+        let lang = |app: &App| app.active_tab().expect("tab").syntax_lang();
+        let mut app = new_app(tmp.path());
+        app.open_file(file.clone()).expect("open");
+        assert_eq!(lang(&app), SyntaxLang::Plain);
 
-        app.select_line(1);
+        let mut config = app.config_snapshot();
+        config
+            .file_associations
+            .insert("conf".to_string(), "shell".to_string());
+        app.apply_config(&config);
+        assert_eq!(lang(&app), SyntaxLang::Shell);
 
-        let tab = app.active_tab().expect("tab");
-        let sel = tab.editor.selection_range().expect("should have selection");
-        assert_eq!(sel, ((1, 0), (2, 0)));
-        // Cursor should be at the start of the selected line
-        assert_eq!(tab.editor.cursor(), (1, 0));
+        app.close_file();
+        app.open_file(file).expect("reopen");
+        assert_eq!(lang(&app), SyntaxLang::Shell);
     }
 
     #[test]
-    fn click_line_number_selects_last_line() {
+    fn format_document_applies_the_formatter_output() {
         let tmp = tempdir().expect("tempdir");
-        let root = tmp.path();
-        let file = root.join("test.txt");
-        fs::write(&file, "line 0\nline 1\nlast line").expect("write");
-        let mut app = new_app(root);
+        let file = tmp.path().join("main.rs");
+        fs::write(&file, "fn main() {\n    let x = 1;\n}\n").expect("write");
+        let mut app = new_app(tmp.path());
         app.open_file(file).expect("open");
+        app.formatters
+            .insert("rust".to_string(), "tr a-z A-Z".to_string());
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(1, 8));
+        }
 
-        app.select_line(2);
-
+        app.run_command_action(CommandAction::FormatDocument)
+            .expect("format");
         let tab = app.active_tab().expect("tab");
-        let sel = tab.editor.selection_range().expect("should have selection");
-        assert_eq!(sel, ((2, 0), (2, 9)));
+        assert_eq!(
+            tab.editor.lines(),
+            ["FN MAIN() {", "    LET X = 1;", "}", ""]
+        );
+        assert_eq!(tab.editor.cursor(), (1, 8));
+        assert!(tab.dirty);
+        assert_eq!(app.status, "Formatted Rust");
+
+        app.run_key_action(KeyAction::Undo).expect("undo");
+        assert_eq!(
+            app.active_tab().expect("tab").editor.lines()[0],
+            "fn main() {"
+        );
     }
 
+    #[cfg(unix)]
     #[test]
-    fn click_line_number_selects_first_line() {
+    fn failing_formatter_leaves_the_buffer_unchanged() {
         let tmp = tempdir().expect("tempdir");
-        let root = tmp.path();
-        let file = root.join("test.txt");
-        fs::write(&file, "first\nsecond\n").expect("write");
-        let mut app = new_app(root);
+        fs::write(
+            tmp.path().join("fail.sh"),
+            "cat > /dev/null\necho 'expected `;` on line 2' >&2\nexit 1\n",
+        )
+        .expect("write script");
+        let file = tmp.path().join("main.rs");
+        fs::write(&file, "fn main() {\n    let x = 1\n}\n").expect("write");
+        let mut app = new_app(tmp.path());
         app.open_file(file).expect("open");
+        app.formatters
+            .insert("rust".to_string(), "sh fail.sh".to_string());
 
-        app.select_line(0);
-
+        app.format_document();
         let tab = app.active_tab().expect("tab");
-        let sel = tab.editor.selection_range().expect("should have selection");
-        assert_eq!(sel, ((0, 0), (1, 0)));
+        assert_eq!(
+            tab.editor.lines(),
+            ["fn main() {", "    let x = 1", "}", ""]
+        );
+        assert!(!tab.dirty);
+        assert_eq!(app.status, "Formatter failed: expected `;` on line 2");
+
+        app.formatters.clear();
+        app.format_document();
+        assert_eq!(app.status, "No formatter for Rust (set formatters.rust)");
     }
 
+    #[cfg(unix)]
     #[test]
-    fn cut_line_removes_middle_line() {
+    fn stalled_formatter_is_killed_and_leaves_the_buffer_unchanged() {
         let tmp = tempdir().expect("tempdir");
-        let root = tmp.path();
-        let file = root.join("test.txt");
-        fs::write(&file, "aaa\nbbb\nccc\n").expect("write");
-        let mut app = new_app(root);
+        let file = tmp.path().join("main.rs");
+        fs::write(&file, "fn main() {}\n").expect("write");
+        let mut app = new_app(tmp.path());
         app.open_file(file).expect("open");
-        // Move cursor to line 1 (bbb)
-        app.tabs[app.active_tab]
-            .editor
-            .move_cursor(ratatui_textarea::CursorMove::Jump(1, 0));
-        app.cut_line();
-        let lines = app.tabs[app.active_tab].editor.lines().to_vec();
-        assert_eq!(lines, vec!["aaa", "ccc", ""]);
+        app.formatters
+            .insert("rust".to_string(), "sleep 60".to_string());
+        app.formatter_timeout_ms = 200;
+
+        let started = Instant::now();
+        app.format_document();
+        assert!(started.elapsed() < Duration::from_secs(10));
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines(), ["fn main() {}", ""]);
+        assert!(!tab.dirty);
+        assert_eq!(app.status, "Formatter failed: sleep timed out after 0.2s");
     }
 
     #[test]
-    fn cut_line_removes_last_line() {
+    fn goto_matching_tag_jumps_between_nested_pairs() {
         let tmp = tempdir().expect("tempdir");
-        let root = tmp.path();
-        let file = root.join("test.txt");
-        fs::write(&file, "aaa\nbbb\n").expect("write");
-        let mut app = new_app(root);
+        let file = tmp.path().join("page.html");
+        fs::write(&file, "<div>\n  <div><br/></div>\n</div>\n").expect("write");
+        let mut app = new_app(tmp.path());
         app.open_file(file).expect("open");
-        // Move cursor to last content line (index 2 is the empty trailing line)
-        app.tabs[app.active_tab]
-            .editor
-            .move_cursor(ratatui_textarea::CursorMove::Jump(2, 0));
-        app.cut_line();
-        let lines = app.tabs[app.active_tab].editor.lines().to_vec();
-        assert_eq!(lines, vec!["aaa", "bbb"]);
-        // Cursor should be clamped, not out of bounds
-        let (row, _) = app.tabs[app.active_tab].editor.cursor();
-        assert!(row < lines.len());
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(1, 4));
+        }
+
+        app.goto_matching_tag();
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (1, 14));
+        app.goto_matching_tag();
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (1, 3));
+
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(1, 10));
+        }
+        app.goto_matching_tag();
+        assert_eq!(app.status, "No matching tag at the cursor");
     }
 }
@@ -1,11 +1,27 @@
-use super::App;
+use super::{App, RenameJob, TreeClipboard};
+use std::collections::{HashSet, VecDeque};
+use std::ffi::OsString;
 use std::fs;
 use std::io;
 use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+use std::time::SystemTime;
 
-use crate::tree_item::TreeItem;
-use crate::types::{ContextAction, PendingAction, PromptMode, PromptState};
-use crate::util::{collect_all_files, fuzzy_score, relative_path, to_u16_saturating};
+use crate::persistence::load_persisted_state;
+use crate::syntax::syntax_lang_with_associations;
+use crate::tree_item::{TreeItem, TreeMarker};
+use crate::types::{
+    ContextAction, Focus, OperationProgress, PendingAction, Platform, PromptMode, PromptState,
+};
+use crate::util::{
+    batch_rename_plan, classify_walk_entry, collect_all_files, compute_git_change_summary,
+    compute_git_file_statuses, compute_git_line_status, confirm_key_label, copy_dir_recursive,
+    delete_summary, describe_paths, detect_git_branch, fuzzy_score, open_command,
+    push_recent_project, relative_path, rename_target_taken, reveal_command, sort_tree_entries,
+    to_u16_saturating, unique_child_path,
+};
 
 impl App {
     fn sanitize_entry_name<'a>(&self, value: &'a str) -> Result<&'a str, &'static str> {
@@ -20,35 +36,16 @@ impl App {
         }
     }
 
-    fn close_tabs_for_path_prefix(&mut self, path: &Path) {
-        let mut indices: Vec<usize> = self
-            .tabs
-            .iter()
-            .enumerate()
-            .filter_map(|(idx, tab)| {
-                if tab.path == path || tab.path.starts_with(path) {
-                    Some(idx)
-                } else {
-                    None
-                }
-            })
-            .collect();
-        indices.sort_unstable();
-        indices.reverse();
-        for idx in indices {
-            self.close_tab_at(idx);
-        }
-    }
-
     fn retarget_tabs_for_rename(&mut self, from: &Path, to: &Path) {
         for tab in &mut self.tabs {
             if tab.path == from {
                 tab.path = to.to_path_buf();
-                continue;
-            }
-            if let Ok(suffix) = tab.path.strip_prefix(from) {
+            } else if let Ok(suffix) = tab.path.strip_prefix(from) {
                 tab.path = to.join(suffix);
+            } else {
+                continue;
             }
+            tab.detected_lang = syntax_lang_with_associations(&tab.path, &self.file_associations);
         }
     }
 
@@ -80,14 +77,202 @@ impl App {
                 depth: 0,
                 is_dir: true,
                 expanded: true,
+                size: None,
+                modified: None,
+                child_count: None,
+                marker: None,
             });
         }
         self.tree = out;
         self.selected = selected_path
             .and_then(|p| self.tree.iter().position(|i| i.path == p))
             .unwrap_or(0);
-        // Invalidate the cached file list; it will be rebuilt lazily when needed.
+        Ok(())
+    }
+
+    /// Open the folder picker where it was left this session, or at the
+    /// project root the first time or once that folder is gone.
+    pub(crate) fn open_folder_picker(&mut self) {
+        if self.folder_picker.dir.is_dir() {
+            let (index, offset) = (self.folder_picker.index, self.folder_picker.offset);
+            self.load_folder_picker(self.folder_picker.dir.clone());
+            let last_row = self.folder_picker.entries.len() + 1;
+            self.folder_picker.index = index.min(last_row);
+            self.folder_picker.offset = offset.min(self.folder_picker.index);
+        } else {
+            let start = self
+                .root
+                .canonicalize()
+                .unwrap_or_else(|_| self.root.clone());
+            self.load_folder_picker(start);
+        }
+        self.folder_picker.open = true;
+    }
+
+    /// Point the folder picker at `dir`, listing its subdirectories.
+    pub(crate) fn load_folder_picker(&mut self, dir: PathBuf) {
+        let mut entries: Vec<PathBuf> = fs::read_dir(&dir)
+            .map(|rd| {
+                rd.flatten()
+                    .map(|e| e.path())
+                    .filter(|p| p.is_dir())
+                    .collect()
+            })
+            .unwrap_or_default();
+        entries.sort_by_key(|p| {
+            p.file_name()
+                .map(|n| n.to_string_lossy().to_lowercase())
+                .unwrap_or_default()
+        });
+        self.folder_picker.dir = dir;
+        self.folder_picker.entries = entries;
+        self.folder_picker.index = 0;
+        self.folder_picker.offset = 0;
+    }
+
+    /// Go up to the parent of the folder being browsed.
+    pub(crate) fn folder_picker_up(&mut self) {
+        if let Some(parent) = self.folder_picker.dir.parent() {
+            self.load_folder_picker(parent.to_path_buf());
+        }
+    }
+
+    pub(crate) fn apply_folder_picker(&mut self) -> io::Result<()> {
+        match self.folder_picker.index {
+            0 => {
+                self.folder_picker.open = false;
+                let dir = self.folder_picker.dir.clone();
+                self.set_root(dir)?;
+            }
+            1 => self.folder_picker_up(),
+            idx => {
+                if let Some(dir) = self.folder_picker.entries.get(idx - 2).cloned() {
+                    self.load_folder_picker(dir);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// List the recent projects other than the current root, dropping any
+    /// whose folder is gone.
+    pub(crate) fn open_recent_project_picker(&mut self) {
+        let root = self
+            .root
+            .canonicalize()
+            .unwrap_or_else(|_| self.root.clone());
+        self.recent_projects.retain(|p| p.is_dir());
+        let projects: Vec<PathBuf> = self
+            .recent_projects
+            .iter()
+            .filter(|p| **p != root)
+            .cloned()
+            .collect();
+        if projects.is_empty() {
+            self.set_status("No other recent projects");
+            return;
+        }
+        self.recent_project_picker.index = 0;
+        self.recent_project_picker.projects = projects;
+        self.recent_project_picker.open = true;
+    }
+
+    pub(crate) fn apply_recent_project_picker(&mut self) -> io::Result<()> {
+        self.recent_project_picker.open = false;
+        let picked = self
+            .recent_project_picker
+            .projects
+            .get(self.recent_project_picker.index)
+            .cloned();
+        match picked {
+            Some(dir) => self.set_root(dir),
+            None => Ok(()),
+        }
+    }
+
+    /// Put the project root at the front of the recent projects. Saved
+    /// with the rest of the state on the next `persist_state`.
+    pub(crate) fn record_recent_project(&mut self) {
+        let root = self
+            .root
+            .canonicalize()
+            .unwrap_or_else(|_| self.root.clone());
+        push_recent_project(&mut self.recent_projects, &root, self.recent_projects_limit);
+    }
+
+    /// Make `new_root` the project root: rebuild the tree and file watcher,
+    /// forget expanded folders outside it, close clean tabs outside it and
+    /// restart the language server in the new workspace.
+    pub(crate) fn set_root(&mut self, new_root: PathBuf) -> io::Result<()> {
+        if !new_root.is_dir() {
+            self.set_status(format!("Not a folder: {}", new_root.display()));
+            return Ok(());
+        }
+        self.root = new_root;
+        let root = self.root.clone();
+        self.expanded.retain(|p| p.starts_with(&root));
+        self.expanded.insert(root.clone());
+        self.tree_selection.clear();
+        self.tree_selection_anchor = None;
+        self.selected = 0;
+        self.project_search = None;
+        self.search_results.open = false;
+        self.search_results.results.clear();
+        self.search_results.index = 0;
+        self.search_results.scroll = 0;
+        let saved = self.state_path.as_deref().and_then(load_persisted_state);
+        if let Some(layout) = saved.and_then(|s| s.project_layout(&root)) {
+            self.files_pane_width = layout.files_pane_width.max(Self::MIN_FILES_PANE_WIDTH);
+            self.files_view_open = layout.files_view_open;
+        }
+
+        let mut kept_outside = 0;
+        for idx in (0..self.tabs.len()).rev() {
+            let tab = &self.tabs[idx];
+            if tab.untitled || tab.path.starts_with(&root) {
+                continue;
+            }
+            if tab.dirty {
+                kept_outside += 1;
+            } else {
+                self.close_tab_at(idx);
+            }
+        }
+
+        // A refresh still running for the old root would report its statuses.
+        self.git_result_rx = None;
+        self.git_refresh_in_flight = false;
+        self.git_branch = detect_git_branch(&root);
+        self.git_file_statuses = compute_git_file_statuses(&root);
+        self.git_change_summary = compute_git_change_summary(&root);
+        for tab in &mut self.tabs {
+            tab.git_line_status =
+                compute_git_line_status(&root, &tab.path, tab.editor.lines().len());
+        }
+
+        // The file index and any walk still running belong to the old root.
         self.cached_file_list.clear();
+        self.file_index_rx = None;
+        self.file_index_stale = true;
+        self.detect_project();
+        self.revert_project_config();
+        let config_problem = self.load_project_config();
+        self.rebuild_tree()?;
+        self.start_fs_watcher();
+        self.restart_lsp();
+        if kept_outside > 0 {
+            self.set_status(format!(
+                "{} ({kept_outside} unsaved tab(s) outside it kept open)",
+                self.root_status()
+            ));
+        } else {
+            self.set_status(self.root_status());
+        }
+        if let Some(problem) = config_problem {
+            self.set_status(problem);
+        }
+        self.record_recent_project();
+        self.persist_state();
         Ok(())
     }
 
@@ -96,6 +281,19 @@ impl App {
         dir: &Path,
         depth: usize,
         out: &mut Vec<TreeItem>,
+    ) -> io::Result<()> {
+        let mut stack = HashSet::new();
+        self.walk_dir_with_stack(dir, depth, out, &mut stack)
+    }
+
+    /// `stack` holds the canonical paths of the directories currently being
+    /// walked, so a symlink back to one of them is not followed.
+    fn walk_dir_with_stack(
+        &self,
+        dir: &Path,
+        depth: usize,
+        out: &mut Vec<TreeItem>,
+        stack: &mut HashSet<PathBuf>,
     ) -> io::Result<()> {
         let is_root = dir == self.root;
 
@@ -107,12 +305,17 @@ impl App {
                 .map(|s| s.to_string_lossy().to_string())
                 .unwrap_or_else(|| dir.display().to_string());
             let expanded = self.expanded.contains(dir);
+            let (size, modified, child_count) = self.tree_item_details(dir, true);
             out.push(TreeItem {
                 path: dir.to_path_buf(),
                 name,
                 depth,
                 is_dir: true,
                 expanded,
+                size,
+                modified,
+                child_count,
+                marker: None,
             });
             if !expanded {
                 return Ok(());
@@ -121,44 +324,90 @@ impl App {
 
         let child_depth = if is_root { depth } else { depth + 1 };
 
-        let mut entries: Vec<_> = fs::read_dir(dir)?
-            .filter_map(Result::ok)
-            .map(|e| e.path())
-            .collect();
-        entries.sort_by_key(|p| {
-            (
-                !p.is_dir(),
-                p.file_name()
-                    .map(|s| s.to_string_lossy().to_ascii_lowercase())
-                    .unwrap_or_default(),
-            )
-        });
+        let entries: Vec<_> = match fs::read_dir(dir) {
+            Ok(read) => read.filter_map(Result::ok).map(|e| e.path()).collect(),
+            Err(e) if !is_root => {
+                let reason = TreeMarker::Error(e.kind().to_string());
+                out.push(TreeItem::marker(dir, child_depth, reason));
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        let entries = sort_tree_entries(
+            entries,
+            self.tree_sort,
+            self.tree_sort_descending,
+            self.tree_dirs_first,
+        );
 
+        let canonical = fs::canonicalize(dir).ok();
+        if let Some(c) = &canonical {
+            stack.insert(c.clone());
+        }
+        let first_child = out.len();
         for path in entries {
-            let Ok(ft) = fs::symlink_metadata(&path).map(|m| m.file_type()) else {
+            let Some(is_dir) = classify_walk_entry(&path, self.follow_symlinks, stack) else {
                 continue;
             };
-            // Avoid following directory symlink cycles.
-            if ft.is_symlink() {
-                continue;
-            }
-            let is_dir = ft.is_dir();
             let name = path
                 .file_name()
                 .map(|s| s.to_string_lossy().to_string())
                 .unwrap_or_else(|| path.display().to_string());
             if is_dir {
-                self.walk_dir(&path, child_depth, out)?;
+                self.walk_dir_with_stack(&path, child_depth, out, stack)?;
             } else {
+                let (size, modified, child_count) = self.tree_item_details(&path, false);
                 out.push(TreeItem {
                     path,
                     name,
                     depth: child_depth,
                     is_dir: false,
                     expanded: false,
+                    size,
+                    modified,
+                    child_count,
+                    marker: None,
                 });
             }
         }
+        if let Some(c) = canonical {
+            stack.remove(&c);
+        }
+        if !is_root && out.len() == first_child {
+            out.push(TreeItem::marker(dir, child_depth, TreeMarker::Empty));
+        }
+        Ok(())
+    }
+
+    /// Size, modified time and (for directories) entry count for the tree
+    /// details column. Skipped entirely while the column is hidden.
+    fn tree_item_details(
+        &self,
+        path: &Path,
+        is_dir: bool,
+    ) -> (Option<u64>, Option<SystemTime>, Option<usize>) {
+        if !self.tree_show_details {
+            return (None, None, None);
+        }
+        let meta = fs::metadata(path).ok();
+        let modified = meta.as_ref().and_then(|m| m.modified().ok());
+        if is_dir {
+            let count = fs::read_dir(path).ok().map(|entries| entries.count());
+            (None, modified, count)
+        } else {
+            (meta.map(|m| m.len()), modified, None)
+        }
+    }
+
+    pub(crate) fn toggle_tree_details(&mut self) -> io::Result<()> {
+        self.tree_show_details = !self.tree_show_details;
+        self.rebuild_tree()?;
+        self.persist_state();
+        if self.tree_show_details {
+            self.set_status("Tree details shown");
+        } else {
+            self.set_status("Tree details hidden");
+        }
         Ok(())
     }
 
@@ -170,19 +419,43 @@ impl App {
         self.status = status.into();
     }
 
+    /// Open Quick Open with an empty query, on the row picked last time if
+    /// the list is still that long.
+    pub(crate) fn open_file_picker(&mut self) {
+        self.file_picker_scope = None;
+        self.file_picker_open = true;
+        self.file_picker_query.clear();
+        self.refresh_file_picker_results();
+    }
+
+    /// Open Quick Open over the files under the selected tree folder, or
+    /// under the selected file's folder.
+    pub(crate) fn open_folder_file_picker(&mut self) {
+        let dir = match self.selected_item() {
+            Some(item) if item.is_dir || item.marker.is_some() => item.path.clone(),
+            Some(item) => item.path.parent().unwrap_or(&self.root).to_path_buf(),
+            None => self.root.clone(),
+        };
+        self.file_picker_scope = (dir != self.root).then_some(dir);
+        self.file_picker_open = true;
+        self.file_picker_query.clear();
+        self.refresh_file_picker_results();
+    }
+
     pub(crate) fn refresh_file_picker_results(&mut self) {
-        // Lazily rebuild the file list if it was invalidated
-        if self.cached_file_list.is_empty() {
-            let mut files = Vec::new();
-            collect_all_files(&self.root, &mut files);
-            self.cached_file_list = files;
+        // Score against the cached index; a stale one keeps serving while it
+        // is rebuilt in the background, and results fill in once it lands.
+        if self.file_index_stale && self.file_index_rx.is_none() {
+            self.start_file_index();
         }
         let query = self.file_picker_query.to_ascii_lowercase();
+        let base = self.file_picker_scope.as_ref().unwrap_or(&self.root);
         let mut scored: Vec<(usize, PathBuf)> = self
             .cached_file_list
             .iter()
+            .filter(|path| path.starts_with(base))
             .filter_map(|path| {
-                let rel = relative_path(&self.root, path).display().to_string();
+                let rel = relative_path(base, path).display().to_string();
                 fuzzy_score(&query, &rel).map(|score| (score, path.clone()))
             })
             .collect();
@@ -196,6 +469,38 @@ impl App {
             .min(self.file_picker_results.len().saturating_sub(1));
     }
 
+    /// Re-walk the project on a worker thread; `poll_file_index` swaps the
+    /// new list in when it's done.
+    pub(crate) fn start_file_index(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        let root = self.root.clone();
+        let follow_symlinks = self.follow_symlinks;
+        thread::spawn(move || {
+            let mut files = Vec::new();
+            collect_all_files(&root, follow_symlinks, &mut files);
+            let _ = tx.send(files);
+        });
+        self.file_index_rx = Some(rx);
+        self.file_index_stale = false;
+    }
+
+    pub(crate) fn poll_file_index(&mut self) {
+        let Some(rx) = self.file_index_rx.as_ref() else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(files) => {
+                self.file_index_rx = None;
+                self.cached_file_list = files;
+                if self.file_picker_open {
+                    self.refresh_file_picker_results();
+                }
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => self.file_index_rx = None,
+        }
+    }
+
     pub(crate) fn open_file_picker_selection(&mut self) -> io::Result<()> {
         let Some(path) = self
             .file_picker_results
@@ -217,6 +522,9 @@ impl App {
         let Some(item) = self.selected_item().cloned() else {
             return Ok(());
         };
+        if item.marker.is_some() {
+            return Ok(());
+        }
         if item.is_dir {
             if self.expanded.contains(&item.path) {
                 self.expanded.remove(&item.path);
@@ -240,7 +548,13 @@ impl App {
             let _ = self.rebuild_tree();
             return;
         }
-        if let Some(parent) = item.path.parent() {
+        // A marker row's path is already its directory.
+        let parent = if item.marker.is_some() {
+            Some(item.path.as_path())
+        } else {
+            item.path.parent()
+        };
+        if let Some(parent) = parent {
             if let Some(idx) = self.tree.iter().position(|i| i.path == parent) {
                 self.selected = idx;
             }
@@ -254,20 +568,7 @@ impl App {
         if !item.is_dir {
             return Ok(());
         }
-        fn collect_dirs(path: &Path, set: &mut std::collections::HashSet<PathBuf>) {
-            if let Ok(entries) = fs::read_dir(path) {
-                for entry in entries.flatten() {
-                    let p = entry.path();
-                    if p.is_dir() {
-                        set.insert(p.clone());
-                        collect_dirs(&p, set);
-                    }
-                }
-            }
-        }
-        self.expanded.insert(item.path.clone());
-        collect_dirs(&item.path, &mut self.expanded);
-        self.rebuild_tree()
+        self.expand_all_under(&item.path)
     }
 
     pub(crate) fn tree_collapse_recursive(&mut self) -> io::Result<()> {
@@ -290,84 +591,596 @@ impl App {
         self.rebuild_tree()
     }
 
-    pub(crate) fn tree_expand_all(&mut self) -> io::Result<()> {
-        fn collect_dirs(path: &Path, set: &mut std::collections::HashSet<PathBuf>) {
-            if let Ok(entries) = fs::read_dir(path) {
-                for entry in entries.flatten() {
-                    let p = entry.path();
-                    if p.is_dir() {
-                        set.insert(p.clone());
-                        collect_dirs(&p, set);
-                    }
+    /// Expand `path` and the directories below it, stopping after
+    /// `TREE_EXPAND_MAX_DEPTH` levels or `TREE_EXPAND_MAX_DIRS` directories.
+    pub(crate) fn expand_all_under(&mut self, path: &Path) -> io::Result<()> {
+        let (count, truncated) = self.expand_dirs_under(
+            path,
+            Self::TREE_EXPAND_MAX_DEPTH,
+            Self::TREE_EXPAND_MAX_DIRS,
+        );
+        self.rebuild_tree()?;
+        if truncated {
+            self.set_status(format!("Expanded {count} folders (stopped at limit)"));
+        } else {
+            self.set_status(format!("Expanded {count} folders"));
+        }
+        Ok(())
+    }
+
+    /// Breadth-first, so a limit cuts off the deepest folders first. Hidden,
+    /// `target` and `node_modules` folders are left collapsed. Returns how
+    /// many folders were expanded and whether a limit was hit.
+    fn expand_dirs_under(
+        &mut self,
+        path: &Path,
+        max_depth: usize,
+        max_dirs: usize,
+    ) -> (usize, bool) {
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([(path.to_path_buf(), 0usize)]);
+        let mut count = 0usize;
+        let mut truncated = false;
+        while let Some((dir, depth)) = queue.pop_front() {
+            if count >= max_dirs {
+                return (count, true);
+            }
+            if let Ok(canonical) = fs::canonicalize(&dir) {
+                seen.insert(canonical);
+            }
+            self.expanded.insert(dir.clone());
+            count += 1;
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for child in entries.filter_map(Result::ok).map(|e| e.path()) {
+                if classify_walk_entry(&child, self.follow_symlinks, &seen) != Some(true) {
+                    continue;
+                }
+                let name = child.file_name().unwrap_or_default().to_string_lossy();
+                if name.starts_with('.') || name == "target" || name == "node_modules" {
+                    continue;
                 }
+                if depth >= max_depth {
+                    truncated = true;
+                    continue;
+                }
+                queue.push_back((child, depth + 1));
             }
         }
-        self.expanded.insert(self.root.clone());
-        collect_dirs(&self.root, &mut self.expanded);
-        self.rebuild_tree()
+        (count, truncated)
+    }
+
+    pub(crate) fn cycle_tree_sort(&mut self) -> io::Result<()> {
+        self.tree_sort = self.tree_sort.next();
+        self.apply_tree_sort()
+    }
+
+    pub(crate) fn toggle_tree_sort_direction(&mut self) -> io::Result<()> {
+        self.tree_sort_descending = !self.tree_sort_descending;
+        self.apply_tree_sort()
+    }
+
+    pub(crate) fn toggle_tree_dirs_first(&mut self) -> io::Result<()> {
+        self.tree_dirs_first = !self.tree_dirs_first;
+        self.apply_tree_sort()
+    }
+
+    fn apply_tree_sort(&mut self) -> io::Result<()> {
+        self.rebuild_tree()?;
+        self.persist_state();
+        let direction = if self.tree_sort_descending {
+            "↓"
+        } else {
+            "↑"
+        };
+        let grouping = if self.tree_dirs_first {
+            ", folders first"
+        } else {
+            ""
+        };
+        self.set_status(format!(
+            "Tree sorted by {} {direction}{grouping}",
+            self.tree_sort.label()
+        ));
+        Ok(())
     }
 
-    pub(crate) fn tree_collapse_all(&mut self) -> io::Result<()> {
+    /// Collapse every folder, leaving only the project root expanded.
+    pub(crate) fn collapse_all(&mut self) -> io::Result<()> {
         self.expanded.clear();
+        self.expanded.insert(self.root.clone());
         self.selected = 0;
-        self.rebuild_tree()
+        self.rebuild_tree()?;
+        self.set_status("Collapsed all folders");
+        Ok(())
     }
 
-    pub(crate) fn delete_path(&mut self, path: PathBuf) -> io::Result<()> {
-        if path == self.root {
+    /// Delete every path in `paths` and close the tabs under them. Unsaved
+    /// edits in those tabs were already confirmed away by the delete prompt.
+    /// A path that fails to delete is reported and the rest still go.
+    pub(crate) fn delete_paths(&mut self, paths: Vec<PathBuf>) -> io::Result<()> {
+        if paths.contains(&self.root) {
             self.set_status("Cannot delete project root");
             return Ok(());
         }
-        if !path.exists() {
+        // Children of a directory that is also being deleted go with it.
+        let nested = |p: &PathBuf| paths.iter().any(|other| other != p && p.starts_with(other));
+        let paths: Vec<PathBuf> = paths.iter().filter(|p| !nested(p)).cloned().collect();
+        if paths.iter().all(|p| !p.exists()) {
             self.set_status("Path no longer exists");
             self.rebuild_tree()?;
             return Ok(());
         }
-        if path.is_dir() {
-            fs::remove_dir_all(&path)?;
-        } else {
-            fs::remove_file(&path)?;
+        let mut deleted = Vec::new();
+        let mut failed = Vec::new();
+        for path in paths {
+            if !path.exists() {
+                continue;
+            }
+            let result = if path.is_dir() {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+            match result {
+                Ok(()) => deleted.push(path),
+                Err(err) => failed.push(format!("{}: {err}", path.display())),
+            }
         }
-        // Close any tab at this path or under this directory.
-        self.close_tabs_for_path_prefix(&path);
-        self.expanded.retain(|p| !p.starts_with(&path));
+        for path in &deleted {
+            self.close_tabs_for_path_prefix(path);
+            self.expanded.retain(|p| !p.starts_with(path));
+        }
+        self.tree_selection.clear();
+        self.tree_selection_anchor = None;
         self.rebuild_tree()?;
-        self.set_status(format!("Deleted {}", path.display()));
+        let summary = match deleted.as_slice() {
+            [] => String::new(),
+            [path] => format!("Deleted {}", path.display()),
+            _ => format!("Deleted {} items", deleted.len()),
+        };
+        if failed.is_empty() {
+            self.set_status(summary);
+        } else if summary.is_empty() {
+            self.set_status(format!("Delete failed: {}", failed.join("; ")));
+        } else {
+            self.set_status(format!("{summary}; failed: {}", failed.join("; ")));
+        }
         Ok(())
     }
 
-    pub(crate) fn create_new_file(&mut self) -> io::Result<()> {
-        let base = self
-            .selected_item()
-            .map(|i| i.path.clone())
-            .unwrap_or_else(|| self.root.clone());
-        let parent = if base.is_dir() {
-            base
-        } else {
-            base.parent().unwrap_or(&self.root).to_path_buf()
+    /// Close every tab at or under `path`, unsaved or not.
+    fn close_tabs_for_path_prefix(&mut self, path: &Path) {
+        let mut idx = self.tabs.len();
+        while idx > 0 {
+            idx -= 1;
+            if self.tabs[idx].path.starts_with(path) {
+                self.close_tab_at(idx);
+            }
+        }
+    }
+
+    /// Selected tree paths in tree order. Selected paths inside folders that
+    /// have since been collapsed follow, sorted.
+    pub(crate) fn tree_selection_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self
+            .tree
+            .iter()
+            .filter(|item| self.tree_selection.contains(&item.path))
+            .map(|item| item.path.clone())
+            .collect();
+        let mut hidden: Vec<PathBuf> = self
+            .tree_selection
+            .iter()
+            .filter(|p| !paths.contains(p))
+            .cloned()
+            .collect();
+        hidden.sort();
+        paths.extend(hidden);
+        paths
+    }
+
+    /// Paths a tree operation should act on: the multi-selection when there
+    /// is one, otherwise the focused row.
+    pub(crate) fn tree_targets(&self) -> Vec<PathBuf> {
+        if !self.tree_selection.is_empty() {
+            return self.tree_selection_paths();
+        }
+        self.selected_item()
+            .map(|item| vec![item.path.clone()])
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn toggle_tree_selection(&mut self) {
+        let Some(path) = self.selected_item().map(|item| item.path.clone()) else {
+            return;
         };
-        let mut n = 1usize;
-        loop {
-            let candidate = parent.join(format!("new_file_{n}.txt"));
-            if !candidate.exists() {
-                fs::write(&candidate, b"")?;
-                self.rebuild_tree()?;
-                self.set_status(format!(
-                    "Created {}",
-                    relative_path(&self.root, &candidate).display()
+        if !self.tree_selection.remove(&path) {
+            self.tree_selection.insert(path.clone());
+        }
+        self.tree_selection_anchor = Some(path);
+        self.set_status(format!("{} selected", self.tree_selection.len()));
+    }
+
+    /// Move the focus by `delta` rows and select every row between the
+    /// anchor and the new focus.
+    pub(crate) fn extend_tree_selection(&mut self, delta: isize) {
+        if self.tree.is_empty() {
+            return;
+        }
+        let anchor_path = self
+            .tree_selection_anchor
+            .clone()
+            .unwrap_or_else(|| self.tree[self.selected].path.clone());
+        let anchor = self
+            .tree
+            .iter()
+            .position(|item| item.path == anchor_path)
+            .unwrap_or(self.selected);
+        self.selected = self
+            .selected
+            .saturating_add_signed(delta)
+            .min(self.tree.len() - 1);
+        let (start, end) = (anchor.min(self.selected), anchor.max(self.selected));
+        self.tree_selection = self.tree[start..=end]
+            .iter()
+            .map(|item| item.path.clone())
+            .collect();
+        self.tree_selection_anchor = Some(self.tree[anchor].path.clone());
+        self.set_status(format!("{} selected", self.tree_selection.len()));
+    }
+
+    pub(crate) fn clear_tree_selection(&mut self) {
+        self.tree_selection.clear();
+        self.tree_selection_anchor = None;
+    }
+
+    pub(crate) fn request_delete(&mut self, paths: Vec<PathBuf>) {
+        if paths.is_empty() {
+            return;
+        }
+        if paths.contains(&self.root) {
+            self.set_status("Cannot delete project root");
+            return;
+        }
+        let mut summary = delete_summary(&paths, Self::DELETE_COUNT_LIMIT);
+        // Unsaved edits under the targets go with them; say so before asking.
+        let unsaved = self
+            .tabs
+            .iter()
+            .filter(|t| t.dirty && paths.iter().any(|p| t.path.starts_with(p)))
+            .count();
+        if unsaved > 0 {
+            summary.push_str(&format!(" and discard {unsaved} unsaved tab(s)"));
+        }
+        self.pending = PendingAction::Delete {
+            paths,
+            summary: summary.clone(),
+        };
+        let key = confirm_key_label(&self.pending, self.confirm_key);
+        self.set_status(format!(
+            "Delete {summary} ? Press {key} to confirm, Esc to cancel."
+        ));
+    }
+
+    /// Ask for the find and replace text of a batch rename over the
+    /// multi-selection or, without one, the entries of the focused folder.
+    pub(crate) fn start_batch_rename(&mut self) {
+        let mut paths = self.tree_selection_paths();
+        if paths.is_empty() {
+            let dir = match self.selected_item() {
+                Some(item) if item.is_dir || item.marker.is_some() => item.path.clone(),
+                _ => {
+                    self.set_status("Select files or a folder to batch rename");
+                    return;
+                }
+            };
+            paths = fs::read_dir(&dir)
+                .map(|entries| entries.flatten().map(|e| e.path()).collect())
+                .unwrap_or_default();
+            paths.sort();
+        }
+        paths.retain(|p| p != &self.root);
+        if paths.is_empty() {
+            self.set_status("Nothing to rename");
+            return;
+        }
+        self.prompt = Some(PromptState {
+            title: format!("Batch rename {}: find (/regex/)", describe_paths(&paths)),
+            value: String::new(),
+            cursor: 0,
+            mode: PromptMode::BatchRenameFind { paths },
+            history: None,
+        });
+    }
+
+    /// Start a confirmed batch rename, checking again that no target has
+    /// appeared since the preview. The first step runs at once, so small
+    /// batches are done before this returns.
+    pub(crate) fn batch_rename(&mut self, mut renames: Vec<(PathBuf, PathBuf)>) -> io::Result<()> {
+        if let Some((_, to)) = renames
+            .iter()
+            .find(|(from, to)| rename_target_taken(from, to))
+        {
+            self.set_status(format!(
+                "Batch rename aborted: {} already exists",
+                relative_path(&self.root, to).display()
+            ));
+            return Ok(());
+        }
+        // Deepest first, so renaming a folder never moves a path still to come.
+        renames.sort_by_key(|(from, _)| std::cmp::Reverse(from.components().count()));
+        self.progress = Some(OperationProgress::new("Renaming", Some(renames.len())));
+        self.rename_job = Some(RenameJob {
+            queue: renames.into(),
+            renamed: 0,
+        });
+        self.poll_batch_rename();
+        Ok(())
+    }
+
+    /// Make the next `BATCH_RENAME_STEP` renames of the running batch
+    /// rename, and report the total once it is done.
+    pub(crate) fn poll_batch_rename(&mut self) {
+        let Some(mut job) = self.rename_job.take() else {
+            return;
+        };
+        let total = job.renamed + job.queue.len();
+        for _ in 0..Self::BATCH_RENAME_STEP {
+            let Some((from, to)) = job.queue.pop_front() else {
+                break;
+            };
+            if let Err(err) = fs::rename(&from, &to) {
+                self.finish_batch_rename(format!(
+                    "Batch rename stopped after {} of {total}: {err}",
+                    job.renamed
                 ));
-                return Ok(());
+                return;
             }
-            n += 1;
+            self.retarget_tabs_for_rename(&from, &to);
+            self.retarget_expanded_for_rename(&from, &to);
+            job.renamed += 1;
+        }
+        if job.queue.is_empty() {
+            self.finish_batch_rename(format!("Renamed {} item(s)", job.renamed));
+            return;
         }
+        self.progress = Some(OperationProgress {
+            label: "Renaming".to_string(),
+            done: job.renamed,
+            total: Some(total),
+        });
+        self.rename_job = Some(job);
     }
 
-    pub(crate) fn apply_prompt(&mut self, mode: PromptMode, value: String) -> io::Result<()> {
-        match mode {
-            PromptMode::NewFile { parent } => {
-                let name = match self.sanitize_entry_name(&value) {
-                    Ok(name) => name,
-                    Err(msg) => {
+    /// Stop the running batch rename, keeping the renames already made.
+    pub(crate) fn cancel_batch_rename(&mut self) {
+        if let Some(job) = self.rename_job.take() {
+            let total = job.renamed + job.queue.len();
+            self.finish_batch_rename(format!(
+                "Batch rename cancelled: {} of {total} renamed",
+                job.renamed
+            ));
+        }
+    }
+
+    fn finish_batch_rename(&mut self, summary: String) {
+        self.rename_job = None;
+        self.progress = None;
+        self.clear_tree_selection();
+        let _ = self.rebuild_tree();
+        self.set_status(summary);
+    }
+
+    pub(crate) fn copy_tree_targets(&mut self, cut: bool) {
+        let paths = self.tree_targets();
+        if paths.is_empty() {
+            return;
+        }
+        if paths.contains(&self.root) {
+            self.set_status("Cannot copy or cut project root");
+            return;
+        }
+        let verb = if cut { "Cut" } else { "Copied" };
+        self.set_status(format!("{verb} {}", describe_paths(&paths)));
+        self.tree_clipboard = Some(TreeClipboard { paths, cut });
+    }
+
+    /// Move `src` into `dest_dir`, renaming on a clash like paste does, and
+    /// retarget open tabs and expanded folders. Returns `None` without
+    /// touching disk when `src` is gone, already there, or would land inside
+    /// itself.
+    fn move_into_dir(&mut self, src: &Path, dest_dir: &Path) -> io::Result<Option<PathBuf>> {
+        let Some(name) = src.file_name() else {
+            return Ok(None);
+        };
+        if !src.exists() || dest_dir.starts_with(src) || src.parent() == Some(dest_dir) {
+            return Ok(None);
+        }
+        let dest = unique_child_path(dest_dir, name);
+        fs::rename(src, &dest)?;
+        self.retarget_tabs_for_rename(src, &dest);
+        self.retarget_expanded_for_rename(src, &dest);
+        Ok(Some(dest))
+    }
+
+    /// Folder a tree drag released on `row` drops into: the folder under the
+    /// cursor, a file's parent folder, or the root below the last row.
+    pub(crate) fn tree_drop_target(&self, row: u16) -> PathBuf {
+        match self.tree_index_from_mouse(row).map(|idx| &self.tree[idx]) {
+            Some(item) if item.is_dir || item.marker.is_some() => item.path.clone(),
+            Some(item) => item.path.parent().unwrap_or(&self.root).to_path_buf(),
+            None => self.root.clone(),
+        }
+    }
+
+    /// Finish a tree drag by moving `src` into `dest_dir`.
+    pub(crate) fn drop_tree_item(&mut self, src: &Path, dest_dir: &Path) -> io::Result<()> {
+        if src == self.root {
+            self.set_status("Cannot move project root");
+            return Ok(());
+        }
+        let Some(dest) = self.move_into_dir(src, dest_dir)? else {
+            self.set_status(format!(
+                "Cannot move {} there",
+                relative_path(&self.root, src).display()
+            ));
+            return Ok(());
+        };
+        self.expanded.insert(dest_dir.to_path_buf());
+        self.rebuild_tree()?;
+        if let Some(idx) = self.tree.iter().position(|item| item.path == dest) {
+            self.selected = idx;
+        }
+        self.set_status(format!(
+            "Moved {} to {}",
+            relative_path(&self.root, src).display(),
+            relative_path(&self.root, &dest).display()
+        ));
+        Ok(())
+    }
+
+    /// Paste the tree clipboard into the focused folder (or the focused
+    /// file's folder). Name clashes get a " copy" suffix; a cut is consumed
+    /// by the paste.
+    pub(crate) fn paste_tree_clipboard(&mut self) -> io::Result<()> {
+        let Some(clipboard) = self.tree_clipboard.take() else {
+            self.set_status("Nothing to paste");
+            return Ok(());
+        };
+        let dest_dir = match self.selected_item() {
+            Some(item) if item.is_dir || item.marker.is_some() => item.path.clone(),
+            Some(item) => item.path.parent().unwrap_or(&self.root).to_path_buf(),
+            None => self.root.clone(),
+        };
+        let mut pasted = Vec::new();
+        let mut skipped = 0;
+        for src in &clipboard.paths {
+            if clipboard.cut {
+                match self.move_into_dir(src, &dest_dir)? {
+                    Some(dest) => pasted.push(dest),
+                    None => skipped += 1,
+                }
+                continue;
+            }
+            let Some(name) = src.file_name() else {
+                skipped += 1;
+                continue;
+            };
+            if !src.exists() || dest_dir.starts_with(src) {
+                skipped += 1;
+                continue;
+            }
+            let dest = unique_child_path(&dest_dir, name);
+            if src.is_dir() {
+                copy_dir_recursive(src, &dest)?;
+            } else {
+                fs::copy(src, &dest)?;
+            }
+            pasted.push(dest);
+        }
+        if !clipboard.cut {
+            self.tree_clipboard = Some(clipboard);
+        }
+        self.clear_tree_selection();
+        self.expanded.insert(dest_dir.clone());
+        self.rebuild_tree()?;
+        if let Some(first) = pasted.first()
+            && let Some(idx) = self.tree.iter().position(|item| &item.path == first)
+        {
+            self.selected = idx;
+        }
+        let mut status = format!(
+            "Pasted {} into {}",
+            describe_paths(&pasted),
+            relative_path(&self.root, &dest_dir).display()
+        );
+        if skipped > 0 {
+            status.push_str(&format!(" ({skipped} skipped)"));
+        }
+        self.set_status(status);
+        Ok(())
+    }
+
+    /// Show `path` in the system file manager (Finder, Explorer, or the
+    /// folder via `xdg-open`).
+    pub(crate) fn reveal_in_os_file_manager(&mut self, path: &Path) {
+        if !path.exists() {
+            self.set_status("Path no longer exists");
+            return;
+        }
+        let (program, args) = reveal_command(path, Platform::current());
+        match spawn_detached(program, &args) {
+            Ok(()) => self.set_status(format!(
+                "Revealed {} in file manager",
+                relative_path(&self.root, path).display()
+            )),
+            Err(err) => self.set_status(format!("Could not run {program}: {err}")),
+        }
+    }
+
+    /// Open `path` with the application the OS associates with it.
+    pub(crate) fn open_externally(&mut self, path: &Path) {
+        if !path.exists() {
+            self.set_status("Path no longer exists");
+            return;
+        }
+        let (program, args) = open_command(path, Platform::current());
+        match spawn_detached(program, &args) {
+            Ok(()) => self.set_status(format!(
+                "Opened {} externally",
+                relative_path(&self.root, path).display()
+            )),
+            Err(err) => self.set_status(format!("Could not run {program}: {err}")),
+        }
+    }
+
+    /// Reveal the active tab's file when the editor has focus, otherwise the
+    /// focused tree row.
+    pub(crate) fn reveal_current_in_os_file_manager(&mut self) {
+        let target = match self.active_tab() {
+            Some(tab) if self.focus == Focus::Editor && !tab.untitled => Some(tab.path.clone()),
+            _ => self.selected_item().map(|item| item.path.clone()),
+        };
+        match target {
+            Some(path) => self.reveal_in_os_file_manager(&path),
+            None => self.set_status("Nothing to reveal"),
+        }
+    }
+
+    pub(crate) fn create_new_file(&mut self) -> io::Result<()> {
+        let base = self
+            .selected_item()
+            .map(|i| i.path.clone())
+            .unwrap_or_else(|| self.root.clone());
+        let parent = if base.is_dir() {
+            base
+        } else {
+            base.parent().unwrap_or(&self.root).to_path_buf()
+        };
+        let mut n = 1usize;
+        loop {
+            let candidate = parent.join(format!("new_file_{n}.txt"));
+            if !candidate.exists() {
+                fs::write(&candidate, b"")?;
+                self.rebuild_tree()?;
+                self.set_status(format!(
+                    "Created {}",
+                    relative_path(&self.root, &candidate).display()
+                ));
+                return Ok(());
+            }
+            n += 1;
+        }
+    }
+
+    pub(crate) fn apply_prompt(&mut self, mode: PromptMode, value: String) -> io::Result<()> {
+        match mode {
+            PromptMode::NewFile { parent } => {
+                let name = match self.sanitize_entry_name(&value) {
+                    Ok(name) => name,
+                    Err(msg) => {
                         self.set_status(msg);
                         return Ok(());
                     }
@@ -452,6 +1265,7 @@ impl App {
                         value: String::new(),
                         cursor: 0,
                         mode: PromptMode::ReplaceInFile { search: value },
+                        history: None,
                     });
                 }
             }
@@ -461,6 +1275,9 @@ impl App {
             PromptMode::ReplaceInFile { search } => {
                 self.replace_in_open_file(&search, &value);
             }
+            PromptMode::SaveAs => {
+                self.save_untitled_as(&value)?;
+            }
             PromptMode::GoToLine => {
                 if let Ok(line_num) = value.parse::<usize>() {
                     if line_num == 0 {
@@ -468,6 +1285,7 @@ impl App {
                         return Ok(());
                     }
                     let target = line_num.saturating_sub(1);
+                    self.push_nav_history();
                     if let Some(tab) = self.active_tab_mut() {
                         let max_line = tab.editor.lines().len().saturating_sub(1);
                         let clamped = target.min(max_line);
@@ -483,6 +1301,44 @@ impl App {
                     self.set_status("Invalid line number");
                 }
             }
+            PromptMode::ZenWidth => match value.parse::<u16>() {
+                Ok(width) if width >= Self::MIN_ZEN_WIDTH => {
+                    self.zen_width = width;
+                    self.persist_state();
+                    self.set_status(format!("Zen mode text width: {width}"));
+                }
+                _ => self.set_status(format!(
+                    "Zen width must be a number >= {}",
+                    Self::MIN_ZEN_WIDTH
+                )),
+            },
+            PromptMode::BatchRenameFind { paths } => {
+                if value.is_empty() {
+                    self.set_status("Find text is empty");
+                    return Ok(());
+                }
+                self.prompt = Some(PromptState {
+                    title: format!("Rename '{value}' to"),
+                    value: String::new(),
+                    cursor: 0,
+                    mode: PromptMode::BatchRenameReplace { paths, find: value },
+                    history: None,
+                });
+            }
+            PromptMode::BatchRenameReplace { paths, find } => {
+                match batch_rename_plan(&paths, &find, &value) {
+                    Ok(renames) if renames.is_empty() => self.set_status("No names match"),
+                    Ok(renames) => {
+                        let count = renames.len();
+                        self.pending = PendingAction::BatchRename { renames };
+                        let key = confirm_key_label(&self.pending, self.confirm_key);
+                        self.set_status(format!(
+                            "Rename {count} item(s)? Press {key} to confirm, Esc to cancel."
+                        ));
+                    }
+                    Err(msg) => self.set_status(format!("Batch rename aborted: {msg}")),
+                }
+            }
         }
         Ok(())
     }
@@ -514,6 +1370,7 @@ impl App {
                     value: String::new(),
                     cursor: 0,
                     mode: PromptMode::NewFile { parent },
+                    history: None,
                 });
             }
             ContextAction::NewFolder => {
@@ -530,6 +1387,7 @@ impl App {
                     value: String::new(),
                     cursor: 0,
                     mode: PromptMode::NewFolder { parent },
+                    history: None,
                 });
             }
             ContextAction::Rename => {
@@ -547,46 +1405,70 @@ impl App {
                     value: default_name,
                     cursor,
                     mode: PromptMode::Rename { target },
+                    history: None,
                 });
             }
             ContextAction::Delete => {
-                if target == self.root {
-                    self.set_status("Cannot delete project root");
-                    return Ok(());
-                }
-                self.pending = PendingAction::Delete(target.clone());
-                self.set_status(format!(
-                    "Delete {} ? Press Enter to confirm, Esc to cancel.",
+                // Right-clicking inside the multi-selection deletes all of it.
+                let paths = if self.tree_selection.contains(&target) {
+                    self.tree_selection_paths()
+                } else {
+                    vec![target]
+                };
+                self.request_delete(paths);
+            }
+            ContextAction::Reveal => self.reveal_in_os_file_manager(&target),
+            ContextAction::ExpandAll => {
+                let dir = if target.is_dir() {
                     target
-                        .file_name()
-                        .map(|s| s.to_string_lossy().to_string())
-                        .unwrap_or_else(|| target.display().to_string()),
-                ));
+                } else {
+                    target.parent().unwrap_or(&self.root).to_path_buf()
+                };
+                self.expand_all_under(&dir)?;
             }
+            ContextAction::CollapseAll => self.collapse_all()?,
             ContextAction::Cancel => {}
         }
         Ok(())
     }
 }
 
+/// Launch a helper program with no stdio, reaping it in the background so
+/// it does not linger as a zombie.
+fn spawn_detached(program: &str, args: &[OsString]) -> io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::{CommandAction, ConfirmKey, TreeSort};
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
     use std::fs;
     use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
     use tempfile::tempdir;
 
     fn new_app(root: &Path) -> App {
-        App::new(root.to_path_buf()).expect("app should initialize")
+        App::with_paths(root.to_path_buf(), None, None).expect("app should initialize")
     }
 
     #[test]
-    fn delete_path_rejects_project_root() {
+    fn delete_paths_rejects_project_root() {
         let tmp = tempdir().expect("tempdir");
         let root = tmp.path();
         let mut app = new_app(root);
 
-        app.delete_path(root.to_path_buf())
+        app.delete_paths(vec![root.to_path_buf()])
             .expect("delete root should be non-fatal");
 
         assert!(root.exists());
@@ -621,7 +1503,7 @@ mod tests {
 
         assert!(path.exists());
         match &app.pending {
-            PendingAction::Delete(p) => assert_eq!(p, &path),
+            PendingAction::Delete { paths, .. } => assert_eq!(paths, &vec![path]),
             _ => panic!("expected pending delete"),
         }
     }
@@ -710,9 +1592,11 @@ mod tests {
         fs::create_dir_all(root.join("src")).expect("create src");
         fs::write(root.join("src/c.rs"), "fn c() {}\n").expect("write c");
         let mut app = new_app(root);
-        // Cache starts empty (lazy); trigger lazy rebuild via file picker
+        // Cache starts empty; the file picker builds it in the background
         assert!(app.cached_file_list.is_empty(), "cache should start empty");
         app.refresh_file_picker_results();
+        assert!(app.cached_file_list.is_empty(), "index is built off-thread");
+        wait_for_file_index(&mut app);
         assert!(
             app.cached_file_list.len() >= 3,
             "cached_file_list should contain at least the 3 created files, got {}",
@@ -736,6 +1620,7 @@ mod tests {
         app.file_picker_open = true;
         app.file_picker_query = "main".to_string();
         app.refresh_file_picker_results();
+        wait_for_file_index(&mut app);
         assert!(
             !app.file_picker_results.is_empty(),
             "should find main.rs via cached list"
@@ -743,6 +1628,91 @@ mod tests {
         assert!(app.file_picker_results[0].ends_with("main.rs"));
     }
 
+    #[test]
+    fn file_index_reused_across_keystrokes() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::write(root.join("a.rs"), "fn a() {}\n").expect("write a");
+        let mut app = new_app(root);
+        app.file_picker_open = true;
+        app.refresh_file_picker_results();
+        wait_for_file_index(&mut app);
+        // Only the index knows this path, so a re-walk would drop it
+        let phantom = root.join("phantom.rs");
+        app.cached_file_list.push(phantom.clone());
+        for query in ["p", "ph", "pha", "phantom"] {
+            app.file_picker_query = query.to_string();
+            app.refresh_file_picker_results();
+            assert_eq!(app.file_picker_results, vec![phantom.clone()]);
+        }
+        assert!(!app.file_index_stale);
+        assert!(app.file_index_rx.is_none());
+    }
+
+    fn wait_for_file_index(app: &mut App) {
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while app.file_index_rx.is_some() && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+            app.poll_file_index();
+        }
+        assert!(app.file_index_rx.is_none(), "file index should finish");
+    }
+
+    /// Build the file index and score the picker against it.
+    fn refresh_with_file_index(app: &mut App) {
+        app.refresh_file_picker_results();
+        wait_for_file_index(app);
+        app.refresh_file_picker_results();
+    }
+
+    /// Poll the watcher until it reports files created or removed.
+    fn wait_for_fs_files_changed(app: &mut App) {
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while !app.file_index_stale && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+            app.poll_fs_changes().expect("poll fs");
+        }
+        assert!(app.file_index_stale, "watcher should invalidate the index");
+    }
+
+    #[test]
+    fn file_index_rebuilt_after_create_and_delete() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::write(root.join("a.rs"), "fn a() {}\n").expect("write a");
+        fs::write(root.join("b.rs"), "fn b() {}\n").expect("write b");
+        let mut app = new_app(root);
+        app.file_picker_open = true;
+        app.refresh_file_picker_results();
+        wait_for_file_index(&mut app);
+        assert_eq!(app.file_picker_results.len(), 2);
+        // Expanding folders leaves the index alone
+        app.rebuild_tree().expect("rebuild");
+        assert!(!app.file_index_stale);
+
+        app.apply_prompt(
+            PromptMode::NewFile {
+                parent: root.to_path_buf(),
+            },
+            "c.rs".to_string(),
+        )
+        .expect("create");
+        wait_for_fs_files_changed(&mut app);
+        // The stale index keeps serving while the new one is built
+        app.refresh_file_picker_results();
+        assert!(app.file_index_rx.is_some());
+        assert_eq!(app.file_picker_results.len(), 2);
+        wait_for_file_index(&mut app);
+        assert!(app.file_picker_results.contains(&root.join("c.rs")));
+
+        fs::remove_file(root.join("b.rs")).expect("delete b");
+        wait_for_fs_files_changed(&mut app);
+        app.refresh_file_picker_results();
+        wait_for_file_index(&mut app);
+        assert!(!app.cached_file_list.contains(&root.join("b.rs")));
+        assert_eq!(app.file_picker_results.len(), 2);
+    }
+
     #[test]
     fn file_picker_empty_query_returns_all() {
         let tmp = tempdir().expect("tempdir");
@@ -751,10 +1721,744 @@ mod tests {
         fs::write(root.join("b.txt"), "b\n").expect("write b");
         let mut app = new_app(root);
         app.file_picker_query.clear();
-        app.refresh_file_picker_results();
+        refresh_with_file_index(&mut app);
         assert!(
             app.file_picker_results.len() >= 2,
             "empty query should return all files"
         );
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_dir_follows_safe_symlinked_dir() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("real")).expect("mkdir real");
+        fs::write(root.join("real/inner.rs"), "fn inner() {}\n").expect("write inner");
+        std::os::unix::fs::symlink(root.join("real"), root.join("linked")).expect("symlink");
+        let mut app = new_app(root);
+        app.expanded.insert(root.join("linked"));
+        app.rebuild_tree().expect("rebuild");
+
+        let linked = app
+            .tree
+            .iter()
+            .find(|i| i.path == root.join("linked"))
+            .expect("symlinked dir should be listed");
+        assert!(linked.is_dir);
+        assert!(
+            app.tree
+                .iter()
+                .any(|i| i.path == root.join("linked/inner.rs")),
+            "children of symlinked dir should be listed"
+        );
+        refresh_with_file_index(&mut app);
+        assert!(app.cached_file_list.contains(&root.join("linked/inner.rs")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_dir_skips_self_referential_and_broken_symlinks() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("real")).expect("mkdir real");
+        fs::write(root.join("real/a.rs"), "fn a() {}\n").expect("write a");
+        std::os::unix::fs::symlink(root.join("real"), root.join("real/loop")).expect("loop");
+        std::os::unix::fs::symlink(root.join("missing"), root.join("broken")).expect("broken");
+        let mut app = new_app(root);
+        app.expanded.insert(root.join("real"));
+        app.rebuild_tree().expect("rebuild");
+
+        assert!(app.tree.iter().any(|i| i.path == root.join("real/a.rs")));
+        assert!(!app.tree.iter().any(|i| i.path == root.join("real/loop")));
+        assert!(!app.tree.iter().any(|i| i.path == root.join("broken")));
+        refresh_with_file_index(&mut app);
+        assert_eq!(app.cached_file_list, vec![root.join("real/a.rs")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_dir_skips_symlinks_when_disabled() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("real")).expect("mkdir real");
+        std::os::unix::fs::symlink(root.join("real"), root.join("linked")).expect("symlink");
+        let mut app = new_app(root);
+        app.follow_symlinks = false;
+        app.rebuild_tree().expect("rebuild");
+
+        assert!(app.tree.iter().any(|i| i.path == root.join("real")));
+        assert!(!app.tree.iter().any(|i| i.path == root.join("linked")));
+    }
+
+    #[test]
+    fn collapse_all_leaves_only_root_expanded() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("a/b/c")).expect("mkdir");
+        fs::create_dir_all(root.join("d")).expect("mkdir");
+        let mut app = new_app(root);
+        app.expand_all_under(root).expect("expand");
+        assert!(app.expanded.contains(&root.join("a/b/c")));
+
+        app.collapse_all().expect("collapse");
+
+        assert_eq!(app.expanded, HashSet::from([root.to_path_buf()]));
+        let names: Vec<&str> = app.tree.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "d"]);
+    }
+
+    #[test]
+    fn expand_all_under_adds_nested_dirs_up_to_limit() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src/app/ui")).expect("mkdir");
+        fs::create_dir_all(root.join("src/util")).expect("mkdir");
+        fs::create_dir_all(root.join("docs")).expect("mkdir");
+        fs::create_dir_all(root.join(".git/objects")).expect("mkdir");
+        let mut app = new_app(root);
+
+        app.expand_all_under(&root.join("src")).expect("expand");
+        for dir in ["src", "src/app", "src/app/ui", "src/util"] {
+            assert!(app.expanded.contains(&root.join(dir)), "{dir}");
+        }
+        assert!(!app.expanded.contains(&root.join("docs")));
+        assert_eq!(app.status, "Expanded 4 folders");
+
+        app.collapse_all().expect("collapse");
+        app.expand_all_under(root).expect("expand");
+        assert!(!app.expanded.contains(&root.join(".git")));
+
+        // Breadth-first: a limit keeps shallow folders and drops deep ones.
+        app.collapse_all().expect("collapse");
+        assert_eq!(app.expand_dirs_under(&root.join("src"), 32, 3), (3, true));
+        assert!(app.expanded.contains(&root.join("src/app")));
+        assert!(app.expanded.contains(&root.join("src/util")));
+        assert!(!app.expanded.contains(&root.join("src/app/ui")));
+
+        app.collapse_all().expect("collapse");
+        assert_eq!(app.expand_dirs_under(&root.join("src"), 1, 100), (3, true));
+        assert!(!app.expanded.contains(&root.join("src/app/ui")));
+    }
+
+    fn sorted_tree_names(
+        app: &mut App,
+        sort: TreeSort,
+        descending: bool,
+        dirs_first: bool,
+    ) -> Vec<String> {
+        app.tree_sort = sort;
+        app.tree_sort_descending = descending;
+        app.tree_dirs_first = dirs_first;
+        app.rebuild_tree().expect("rebuild");
+        app.tree.iter().map(|i| i.name.clone()).collect()
+    }
+
+    #[test]
+    fn tree_sort_orders_entries_by_name_mtime_and_size() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir(root.join("zdir")).expect("mkdir");
+        // (name, size in bytes, mtime in seconds since the epoch)
+        for (name, size, mtime) in [
+            ("a.txt", 10, 2_000),
+            ("b.rs", 1000, 3_000),
+            ("c.md", 100, 1_000),
+        ] {
+            let path = root.join(name);
+            fs::write(&path, "x".repeat(size)).expect("write");
+            let modified = UNIX_EPOCH + Duration::from_secs(mtime);
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|f| f.set_modified(modified))
+                .expect("set mtime");
+        }
+        let mut app = new_app(root);
+
+        assert_eq!(
+            sorted_tree_names(&mut app, TreeSort::Name, true, true),
+            ["zdir", "c.md", "b.rs", "a.txt"]
+        );
+        assert_eq!(
+            sorted_tree_names(&mut app, TreeSort::Modified, false, true),
+            ["zdir", "c.md", "a.txt", "b.rs"]
+        );
+        assert_eq!(
+            sorted_tree_names(&mut app, TreeSort::Size, true, false),
+            ["b.rs", "c.md", "a.txt", "zdir"]
+        );
+        assert_eq!(
+            sorted_tree_names(&mut app, TreeSort::Extension, false, false),
+            ["zdir", "c.md", "b.rs", "a.txt"]
+        );
+    }
+
+    #[test]
+    fn folder_file_picker_only_lists_files_under_the_selected_folder() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src/ui")).expect("mkdir");
+        fs::create_dir_all(root.join("docs")).expect("mkdir");
+        for file in ["src/main.rs", "src/ui/view.rs", "docs/main.md", "main.txt"] {
+            fs::write(root.join(file), "").expect("write");
+        }
+        let mut app = new_app(root);
+        app.expanded.insert(root.join("src"));
+        app.rebuild_tree().expect("rebuild");
+        let row = |app: &App, path: &Path| app.tree.iter().position(|i| i.path == path);
+
+        app.selected = row(&app, &root.join("src")).expect("src row");
+        app.run_command_action(CommandAction::FindInFolder)
+            .expect("picker");
+        assert!(app.file_picker_open);
+        wait_for_file_index(&mut app);
+        let mut found = app.file_picker_results.clone();
+        found.sort();
+        assert_eq!(
+            found,
+            [root.join("src/main.rs"), root.join("src/ui/view.rs")]
+        );
+
+        app.file_picker_query = "main".to_string();
+        app.refresh_file_picker_results();
+        assert_eq!(app.file_picker_results, [root.join("src/main.rs")]);
+
+        // A selected file scopes the picker to its folder.
+        app.selected = row(&app, &root.join("src/main.rs")).expect("file row");
+        app.open_folder_file_picker();
+        assert_eq!(app.file_picker_scope, Some(root.join("src")));
+
+        app.open_file_picker();
+        assert_eq!(app.file_picker_scope, None);
+        assert_eq!(app.file_picker_results.len(), 4);
+    }
+
+    #[test]
+    fn shift_range_selects_rows_between_anchor_and_focus() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        for name in ["a.txt", "b.txt", "c.txt", "d.txt"] {
+            fs::write(root.join(name), "").expect("write");
+        }
+        let mut app = new_app(root);
+        app.selected = 1;
+
+        app.extend_tree_selection(1);
+        app.extend_tree_selection(1);
+        assert_eq!(
+            app.tree_selection_paths(),
+            vec![root.join("b.txt"), root.join("c.txt"), root.join("d.txt")]
+        );
+
+        // Moving back toward the anchor shrinks the range.
+        app.extend_tree_selection(-1);
+        assert_eq!(app.selected, 2);
+        assert_eq!(
+            app.tree_selection_paths(),
+            vec![root.join("b.txt"), root.join("c.txt")]
+        );
+        assert_eq!(app.tree_targets(), app.tree_selection_paths());
+    }
+
+    #[test]
+    fn bulk_delete_removes_selection_and_closes_its_tabs() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let dir = root.join("dir");
+        fs::create_dir(&dir).expect("mkdir");
+        let clean = root.join("clean.rs");
+        let dirty = dir.join("dirty.rs");
+        let kept = root.join("kept.rs");
+        for path in [&clean, &dirty, &kept] {
+            fs::write(path, "fn f() {}\n").expect("write");
+        }
+        let mut app = new_app(root);
+        app.open_file(clean.clone()).expect("open clean");
+        app.open_file(dirty.clone()).expect("open dirty");
+        app.open_file(kept.clone()).expect("open kept");
+        let dirty_idx = app.tabs.iter().position(|t| t.path == dirty).expect("tab");
+        app.tabs[dirty_idx].dirty = true;
+        app.tree_selection = [dir.clone(), dirty.clone(), clean.clone()].into();
+
+        // The unsaved tab is confirmed away before anything is deleted.
+        app.request_delete(app.tree_targets());
+        assert!(dir.exists());
+        assert!(app.status.contains("discard 1 unsaved tab(s)"));
+        app.handle_pending_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("confirm");
+
+        assert!(!dir.exists());
+        assert!(!clean.exists());
+        assert!(kept.exists());
+        assert!(app.tree_selection.is_empty());
+        let open: Vec<_> = app.tabs.iter().map(|t| t.path.clone()).collect();
+        assert_eq!(open, vec![kept.clone()]);
+        assert_eq!(app.tabs[app.active_tab].path, kept);
+        assert!(matches!(app.pending, PendingAction::None));
+        assert_eq!(app.status, "Deleted 2 items");
+    }
+
+    #[test]
+    fn cut_and_paste_moves_selection_into_focused_folder() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir(root.join("dest")).expect("mkdir");
+        fs::write(root.join("a.txt"), "a").expect("write a");
+        fs::write(root.join("b.txt"), "b").expect("write b");
+        let mut app = new_app(root);
+        app.tree_selection = [root.join("a.txt"), root.join("b.txt")].into();
+        app.copy_tree_targets(true);
+
+        app.selected = app
+            .tree
+            .iter()
+            .position(|i| i.path == root.join("dest"))
+            .expect("dest row");
+        app.paste_tree_clipboard().expect("paste");
+
+        assert!(!root.join("a.txt").exists());
+        assert!(root.join("dest/a.txt").exists());
+        assert!(root.join("dest/b.txt").exists());
+        assert!(app.tree_clipboard.is_none());
+    }
+
+    #[test]
+    fn tree_drop_target_resolves_folder_for_row() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir(root.join("dir")).expect("mkdir");
+        fs::write(root.join("dir/inner.txt"), "").expect("write inner");
+        let mut app = new_app(root);
+        app.expanded.insert(root.join("dir"));
+        app.rebuild_tree().expect("rebuild");
+        app.tree_rect = ratatui::layout::Rect::new(0, 0, 30, 20);
+        let row = |app: &App, path: &Path| {
+            let idx = app.tree.iter().position(|i| i.path == path).expect("row");
+            u16::try_from(idx).expect("row fits") + 1
+        };
+
+        let dir_row = row(&app, &root.join("dir"));
+        let file_row = row(&app, &root.join("dir/inner.txt"));
+        assert_eq!(app.tree_drop_target(dir_row), root.join("dir"));
+        assert_eq!(app.tree_drop_target(file_row), root.join("dir"));
+        assert_eq!(app.tree_drop_target(18), root.to_path_buf());
+    }
+
+    #[test]
+    fn expanded_empty_dir_shows_empty_marker() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir(root.join("empty")).expect("mkdir");
+        let mut app = new_app(root);
+        app.expanded.insert(root.join("empty"));
+        app.rebuild_tree().expect("rebuild");
+
+        let marker = &app.tree[1];
+        assert_eq!(marker.marker, Some(TreeMarker::Empty));
+        assert_eq!(marker.name, "(empty)");
+        assert_eq!(marker.path, root.join("empty"));
+        assert_eq!(marker.depth, 1);
+        app.selected = 1;
+        app.tree_activate_selected().expect("activate");
+        assert!(app.tabs.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_dir_shows_error_marker() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let locked = root.join("locked");
+        fs::create_dir(&locked).expect("mkdir");
+        fs::write(root.join("other.txt"), "").expect("write");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).expect("chmod");
+        if fs::read_dir(&locked).is_ok() {
+            // Running as root: permissions are not enforced.
+            return;
+        }
+        let mut app = new_app(root);
+        app.expanded.insert(locked.clone());
+        app.rebuild_tree().expect("rebuild");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        let idx = app
+            .tree
+            .iter()
+            .position(|i| i.name == "locked")
+            .expect("row");
+        let marker = &app.tree[idx + 1];
+        assert_eq!(marker.name, "(permission denied)");
+        assert_eq!(
+            marker.marker,
+            Some(TreeMarker::Error("permission denied".to_string()))
+        );
+        assert!(app.tree.iter().any(|i| i.name == "other.txt"));
+    }
+
+    #[test]
+    fn drop_tree_item_moves_file_and_retargets_tab() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir(root.join("dest")).expect("mkdir");
+        let src = root.join("a.rs");
+        fs::write(&src, "fn a() {}\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(src.clone()).expect("open");
+
+        app.drop_tree_item(&src, &root.join("dest")).expect("drop");
+
+        let moved = root.join("dest/a.rs");
+        assert!(!src.exists());
+        assert!(moved.exists());
+        assert_eq!(app.tabs[app.active_tab].path, moved);
+        assert_eq!(app.tree[app.selected].path, moved);
+
+        app.drop_tree_item(&moved, &root.join("dest"))
+            .expect("drop in place");
+        assert!(moved.exists());
+        assert!(app.status.starts_with("Cannot move"));
+    }
+
+    #[test]
+    fn tree_details_load_metadata_only_when_shown() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir(root.join("dir")).expect("mkdir");
+        fs::write(root.join("dir/inner.txt"), "").expect("write inner");
+        fs::write(root.join("file.txt"), "hello").expect("write file");
+        let mut app = new_app(root);
+        let item = |app: &App, name: &str| {
+            app.tree
+                .iter()
+                .find(|i| i.name == name)
+                .cloned()
+                .expect("tree row")
+        };
+        app.tree_show_details = false;
+        app.rebuild_tree().expect("rebuild");
+        assert_eq!(item(&app, "file.txt").size, None);
+
+        app.tree_show_details = true;
+        app.rebuild_tree().expect("rebuild");
+
+        let file = item(&app, "file.txt");
+        assert_eq!(file.size, Some(5));
+        assert!(file.modified.is_some());
+        assert_eq!(item(&app, "dir").child_count, Some(1));
+    }
+
+    #[test]
+    fn delete_confirmation_counts_folder_contents() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let dir = root.join("dir");
+        fs::create_dir_all(dir.join("sub")).expect("mkdir");
+        for name in ["a.txt", "sub/b.txt", "sub/c.txt"] {
+            fs::write(dir.join(name), "").expect("write");
+        }
+        let file = root.join("note.txt");
+        fs::write(&file, "").expect("write note");
+        let mut app = new_app(root);
+
+        app.request_delete(vec![dir]);
+        match &app.pending {
+            PendingAction::Delete { summary, .. } => {
+                assert_eq!(summary, "'dir' (3 files, 1 folder inside)")
+            }
+            _ => panic!("expected pending delete"),
+        }
+
+        app.request_delete(vec![file]);
+        assert_eq!(
+            app.status,
+            "Delete 'note.txt' ? Press Enter/Y to confirm, Esc to cancel."
+        );
+    }
+
+    fn answer_prompt(app: &mut App, value: &str) {
+        let prompt = app.prompt.take().expect("prompt open");
+        app.apply_prompt(prompt.mode, value.to_string())
+            .expect("apply prompt");
+    }
+
+    #[test]
+    fn batch_rename_previews_then_renames_and_retargets_tabs() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        for name in ["a_old.txt", "b_old.txt", "keep.txt"] {
+            fs::write(root.join(name), "").expect("write");
+        }
+        let mut app = new_app(root);
+        app.open_file(root.join("a_old.txt")).expect("open");
+        app.tree_selection = ["a_old.txt", "b_old.txt", "keep.txt"]
+            .iter()
+            .map(|name| root.join(name))
+            .collect();
+
+        app.run_command_action(CommandAction::BatchRename)
+            .expect("batch rename");
+        answer_prompt(&mut app, "_old");
+        answer_prompt(&mut app, "_new");
+        match &app.pending {
+            PendingAction::BatchRename { renames } => assert_eq!(
+                renames,
+                &vec![
+                    (root.join("a_old.txt"), root.join("a_new.txt")),
+                    (root.join("b_old.txt"), root.join("b_new.txt")),
+                ]
+            ),
+            _ => panic!("expected a batch rename preview"),
+        }
+        assert!(root.join("a_old.txt").exists());
+
+        app.handle_pending_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("confirm");
+        for name in ["a_new.txt", "b_new.txt", "keep.txt"] {
+            assert!(root.join(name).exists(), "{name} should exist");
+        }
+        assert!(!root.join("a_old.txt").exists());
+        assert_eq!(app.tabs[0].path, root.join("a_new.txt"));
+        assert!(app.tree_selection.is_empty());
+    }
+
+    #[test]
+    fn batch_rename_collision_aborts_without_renaming_anything() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        for name in ["a_old.txt", "b_old.txt", "b_new.txt"] {
+            fs::write(root.join(name), "").expect("write");
+        }
+        let mut app = new_app(root);
+        app.tree_selection = [root.join("a_old.txt"), root.join("b_old.txt")].into();
+
+        app.start_batch_rename();
+        answer_prompt(&mut app, "_old");
+        answer_prompt(&mut app, "_new");
+        assert!(matches!(app.pending, PendingAction::None));
+        assert_eq!(
+            app.status,
+            "Batch rename aborted: 'b_new.txt' already exists"
+        );
+
+        // A target that appears between the preview and the confirm also
+        // stops the whole batch.
+        fs::remove_file(root.join("b_new.txt")).expect("remove");
+        app.start_batch_rename();
+        answer_prompt(&mut app, "_old");
+        answer_prompt(&mut app, "_new");
+        assert!(matches!(app.pending, PendingAction::BatchRename { .. }));
+        fs::write(root.join("b_new.txt"), "").expect("write");
+        app.handle_pending_key(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE))
+            .expect("confirm");
+        assert!(root.join("a_old.txt").exists());
+        assert!(root.join("b_old.txt").exists());
+        assert!(!root.join("a_new.txt").exists());
+        assert!(app.status.starts_with("Batch rename aborted"));
+    }
+
+    fn old_files(root: &Path, count: usize) -> Vec<PathBuf> {
+        let paths: Vec<PathBuf> = (0..count)
+            .map(|i| root.join(format!("f{i:03}.old")))
+            .collect();
+        for path in &paths {
+            fs::write(path, "").expect("write");
+        }
+        paths
+    }
+
+    fn count_with_extension(root: &Path, ext: &str) -> usize {
+        fs::read_dir(root)
+            .expect("read dir")
+            .flatten()
+            .filter(|e| e.path().extension().is_some_and(|x| x == ext))
+            .count()
+    }
+
+    #[test]
+    fn long_batch_rename_reports_progress_each_step() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let step = App::BATCH_RENAME_STEP;
+        let count = step * 2 + 10;
+        let paths = old_files(root, count);
+        let mut app = new_app(root);
+
+        let renames = batch_rename_plan(&paths, ".old", ".new").expect("plan");
+        app.batch_rename(renames).expect("start");
+        let progress = app.progress.clone().expect("progress");
+        assert_eq!((progress.done, progress.total), (step, Some(count)));
+        assert_eq!(
+            progress.status_text(),
+            format!("Renaming: {step} of {count} | Esc cancel")
+        );
+        app.poll_batch_rename();
+        assert_eq!(app.progress.as_ref().map(|p| p.done), Some(step * 2));
+        app.poll_batch_rename();
+
+        assert!(app.progress.is_none());
+        assert!(app.rename_job.is_none());
+        assert_eq!(app.status, format!("Renamed {count} item(s)"));
+        assert_eq!(count_with_extension(root, "new"), count);
+    }
+
+    #[test]
+    fn esc_cancels_a_batch_rename_keeping_what_was_renamed() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let step = App::BATCH_RENAME_STEP;
+        let count = step * 3;
+        let paths = old_files(root, count);
+        let mut app = new_app(root);
+
+        let renames = batch_rename_plan(&paths, ".old", ".new").expect("plan");
+        app.batch_rename(renames).expect("start");
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("esc");
+        app.poll_batch_rename();
+
+        assert!(app.progress.is_none());
+        assert!(app.rename_job.is_none());
+        assert_eq!(
+            app.status,
+            format!("Batch rename cancelled: {step} of {count} renamed")
+        );
+        assert_eq!(count_with_extension(root, "new"), step);
+        assert_eq!(count_with_extension(root, "old"), count - step);
+    }
+
+    #[test]
+    fn delete_prompt_accepts_only_the_configured_confirm_key() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("note.txt");
+        let mut app = new_app(tmp.path());
+        let ctrl_d = KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL);
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+
+        fs::write(&file, "").expect("write");
+        app.confirm_key = ConfirmKey::Chord;
+        app.request_delete(vec![file.clone()]);
+        assert_eq!(
+            app.status,
+            "Delete 'note.txt' ? Press Ctrl+D to confirm, Esc to cancel."
+        );
+        app.handle_pending_key(enter).expect("enter");
+        assert!(file.exists());
+        assert!(app.status.contains("Ctrl+D confirm"));
+        app.handle_pending_key(ctrl_d).expect("ctrl+d");
+        assert!(!file.exists());
+
+        fs::write(&file, "").expect("write");
+        app.confirm_key = ConfirmKey::Enter;
+        app.request_delete(vec![file.clone()]);
+        app.handle_pending_key(ctrl_d).expect("ctrl+d");
+        assert!(file.exists());
+        assert!(app.status.contains("Enter/Y confirm"));
+        app.handle_pending_key(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE))
+            .expect("y");
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn open_folder_rebuilds_tree_under_new_root() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path().canonicalize().expect("canonical");
+        let sub = root.join("sub");
+        let other = root.join("other");
+        fs::create_dir_all(sub.join("inner")).expect("mkdir");
+        fs::create_dir_all(&other).expect("mkdir");
+        fs::write(sub.join("inner/deep.txt"), "").expect("write");
+        fs::write(sub.join("z.txt"), "z\n").expect("write");
+        fs::write(other.join("x.txt"), "x\n").expect("write");
+        fs::write(other.join("y.txt"), "y\n").expect("write");
+        let mut app = new_app(&root);
+        app.expanded.insert(sub.clone());
+        app.expanded.insert(sub.join("inner"));
+        app.expanded.insert(other.clone());
+        app.open_file(other.join("x.txt")).expect("open x");
+        app.open_file(other.join("y.txt")).expect("open y");
+        app.handle_key(KeyEvent::new(KeyCode::Char('!'), KeyModifiers::NONE))
+            .expect("edit y");
+        app.open_file(sub.join("z.txt")).expect("open z");
+
+        app.run_command_action(CommandAction::OpenFolder)
+            .expect("picker");
+        assert!(app.folder_picker.open);
+        assert_eq!(app.folder_picker.dir, root);
+        assert_eq!(app.folder_picker.entries, vec![other.clone(), sub.clone()]);
+        app.folder_picker.index = 3; // Open, .., other, sub
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("enter sub");
+        assert_eq!(app.folder_picker.dir, sub);
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("open sub");
+
+        assert!(!app.folder_picker.open);
+        assert_eq!(app.root, sub);
+        assert!(app.tree.iter().all(|item| item.path.starts_with(&sub)));
+        assert!(app.tree.iter().any(|item| item.path.ends_with("deep.txt")));
+        assert!(app.expanded.contains(&sub.join("inner")));
+        assert!(!app.expanded.contains(&other));
+        assert!(!app.expanded.contains(&root));
+        let open: Vec<PathBuf> = app.tabs.iter().map(|t| t.path.clone()).collect();
+        assert_eq!(open, vec![other.join("y.txt"), sub.join("z.txt")]);
+    }
+
+    #[test]
+    fn folder_picker_reopens_where_it_was_left() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path().canonicalize().expect("canonicalize");
+        for name in ["a", "b", "c"] {
+            fs::create_dir(root.join(name)).expect("mkdir");
+        }
+        let mut app = new_app(&root);
+
+        app.open_folder_picker();
+        app.folder_picker.index = 4; // Open, .., a, b, c
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("close");
+        app.open_folder_picker();
+        assert_eq!(app.folder_picker.dir, root);
+        assert_eq!(app.folder_picker.index, 4);
+
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("close");
+        fs::remove_dir(root.join("b")).expect("rmdir");
+        fs::remove_dir(root.join("c")).expect("rmdir");
+        app.open_folder_picker();
+        assert_eq!(app.folder_picker.entries, vec![root.join("a")]);
+        assert_eq!(app.folder_picker.index, 2);
+    }
+
+    #[test]
+    fn recent_projects_follow_root_changes() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path().canonicalize().expect("canonicalize");
+        let (one, two) = (root.join("one"), root.join("two"));
+        fs::create_dir(&one).expect("mkdir");
+        fs::create_dir(&two).expect("mkdir");
+        let mut app = new_app(&one);
+        assert_eq!(app.recent_projects.first(), Some(&one));
+
+        app.set_root(two.clone()).expect("set root");
+        assert_eq!(app.recent_projects[..2], [two.clone(), one.clone()]);
+
+        app.run_command_action(CommandAction::OpenRecentProject)
+            .expect("picker");
+        assert!(app.recent_project_picker.open);
+        assert!(!app.recent_project_picker.projects.contains(&two));
+        assert_eq!(app.recent_project_picker.projects.first(), Some(&one));
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("open one");
+        assert!(!app.recent_project_picker.open);
+        assert_eq!(app.root, one);
+        assert_eq!(app.recent_projects[..2], [one.clone(), two.clone()]);
+
+        fs::remove_dir(&two).expect("rmdir");
+        app.open_recent_project_picker();
+        assert!(!app.recent_projects.contains(&two));
+        assert!(!app.recent_project_picker.projects.contains(&two));
+    }
 }
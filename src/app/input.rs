@@ -7,14 +7,15 @@ use ratatui::crossterm::event::{
 };
 
 use crate::keybinds::KeyScope;
-use crate::types::{Focus, PendingAction};
-use crate::util::{inside, to_u16_saturating};
+use crate::types::{EscAction, Focus, GutterColumn, PendingAction, PromptMode};
+use crate::util::{inside, relative_path, to_u16_saturating};
 
 impl App {
     pub(crate) fn handle_key(&mut self, key: KeyEvent) -> io::Result<()> {
         if key.kind != KeyEventKind::Press {
             return Ok(());
         }
+        self.end_typing_run_unless_typing(&key);
 
         if self.keybind_editor.open {
             return self.handle_keybind_editor_key(key);
@@ -28,6 +29,9 @@ impl App {
         if self.active_tab().is_some_and(|t| t.conflict_prompt_open) {
             return self.handle_conflict_prompt_key(key);
         }
+        if self.unsaved_diff_open {
+            return self.handle_unsaved_diff_key(key);
+        }
         if self.prompt.is_some() {
             return self.handle_prompt_key(key);
         }
@@ -46,6 +50,27 @@ impl App {
         if self.theme_browser_open {
             return self.handle_theme_browser_key(key);
         }
+        if self.language_picker.open {
+            return self.handle_language_picker_key(key);
+        }
+        if self.symbol_picker.open {
+            return self.handle_symbol_picker_key(key);
+        }
+        if self.diff_tab_picker.open {
+            return self.handle_diff_tab_picker_key(key);
+        }
+        if self.folder_picker.open {
+            return self.handle_folder_picker_key(key);
+        }
+        if self.recent_project_picker.open {
+            return self.handle_recent_project_picker_key(key);
+        }
+        if self.tab_diff.is_some() {
+            return self.handle_tab_diff_key(key);
+        }
+        if self.diagnostics_panel.open {
+            return self.handle_diagnostics_panel_key(key);
+        }
         if self.menu_open {
             return self.handle_menu_key(key);
         }
@@ -56,6 +81,18 @@ impl App {
         if self.handle_pending_key(key)? {
             return Ok(());
         }
+        if key.code == KeyCode::Esc && self.cancel_operation() {
+            return Ok(());
+        }
+
+        // Char bindings ignore Shift, so Ctrl+C would otherwise be taken by
+        // the global Ctrl+Shift+C collapse-all before the tree sees it.
+        if self.focus == Focus::Tree
+            && !key.modifiers.contains(KeyModifiers::SHIFT)
+            && self.handle_tree_clipboard_key(key)?
+        {
+            return Ok(());
+        }
 
         // Global keybind lookup
         if let Some(action) = self.keybinds.lookup(&key, KeyScope::Global) {
@@ -65,14 +102,32 @@ impl App {
         // Non-remappable keys
         match (key.modifiers, key.code) {
             (_, KeyCode::Esc) => {
-                if self.open_path().is_some() && self.is_dirty() {
-                    self.pending = PendingAction::ClosePrompt;
-                    self.set_status("Unsaved changes: Enter save+close | Esc discard | C cancel");
+                if self.focus == Focus::Tree && !self.tree_selection.is_empty() {
+                    self.clear_tree_selection();
+                    self.set_status("Selection cleared");
                     return Ok(());
                 }
-                if self.focus == Focus::Editor && self.open_path().is_some() {
-                    self.close_file();
-                    return Ok(());
+                match self.editor_esc {
+                    EscAction::Close => {
+                        if self.open_path().is_some() && self.is_dirty() {
+                            self.pending = PendingAction::ClosePrompt;
+                            self.set_status(self.close_prompt_status());
+                            return Ok(());
+                        }
+                        if self.focus == Focus::Editor && self.open_path().is_some() {
+                            self.close_file();
+                            return Ok(());
+                        }
+                    }
+                    EscAction::Clear if self.focus == Focus::Editor => {
+                        self.clear_editor_highlights();
+                        return Ok(());
+                    }
+                    EscAction::Clear | EscAction::Nothing => {
+                        if self.focus == Focus::Editor {
+                            return Ok(());
+                        }
+                    }
                 }
             }
             (KeyModifiers::NONE, KeyCode::Tab) => {
@@ -91,17 +146,8 @@ impl App {
             }
             (KeyModifiers::NONE, KeyCode::Delete) => {
                 if self.focus == Focus::Tree {
-                    if let Some(item) = self.selected_item().cloned() {
-                        if item.path == self.root {
-                            self.set_status("Cannot delete project root");
-                            return Ok(());
-                        }
-                        self.pending = PendingAction::Delete(item.path.clone());
-                        self.set_status(format!(
-                            "Delete {} ? Press Enter to confirm, Esc to cancel.",
-                            item.name,
-                        ));
-                    }
+                    let paths = self.tree_targets();
+                    self.request_delete(paths);
                     return Ok(());
                 }
             }
@@ -113,7 +159,37 @@ impl App {
             Focus::Editor => self.handle_editor_key(key),
         }
     }
+
+    /// Lines to scroll for a wheel notch arriving at `now`. With
+    /// acceleration on, each pair of rapid notches in the same direction
+    /// adds another `scroll_lines`, up to `MAX_SCROLL_ACCEL` times the step.
+    pub(crate) fn wheel_step(&mut self, down: bool, now: Instant) -> usize {
+        let window = Duration::from_millis(Self::SCROLL_ACCEL_WINDOW_MS);
+        let streak = match self.wheel_streak {
+            Some((last, last_down, streak))
+                if last_down == down && now.saturating_duration_since(last) < window =>
+            {
+                streak + 1
+            }
+            _ => 0,
+        };
+        self.wheel_streak = Some((now, down, streak));
+        let factor = if self.scroll_acceleration {
+            (1 + streak / 2).min(Self::MAX_SCROLL_ACCEL)
+        } else {
+            1
+        };
+        self.scroll_lines.max(1) * factor
+    }
+
     pub(crate) fn handle_mouse(&mut self, mouse: MouseEvent) -> io::Result<()> {
+        // A click may edit (context menu, middle-click paste), so group the
+        // typing before it.
+        if matches!(mouse.kind, MouseEventKind::Down(_))
+            && let Some(tab) = self.active_tab_mut()
+        {
+            tab.end_typing_run();
+        }
         if self.help_open {
             if matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
                 self.help_open = false;
@@ -125,11 +201,16 @@ impl App {
         if self.prompt.is_some()
             || matches!(
                 self.pending,
-                PendingAction::ClosePrompt | PendingAction::Delete(_)
+                PendingAction::QuitPrompt
+                    | PendingAction::ClosePrompt
+                    | PendingAction::Delete { .. }
+                    | PendingAction::BatchRename { .. }
+                    | PendingAction::OpenWith { .. }
             )
             || self
                 .active_tab()
                 .is_some_and(|t| t.recovery_prompt_open || t.conflict_prompt_open)
+            || self.unsaved_diff_open
         {
             if matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
                 // If prompt is open and click is inside the input area, move cursor
@@ -144,19 +225,36 @@ impl App {
                     return Ok(());
                 }
                 // Dismiss the modal on click outside (Esc-equivalent)
-                if self.prompt.is_some() {
+                if let Some(prompt) = &self.prompt {
+                    if matches!(prompt.mode, PromptMode::FindInFile) {
+                        self.cancel_incremental_find();
+                    }
                     self.prompt = None;
-                } else if matches!(self.pending, PendingAction::Delete(_)) {
+                } else if matches!(self.pending, PendingAction::Delete { .. }) {
                     self.pending = PendingAction::None;
                     self.set_status("Delete cancelled");
+                } else if matches!(self.pending, PendingAction::BatchRename { .. }) {
+                    self.pending = PendingAction::None;
+                    self.set_status("Rename cancelled");
+                } else if matches!(self.pending, PendingAction::OpenWith { .. }) {
+                    self.pending = PendingAction::None;
+                    self.set_status("Open cancelled");
                 } else if matches!(self.pending, PendingAction::ClosePrompt) {
                     self.pending = PendingAction::None;
                     self.set_status("Close cancelled");
-                } else if let Some(tab) = self.active_tab_mut() {
-                    if tab.recovery_prompt_open {
-                        tab.recovery_prompt_open = false;
-                    } else if tab.conflict_prompt_open {
-                        tab.conflict_prompt_open = false;
+                } else if matches!(self.pending, PendingAction::QuitPrompt) {
+                    self.pending = PendingAction::None;
+                    self.set_status("Quit canceled");
+                } else if self.unsaved_diff_open {
+                    self.unsaved_diff_open = false;
+                } else {
+                    self.prompt_diff_scroll = 0;
+                    if let Some(tab) = self.active_tab_mut() {
+                        if tab.recovery_prompt_open {
+                            tab.recovery_prompt_open = false;
+                        } else if tab.conflict_prompt_open {
+                            tab.conflict_prompt_open = false;
+                        }
                     }
                 }
             }
@@ -186,6 +284,34 @@ impl App {
             return self.handle_theme_browser_mouse(mouse);
         }
 
+        if self.language_picker.open {
+            return self.handle_language_picker_mouse(mouse);
+        }
+
+        if self.symbol_picker.open {
+            return self.handle_symbol_picker_mouse(mouse);
+        }
+
+        if self.diff_tab_picker.open {
+            return self.handle_diff_tab_picker_mouse(mouse);
+        }
+
+        if self.folder_picker.open {
+            return self.handle_folder_picker_mouse(mouse);
+        }
+
+        if self.recent_project_picker.open {
+            return self.handle_recent_project_picker_mouse(mouse);
+        }
+
+        if self.tab_diff.is_some() {
+            return self.handle_tab_diff_mouse(mouse);
+        }
+
+        if self.diagnostics_panel.open {
+            return self.handle_diagnostics_panel_mouse(mouse);
+        }
+
         if self.files_view_open {
             match mouse.kind {
                 MouseEventKind::Down(MouseButton::Left) => {
@@ -196,12 +322,7 @@ impl App {
                 }
                 MouseEventKind::Drag(MouseButton::Left) | MouseEventKind::Moved => {
                     if self.divider_dragging {
-                        // Convert absolute X to pane width by using content frame start.
-                        let desired = mouse.column.saturating_sub(self.tree_rect.x);
-                        self.files_pane_width = desired.max(Self::MIN_FILES_PANE_WIDTH);
-                        self.clamp_files_pane_width(
-                            self.editor_rect.width + self.tree_rect.width + self.divider_rect.width,
-                        );
+                        self.drag_files_pane_divider(mouse.column);
                         return Ok(());
                     }
                 }
@@ -217,22 +338,40 @@ impl App {
             }
         }
 
+        // Tree drag carried outside the tree: swallow motion, cancel on release
+        if self.tree_drag.is_some() && !inside(mouse.column, mouse.row, self.tree_rect) {
+            match mouse.kind {
+                MouseEventKind::Drag(MouseButton::Left) | MouseEventKind::Moved => {
+                    return Ok(());
+                }
+                MouseEventKind::Up(MouseButton::Left) => {
+                    self.tree_drag = None;
+                    self.set_status("Move canceled");
+                    return Ok(());
+                }
+                _ => self.tree_drag = None,
+            }
+        }
+
         if inside(mouse.column, mouse.row, self.tree_rect) {
             match mouse.kind {
                 MouseEventKind::Down(MouseButton::Left) => {
                     if inside(mouse.column, mouse.row, self.tree_expand_btn_rect) {
-                        self.tree_expand_all()?;
-                        self.set_status("Expanded all folders");
+                        self.expand_all_under(&self.root.clone())?;
                         return Ok(());
                     }
                     if inside(mouse.column, mouse.row, self.tree_collapse_btn_rect) {
-                        self.tree_collapse_all()?;
-                        self.set_status("Collapsed all folders");
+                        self.collapse_all()?;
                         return Ok(());
                     }
                     if let Some(idx) = self.tree_index_from_mouse(mouse.row) {
                         self.selected = idx;
+                        if self.tree[idx].marker.is_some() {
+                            self.focus = Focus::Tree;
+                            return Ok(());
+                        }
                         let path = self.tree[idx].path.clone();
+                        self.tree_drag = Some(path.clone());
                         if path.is_dir() {
                             self.tree_activate_selected()?;
                             self.focus = Focus::Tree;
@@ -253,49 +392,99 @@ impl App {
                         }
                     }
                 }
+                MouseEventKind::Drag(MouseButton::Left) => {
+                    if let Some(src) = self.tree_drag.as_ref() {
+                        let name = relative_path(&self.root, src).display().to_string();
+                        self.set_status(format!("Moving {name}: release over a folder"));
+                    }
+                }
+                MouseEventKind::Up(MouseButton::Left) => {
+                    if let Some(src) = self.tree_drag.take() {
+                        // Releasing on the pressed row is a plain click.
+                        let same_row = self
+                            .tree_index_from_mouse(mouse.row)
+                            .is_some_and(|idx| self.tree[idx].path == src);
+                        if !same_row {
+                            let target = self.tree_drop_target(mouse.row);
+                            self.drop_tree_item(&src, &target)?;
+                        }
+                    }
+                }
                 MouseEventKind::Down(MouseButton::Right) => {
                     self.open_tree_context_menu_at(mouse.column, mouse.row);
                 }
                 MouseEventKind::ScrollDown => {
-                    self.selected = (self.selected + Self::SCROLL_LINES)
-                        .min(self.tree.len().saturating_sub(1));
+                    let step = self.wheel_step(true, Instant::now());
+                    self.selected = (self.selected + step).min(self.tree.len().saturating_sub(1));
                 }
                 MouseEventKind::ScrollUp => {
-                    self.selected = self.selected.saturating_sub(Self::SCROLL_LINES);
+                    let step = self.wheel_step(false, Instant::now());
+                    self.selected = self.selected.saturating_sub(step);
                 }
                 _ => {}
             }
             return Ok(());
         }
 
+        // Tab drag in progress: swallow motion, reorder on drop over the tab bar
+        if let Some(from) = self.tab_drag {
+            match mouse.kind {
+                MouseEventKind::Drag(MouseButton::Left) | MouseEventKind::Moved => {
+                    return Ok(());
+                }
+                MouseEventKind::Up(MouseButton::Left) => {
+                    self.tab_drag = None;
+                    let on_tab_bar = mouse.row == self.editor_rect.y
+                        && inside(mouse.column, mouse.row, self.editor_rect);
+                    match self.tab_drop_index(mouse.column) {
+                        Some(to) if on_tab_bar && to != from => self.move_tab(from, to),
+                        Some(_) if on_tab_bar => {}
+                        _ => self.set_status("Tab move canceled"),
+                    }
+                    return Ok(());
+                }
+                _ => self.tab_drag = None,
+            }
+        }
+
         // Tab bar click detection (title bar row of editor block)
         if mouse.row == self.editor_rect.y && inside(mouse.column, mouse.row, self.editor_rect) {
             match mouse.kind {
                 MouseEventKind::Down(MouseButton::Left) => {
-                    for (i, (name_rect, close_rect)) in self.tab_rects.iter().enumerate() {
+                    for (visible_idx, (name_rect, close_rect)) in self.tab_rects.iter().enumerate()
+                    {
+                        let i = self.tab_scroll_offset + visible_idx;
+                        if i >= self.tabs.len() {
+                            break;
+                        }
                         if inside(mouse.column, mouse.row, *close_rect) {
                             // Click on [x] — close this tab
                             if self.tabs[i].dirty {
                                 self.switch_to_tab(i);
                                 self.pending = PendingAction::ClosePrompt;
-                                self.set_status(
-                                    "Unsaved changes: Enter save+close | Esc discard | C cancel",
-                                );
+                                self.set_status(self.close_prompt_status());
                             } else {
                                 self.close_tab_at(i);
                             }
                             return Ok(());
                         }
                         if inside(mouse.column, mouse.row, *name_rect) {
-                            // Click on tab name — switch to it
+                            // Click on tab name — switch to it and start a potential drag
                             self.switch_to_tab(i);
+                            self.tab_drag = Some(i);
                             return Ok(());
                         }
                     }
                     return Ok(());
                 }
-                // Scroll events on the tab bar fall through to the editor scroll handler
-                MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {}
+                MouseEventKind::ScrollDown => {
+                    self.scroll_tab_bar(true);
+                    return Ok(());
+                }
+                MouseEventKind::ScrollUp => {
+                    self.scroll_tab_bar(false);
+                    return Ok(());
+                }
                 _ => return Ok(()),
             }
         }
@@ -307,19 +496,16 @@ impl App {
                     let inner_x = mouse
                         .column
                         .saturating_sub(self.editor_rect.x.saturating_add(1));
-                    if inner_x < Self::EDITOR_GUTTER_WIDTH {
-                        if inner_x < 6 {
-                            // Line number area → select full line
-                            if let Some(row) = self.gutter_row_from_mouse(mouse.row) {
+                    if let Some(column) = self.gutter().column_at(inner_x) {
+                        if let Some(row) = self.gutter_row_from_mouse(mouse.row) {
+                            if column == GutterColumn::Fold {
+                                self.toggle_fold_at_row(row);
+                            } else {
+                                // Numbers and markers → select full line
                                 self.select_line(row);
                                 self.gutter_drag_anchor = Some(row);
                                 self.editor_dragging = true;
                             }
-                        } else {
-                            // Fold/marker area → toggle fold
-                            if let Some(row) = self.gutter_row_from_mouse(mouse.row) {
-                                self.toggle_fold_at_row(row);
-                            }
                         }
                         return Ok(());
                     }
@@ -363,9 +549,20 @@ impl App {
                     self.gutter_drag_anchor = None;
                     return Ok(());
                 }
+                MouseEventKind::Down(MouseButton::Middle) => {
+                    self.focus = Focus::Editor;
+                    if let Some((row, col)) = self.editor_pos_from_mouse(mouse.column, mouse.row)
+                        && let Some(text) = self.middle_click_text()
+                    {
+                        self.paste_at(row, col, &text);
+                    }
+                    return Ok(());
+                }
                 MouseEventKind::Down(MouseButton::Right) => {
                     self.focus = Focus::Editor;
                     self.editor_context_menu_pos = (mouse.column, mouse.row);
+                    self.editor_context_menu_target =
+                        self.editor_pos_from_mouse(mouse.column, mouse.row);
                     self.editor_context_menu_index = 0;
                     self.editor_context_menu_open = true;
                     return Ok(());
@@ -377,26 +574,19 @@ impl App {
                     {
                         self.rebuild_visible_rows();
                     }
-                    let viewport_h = self.editor_rect.height.saturating_sub(2) as usize;
+                    let viewport_h = self.editor_text_height();
+                    let down = matches!(mouse.kind, MouseEventKind::ScrollDown);
+                    let step = self.wheel_step(down, Instant::now());
                     if let Some(tab) = self.active_tab_mut() {
                         let max_scroll = tab
                             .visible_rows_map
                             .len()
                             .saturating_sub(viewport_h.max(1));
-                        match mouse.kind {
-                            MouseEventKind::ScrollDown => {
-                                tab.editor_scroll_row = tab
-                                    .editor_scroll_row
-                                    .saturating_add(Self::SCROLL_LINES)
-                                    .min(max_scroll)
-                            }
-                            MouseEventKind::ScrollUp => {
-                                tab.editor_scroll_row = tab
-                                    .editor_scroll_row
-                                    .saturating_sub(Self::SCROLL_LINES)
-                            }
-                            _ => {}
-                        }
+                        tab.editor_scroll_row = if down {
+                            tab.editor_scroll_row.saturating_add(step).min(max_scroll)
+                        } else {
+                            tab.editor_scroll_row.saturating_sub(step)
+                        };
                     }
                     // Move cursor to stay within the visible viewport so that
                     // subsequent actions don't snap the scroll back to the old
@@ -406,17 +596,16 @@ impl App {
                 }
                 MouseEventKind::ScrollLeft | MouseEventKind::ScrollRight => {
                     if !self.word_wrap {
+                        let step = self.scroll_lines;
                         if let Some(tab) = self.active_tab_mut() {
                             match mouse.kind {
                                 MouseEventKind::ScrollLeft => {
-                                    tab.editor_scroll_col = tab
-                                        .editor_scroll_col
-                                        .saturating_sub(Self::SCROLL_LINES);
+                                    tab.editor_scroll_col =
+                                        tab.editor_scroll_col.saturating_sub(step);
                                 }
                                 MouseEventKind::ScrollRight => {
-                                    tab.editor_scroll_col = tab
-                                        .editor_scroll_col
-                                        .saturating_add(Self::SCROLL_LINES);
+                                    tab.editor_scroll_col =
+                                        tab.editor_scroll_col.saturating_add(step);
                                 }
                                 _ => {}
                             }
@@ -10,10 +10,12 @@ use ratatui_textarea::Input;
 use crate::keybinds::{
     KeyAction, KeyBind, KeyBindings, KeyScope, save_keybindings, selected_action,
 };
-use crate::types::{Focus, PendingAction, PromptMode};
+use crate::syntax::SyntaxLang;
+use crate::tab::{DiagnosticPanelRow, SearchResultRow, Tab, TypingKind};
+use crate::types::{ConfirmKey, Focus, PendingAction, PromptMode};
 use crate::util::{
-    context_actions, editor_context_actions, inside, pending_hint, primary_mod_label,
-    text_to_lines, to_u16_saturating,
+    confirm_key_label, context_actions, diagnostic_panel_rows, editor_context_actions, inside,
+    pending_hint, search_result_rows, text_to_lines, to_u16_saturating,
 };
 
 impl App {
@@ -40,8 +42,12 @@ impl App {
         let Some(prompt) = self.prompt.as_mut() else {
             return Ok(());
         };
+        let value_before = prompt.value.clone();
         match (key.modifiers, key.code) {
             (_, KeyCode::Esc) => {
+                if matches!(prompt.mode, PromptMode::FindInFile) {
+                    self.cancel_incremental_find();
+                }
                 self.prompt = None;
                 self.set_status("Canceled");
             }
@@ -55,8 +61,12 @@ impl App {
                 }
                 let mode = prompt.mode.clone();
                 self.prompt = None;
+                self.find_origin = None;
+                self.record_prompt_history(&mode, &value);
                 self.apply_prompt(mode, value)?;
             }
+            (_, KeyCode::Up) => self.recall_prompt_history(true),
+            (_, KeyCode::Down) => self.recall_prompt_history(false),
             (_, KeyCode::Backspace) => {
                 if prompt.cursor > 0 {
                     prompt.value.remove(prompt.cursor - 1);
@@ -92,6 +102,13 @@ impl App {
             }
             _ => {}
         }
+        if let Some(prompt) = &self.prompt
+            && matches!(prompt.mode, PromptMode::FindInFile)
+            && prompt.value != value_before
+        {
+            let query = prompt.value.clone();
+            self.incremental_find(&query);
+        }
         Ok(())
     }
 
@@ -139,6 +156,7 @@ impl App {
             (_, KeyCode::Esc) => {
                 self.search_results.open = false;
                 self.set_status("Closed search results");
+                self.cancel_project_search();
             }
             (_, KeyCode::Down) | (_, KeyCode::Char('j')) => {
                 if self.search_results.index + 1 < self.search_results.results.len() {
@@ -210,33 +228,64 @@ impl App {
         Ok(())
     }
 
+    /// Whether `key` confirms the open quit, close or delete prompt under the
+    /// `confirm_key` setting.
+    fn confirms_pending(&self, key: &KeyEvent) -> bool {
+        let Some((chord, letter)) = self.pending.confirm_keys() else {
+            return false;
+        };
+        let mods = key.modifiers;
+        match (self.confirm_key, key.code) {
+            (ConfirmKey::Enter, KeyCode::Enter) => mods == KeyModifiers::NONE,
+            (ConfirmKey::Enter, KeyCode::Char(c)) => {
+                !mods.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+                    && letter == Some(c.to_ascii_lowercase())
+            }
+            (ConfirmKey::Chord, KeyCode::Char(c)) => {
+                mods.contains(KeyModifiers::CONTROL)
+                    && !mods.contains(KeyModifiers::ALT)
+                    && c.to_ascii_lowercase() == chord
+            }
+            _ => false,
+        }
+    }
+
     pub(crate) fn handle_pending_key(&mut self, key: KeyEvent) -> io::Result<bool> {
+        if self.confirms_pending(&key) {
+            match std::mem::replace(&mut self.pending, PendingAction::None) {
+                PendingAction::QuitPrompt => self.save_all_and_quit()?,
+                PendingAction::ClosePrompt => {
+                    self.save_file()?;
+                    self.close_file();
+                }
+                PendingAction::Delete { paths, .. } => self.delete_paths(paths)?,
+                PendingAction::BatchRename { renames } => self.batch_rename(renames)?,
+                PendingAction::None | PendingAction::OpenWith { .. } => {}
+            }
+            return Ok(true);
+        }
         match (&self.pending, key.modifiers, key.code) {
             (PendingAction::None, _, _) => Ok(false),
-            (PendingAction::Quit, KeyModifiers::CONTROL, KeyCode::Char('q' | 'Q')) => {
+            (PendingAction::QuitPrompt, KeyModifiers::CONTROL, KeyCode::Char('q' | 'Q')) => {
                 self.quit = true;
                 Ok(true)
             }
-            (_, mods, KeyCode::Char('q' | 'Q'))
-                if mods.contains(KeyModifiers::CONTROL) && !mods.contains(KeyModifiers::ALT) =>
-            {
+            (PendingAction::QuitPrompt, KeyModifiers::NONE, KeyCode::Char('d' | 'D')) => {
                 self.pending = PendingAction::None;
-                self.run_key_action(KeyAction::Quit)?;
+                self.discard_all_and_quit();
                 Ok(true)
             }
-            (PendingAction::ClosePrompt, mods, KeyCode::Char('s'))
-            | (PendingAction::ClosePrompt, mods, KeyCode::Char('S'))
-                if mods.contains(KeyModifiers::CONTROL) && !mods.contains(KeyModifiers::ALT) =>
-            {
+            (PendingAction::QuitPrompt, KeyModifiers::NONE, KeyCode::Char('c' | 'C'))
+            | (PendingAction::QuitPrompt, KeyModifiers::NONE, KeyCode::Esc) => {
                 self.pending = PendingAction::None;
-                self.save_file()?;
-                self.close_file();
+                self.set_status("Quit canceled");
                 Ok(true)
             }
-            (PendingAction::ClosePrompt, KeyModifiers::NONE, KeyCode::Enter) => {
+            (_, mods, KeyCode::Char('q' | 'Q'))
+                if mods.contains(KeyModifiers::CONTROL) && !mods.contains(KeyModifiers::ALT) =>
+            {
                 self.pending = PendingAction::None;
-                self.save_file()?;
-                self.close_file();
+                self.run_key_action(KeyAction::Quit)?;
                 Ok(true)
             }
             (PendingAction::ClosePrompt, KeyModifiers::NONE, KeyCode::Esc) => {
@@ -250,43 +299,68 @@ impl App {
                 self.set_status("Close canceled");
                 Ok(true)
             }
-            (PendingAction::Delete(path), mods, KeyCode::Char('d' | 'D'))
-                if mods.contains(KeyModifiers::CONTROL) && !mods.contains(KeyModifiers::ALT) =>
-            {
-                let target = path.clone();
+            (PendingAction::Delete { .. }, KeyModifiers::NONE, KeyCode::Char('n'))
+            | (PendingAction::Delete { .. }, KeyModifiers::NONE, KeyCode::Char('N'))
+            | (PendingAction::Delete { .. }, KeyModifiers::NONE, KeyCode::Esc) => {
                 self.pending = PendingAction::None;
-                self.delete_path(target)?;
+                self.set_status("Delete canceled");
                 Ok(true)
             }
-            (PendingAction::Delete(path), KeyModifiers::NONE, KeyCode::Enter)
-            | (PendingAction::Delete(path), KeyModifiers::NONE, KeyCode::Char('y'))
-            | (PendingAction::Delete(path), KeyModifiers::NONE, KeyCode::Char('Y')) => {
-                let target = path.clone();
+            (PendingAction::BatchRename { .. }, KeyModifiers::NONE, KeyCode::Char('n' | 'N'))
+            | (PendingAction::BatchRename { .. }, KeyModifiers::NONE, KeyCode::Esc) => {
                 self.pending = PendingAction::None;
-                self.delete_path(target)?;
+                self.set_status("Rename canceled");
                 Ok(true)
             }
-            (PendingAction::Delete(_), KeyModifiers::NONE, KeyCode::Char('n'))
-            | (PendingAction::Delete(_), KeyModifiers::NONE, KeyCode::Char('N'))
-            | (PendingAction::Delete(_), KeyModifiers::NONE, KeyCode::Esc) => {
+            (
+                PendingAction::OpenWith { path, .. },
+                KeyModifiers::NONE,
+                KeyCode::Char('o' | 'O'),
+            ) => {
+                let path = path.clone();
                 self.pending = PendingAction::None;
-                self.set_status("Delete canceled");
+                self.open_externally(&path);
                 Ok(true)
             }
-            (_, KeyModifiers::NONE, KeyCode::Esc) => {
+            (
+                PendingAction::OpenWith { path, as_preview },
+                KeyModifiers::NONE,
+                KeyCode::Char('t' | 'T'),
+            ) => {
+                let (path, as_preview) = (path.clone(), *as_preview);
                 self.pending = PendingAction::None;
-                self.set_status("Canceled");
+                self.force_open_as_text(path, as_preview)?;
+                Ok(true)
+            }
+            (PendingAction::OpenWith { .. }, KeyModifiers::NONE, KeyCode::Esc) => {
+                self.pending = PendingAction::None;
+                self.set_status("Open canceled");
                 Ok(true)
             }
             _ => {
-                self.set_status(pending_hint(&self.pending));
+                self.set_status(pending_hint(&self.pending, self.confirm_key));
                 Ok(true)
             }
         }
     }
 
+    /// Tree copy/cut/paste follow the editor's clipboard bindings. Returns
+    /// whether the key was consumed.
+    pub(crate) fn handle_tree_clipboard_key(&mut self, key: KeyEvent) -> io::Result<bool> {
+        match self.keybinds.lookup(&key, KeyScope::Editor) {
+            Some(KeyAction::Copy) => self.copy_tree_targets(false),
+            Some(KeyAction::Cut) => self.copy_tree_targets(true),
+            Some(KeyAction::Paste) => self.paste_tree_clipboard()?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
     pub(crate) fn handle_tree_key(&mut self, key: KeyEvent) -> io::Result<()> {
         match (key.modifiers, key.code) {
+            (KeyModifiers::SHIFT, KeyCode::Down) => self.extend_tree_selection(1),
+            (KeyModifiers::SHIFT, KeyCode::Up) => self.extend_tree_selection(-1),
+            (KeyModifiers::NONE, KeyCode::Char(' ')) => self.toggle_tree_selection(),
             (KeyModifiers::NONE, KeyCode::Down) | (KeyModifiers::NONE, KeyCode::Char('j')) => {
                 if self.selected + 1 < self.tree.len() {
                     self.selected += 1;
@@ -315,6 +389,9 @@ impl App {
             self.set_status("No file open. Focus returned to files.");
             return Ok(());
         }
+        if self.active_tab().is_some_and(|t| t.read_only) {
+            return self.handle_read_only_editor_key(key);
+        }
 
         // Non-remappable: Tab (completion/ghost/indent), auto-pair insertion
         match (key.modifiers, key.code) {
@@ -352,6 +429,16 @@ impl App {
                 self.request_lsp_completion();
                 return Ok(());
             }
+            (KeyModifiers::NONE, KeyCode::Enter) if self.continue_list_or_comment() => {
+                return Ok(());
+            }
+            (KeyModifiers::NONE, KeyCode::Enter) => {
+                self.insert_newline_with_indent();
+                return Ok(());
+            }
+            (KeyModifiers::NONE, KeyCode::Backspace) if self.soft_tab_backspace() => {
+                return Ok(());
+            }
             (KeyModifiers::NONE, KeyCode::Char(c))
                 if matches!(c, '(' | '[' | '{' | '"' | '\'')
                     && self
@@ -404,9 +491,18 @@ impl App {
             return self.run_key_action(action);
         }
 
-        let modified = self
-            .active_tab_mut()
-            .is_some_and(|t| t.editor.input(Input::from(key)));
+        let typing = self.active_tab().and_then(|t| typing_edit(t, &key));
+        let modified = self.active_tab_mut().is_some_and(|t| {
+            let joins = typing.is_some_and(|(kind, ch)| t.joins_typing_run(kind, ch));
+            if !joins {
+                t.end_typing_run();
+            }
+            let modified = t.editor.input(Input::from(key));
+            if let (true, Some((kind, ch))) = (modified, typing) {
+                t.record_typing(kind, ch, joins);
+            }
+            modified
+        });
         if modified {
             self.on_editor_content_changed();
         }
@@ -415,6 +511,48 @@ impl App {
         Ok(())
     }
 
+    /// Close the active tab's typing run before any key that does not type
+    /// or delete a single char, so the run is grouped before that key edits.
+    pub(crate) fn end_typing_run_unless_typing(&mut self, key: &KeyEvent) {
+        if let Some(tab) = self.active_tab_mut()
+            && typing_edit(tab, key).is_none()
+        {
+            tab.end_typing_run();
+        }
+    }
+
+    /// Editor keys for a read-only tab: bindings that leave the text alone
+    /// and cursor movement go through, anything that would edit is refused.
+    fn handle_read_only_editor_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        if let Some(action) = self.keybinds.lookup(&key, KeyScope::Editor) {
+            if action.edits_buffer() {
+                self.refuse_read_only_edit();
+                return Ok(());
+            }
+            return self.run_key_action(action);
+        }
+        let moves = matches!(
+            key.code,
+            KeyCode::Left
+                | KeyCode::Right
+                | KeyCode::Up
+                | KeyCode::Down
+                | KeyCode::Home
+                | KeyCode::End
+                | KeyCode::PageUp
+                | KeyCode::PageDown
+        );
+        if !moves {
+            self.refuse_read_only_edit();
+            return Ok(());
+        }
+        if let Some(tab) = self.active_tab_mut() {
+            tab.editor.input(Input::from(key));
+        }
+        self.sync_editor_scroll_guess();
+        Ok(())
+    }
+
     pub(crate) fn run_key_action(&mut self, action: KeyAction) -> io::Result<()> {
         match action {
             // Global
@@ -423,9 +561,7 @@ impl App {
                 if !self.tabs.is_empty() {
                     if self.is_dirty() {
                         self.pending = PendingAction::ClosePrompt;
-                        self.set_status(
-                            "Unsaved changes: Enter save+close | Esc discard | C cancel",
-                        );
+                        self.set_status(self.close_prompt_status());
                     } else {
                         self.close_file();
                     }
@@ -433,35 +569,23 @@ impl App {
             }
             KeyAction::Quit => {
                 if self.any_tab_dirty() {
-                    if matches!(self.pending, PendingAction::Quit) {
+                    if matches!(self.pending, PendingAction::QuitPrompt) {
                         self.quit = true;
                     } else {
-                        self.pending = PendingAction::Quit;
+                        self.pending = PendingAction::QuitPrompt;
+                        let dirty = self.dirty_tab_paths().len();
+                        let key = confirm_key_label(&self.pending, self.confirm_key);
                         self.set_status(format!(
-                            "Unsaved changes. Press {}+Q again to quit.",
-                            primary_mod_label()
+                            "{dirty} unsaved file(s): {key} save all | D discard all | C cancel"
                         ));
                     }
                 } else {
                     self.quit = true;
                 }
             }
-            KeyAction::ToggleFiles => {
-                self.files_view_open = !self.files_view_open;
-                if !self.files_view_open {
-                    self.focus = Focus::Editor;
-                    self.set_status("Files view hidden");
-                } else {
-                    self.set_status("Files view shown");
-                }
-            }
+            KeyAction::ToggleFiles => self.toggle_files_view(),
             KeyAction::CommandPalette => self.open_command_palette(),
-            KeyAction::QuickOpen => {
-                self.file_picker_open = true;
-                self.file_picker_query.clear();
-                self.file_picker_index = 0;
-                self.refresh_file_picker_results();
-            }
+            KeyAction::QuickOpen => self.open_file_picker(),
             KeyAction::Find => {
                 self.open_find_prompt();
             }
@@ -496,14 +620,27 @@ impl App {
                     self.switch_to_tab(next);
                 }
             }
+            KeyAction::LastUsedTab => self.last_used_tab(),
+            KeyAction::NavigateBack => self.navigate_back()?,
+            KeyAction::NavigateForward => self.navigate_forward()?,
+            KeyAction::GoToLastEdit => self.goto_last_edit()?,
+            KeyAction::ShowDiagnostics => self.open_diagnostics_panel(),
+            KeyAction::IncreaseFilesPane => self.resize_files_pane(true),
+            KeyAction::DecreaseFilesPane => self.resize_files_pane(false),
+            KeyAction::ToggleZen => self.toggle_zen_mode(),
+            KeyAction::KeepTabOpen => self.promote_preview_tab(),
+            KeyAction::ReopenClosedTab => self.reopen_closed_tab()?,
+            KeyAction::NewUntitled => self.new_untitled_tab(),
+            KeyAction::GoToSymbol => self.open_symbol_picker(),
+            KeyAction::CycleTreeSort => self.cycle_tree_sort()?,
+            KeyAction::NextSearchResult => self.next_search_result()?,
+            KeyAction::PrevSearchResult => self.prev_search_result()?,
             KeyAction::ToggleWordWrap => self.toggle_word_wrap(),
             KeyAction::TreeExpandAll => {
-                self.tree_expand_all()?;
-                self.set_status("Expanded all folders");
+                self.expand_all_under(&self.root.clone())?;
             }
             KeyAction::TreeCollapseAll => {
-                self.tree_collapse_all()?;
-                self.set_status("Collapsed all folders");
+                self.collapse_all()?;
             }
             KeyAction::TreeExpandRecursive => {
                 self.tree_expand_recursive()?;
@@ -523,6 +660,8 @@ impl App {
             KeyAction::Unfold => self.unfold_current_block(),
             KeyAction::FoldAll => self.fold_all(),
             KeyAction::UnfoldAll => self.unfold_all(),
+            KeyAction::NextFold => self.jump_to_fold(true),
+            KeyAction::PrevFold => self.jump_to_fold(false),
             KeyAction::FindNext => {
                 if self
                     .active_tab_mut()
@@ -550,7 +689,7 @@ impl App {
             KeyAction::Dedent => self.dedent_lines(),
             KeyAction::Completion => self.request_lsp_completion(),
             KeyAction::Undo => {
-                if self.active_tab_mut().is_some_and(|t| t.editor.undo()) {
+                if self.active_tab_mut().is_some_and(|t| t.undo()) {
                     self.on_editor_content_changed();
                     self.set_status("Undo");
                 } else {
@@ -559,7 +698,7 @@ impl App {
                 self.sync_editor_scroll_guess();
             }
             KeyAction::Redo => {
-                if self.active_tab_mut().is_some_and(|t| t.editor.redo()) {
+                if self.active_tab_mut().is_some_and(|t| t.redo()) {
                     self.on_editor_content_changed();
                     self.set_status("Redo");
                 } else {
@@ -578,8 +717,17 @@ impl App {
             KeyAction::CutLine => self.cut_line(),
             KeyAction::Paste => self.paste_from_clipboard(),
             KeyAction::ToggleComment => self.toggle_comment(),
+            KeyAction::ExpandEmmet => self.expand_emmet(),
+            KeyAction::ReflowParagraph => self.reflow_paragraph(),
+            KeyAction::ToggleTaskCheckbox => self.toggle_task_checkbox(),
+            KeyAction::ToggleListItem => self.toggle_list_item(),
             KeyAction::PageDown => self.page_down(),
             KeyAction::PageUp => self.page_up(),
+            KeyAction::CenterCursorLine => self.center_cursor_line(),
+            KeyAction::ScrollCursorToTop => self.scroll_cursor_to_top(),
+            KeyAction::ScrollCursorToBottom => self.scroll_cursor_to_bottom(),
+            KeyAction::NextDiagnostic => self.next_diagnostic(),
+            KeyAction::PrevDiagnostic => self.prev_diagnostic(),
             KeyAction::GoToStart => {
                 if let Some(tab) = self.active_tab_mut() {
                     tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(0, 0));
@@ -798,7 +946,40 @@ impl App {
         Ok(())
     }
 
+    /// Scroll the diff shown in the conflict/recovery prompts. Returns true
+    /// when the key was a scroll key.
+    fn scroll_prompt_diff(&mut self, key: &KeyEvent) -> bool {
+        let page = self.prompt_diff_page.max(1);
+        let scroll = &mut self.prompt_diff_scroll;
+        match key.code {
+            KeyCode::Up => *scroll = scroll.saturating_sub(1),
+            KeyCode::Down => *scroll += 1,
+            KeyCode::PageUp => *scroll = scroll.saturating_sub(page),
+            KeyCode::PageDown => *scroll += page,
+            _ => return false,
+        }
+        true
+    }
+
+    pub(crate) fn handle_unsaved_diff_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        if self.scroll_prompt_diff(&key) {
+            return Ok(());
+        }
+        if matches!(
+            key.code,
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') | KeyCode::Char('Q')
+        ) {
+            self.prompt_diff_scroll = 0;
+            self.unsaved_diff_open = false;
+        }
+        Ok(())
+    }
+
     pub(crate) fn handle_recovery_prompt_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        if self.scroll_prompt_diff(&key) {
+            return Ok(());
+        }
+        self.prompt_diff_scroll = 0;
         match (key.modifiers, key.code) {
             (_, KeyCode::Enter) | (_, KeyCode::Char('r')) | (_, KeyCode::Char('R')) => {
                 let text = self.active_tab().and_then(|t| t.recovery_text.clone());
@@ -835,6 +1016,10 @@ impl App {
     }
 
     pub(crate) fn handle_conflict_prompt_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        if self.scroll_prompt_diff(&key) {
+            return Ok(());
+        }
+        self.prompt_diff_scroll = 0;
         match (key.modifiers, key.code) {
             (_, KeyCode::Char('r')) | (_, KeyCode::Char('R')) => {
                 let disk = self.active_tab().and_then(|t| t.conflict_disk_text.clone());
@@ -882,37 +1067,285 @@ impl App {
 
     pub(crate) fn handle_theme_browser_key(&mut self, key: KeyEvent) -> io::Result<()> {
         match (key.modifiers, key.code) {
-            (_, KeyCode::Esc) => {
-                self.active_theme_index = self.preview_revert_index;
-                self.theme_index = self.preview_revert_index;
+            (_, KeyCode::Esc) => self.cancel_theme_browser(),
+            (_, KeyCode::Down) => self.step_theme_browser(true),
+            (_, KeyCode::Up) => self.step_theme_browser(false),
+            (_, KeyCode::Enter) => {
+                self.persist_theme_selection();
                 self.theme_browser_open = false;
                 self.menu_open = false;
-                self.set_status(format!("Theme reverted: {}", self.active_theme().name));
+                self.set_status(format!("Theme: {}", self.active_theme().name));
             }
-            (_, KeyCode::Down) | (_, KeyCode::Char('j')) => {
-                if self.theme_index + 1 < self.themes.len() {
-                    self.theme_index += 1;
-                    self.active_theme_index = self.theme_index;
-                    self.set_status(format!("Preview: {}", self.active_theme().name));
+            (_, KeyCode::Backspace) => {
+                self.theme_query.pop();
+                self.refresh_theme_results();
+            }
+            (m, KeyCode::Char(c)) if !m.contains(KeyModifiers::CONTROL) => {
+                self.theme_query.push(c);
+                self.refresh_theme_results();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+    pub(crate) fn handle_language_picker_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        match key.code {
+            KeyCode::Esc => self.language_picker.open = false,
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.language_picker.index < SyntaxLang::ALL.len() {
+                    self.language_picker.index += 1;
                 }
             }
-            (_, KeyCode::Up) | (_, KeyCode::Char('k')) => {
-                if self.theme_index > 0 {
-                    self.theme_index -= 1;
-                    self.active_theme_index = self.theme_index;
-                    self.set_status(format!("Preview: {}", self.active_theme().name));
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.language_picker.index = self.language_picker.index.saturating_sub(1);
+            }
+            KeyCode::Enter => self.apply_language_picker(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_language_picker_mouse(&mut self, mouse: MouseEvent) -> io::Result<()> {
+        if Self::left_click_outside(mouse, self.language_picker.rect) {
+            self.language_picker.open = false;
+            return Ok(());
+        }
+        if matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
+            let row = mouse.row.saturating_sub(self.language_picker.rect.y + 1) as usize;
+            if row <= SyntaxLang::ALL.len() {
+                self.language_picker.index = row;
+                self.apply_language_picker();
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_diff_tab_picker_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let picker = &mut self.diff_tab_picker;
+        match key.code {
+            KeyCode::Esc => picker.open = false,
+            KeyCode::Down | KeyCode::Char('j') => {
+                if picker.index + 1 < picker.tabs.len() {
+                    picker.index += 1;
                 }
             }
-            (_, KeyCode::Enter) => {
-                self.persist_theme_selection();
-                self.theme_browser_open = false;
-                self.menu_open = false;
-                self.set_status(format!("Theme: {}", self.active_theme().name));
+            KeyCode::Up | KeyCode::Char('k') => {
+                picker.index = picker.index.saturating_sub(1);
             }
+            KeyCode::Enter => self.apply_diff_tab_picker(),
             _ => {}
         }
         Ok(())
     }
+
+    pub(crate) fn handle_diff_tab_picker_mouse(&mut self, mouse: MouseEvent) -> io::Result<()> {
+        if Self::left_click_outside(mouse, self.diff_tab_picker.rect) {
+            self.diff_tab_picker.open = false;
+            return Ok(());
+        }
+        if matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
+            let row = mouse.row.saturating_sub(self.diff_tab_picker.rect.y + 1) as usize;
+            if row < self.diff_tab_picker.tabs.len() {
+                self.diff_tab_picker.index = row;
+                self.apply_diff_tab_picker();
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_recent_project_picker_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let picker = &mut self.recent_project_picker;
+        match key.code {
+            KeyCode::Esc => picker.open = false,
+            KeyCode::Down | KeyCode::Char('j') => {
+                if picker.index + 1 < picker.projects.len() {
+                    picker.index += 1;
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                picker.index = picker.index.saturating_sub(1);
+            }
+            KeyCode::Enter => self.apply_recent_project_picker()?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_recent_project_picker_mouse(
+        &mut self,
+        mouse: MouseEvent,
+    ) -> io::Result<()> {
+        let rect = self.recent_project_picker.rect;
+        if Self::left_click_outside(mouse, rect) {
+            self.recent_project_picker.open = false;
+            return Ok(());
+        }
+        if matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
+            let row = mouse.row.saturating_sub(rect.y + 1) as usize;
+            if row < self.recent_project_picker.projects.len() {
+                self.recent_project_picker.index = row;
+                self.apply_recent_project_picker()?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_folder_picker_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let rows = self.folder_picker.entries.len() + 2;
+        match key.code {
+            KeyCode::Esc => self.folder_picker.open = false,
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.folder_picker.index + 1 < rows {
+                    self.folder_picker.index += 1;
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.folder_picker.index = self.folder_picker.index.saturating_sub(1);
+            }
+            KeyCode::Backspace | KeyCode::Left => self.folder_picker_up(),
+            KeyCode::Enter => self.apply_folder_picker()?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_folder_picker_mouse(&mut self, mouse: MouseEvent) -> io::Result<()> {
+        if Self::left_click_outside(mouse, self.folder_picker.rect) {
+            self.folder_picker.open = false;
+            return Ok(());
+        }
+        if matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
+            let row = mouse.row.saturating_sub(self.folder_picker.rect.y + 1) as usize
+                + self.folder_picker.offset;
+            if row < self.folder_picker.entries.len() + 2 {
+                self.folder_picker.index = row;
+                self.apply_folder_picker()?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_diagnostics_panel_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let total = self.project_diagnostics().len();
+        let panel = &mut self.diagnostics_panel;
+        panel.index = panel.index.min(total.saturating_sub(1));
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => panel.open = false,
+            KeyCode::Down | KeyCode::Char('j') => {
+                if panel.index + 1 < total {
+                    panel.index += 1;
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => panel.index = panel.index.saturating_sub(1),
+            KeyCode::Enter => self.open_selected_diagnostic()?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_diagnostics_panel_mouse(&mut self, mouse: MouseEvent) -> io::Result<()> {
+        if Self::left_click_outside(mouse, self.diagnostics_panel.rect) {
+            self.diagnostics_panel.open = false;
+            return Ok(());
+        }
+        if matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
+            let row = mouse.row.saturating_sub(self.diagnostics_panel.rect.y + 1) as usize;
+            let rows = diagnostic_panel_rows(&self.project_diagnostics());
+            let idx = match rows.get(self.diagnostics_panel.scroll + row) {
+                Some(DiagnosticPanelRow::Item(idx)) => Some(*idx),
+                Some(DiagnosticPanelRow::Header { first, .. }) => Some(*first),
+                None => None,
+            };
+            if let Some(idx) = idx {
+                self.diagnostics_panel.index = idx;
+                self.open_selected_diagnostic()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Scroll both columns of the side-by-side tab diff together.
+    pub(crate) fn handle_tab_diff_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(diff) = self.tab_diff.as_mut() else {
+            return Ok(());
+        };
+        let page = diff.page.max(1);
+        let max = diff.rows.len().saturating_sub(page);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.tab_diff = None,
+            KeyCode::Up | KeyCode::Char('k') => diff.scroll = diff.scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => diff.scroll = (diff.scroll + 1).min(max),
+            KeyCode::PageUp => diff.scroll = diff.scroll.saturating_sub(page),
+            KeyCode::PageDown => diff.scroll = (diff.scroll + page).min(max),
+            KeyCode::Home => diff.scroll = 0,
+            KeyCode::End => diff.scroll = max,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_tab_diff_mouse(&mut self, mouse: MouseEvent) -> io::Result<()> {
+        let Some(diff) = self.tab_diff.as_mut() else {
+            return Ok(());
+        };
+        let max = diff.rows.len().saturating_sub(diff.page.max(1));
+        match mouse.kind {
+            MouseEventKind::ScrollUp => diff.scroll = diff.scroll.saturating_sub(3),
+            MouseEventKind::ScrollDown => diff.scroll = (diff.scroll + 3).min(max),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_symbol_picker_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        match (key.modifiers, key.code) {
+            (_, KeyCode::Esc) => self.symbol_picker.open = false,
+            (_, KeyCode::Enter) => self.apply_symbol_picker(),
+            (_, KeyCode::Down) => {
+                if self.symbol_picker.index + 1 < self.symbol_picker.results.len() {
+                    self.symbol_picker.index += 1;
+                }
+            }
+            (_, KeyCode::Up) => {
+                self.symbol_picker.index = self.symbol_picker.index.saturating_sub(1);
+            }
+            (_, KeyCode::Backspace) => {
+                self.symbol_picker.query.pop();
+                self.symbol_picker.index = 0;
+                self.refresh_symbol_picker_results();
+            }
+            (_, KeyCode::Char(c)) => {
+                if !key.modifiers.contains(KeyModifiers::CONTROL)
+                    && !key.modifiers.contains(KeyModifiers::ALT)
+                {
+                    self.symbol_picker.query.push(c);
+                    self.symbol_picker.index = 0;
+                    self.refresh_symbol_picker_results();
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_symbol_picker_mouse(&mut self, mouse: MouseEvent) -> io::Result<()> {
+        if Self::left_click_outside(mouse, self.symbol_picker.rect) {
+            self.symbol_picker.open = false;
+            return Ok(());
+        }
+        if matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
+            // Results start below the query line and a blank spacer.
+            let first_row = self.symbol_picker.rect.y + 3;
+            if let Some(row) = mouse.row.checked_sub(first_row).map(usize::from)
+                && row < self.symbol_picker.results.len()
+            {
+                self.symbol_picker.index = row;
+                self.apply_symbol_picker();
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) fn handle_completion_mouse(&mut self, mouse: MouseEvent) -> io::Result<()> {
         if !matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
             return Ok(());
@@ -967,32 +1400,22 @@ impl App {
     }
 
     pub(crate) fn handle_theme_browser_mouse(&mut self, mouse: MouseEvent) -> io::Result<()> {
-        if Self::left_click_outside(mouse, self.theme_browser_rect) {
-            self.active_theme_index = self.preview_revert_index;
-            self.theme_index = self.preview_revert_index;
-            self.theme_browser_open = false;
-            self.menu_open = false;
-            self.set_status(format!("Theme reverted: {}", self.active_theme().name));
+        if Self::left_click_outside(mouse, self.theme_browser_rect)
+            && Self::left_click_outside(mouse, self.theme_preview_rect)
+        {
+            self.cancel_theme_browser();
             return Ok(());
         }
         match mouse.kind {
-            MouseEventKind::ScrollDown => {
-                if self.theme_index + 1 < self.themes.len() {
-                    self.theme_index += 1;
-                    self.active_theme_index = self.theme_index;
-                }
-            }
-            MouseEventKind::ScrollUp => {
-                if self.theme_index > 0 {
-                    self.theme_index -= 1;
-                    self.active_theme_index = self.theme_index;
-                }
-            }
-            MouseEventKind::Down(MouseButton::Left) => {
+            MouseEventKind::ScrollDown => self.step_theme_browser(true),
+            MouseEventKind::ScrollUp => self.step_theme_browser(false),
+            MouseEventKind::Down(MouseButton::Left)
+                if inside(mouse.column, mouse.row, self.theme_browser_rect) =>
+            {
                 let row = mouse.row.saturating_sub(self.theme_browser_rect.y + 1) as usize;
-                if row < self.themes.len() {
-                    self.theme_index = row;
-                    self.active_theme_index = row;
+                if let Some(&idx) = self.theme_results.get(row) {
+                    self.theme_index = idx;
+                    self.active_theme_index = idx;
                     self.persist_theme_selection();
                     self.theme_browser_open = false;
                     self.menu_open = false;
@@ -1081,13 +1504,44 @@ impl App {
         }
         if !inside(mouse.column, mouse.row, self.search_results_rect) {
             self.search_results.open = false;
+            self.cancel_project_search();
             return Ok(());
         }
         let row = mouse.row.saturating_sub(self.search_results_rect.y + 1) as usize;
-        if row < self.search_results.results.len() {
-            self.search_results.index = row;
+        let rows = search_result_rows(&self.search_results.results);
+        let hit = match rows.get(self.search_results.scroll + row) {
+            Some(SearchResultRow::Hit(idx)) => Some(*idx),
+            Some(SearchResultRow::Context { hit, .. }) => Some(*hit),
+            Some(SearchResultRow::Header { first_hit, .. }) => Some(*first_hit),
+            None => None,
+        };
+        if let Some(idx) = hit {
+            self.search_results.index = idx;
             self.open_selected_search_result()?;
         }
         Ok(())
     }
 }
+
+/// Plain typing and single-char deletions, which coalesce into one undo
+/// step, with the char typed or about to be deleted.
+fn typing_edit(tab: &Tab, key: &KeyEvent) -> Option<(TypingKind, char)> {
+    if tab.editor.selection_range().is_some() {
+        return None;
+    }
+    let (row, col) = tab.editor.cursor();
+    let line = tab.editor.lines().get(row)?;
+    match (key.modifiers, key.code) {
+        (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(c)) => {
+            Some((TypingKind::Insert, c))
+        }
+        (KeyModifiers::NONE, KeyCode::Backspace) => line
+            .chars()
+            .nth(col.checked_sub(1)?)
+            .map(|c| (TypingKind::Delete, c)),
+        (KeyModifiers::NONE, KeyCode::Delete) => {
+            line.chars().nth(col).map(|c| (TypingKind::Delete, c))
+        }
+        _ => None,
+    }
+}
@@ -2,12 +2,16 @@ use super::App;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::TryRecvError;
+use std::time::{Duration, Instant};
 
 use serde_json::{Value, json};
 use url::Url;
 
-use crate::lsp_client::{LspClient, LspCompletionItem, LspDiagnostic, LspInbound};
-use crate::syntax::{is_ident_char, keywords_for_lang, syntax_lang_for_path};
+use crate::lsp_client::{
+    LspClient, LspCompletionItem, LspDiagnostic, LspInbound, PendingLspRequest, content_change,
+};
+use crate::syntax::{SyntaxLang, is_ident_char, keywords_for_lang};
+use crate::tab::ProjectDiagnostic;
 use crate::util::{file_uri, to_u16_saturating};
 
 impl App {
@@ -32,7 +36,9 @@ impl App {
             }),
         ) {
             Ok(id) => {
-                self.pending_definition_request = Some(id);
+                let previous = self.pending_definition_request.take();
+                self.cancel_lsp_request(previous);
+                self.pending_definition_request = self.pending_request(id);
                 self.set_status("Go to definition requested");
             }
             Err(_) => self.set_status("Failed to request definition"),
@@ -93,6 +99,7 @@ impl App {
             self.set_status("Unsaved changes: save or close before jumping to definition");
             return Ok(());
         }
+        self.push_nav_history();
         if self.open_path() != Some(&path) {
             self.open_file(path)?;
         }
@@ -138,6 +145,7 @@ impl App {
             ];
             if candidates.iter().any(|p| trimmed.starts_with(p)) {
                 let col = line.find("fn ").unwrap_or(0);
+                self.push_nav_history();
                 self.tabs[self.active_tab]
                     .editor
                     .move_cursor(ratatui_textarea::CursorMove::Jump(
@@ -152,30 +160,64 @@ impl App {
         false
     }
 
+    /// Drop the language server so the next one starts in the current root,
+    /// and reopen every tab's document in it. A server that failed to start
+    /// is tried again.
+    pub(crate) fn restart_lsp(&mut self) {
+        // Closing the server's stdin shuts it down.
+        self.lsp = None;
+        self.lsp_unavailable = None;
+        self.completion.reset();
+        self.pending_completion_request = None;
+        self.pending_definition_request = None;
+        self.unopened_diagnostics.clear();
+        for tab in &mut self.tabs {
+            tab.open_doc_uri = None;
+            tab.open_doc_version = 0;
+            tab.diagnostics.clear();
+            tab.pending_diagnostics = None;
+        }
+        let active = self.active_tab;
+        for idx in 0..self.tabs.len() {
+            self.active_tab = idx;
+            let path = self.tabs[idx].path.clone();
+            self.ensure_lsp_for_path(&path);
+        }
+        self.active_tab = active;
+    }
+
     pub(crate) fn ensure_lsp_for_path(&mut self, path: &Path) {
-        let is_rust = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .is_some_and(|e| e.eq_ignore_ascii_case("rs"));
-        if !is_rust {
+        let Some(language_id) = self.lsp_server.language_id(path) else {
             if let Some(tab) = self.active_tab_mut() {
                 tab.open_doc_uri = None;
                 tab.open_doc_version = 0;
                 tab.diagnostics.clear();
+                tab.pending_diagnostics = None;
             }
             self.completion.reset();
             self.pending_completion_request = None;
             self.pending_definition_request = None;
             return;
-        }
+        };
         if self.lsp.is_none() {
-            match LspClient::new_rust_analyzer(&self.root) {
+            if self.lsp_unavailable.is_some() {
+                return;
+            }
+            match LspClient::start(&self.lsp_bin, self.lsp_server.args, &self.root) {
                 Ok(client) => {
                     self.lsp = Some(client);
                     self.set_status("LSP connected");
                 }
                 Err(err) => {
-                    self.set_status(format!("LSP unavailable: {}", err));
+                    // Reported once; the editor carries on without LSP
+                    // features until the next restart.
+                    let message = format!(
+                        "LSP unavailable: could not start {}: {}",
+                        self.lsp_bin.display(),
+                        err
+                    );
+                    self.set_status(message.clone());
+                    self.lsp_unavailable = Some(message);
                     return;
                 }
             }
@@ -186,6 +228,7 @@ impl App {
             if let Some(tab) = self.active_tab_mut() {
                 tab.open_doc_uri = Some(uri.clone());
                 tab.open_doc_version = version;
+                tab.lsp_synced_text = text.clone();
             }
             if let Some(lsp) = self.lsp.as_ref() {
                 let _ = lsp.send_notification(
@@ -193,7 +236,7 @@ impl App {
                     json!({
                         "textDocument": {
                             "uri": uri,
-                            "languageId": "rust",
+                            "languageId": language_id,
                             "version": version,
                             "text": text
                         }
@@ -204,13 +247,21 @@ impl App {
     }
 
     pub(crate) fn notify_lsp_did_change(&mut self) {
-        let uri = self.active_tab().and_then(|t| t.open_doc_uri.clone());
+        let uri = self
+            .active_tab()
+            .filter(|t| !t.read_only)
+            .and_then(|t| t.open_doc_uri.clone());
         let (Some(uri), Some(lsp)) = (uri, self.lsp.as_ref()) else {
             return;
         };
         let tab = &mut self.tabs[self.active_tab];
-        tab.open_doc_version += 1;
         let text = tab.editor.lines().join("\n");
+        let incremental = lsp.incremental_sync;
+        let Some(change) = content_change(&tab.lsp_synced_text, &text, incremental) else {
+            return;
+        };
+        tab.open_doc_version += 1;
+        tab.lsp_synced_text = text;
         let version = tab.open_doc_version;
         let _ = lsp.send_notification(
             "textDocument/didChange",
@@ -219,9 +270,7 @@ impl App {
                     "uri": uri,
                     "version": version
                 },
-                "contentChanges": [
-                    { "text": text }
-                ]
+                "contentChanges": [change]
             }),
         );
     }
@@ -244,43 +293,105 @@ impl App {
                         self.handle_publish_diagnostics(params);
                     }
                 }
-                LspInbound::Response { id, result } => {
-                    if self.pending_completion_request == Some(id) {
-                        self.pending_completion_request = None;
-                        self.handle_completion_response(result);
-                    } else if self.pending_definition_request == Some(id) {
-                        self.pending_definition_request = None;
-                        let _ = self.handle_definition_response(result);
-                    }
-                }
+                LspInbound::Response { id, result } => self.handle_lsp_response(id, result),
+            }
+        }
+        self.cancel_stale_lsp_requests();
+        self.show_pending_diagnostics();
+    }
+
+    /// Show diagnostics that have waited out `diagnostics_delay_ms` without
+    /// being replaced by a newer report.
+    pub(crate) fn show_pending_diagnostics(&mut self) {
+        let delay = Duration::from_millis(self.diagnostics_delay_ms);
+        for tab in &mut self.tabs {
+            let pending = &mut tab.pending_diagnostics;
+            if let Some((_, diagnostics)) = pending.take_if(|(at, _)| at.elapsed() >= delay) {
+                tab.diagnostics = diagnostics;
+            }
+        }
+    }
+
+    /// Route a response to the request that is waiting for it. Responses to
+    /// requests that were cancelled, replaced, or made from an editor state
+    /// the user has since left are dropped.
+    pub(crate) fn handle_lsp_response(&mut self, id: i64, result: Value) {
+        if let Some(request) = self.pending_completion_request.take_if(|r| r.id == id) {
+            if self.request_is_current(&request) {
+                self.handle_completion_response(result);
+            }
+        } else if let Some(request) = self.pending_definition_request.take_if(|r| r.id == id) {
+            if self.request_is_current(&request) {
+                let _ = self.handle_definition_response(result);
             }
         }
     }
 
+    /// Record request `id` as sent from the active tab's current state.
+    fn pending_request(&self, id: i64) -> Option<PendingLspRequest> {
+        let tab = self.active_tab()?;
+        Some(PendingLspRequest {
+            id,
+            path: tab.path.clone(),
+            cursor: tab.editor.cursor(),
+            version: tab.open_doc_version,
+        })
+    }
+
+    /// Whether the active tab is still at the file, cursor, and document
+    /// version `request` was sent from.
+    fn request_is_current(&self, request: &PendingLspRequest) -> bool {
+        self.active_tab().is_some_and(|tab| {
+            tab.path == request.path
+                && tab.editor.cursor() == request.cursor
+                && tab.open_doc_version == request.version
+        })
+    }
+
+    fn cancel_lsp_request(&self, request: Option<PendingLspRequest>) {
+        if let (Some(request), Some(lsp)) = (request, self.lsp.as_ref()) {
+            let _ = lsp.cancel_request(request.id);
+        }
+    }
+
+    /// Cancel in-flight requests whose triggering state is gone, e.g. the
+    /// cursor moved or the buffer changed before the server answered.
+    pub(crate) fn cancel_stale_lsp_requests(&mut self) {
+        let stale = |app: &Self, pending: &Option<PendingLspRequest>| {
+            pending
+                .as_ref()
+                .is_some_and(|request| !app.request_is_current(request))
+        };
+        if stale(self, &self.pending_completion_request) {
+            let request = self.pending_completion_request.take();
+            self.cancel_lsp_request(request);
+        }
+        if stale(self, &self.pending_definition_request) {
+            let request = self.pending_definition_request.take();
+            self.cancel_lsp_request(request);
+        }
+    }
+
     pub(crate) fn handle_publish_diagnostics(&mut self, params: Value) {
         let uri = params
             .get("uri")
             .and_then(Value::as_str)
             .unwrap_or_default()
             .to_string();
-        // Find the tab that matches this URI
-        let tab_idx = self
-            .tabs
-            .iter()
-            .position(|t| t.open_doc_uri.as_deref() == Some(uri.as_str()));
-        let Some(tab_idx) = tab_idx else {
-            return;
-        };
         let mut diagnostics = Vec::new();
         if let Some(items) = params.get("diagnostics").and_then(Value::as_array) {
             for d in items {
-                let line = d
-                    .get("range")
-                    .and_then(|r| r.get("start"))
-                    .and_then(|s| s.get("line"))
-                    .and_then(Value::as_u64)
-                    .unwrap_or(0) as usize
-                    + 1;
+                let position = |edge: &str| {
+                    let pos = d.get("range").and_then(|r| r.get(edge));
+                    let field = |name: &str| {
+                        pos.and_then(|p| p.get(name))
+                            .and_then(Value::as_u64)
+                            .unwrap_or(0) as usize
+                    };
+                    (field("line"), field("character"))
+                };
+                let (start_line, col) = position("start");
+                let (end_line, end_col) = position("end");
                 let severity = match d.get("severity").and_then(Value::as_u64).unwrap_or(0) {
                     1 => "error",
                     2 => "warning",
@@ -295,13 +406,166 @@ impl App {
                     .unwrap_or_default()
                     .to_string();
                 diagnostics.push(LspDiagnostic {
-                    line,
+                    line: start_line + 1,
+                    col,
+                    end_line: end_line.max(start_line) + 1,
+                    end_col,
                     severity,
                     message,
                 });
             }
         }
-        self.tabs[tab_idx].diagnostics = diagnostics;
+        // Find the tab that matches this URI; other files are kept for the
+        // problems panel.
+        let tab_idx = self
+            .tabs
+            .iter()
+            .position(|t| t.open_doc_uri.as_deref() == Some(uri.as_str()));
+        if let Some(tab_idx) = tab_idx {
+            self.unopened_diagnostics.remove(&self.tabs[tab_idx].path);
+            let tab = &mut self.tabs[tab_idx];
+            if self.diagnostics_delay_ms == 0 {
+                tab.diagnostics = diagnostics;
+                tab.pending_diagnostics = None;
+            } else {
+                // A newer report replaces one still waiting, so it never shows.
+                tab.pending_diagnostics = Some((Instant::now(), diagnostics));
+            }
+            return;
+        }
+        let Some(path) = Url::parse(&uri).ok().and_then(|u| u.to_file_path().ok()) else {
+            return;
+        };
+        if diagnostics.is_empty() {
+            self.unopened_diagnostics.remove(&path);
+        } else {
+            self.unopened_diagnostics.insert(path, diagnostics);
+        }
+    }
+
+    /// Every known diagnostic, grouped by file in path order and sorted by
+    /// position within each file. Open tabs supply their own; the rest come
+    /// from reports for files that aren't open.
+    pub(crate) fn project_diagnostics(&self) -> Vec<ProjectDiagnostic> {
+        let mut files: Vec<(&PathBuf, &[LspDiagnostic])> = self
+            .tabs
+            .iter()
+            .map(|t| (&t.path, t.diagnostics.as_slice()))
+            .collect();
+        files.extend(
+            self.unopened_diagnostics
+                .iter()
+                .filter(|(path, _)| !self.tabs.iter().any(|t| &t.path == *path))
+                .map(|(path, diagnostics)| (path, diagnostics.as_slice())),
+        );
+        files.sort_by(|a, b| a.0.cmp(b.0));
+        let mut out = Vec::new();
+        for (path, diagnostics) in files {
+            let mut diagnostics: Vec<&LspDiagnostic> = diagnostics.iter().collect();
+            diagnostics.sort_by_key(|d| (d.line, d.col));
+            out.extend(diagnostics.into_iter().map(|d| ProjectDiagnostic {
+                path: path.clone(),
+                diagnostic: d.clone(),
+            }));
+        }
+        out
+    }
+
+    pub(crate) fn open_diagnostics_panel(&mut self) {
+        let total = self.project_diagnostics().len();
+        self.diagnostics_panel.open = true;
+        self.diagnostics_panel.index = 0;
+        self.diagnostics_panel.scroll = 0;
+        self.set_status(match total {
+            0 => "No problems".to_string(),
+            1 => "1 problem".to_string(),
+            n => format!("{n} problems"),
+        });
+    }
+
+    /// Jump to the problem selected in the panel, opening its file if needed.
+    pub(crate) fn open_selected_diagnostic(&mut self) -> io::Result<()> {
+        let Some(entry) = self
+            .project_diagnostics()
+            .into_iter()
+            .nth(self.diagnostics_panel.index)
+        else {
+            return Ok(());
+        };
+        self.diagnostics_panel.open = false;
+        self.push_nav_history();
+        if self.open_path() != Some(&entry.path) {
+            self.open_file(entry.path.clone())?;
+            if self.open_path() != Some(&entry.path) {
+                return Ok(());
+            }
+        }
+        self.jump_to_diagnostic(&entry.diagnostic);
+        Ok(())
+    }
+
+    pub(crate) fn next_diagnostic(&mut self) {
+        self.step_diagnostic(true);
+    }
+
+    pub(crate) fn prev_diagnostic(&mut self) {
+        self.step_diagnostic(false);
+    }
+
+    /// Move the cursor to the active tab's next (or previous) diagnostic in
+    /// line/column order, wrapping past either end. Only errors are visited
+    /// when `diagnostic_errors_only` is set.
+    fn step_diagnostic(&mut self, forward: bool) {
+        let errors_only = self.diagnostic_errors_only;
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let mut diagnostics: Vec<&LspDiagnostic> = tab
+            .diagnostics
+            .iter()
+            .filter(|d| !errors_only || d.severity == "error")
+            .collect();
+        diagnostics.sort_by_key(|d| (d.line, d.col));
+        let (row, col) = tab.editor.cursor();
+        let here = (row + 1, col);
+        let target = if forward {
+            diagnostics
+                .iter()
+                .find(|d| (d.line, d.col) > here)
+                .or(diagnostics.first())
+        } else {
+            diagnostics
+                .iter()
+                .rev()
+                .find(|d| (d.line, d.col) < here)
+                .or(diagnostics.last())
+        };
+        let Some(diag) = target.map(|d| (*d).clone()) else {
+            self.set_status(if errors_only {
+                "No errors"
+            } else {
+                "No diagnostics"
+            });
+            return;
+        };
+        self.jump_to_diagnostic(&diag);
+    }
+
+    /// Put the cursor at the start of `diag` in the active tab and show its
+    /// message.
+    fn jump_to_diagnostic(&mut self, diag: &LspDiagnostic) {
+        if let Some(tab) = self.active_tab_mut() {
+            let last_row = tab.editor.lines().len().saturating_sub(1);
+            let row = diag.line.saturating_sub(1).min(last_row);
+            let col = diag.col.min(tab.editor.lines()[row].chars().count());
+            tab.editor.cancel_selection();
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(row),
+                to_u16_saturating(col),
+            ));
+        }
+        self.sync_editor_scroll_guess();
+        self.set_status(format!("[{}] {}", diag.severity, diag.message));
     }
 
     pub(crate) fn request_lsp_completion(&mut self) {
@@ -325,7 +589,9 @@ impl App {
             }),
         ) {
             Ok(id) => {
-                self.pending_completion_request = Some(id);
+                let previous = self.pending_completion_request.take();
+                self.cancel_lsp_request(previous);
+                self.pending_completion_request = self.pending_request(id);
                 self.set_status("Completion requested");
             }
             Err(_) => {
@@ -417,7 +683,10 @@ impl App {
         let prefix = self.current_identifier_prefix();
         let mut seen = std::collections::BTreeSet::new();
         let mut out = Vec::new();
-        for kw in keywords_for_lang(syntax_lang_for_path(self.open_path().map(|p| p.as_path()))) {
+        let lang = self
+            .active_tab()
+            .map_or(SyntaxLang::Plain, |tab| tab.syntax_lang());
+        for kw in keywords_for_lang(lang) {
             if (prefix.is_empty() || kw.starts_with(&prefix))
                 && kw != &prefix
                 && seen.insert((*kw).to_string())
@@ -609,3 +878,299 @@ impl App {
             .map(ToString::to_string)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui_textarea::CursorMove;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn new_app(root: &Path) -> App {
+        App::with_paths(root.to_path_buf(), None, None).expect("app should initialize")
+    }
+
+    fn diag(line: usize, col: usize, severity: &str) -> LspDiagnostic {
+        LspDiagnostic {
+            line,
+            col,
+            end_line: line,
+            end_col: col + 1,
+            severity: severity.to_string(),
+            message: format!("{severity} at {line}:{col}"),
+        }
+    }
+
+    fn app_with_diagnostics(root: &Path) -> App {
+        let file = root.join("main.rs");
+        fs::write(&file, "fn a() {}\nfn b() {}\nfn c() {}\nfn d() {}\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        app.diagnostic_errors_only = false;
+        // Out of order, to check they are visited by position
+        app.tabs[app.active_tab].diagnostics = vec![
+            diag(3, 3, "error"),
+            diag(1, 5, "warning"),
+            diag(3, 0, "hint"),
+            diag(4, 1, "error"),
+        ];
+        app
+    }
+
+    fn cursor(app: &App) -> (usize, usize) {
+        app.active_tab().expect("tab").editor.cursor()
+    }
+
+    #[test]
+    fn next_diagnostic_visits_in_order_and_wraps() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = app_with_diagnostics(tmp.path());
+
+        let mut visited = Vec::new();
+        for _ in 0..5 {
+            app.next_diagnostic();
+            visited.push(cursor(&app));
+        }
+        assert_eq!(visited, [(0, 5), (2, 0), (2, 3), (3, 1), (0, 5)]);
+        assert_eq!(app.status, "[warning] warning at 1:5");
+
+        app.prev_diagnostic();
+        assert_eq!(cursor(&app), (3, 1));
+        app.prev_diagnostic();
+        assert_eq!(cursor(&app), (2, 3));
+    }
+
+    #[test]
+    fn errors_only_skips_other_severities() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = app_with_diagnostics(tmp.path());
+        app.diagnostic_errors_only = true;
+
+        app.next_diagnostic();
+        assert_eq!(cursor(&app), (2, 3));
+        app.next_diagnostic();
+        assert_eq!(cursor(&app), (3, 1));
+        app.next_diagnostic();
+        assert_eq!(cursor(&app), (2, 3));
+
+        app.tabs[app.active_tab]
+            .diagnostics
+            .retain(|d| d.severity != "error");
+        app.next_diagnostic();
+        assert_eq!(app.status, "No errors");
+        app.diagnostic_errors_only = false;
+        app.tabs[app.active_tab].diagnostics.clear();
+        app.prev_diagnostic();
+        assert_eq!(app.status, "No diagnostics");
+    }
+
+    #[test]
+    fn project_diagnostics_collects_open_and_unopened_files() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path().canonicalize().expect("canonical root");
+        let mut app = app_with_diagnostics(&root);
+        let other = root.join("other.rs");
+        fs::write(&other, "fn x() {}\nfn y() {}\n").expect("write other");
+        app.handle_publish_diagnostics(json!({
+            "uri": file_uri(&other).expect("uri"),
+            "diagnostics": [{
+                "range": {
+                    "start": { "line": 1, "character": 3 },
+                    "end": { "line": 1, "character": 4 },
+                },
+                "severity": 1,
+                "message": "mismatched types",
+            }],
+        }));
+
+        let entries = app.project_diagnostics();
+        let summary: Vec<(&PathBuf, usize, &str)> = entries
+            .iter()
+            .map(|e| (&e.path, e.diagnostic.line, &*e.diagnostic.severity))
+            .collect();
+        let main = root.join("main.rs");
+        assert_eq!(
+            summary,
+            [
+                (&main, 1, "warning"),
+                (&main, 3, "hint"),
+                (&main, 3, "error"),
+                (&main, 4, "error"),
+                (&other, 2, "error"),
+            ]
+        );
+
+        app.open_diagnostics_panel();
+        assert_eq!(app.status, "5 problems");
+        for _ in 0..10 {
+            app.handle_key(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE))
+                .expect("down");
+        }
+        assert_eq!(app.diagnostics_panel.index, 4);
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("open");
+        assert!(!app.diagnostics_panel.open);
+        assert_eq!(app.open_path(), Some(&other));
+        assert_eq!(cursor(&app), (1, 3));
+        assert_eq!(app.status, "[error] mismatched types");
+
+        // A clean report for the file drops it from the panel
+        app.handle_publish_diagnostics(json!({
+            "uri": file_uri(&other).expect("uri"),
+            "diagnostics": [],
+        }));
+        assert!(app.unopened_diagnostics.is_empty());
+    }
+
+    fn app_with_rust_file(root: &Path) -> (App, PathBuf) {
+        let file = root.join("main.rs");
+        fs::write(&file, "fn main() {\n    let value = 1;\n}\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file.clone()).expect("open");
+        (app, file)
+    }
+
+    #[test]
+    fn completion_response_after_cursor_moved_is_discarded() {
+        let tmp = tempdir().expect("tempdir");
+        let (mut app, _) = app_with_rust_file(tmp.path());
+        let items = json!([{ "label": "value" }]);
+
+        app.pending_completion_request = app.pending_request(7);
+        app.handle_lsp_response(7, items.clone());
+        assert!(app.completion.open);
+        app.completion.reset();
+        app.completion.items.clear();
+
+        app.pending_completion_request = app.pending_request(8);
+        let tab = app.active_tab_mut().expect("tab");
+        tab.editor.move_cursor(CursorMove::Down);
+        app.handle_lsp_response(8, items);
+        assert!(!app.completion.open);
+        assert!(app.completion.items.is_empty());
+        assert!(app.pending_completion_request.is_none());
+    }
+
+    #[test]
+    fn stale_definition_request_is_cancelled_and_late_response_ignored() {
+        let tmp = tempdir().expect("tempdir");
+        let (mut app, file) = app_with_rust_file(tmp.path());
+        let response = json!({
+            "uri": file_uri(&file).expect("uri"),
+            "range": {
+                "start": { "line": 2, "character": 0 },
+                "end": { "line": 2, "character": 1 }
+            }
+        });
+
+        app.pending_definition_request = app.pending_request(3);
+        let tab = app.active_tab_mut().expect("tab");
+        tab.editor.move_cursor(CursorMove::Down);
+        tab.editor.move_cursor(CursorMove::End);
+        app.poll_lsp();
+        assert!(app.pending_definition_request.is_none());
+        app.handle_lsp_response(3, response.clone());
+        assert_eq!(cursor(&app), (1, 18));
+
+        // A buffer edit also invalidates a request made at the same cursor.
+        app.pending_definition_request = app.pending_request(4);
+        app.active_tab_mut().expect("tab").open_doc_version += 1;
+        app.handle_lsp_response(4, response);
+        assert_eq!(cursor(&app), (1, 18));
+        assert!(app.pending_definition_request.is_none());
+    }
+
+    #[test]
+    fn missing_lsp_binary_leaves_app_usable_and_is_retried_on_restart() {
+        let tmp = tempdir().expect("tempdir");
+        let first = tmp.path().join("a.rs");
+        let second = tmp.path().join("b.rs");
+        fs::write(&first, "fn a() {}\n").expect("write");
+        fs::write(&second, "fn b() {}\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.lsp_bin = tmp.path().join("no-such-rust-analyzer");
+        let expected = format!(
+            "LSP unavailable: could not start {}: ",
+            app.lsp_bin.display()
+        );
+
+        app.open_file(first.clone()).expect("open");
+        assert!(app.lsp.is_none());
+        let reason = app.lsp_unavailable.clone().expect("unavailable");
+        assert!(reason.starts_with(&expected), "{reason}");
+        assert_eq!(app.status, reason);
+
+        // Only reported once, and editing and saving still work.
+        app.status.clear();
+        app.open_file(second).expect("open");
+        assert!(!app.status.starts_with(&expected));
+        app.active_tab_mut().expect("tab").editor.insert_str("// x");
+        app.on_editor_content_changed();
+        app.save_file().expect("save");
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("b.rs")).expect("read"),
+            "// xfn b() {}\n"
+        );
+
+        app.status.clear();
+        app.restart_lsp();
+        assert!(app.status.starts_with(&expected));
+        assert!(app.lsp_unavailable.is_some());
+    }
+
+    #[test]
+    fn diagnostics_superseded_within_delay_are_never_shown() {
+        let tmp = tempdir().expect("tempdir");
+        let (mut app, file) = app_with_rust_file(tmp.path());
+        let uri = file_uri(&file).expect("uri");
+        app.tabs[app.active_tab].open_doc_uri = Some(uri.clone());
+        app.diagnostics_delay_ms = 500;
+        let publish = |app: &mut App, message: &str| {
+            app.handle_publish_diagnostics(json!({
+                "uri": uri,
+                "diagnostics": [{
+                    "range": {
+                        "start": { "line": 1, "character": 4 },
+                        "end": { "line": 1, "character": 7 },
+                    },
+                    "severity": 1,
+                    "message": message,
+                }],
+            }));
+        };
+
+        publish(&mut app, "expected `;`");
+        app.poll_lsp();
+        assert!(app.tabs[app.active_tab].diagnostics.is_empty());
+
+        // Superseded before its delay ran out: only the newer report shows.
+        publish(&mut app, "unused variable");
+        let tab = &mut app.tabs[app.active_tab];
+        let (published, _) = tab.pending_diagnostics.as_mut().expect("pending");
+        *published -= std::time::Duration::from_millis(600);
+        app.poll_lsp();
+        let tab = &app.tabs[app.active_tab];
+        let messages: Vec<&str> = tab.diagnostics.iter().map(|d| &*d.message).collect();
+        assert_eq!(messages, ["unused variable"]);
+        assert!(tab.pending_diagnostics.is_none());
+    }
+
+    #[test]
+    fn diagnostics_over_the_line_cap_are_counted_in_the_status() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = app_with_diagnostics(tmp.path());
+        app.diagnostics_max_per_line = 1;
+        let tab = app.active_tab_mut().expect("tab");
+        tab.diagnostics.push(diag(3, 6, "warning"));
+        tab.editor.move_cursor(CursorMove::Jump(2, 0));
+
+        // The hint under the cursor is hidden behind the line's error.
+        app.update_status_for_cursor();
+        assert_eq!(app.status, "[error] error at 3:3 (+2 more)");
+
+        app.diagnostics_max_per_line = 0;
+        app.update_status_for_cursor();
+        assert_eq!(app.status, "[hint] hint at 3:0");
+    }
+}
@@ -1,8 +1,14 @@
-use super::App;
-use std::io;
-use std::process::Command;
+use super::{App, ProjectSearchJob};
+use std::io::{self, BufRead, BufReader};
+use std::process::{ChildStdout, Command, Stdio};
+use std::sync::mpsc::{self, Sender, TryRecvError};
+use std::thread;
 
-use crate::util::{parse_rg_line, relative_path, to_u16_saturating};
+use crate::tab::ProjectSearchHit;
+use crate::types::OperationProgress;
+use crate::util::{
+    attach_search_context, group_search_hits, parse_rg_line, relative_path, to_u16_saturating,
+};
 
 impl App {
     pub(crate) fn search_in_open_file(&mut self, query: &str) {
@@ -32,73 +38,221 @@ impl App {
         }
     }
 
+    /// Live search while the find prompt is open: always match from the
+    /// cursor position captured when the prompt opened.
+    pub(crate) fn incremental_find(&mut self, query: &str) {
+        let Some((row, col)) = self.find_origin else {
+            return;
+        };
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+            to_u16_saturating(row),
+            to_u16_saturating(col),
+        ));
+        if query.is_empty() {
+            let _ = tab.editor.set_search_pattern("");
+            self.sync_editor_scroll_guess();
+            return;
+        }
+        match tab.editor.set_search_pattern(query) {
+            Ok(()) => {
+                if tab.editor.search_forward(true) {
+                    self.set_status(format!("Find: {}", query));
+                } else {
+                    self.set_status(format!("No match: {}", query));
+                }
+            }
+            Err(_) => {
+                // Partial regexes like "foo(" are expected while typing.
+                let _ = tab.editor.set_search_pattern("");
+                self.set_status(format!("Incomplete regex: {}", query));
+            }
+        }
+        self.sync_editor_scroll_guess();
+    }
+
+    pub(crate) fn cancel_incremental_find(&mut self) {
+        self.replace_after_find = false;
+        let Some((row, col)) = self.find_origin.take() else {
+            return;
+        };
+        if let Some(tab) = self.active_tab_mut() {
+            let _ = tab.editor.set_search_pattern("");
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(row),
+                to_u16_saturating(col),
+            ));
+        }
+        self.sync_editor_scroll_guess();
+    }
+
     pub(crate) fn replace_in_open_file(&mut self, search: &str, replacement: &str) {
         if self.open_path().is_none() {
             self.set_status("Open a file first");
             return;
         }
+        if self.refuse_read_only_edit() {
+            return;
+        }
         if search.is_empty() {
             self.set_status("Search pattern cannot be empty");
             return;
         }
-        let mut lines = self.tabs[self.active_tab].editor.lines().to_vec();
+        let tab = &mut self.tabs[self.active_tab];
+        let lines = tab.editor.lines();
         let mut count = 0usize;
-        for line in &mut lines {
+        let mut span: Option<(usize, usize)> = None;
+        for (row, line) in lines.iter().enumerate() {
             let occurrences = line.matches(search).count();
             if occurrences > 0 {
-                *line = line.replace(search, replacement);
                 count += occurrences;
+                span = Some((span.map_or(row, |(first, _)| first), row));
             }
         }
-        if count > 0 {
-            let cursor = self.tabs[self.active_tab].editor.cursor();
-            self.replace_editor_text(lines, cursor);
-            self.on_editor_content_changed();
-            self.set_status(format!("Replaced {} occurrence(s)", count));
-        } else {
+        let Some((first, last)) = span else {
             self.set_status(format!("No occurrences of '{}' found", search));
-        }
+            return;
+        };
+        let old_chars = lines[first..=last]
+            .iter()
+            .map(|l| l.chars().count() + 1)
+            .sum::<usize>()
+            - 1;
+        let new_text = lines[first..=last]
+            .iter()
+            .map(|l| l.replace(search, replacement))
+            .collect::<Vec<_>>()
+            .join("\n");
+        // Leave the cursor just past the final replacement.
+        let last_line = &lines[last];
+        let last_start = last_line
+            .match_indices(search)
+            .last()
+            .map_or(0, |(idx, _)| idx);
+        let end_col = last_line[..last_start]
+            .replace(search, replacement)
+            .chars()
+            .count()
+            + replacement.chars().count();
+
+        // Swap only the affected lines through the textarea so history is
+        // kept, then group the delete and insert into one undo step.
+        tab.editor.cancel_selection();
+        tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+            to_u16_saturating(first),
+            0,
+        ));
+        let mut steps = usize::from(tab.editor.delete_str(old_chars));
+        steps += usize::from(tab.editor.insert_str(new_text));
+        tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+            to_u16_saturating(last),
+            to_u16_saturating(end_col),
+        ));
+        tab.group_last_edits(steps);
+        self.on_editor_content_changed();
+        self.sync_editor_scroll_guess();
+        self.set_status(format!("Replaced {} occurrence(s)", count));
     }
 
+    /// Start searching the project with `rg` in the background, cancelling
+    /// any search still running. Hits stream into the results list as
+    /// `poll_project_search` receives them.
     pub(crate) fn search_in_project(&mut self, query: &str) {
         let trimmed = query.trim();
         if trimmed.is_empty() {
             self.set_status("Search query is empty");
             return;
         }
-        let output = Command::new("rg")
+        self.project_search = None;
+        let child = Command::new("rg")
             .arg("--line-number")
             .arg("--no-heading")
             .arg("--color")
             .arg("never")
             .arg("--smart-case")
+            .arg("--")
             .arg(trimmed)
             .arg(&self.root)
-            .output();
-        let Ok(output) = output else {
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else {
             self.set_status(
                 "rg (ripgrep) not found -- install: https://github.com/BurntSushi/ripgrep#installation",
             );
             return;
         };
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let mut hits = Vec::new();
-        for line in stdout.lines() {
-            if let Some(hit) = parse_rg_line(line) {
-                hits.push(hit);
-            }
-        }
+        let Some(stdout) = child.stdout.take() else {
+            let _ = child.kill();
+            let _ = child.wait();
+            self.set_status("Search failed to start");
+            return;
+        };
+        let (tx, rx) = mpsc::channel();
+        let context_lines = self.search_results.context_lines;
+        thread::spawn(move || stream_search_hits(stdout, context_lines, &tx));
+        self.project_search = Some(ProjectSearchJob { rx, child });
         self.search_results.query = trimmed.to_string();
-        self.search_results.results = hits;
+        self.search_results.results.clear();
         self.search_results.index = 0;
+        self.search_results.scroll = 0;
         self.search_results.open = true;
-        if self.search_results.results.is_empty() {
-            self.set_status(format!("No results for '{}'", trimmed));
+        self.progress = Some(OperationProgress::new(
+            format!("Searching for '{trimmed}'"),
+            None,
+        ));
+        self.set_status(format!("Searching for '{}'...", trimmed));
+    }
+
+    /// Append hits from the running project search, and report the totals
+    /// once it finishes.
+    pub(crate) fn poll_project_search(&mut self) {
+        let Some(job) = self.project_search.as_ref() else {
+            return;
+        };
+        let finished = loop {
+            match job.rx.try_recv() {
+                Ok(hits) => self.search_results.results.extend(hits),
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        };
+        let query = self.search_results.query.clone();
+        let total = self.search_results.results.len();
+        if !finished {
+            self.progress = Some(OperationProgress {
+                label: format!("Searching for '{query}'"),
+                done: total,
+                total: None,
+            });
+            if total > 0 {
+                self.set_status(format!("Searching for '{}'... {} results", query, total));
+            }
+            return;
+        }
+        self.project_search = None;
+        self.progress = None;
+        if total == 0 {
+            self.set_status(format!("No results for '{}'", query));
         } else {
+            let files = group_search_hits(&self.search_results.results).len();
             self.set_status(format!(
-                "{} results for '{}'",
-                self.search_results.results.len(),
-                trimmed
+                "{} results in {} file(s) for '{}'",
+                total, files, query
+            ));
+        }
+    }
+
+    /// Stop a running project search, keeping the hits found so far.
+    pub(crate) fn cancel_project_search(&mut self) {
+        if self.project_search.take().is_some() {
+            self.progress = None;
+            self.set_status(format!(
+                "Search cancelled ({} results)",
+                self.search_results.results.len()
             ));
         }
     }
@@ -112,6 +266,7 @@ impl App {
         else {
             return Ok(());
         };
+        self.push_nav_history();
         self.open_file(hit.path.clone())?;
         let target_row = hit.line.saturating_sub(1);
         if let Some(tab) = self.active_tab_mut() {
@@ -129,4 +284,375 @@ impl App {
         ));
         Ok(())
     }
+
+    pub(crate) fn next_search_result(&mut self) -> io::Result<()> {
+        self.step_search_result(true)
+    }
+
+    pub(crate) fn prev_search_result(&mut self) -> io::Result<()> {
+        self.step_search_result(false)
+    }
+
+    fn step_search_result(&mut self, forward: bool) -> io::Result<()> {
+        let total = self.search_results.results.len();
+        if total == 0 {
+            self.set_status("No search results");
+            return Ok(());
+        }
+        let current = self.search_results.index.min(total - 1);
+        self.search_results.index = if forward {
+            (current + 1) % total
+        } else {
+            (current + total - 1) % total
+        };
+        self.open_selected_search_result()?;
+        let hit = &self.search_results.results[self.search_results.index];
+        self.set_status(format!(
+            "Result {} of {}: {}:{}",
+            self.search_results.index + 1,
+            total,
+            relative_path(&self.root, &hit.path).display(),
+            hit.line
+        ));
+        Ok(())
+    }
+}
+
+/// Read `rg --no-heading` output, sending the hits for each file as soon as
+/// the next file starts. Stops early once the receiver is gone.
+fn stream_search_hits(
+    stdout: ChildStdout,
+    context_lines: usize,
+    tx: &Sender<Vec<ProjectSearchHit>>,
+) {
+    let send = |batch: &mut Vec<ProjectSearchHit>| {
+        attach_search_context(batch, context_lines);
+        tx.send(std::mem::take(batch)).is_ok()
+    };
+    let mut batch: Vec<ProjectSearchHit> = Vec::new();
+    for line in BufReader::new(stdout).split(b'\n') {
+        let Ok(line) = line else {
+            break;
+        };
+        let Some(hit) = parse_rg_line(String::from_utf8_lossy(&line).trim_end_matches('\r')) else {
+            continue;
+        };
+        if batch.first().is_some_and(|b| b.path != hit.path) && !send(&mut batch) {
+            return;
+        }
+        batch.push(hit);
+    }
+    if !batch.is_empty() {
+        send(&mut batch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tab::ProjectSearchHit;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use tempfile::tempdir;
+
+    fn new_app(root: &Path) -> App {
+        App::with_paths(root.to_path_buf(), None, None).expect("app should initialize")
+    }
+
+    fn hit(path: PathBuf, line: usize) -> ProjectSearchHit {
+        ProjectSearchHit {
+            path,
+            line,
+            preview: String::new(),
+            context_before: Vec::new(),
+            context_after: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn next_search_result_wraps_to_first_and_opens_file() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let a = root.join("a.txt");
+        let b = root.join("b.txt");
+        fs::write(&a, "one\ntwo\nthree\n").expect("write a");
+        fs::write(&b, "alpha\nbeta\n").expect("write b");
+        let mut app = new_app(root);
+        app.search_results.results = vec![hit(a.clone(), 3), hit(b.clone(), 2)];
+        app.search_results.index = 0;
+
+        app.next_search_result().expect("next");
+        assert_eq!(app.search_results.index, 1);
+        assert_eq!(app.open_path(), Some(&b));
+        assert_eq!(app.active_tab().expect("tab").editor.cursor().0, 1);
+
+        app.next_search_result().expect("wrap");
+        assert_eq!(app.search_results.index, 0);
+        assert_eq!(app.open_path(), Some(&a));
+        assert_eq!(app.active_tab().expect("tab").editor.cursor().0, 2);
+        assert!(app.status.starts_with("Result 1 of 2"));
+        assert!(!app.search_results.open);
+    }
+
+    #[test]
+    fn prev_search_result_wraps_to_last() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let a = root.join("a.txt");
+        let b = root.join("b.txt");
+        fs::write(&a, "one\n").expect("write a");
+        fs::write(&b, "alpha\nbeta\n").expect("write b");
+        let mut app = new_app(root);
+        app.search_results.results = vec![hit(a, 1), hit(b.clone(), 2)];
+        app.search_results.index = 0;
+
+        app.prev_search_result().expect("prev");
+        assert_eq!(app.search_results.index, 1);
+        assert_eq!(app.open_path(), Some(&b));
+    }
+
+    #[test]
+    fn step_search_result_without_results_is_noop() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        app.next_search_result().expect("next");
+        assert!(app.tabs.is_empty());
+        assert_eq!(app.status, "No search results");
+    }
+
+    fn type_str(app: &mut App, text: &str) {
+        for ch in text.chars() {
+            app.handle_prompt_key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE))
+                .expect("key");
+        }
+    }
+
+    #[test]
+    fn incremental_find_narrows_to_first_match_while_typing() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("a.txt");
+        fs::write(&file, "start\nfoo bar\nfood\nfoodie\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        app.open_find_prompt();
+
+        type_str(&mut app, "foo");
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (1, 0));
+        type_str(&mut app, "d");
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (2, 0));
+        type_str(&mut app, "i");
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (3, 0));
+        assert!(app.prompt.is_some(), "prompt stays open until Enter");
+
+        app.handle_prompt_key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE))
+            .expect("backspace");
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (2, 0));
+    }
+
+    #[test]
+    fn incremental_find_esc_restores_original_cursor() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("a.txt");
+        fs::write(&file, "one\ntwo\nthree needle\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        app.active_tab_mut()
+            .expect("tab")
+            .editor
+            .move_cursor(ratatui_textarea::CursorMove::Jump(1, 2));
+        app.open_find_prompt();
+
+        type_str(&mut app, "needle");
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (2, 6));
+
+        app.handle_prompt_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("esc");
+        assert!(app.prompt.is_none());
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.cursor(), (1, 2));
+        assert!(tab.editor.search_pattern().is_none());
+    }
+
+    #[test]
+    fn incremental_find_enter_keeps_match() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("a.txt");
+        fs::write(&file, "alpha\nbeta\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        app.open_find_prompt();
+        type_str(&mut app, "beta");
+        app.handle_prompt_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("enter");
+        assert!(app.prompt.is_none());
+        assert!(app.find_origin.is_none());
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (1, 0));
+    }
+
+    #[test]
+    fn prompt_history_recalls_find_and_replace_input() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("a.txt");
+        fs::write(&file, "alpha beta\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        app.prompt_history.clear();
+        let key = |app: &mut App, code| {
+            app.handle_prompt_key(KeyEvent::new(code, KeyModifiers::NONE))
+                .expect("key");
+        };
+        for (search, replacement) in [("alpha", "one"), ("beta", "two"), ("alpha", "one")] {
+            app.open_replace_prompt();
+            type_str(&mut app, search);
+            key(&mut app, KeyCode::Enter);
+            type_str(&mut app, replacement);
+            key(&mut app, KeyCode::Enter);
+        }
+        assert_eq!(app.prompt_history["find"], ["alpha", "beta"]);
+        assert_eq!(app.prompt_history["replace"], ["one", "two"]);
+
+        app.open_find_prompt();
+        type_str(&mut app, "dra");
+        key(&mut app, KeyCode::Up);
+        assert_eq!(app.prompt.as_ref().expect("prompt").value, "alpha");
+        key(&mut app, KeyCode::Up);
+        key(&mut app, KeyCode::Up);
+        assert_eq!(app.prompt.as_ref().expect("prompt").value, "beta");
+        key(&mut app, KeyCode::Down);
+        key(&mut app, KeyCode::Down);
+        let prompt = app.prompt.as_ref().expect("prompt");
+        assert_eq!((prompt.value.as_str(), prompt.cursor), ("dra", 3));
+    }
+
+    #[test]
+    fn replace_all_is_one_undo_step_and_ends_at_last_match() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("r.txt");
+        let original = "keep\nfoo and foo\nmiddle\nbar foo tail\nend";
+        fs::write(&file, original).expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+
+        app.replace_in_open_file("foo", "quux");
+        assert_eq!(app.status, "Replaced 3 occurrence(s)");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(
+            tab.editor.lines(),
+            ["keep", "quux and quux", "middle", "bar quux tail", "end"]
+        );
+        assert_eq!(tab.editor.cursor(), (3, 8));
+
+        assert!(app.active_tab_mut().expect("tab").undo());
+        assert_eq!(
+            app.active_tab().expect("tab").editor.lines().join("\n"),
+            original
+        );
+        assert!(app.active_tab_mut().expect("tab").redo());
+        assert_eq!(
+            app.active_tab().expect("tab").editor.lines()[3],
+            "bar quux tail"
+        );
+    }
+
+    fn rg_available() -> bool {
+        Command::new("rg").arg("--version").output().is_ok()
+    }
+
+    fn wait_for_project_search(app: &mut App) {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while app.project_search.is_some() && std::time::Instant::now() < deadline {
+            thread::sleep(std::time::Duration::from_millis(10));
+            app.poll_project_search();
+        }
+        assert!(app.project_search.is_none(), "search should finish");
+    }
+
+    #[test]
+    fn project_search_streams_hits_from_every_file() {
+        if !rg_available() {
+            return;
+        }
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("sub")).expect("mkdir");
+        fs::write(root.join("a.txt"), "needle one\nplain\n").expect("write a");
+        fs::write(root.join("b.txt"), "plain\nneedle two\nneedle three\n").expect("write b");
+        fs::write(root.join("sub/c.txt"), "needle four\n").expect("write c");
+        fs::write(root.join("d.txt"), "nothing here\n").expect("write d");
+        let mut app = new_app(root);
+        app.search_results.context_lines = 0;
+
+        app.search_in_project("needle");
+        assert!(app.search_results.open);
+        wait_for_project_search(&mut app);
+        let mut found: Vec<(PathBuf, usize)> = app
+            .search_results
+            .results
+            .iter()
+            .map(|h| (h.path.clone(), h.line))
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                (root.join("a.txt"), 1),
+                (root.join("b.txt"), 2),
+                (root.join("b.txt"), 3),
+                (root.join("sub/c.txt"), 1),
+            ]
+        );
+        assert_eq!(app.status, "4 results in 3 file(s) for 'needle'");
+    }
+
+    #[test]
+    fn new_project_search_cancels_the_previous_one() {
+        if !rg_available() {
+            return;
+        }
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::write(root.join("a.txt"), "alpha\nbeta\n").expect("write a");
+        let mut app = new_app(root);
+
+        app.search_in_project("alpha");
+        app.search_in_project("beta");
+        wait_for_project_search(&mut app);
+        assert_eq!(app.search_results.query, "beta");
+        let lines: Vec<usize> = app.search_results.results.iter().map(|h| h.line).collect();
+        assert_eq!(lines, [2]);
+
+        app.search_in_project("alpha");
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("esc");
+        assert!(app.project_search.is_none());
+        assert!(!app.search_results.open);
+    }
+
+    #[test]
+    fn project_search_shows_progress_until_done_or_cancelled() {
+        if !rg_available() {
+            return;
+        }
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::write(root.join("a.txt"), "needle\n").expect("write a");
+        let mut app = new_app(root);
+
+        app.search_in_project("needle");
+        let progress = app.progress.clone().expect("progress while searching");
+        assert_eq!(progress.label, "Searching for 'needle'");
+        assert_eq!(progress.total, None);
+        wait_for_project_search(&mut app);
+        assert!(app.progress.is_none());
+
+        app.search_in_project("needle");
+        assert!(app.cancel_operation());
+        assert!(app.progress.is_none());
+        assert!(app.status.starts_with("Search cancelled ("));
+        assert!(!app.cancel_operation());
+    }
 }
@@ -0,0 +1,255 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::App;
+use crate::types::{ConfirmKey, EscAction};
+
+const CONFIG_FILE_REL: &str = "lazyide/config.toml";
+const PROJECT_CONFIG_FILE: &str = ".lazyide.toml";
+
+/// Editor settings from `config.toml`. Every setting has a default, so a
+/// partial file is fine.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct Config {
+    pub(crate) theme: String,
+    pub(crate) word_wrap: bool,
+    pub(crate) scroll_off: usize,
+    pub(crate) scroll_lines: usize,
+    pub(crate) scroll_acceleration: bool,
+    pub(crate) line_numbers: bool,
+    pub(crate) fold_arrows: bool,
+    /// Fold nesting depth folded when a file opens; 0 folds nothing.
+    pub(crate) default_fold_level: usize,
+    pub(crate) indent_width: u8,
+    pub(crate) indent_hard_tabs: bool,
+    /// Columns a hard tab is drawn across; 0 follows `indent_width`.
+    pub(crate) render_tab_width: u8,
+    pub(crate) highlight_trailing_whitespace: bool,
+    pub(crate) bracket_colorization: bool,
+    pub(crate) rainbow_indent: bool,
+    pub(crate) dim_commented_code: bool,
+    pub(crate) spell_check: bool,
+    pub(crate) lint_mixed_indent: bool,
+    pub(crate) auto_save: bool,
+    pub(crate) auto_save_delay_ms: u64,
+    pub(crate) follow_symlinks: bool,
+    pub(crate) search_context_lines: usize,
+    pub(crate) diagnostics_delay_ms: u64,
+    pub(crate) diagnostics_max_per_line: usize,
+    pub(crate) reflow_width: usize,
+    pub(crate) editor_esc: EscAction,
+    pub(crate) confirm_key: ConfirmKey,
+    pub(crate) recent_projects_limit: usize,
+    /// Most tabs kept open before the least recently used clean, unpinned
+    /// one is closed; 0 means no limit.
+    pub(crate) max_open_tabs: usize,
+    /// Language server command; empty runs the one for the project kind.
+    pub(crate) lsp_server: String,
+    /// Formatter command per language key (see `SyntaxLang::config_key`),
+    /// fed the buffer on stdin and printing the result on stdout.
+    pub(crate) formatters: BTreeMap<String, String>,
+    /// Language key per file extension or exact file name, checked before
+    /// the built-in detection.
+    pub(crate) file_associations: BTreeMap<String, String>,
+}
+
+/// A config file read on top of a base config.
+#[derive(Debug)]
+pub(crate) struct ConfigOverlay {
+    pub(crate) config: Config,
+    /// The base's values for the settings the file replaced, so they can be
+    /// put back with `restore_config`.
+    pub(crate) replaced: toml::Table,
+    pub(crate) warnings: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            theme: "One Dark Pro".to_string(),
+            word_wrap: false,
+            scroll_off: App::DEFAULT_SCROLL_OFF,
+            scroll_lines: App::DEFAULT_SCROLL_LINES,
+            scroll_acceleration: false,
+            line_numbers: true,
+            fold_arrows: true,
+            default_fold_level: 0,
+            indent_width: 4,
+            indent_hard_tabs: false,
+            render_tab_width: 0,
+            highlight_trailing_whitespace: false,
+            bracket_colorization: true,
+            rainbow_indent: false,
+            dim_commented_code: false,
+            spell_check: false,
+            lint_mixed_indent: false,
+            auto_save: false,
+            auto_save_delay_ms: App::DEFAULT_AUTO_SAVE_DELAY_MS,
+            follow_symlinks: true,
+            search_context_lines: App::DEFAULT_SEARCH_CONTEXT_LINES,
+            diagnostics_delay_ms: App::DEFAULT_DIAGNOSTICS_DELAY_MS,
+            diagnostics_max_per_line: App::DEFAULT_DIAGNOSTICS_MAX_PER_LINE,
+            reflow_width: App::DEFAULT_REFLOW_WIDTH,
+            editor_esc: EscAction::Close,
+            confirm_key: ConfirmKey::Enter,
+            recent_projects_limit: App::DEFAULT_RECENT_PROJECTS_LIMIT,
+            max_open_tabs: 0,
+            lsp_server: String::new(),
+            formatters: BTreeMap::new(),
+            file_associations: BTreeMap::new(),
+        }
+    }
+}
+
+pub(crate) fn config_file_path() -> Option<PathBuf> {
+    if let Ok(xdg) = std::env::var("XDG_CONFIG_HOME")
+        && !xdg.is_empty()
+    {
+        return Some(PathBuf::from(xdg).join(CONFIG_FILE_REL));
+    }
+    if let Ok(appdata) = std::env::var("APPDATA")
+        && !appdata.is_empty()
+    {
+        return Some(PathBuf::from(appdata).join(CONFIG_FILE_REL));
+    }
+    std::env::var("HOME")
+        .ok()
+        .map(|home| PathBuf::from(home).join(".config").join(CONFIG_FILE_REL))
+}
+
+/// The per-project config file for `root`, which overrides the global one.
+pub(crate) fn project_config_path(root: &Path) -> PathBuf {
+    root.join(PROJECT_CONFIG_FILE)
+}
+
+/// Apply the TOML in `raw` on top of `base`: settings it names replace the
+/// base values and the rest are kept. Unknown keys are skipped and returned
+/// as warnings; a syntax or type error fails the whole file.
+pub(crate) fn parse_config(raw: &str, base: &Config) -> Result<ConfigOverlay, String> {
+    overlay(base, raw.parse().map_err(error_message)?)
+}
+
+fn overlay(base: &Config, settings: toml::Table) -> Result<ConfigOverlay, String> {
+    let mut merged = toml::Table::try_from(base).map_err(|e| e.to_string())?;
+    let mut replaced = toml::Table::new();
+    let mut warnings = Vec::new();
+    for (key, value) in settings {
+        match merged.insert(key.clone(), value) {
+            Some(previous) => {
+                replaced.insert(key, previous);
+            }
+            None => {
+                merged.remove(&key);
+                warnings.push(format!("unknown key `{key}`"));
+            }
+        }
+    }
+    let config = merged.try_into().map_err(error_message)?;
+    Ok(ConfigOverlay {
+        config,
+        replaced,
+        warnings,
+    })
+}
+
+/// `current` with the settings in `replaced` put back to their old values.
+pub(crate) fn restore_config(current: &Config, replaced: toml::Table) -> Config {
+    overlay(current, replaced).map_or_else(|_| current.clone(), |o| o.config)
+}
+
+/// A parse error on one line, for the status bar.
+fn error_message(e: toml::de::Error) -> String {
+    e.message().replace('\n', "; ")
+}
+
+/// Read the config at `path` over `base`. A missing file leaves `base`
+/// unchanged.
+pub(crate) fn load_config(path: &Path, base: &Config) -> Result<ConfigOverlay, String> {
+    match fs::read_to_string(path) {
+        Ok(raw) => parse_config(&raw, base),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => overlay(base, toml::Table::new()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn config_file_sets_named_fields() {
+        let raw = r#"
+theme = "Dracula"
+word_wrap = true
+scroll_off = 8
+indent_width = 2
+indent_hard_tabs = true
+render_tab_width = 8
+auto_save_delay_ms = 900
+"#;
+        let loaded = parse_config(raw, &Config::default()).expect("parses");
+        assert!(loaded.warnings.is_empty());
+        assert_eq!(
+            loaded.config,
+            Config {
+                theme: "Dracula".to_string(),
+                word_wrap: true,
+                scroll_off: 8,
+                indent_width: 2,
+                indent_hard_tabs: true,
+                render_tab_width: 8,
+                auto_save_delay_ms: 900,
+                ..Config::default()
+            }
+        );
+    }
+
+    #[test]
+    fn partial_or_missing_file_keeps_defaults() {
+        let tmp = tempdir().expect("tempdir");
+        let path = tmp.path().join("config.toml");
+        let loaded = load_config(&path, &Config::default()).expect("loads");
+        assert_eq!(loaded.config, Config::default());
+        assert!(loaded.warnings.is_empty());
+
+        fs::write(&path, "bracket_colorization = false\n").expect("write");
+        let loaded = load_config(&path, &Config::default()).expect("loads");
+        let config = loaded.config;
+        assert!(!config.bracket_colorization);
+        assert_eq!(config.scroll_off, App::DEFAULT_SCROLL_OFF);
+        assert_eq!(config.theme, "One Dark Pro");
+    }
+
+    #[test]
+    fn unknown_keys_warn_and_bad_values_fail() {
+        let loaded = parse_config("wrod_wrap = true\nword_wrap = true\n", &Config::default())
+            .expect("unknown keys are not fatal");
+        assert!(loaded.config.word_wrap);
+        assert_eq!(loaded.warnings, ["unknown key `wrod_wrap`"]);
+
+        assert!(parse_config("scroll_off = \"lots\"\n", &Config::default()).is_err());
+        assert!(parse_config("word_wrap = \n", &Config::default()).is_err());
+    }
+
+    #[test]
+    fn restore_puts_back_only_the_replaced_settings() {
+        let base = Config::default();
+        let loaded = parse_config("word_wrap = true\nscroll_off = 9\n", &base).expect("parses");
+        let mut current = loaded.config;
+        current.auto_save = true;
+        let restored = restore_config(&current, loaded.replaced);
+        assert_eq!(
+            restored,
+            Config {
+                auto_save: true,
+                ..base
+            }
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use crate::tab::{DiffLine, DiffLineKind, DiffRow, DiffRowKind};
+
+/// Above this many LCS cells the changed middle is shown as a plain
+/// remove-then-add block instead of being aligned line by line.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Line-level diff turning `old` into `new`, with every line classified as
+/// context, removed or added.
+pub(crate) fn diff_lines(old: &[String], new: &[String]) -> Vec<DiffLine> {
+    let line = |kind, text: &String| DiffLine {
+        kind,
+        text: text.clone(),
+    };
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut out: Vec<DiffLine> = old[..prefix]
+        .iter()
+        .map(|l| line(DiffLineKind::Context, l))
+        .collect();
+    if old_mid.len().saturating_mul(new_mid.len()) > MAX_DIFF_CELLS {
+        out.extend(old_mid.iter().map(|l| line(DiffLineKind::Removed, l)));
+        out.extend(new_mid.iter().map(|l| line(DiffLineKind::Added, l)));
+    } else {
+        // lcs[i][j] = LCS length of old_mid[i..] and new_mid[j..]
+        let (n, m) = (old_mid.len(), new_mid.len());
+        let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if old_mid[i] == new_mid[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && old_mid[i] == new_mid[j] {
+                out.push(line(DiffLineKind::Context, &old_mid[i]));
+                i += 1;
+                j += 1;
+            } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                out.push(line(DiffLineKind::Removed, &old_mid[i]));
+                i += 1;
+            } else {
+                out.push(line(DiffLineKind::Added, &new_mid[j]));
+                j += 1;
+            }
+        }
+    }
+    out.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|l| line(DiffLineKind::Context, l)),
+    );
+    out
+}
+
+/// Unified view of `diff_lines`: only changed lines plus `context` lines
+/// around them, with an `@@ -old +new @@` header starting each hunk.
+pub(crate) fn unified_diff(old: &[String], new: &[String], context: usize) -> Vec<DiffLine> {
+    let full = diff_lines(old, new);
+    let changed: Vec<usize> = full
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| l.kind != DiffLineKind::Context)
+        .map(|(i, _)| i)
+        .collect();
+    let mut keep = vec![false; full.len()];
+    for &i in &changed {
+        let end = (i + context + 1).min(full.len());
+        keep[i.saturating_sub(context)..end].fill(true);
+    }
+    let mut out = Vec::new();
+    let (mut old_no, mut new_no) = (1, 1);
+    let mut in_hunk = false;
+    for (i, l) in full.into_iter().enumerate() {
+        if keep[i] && !in_hunk {
+            out.push(DiffLine {
+                kind: DiffLineKind::Hunk,
+                text: format!("@@ -{old_no} +{new_no} @@"),
+            });
+        }
+        in_hunk = keep[i];
+        match l.kind {
+            DiffLineKind::Removed => old_no += 1,
+            DiffLineKind::Added => new_no += 1,
+            _ => {
+                old_no += 1;
+                new_no += 1;
+            }
+        }
+        if keep[i] {
+            out.push(l);
+        }
+    }
+    out
+}
+
+/// `diff_lines` paired up for a side-by-side view: each run of removals and
+/// additions is zipped into changed rows, and whichever side is longer
+/// faces gaps for the rest.
+pub(crate) fn side_by_side_rows(old: &[String], new: &[String]) -> Vec<DiffRow> {
+    fn flush(rows: &mut Vec<DiffRow>, removed: &mut Vec<String>, added: &mut Vec<String>) {
+        let mut left = removed.drain(..);
+        let mut right = added.drain(..);
+        loop {
+            let (kind, l, r) = match (left.next(), right.next()) {
+                (None, None) => break,
+                (l @ Some(_), r @ Some(_)) => (DiffRowKind::Changed, l, r),
+                (l, None) => (DiffRowKind::Removed, l, None),
+                (None, r) => (DiffRowKind::Added, None, r),
+            };
+            rows.push(DiffRow {
+                kind,
+                left: l,
+                right: r,
+            });
+        }
+    }
+
+    let mut rows = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    for line in diff_lines(old, new) {
+        match line.kind {
+            DiffLineKind::Removed => removed.push(line.text),
+            DiffLineKind::Added => added.push(line.text),
+            DiffLineKind::Context | DiffLineKind::Hunk => {
+                flush(&mut rows, &mut removed, &mut added);
+                rows.push(DiffRow {
+                    kind: DiffRowKind::Same,
+                    left: Some(line.text.clone()),
+                    right: Some(line.text),
+                });
+            }
+        }
+    }
+    flush(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    fn kinds(diff: &[DiffLine]) -> Vec<(DiffLineKind, &str)> {
+        diff.iter().map(|l| (l.kind, l.text.as_str())).collect()
+    }
+
+    #[test]
+    fn test_diff_lines_classifies_changes() {
+        let old = lines("a\nb\nc\nd");
+        let new = lines("a\nB\nc\nd\ne");
+        let diff = diff_lines(&old, &new);
+        assert_eq!(
+            kinds(&diff),
+            vec![
+                (DiffLineKind::Context, "a"),
+                (DiffLineKind::Removed, "b"),
+                (DiffLineKind::Added, "B"),
+                (DiffLineKind::Context, "c"),
+                (DiffLineKind::Context, "d"),
+                (DiffLineKind::Added, "e"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_lines_identical_and_empty() {
+        let same = lines("x\ny");
+        assert!(
+            diff_lines(&same, &same)
+                .iter()
+                .all(|l| l.kind == DiffLineKind::Context)
+        );
+        let diff = diff_lines(&[], &same);
+        assert_eq!(
+            kinds(&diff),
+            vec![(DiffLineKind::Added, "x"), (DiffLineKind::Added, "y")]
+        );
+    }
+
+    #[test]
+    fn test_diff_lines_aligns_moved_middle() {
+        let old = lines("keep\nfoo\nbar\nbaz\nend");
+        let new = lines("keep\nbar\nbaz\nqux\nend");
+        let diff = diff_lines(&old, &new);
+        assert_eq!(
+            kinds(&diff),
+            vec![
+                (DiffLineKind::Context, "keep"),
+                (DiffLineKind::Removed, "foo"),
+                (DiffLineKind::Context, "bar"),
+                (DiffLineKind::Context, "baz"),
+                (DiffLineKind::Added, "qux"),
+                (DiffLineKind::Context, "end"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_trims_context_into_hunks() {
+        let old: Vec<String> = (1..=20).map(|i| i.to_string()).collect();
+        let mut new = old.clone();
+        new[1] = "two".to_string();
+        new[17] = "eighteen".to_string();
+        let diff = unified_diff(&old, &new, 1);
+        assert_eq!(
+            kinds(&diff),
+            vec![
+                (DiffLineKind::Hunk, "@@ -1 +1 @@"),
+                (DiffLineKind::Context, "1"),
+                (DiffLineKind::Removed, "2"),
+                (DiffLineKind::Added, "two"),
+                (DiffLineKind::Context, "3"),
+                (DiffLineKind::Hunk, "@@ -17 +17 @@"),
+                (DiffLineKind::Context, "17"),
+                (DiffLineKind::Removed, "18"),
+                (DiffLineKind::Added, "eighteen"),
+                (DiffLineKind::Context, "19"),
+            ]
+        );
+        assert!(unified_diff(&old, &old, 3).is_empty());
+    }
+
+    #[test]
+    fn test_side_by_side_rows_pair_changes_and_leave_gaps() {
+        let old = lines("fn main() {\n    let a = 1;\n    let b = 2;\n    old();\n}\ntail");
+        let new = lines("fn main() {\n    let a = 10;\n    new();\n}\nextra\ntail");
+        let rows = side_by_side_rows(&old, &new);
+        let view: Vec<(DiffRowKind, Option<&str>, Option<&str>)> = rows
+            .iter()
+            .map(|r| (r.kind, r.left.as_deref(), r.right.as_deref()))
+            .collect();
+        assert_eq!(
+            view,
+            vec![
+                (DiffRowKind::Same, Some("fn main() {"), Some("fn main() {")),
+                (
+                    DiffRowKind::Changed,
+                    Some("    let a = 1;"),
+                    Some("    let a = 10;")
+                ),
+                (
+                    DiffRowKind::Changed,
+                    Some("    let b = 2;"),
+                    Some("    new();")
+                ),
+                (DiffRowKind::Removed, Some("    old();"), None),
+                (DiffRowKind::Same, Some("}"), Some("}")),
+                (DiffRowKind::Added, None, Some("extra")),
+                (DiffRowKind::Same, Some("tail"), Some("tail")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_side_by_side_rows_one_side_empty() {
+        let text = lines("a\nb");
+        let rows = side_by_side_rows(&[], &text);
+        assert!(
+            rows.iter()
+                .all(|r| r.kind == DiffRowKind::Added && r.left.is_none())
+        );
+        assert_eq!(rows.len(), 2);
+        let rows = side_by_side_rows(&text, &text);
+        assert!(rows.iter().all(|r| r.kind == DiffRowKind::Same));
+    }
+}
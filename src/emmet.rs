@@ -0,0 +1,228 @@
+/// Elements written without a closing tag.
+const VOID_TAGS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Repetition cap, so a stray `*99999` can't flood the buffer.
+const MAX_REPEAT: usize = 100;
+
+/// Cap on the lines of a whole expansion, since nested repeats multiply.
+pub(crate) const MAX_LINES: usize = 1000;
+
+/// Why an abbreviation was not expanded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EmmetError {
+    /// Not a complete abbreviation.
+    Malformed,
+    /// Would expand past `MAX_LINES`.
+    TooLarge,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Node {
+    tag: String,
+    id: Option<String>,
+    classes: Vec<String>,
+    repeat: usize,
+    children: Vec<Node>,
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':')) {
+                break;
+            }
+            name.push(c);
+            self.chars.next();
+        }
+        name
+    }
+
+    /// `node ('+' node)*`
+    fn siblings(&mut self) -> Option<Vec<Node>> {
+        let mut nodes = vec![self.node()?];
+        while self.chars.next_if_eq(&'+').is_some() {
+            nodes.push(self.node()?);
+        }
+        Some(nodes)
+    }
+
+    /// `tag? ('#' id | '.' class)* ('*' N)? ('>' siblings)?`
+    fn node(&mut self) -> Option<Node> {
+        let tag = self.name();
+        let mut id = None;
+        let mut classes = Vec::new();
+        loop {
+            let is_id = if self.chars.next_if_eq(&'#').is_some() {
+                true
+            } else if self.chars.next_if_eq(&'.').is_some() {
+                false
+            } else {
+                break;
+            };
+            let name = self.name();
+            if name.is_empty() {
+                return None;
+            }
+            if is_id {
+                id = Some(name);
+            } else {
+                classes.push(name);
+            }
+        }
+        if tag.is_empty() && id.is_none() && classes.is_empty() {
+            return None;
+        }
+        let mut repeat = 1;
+        if self.chars.next_if_eq(&'*').is_some() {
+            let digits = self.name();
+            repeat = digits
+                .parse()
+                .ok()
+                .filter(|n| (1..=MAX_REPEAT).contains(n))?;
+        }
+        let children = if self.chars.next_if_eq(&'>').is_some() {
+            self.siblings()?
+        } else {
+            Vec::new()
+        };
+        Some(Node {
+            tag: if tag.is_empty() {
+                "div".to_string()
+            } else {
+                tag
+            },
+            id,
+            classes,
+            repeat,
+            children,
+        })
+    }
+}
+
+/// Lines `render` would produce, saturating instead of overflowing.
+fn line_count(nodes: &[Node]) -> usize {
+    nodes.iter().fold(0usize, |total, node| {
+        let per_copy = if VOID_TAGS.contains(&node.tag.as_str()) || node.children.is_empty() {
+            1
+        } else {
+            line_count(&node.children).saturating_add(2)
+        };
+        total.saturating_add(per_copy.saturating_mul(node.repeat))
+    })
+}
+
+fn render(nodes: &[Node], depth: usize, indent_unit: &str, out: &mut Vec<String>) {
+    for node in nodes {
+        let pad = indent_unit.repeat(depth);
+        let mut open = format!("<{}", node.tag);
+        if let Some(id) = &node.id {
+            open.push_str(&format!(" id=\"{id}\""));
+        }
+        if !node.classes.is_empty() {
+            open.push_str(&format!(" class=\"{}\"", node.classes.join(" ")));
+        }
+        open.push('>');
+        for _ in 0..node.repeat {
+            if VOID_TAGS.contains(&node.tag.as_str()) {
+                out.push(format!("{pad}{open}"));
+            } else if node.children.is_empty() {
+                out.push(format!("{pad}{open}</{}>", node.tag));
+            } else {
+                out.push(format!("{pad}{open}"));
+                render(&node.children, depth + 1, indent_unit, out);
+                out.push(format!("{pad}</{}>", node.tag));
+            }
+        }
+    }
+}
+
+/// Expand an Emmet-style abbreviation (tags, `#id`, `.class`, children `>`,
+/// siblings `+`, repetition `*N`) into HTML lines indented with
+/// `indent_unit` per level. Expansions longer than `MAX_LINES` are refused.
+pub(crate) fn expand_abbreviation(
+    abbr: &str,
+    indent_unit: &str,
+) -> Result<Vec<String>, EmmetError> {
+    let mut parser = Parser {
+        chars: abbr.chars().peekable(),
+    };
+    let nodes = parser.siblings().ok_or(EmmetError::Malformed)?;
+    if parser.chars.next().is_some() {
+        return Err(EmmetError::Malformed);
+    }
+    if line_count(&nodes) > MAX_LINES {
+        return Err(EmmetError::TooLarge);
+    }
+    let mut out = Vec::new();
+    render(&nodes, 0, indent_unit, &mut out);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_list_expands_with_indentation() {
+        assert_eq!(
+            expand_abbreviation("ul>li*2", "  ").expect("expands"),
+            ["<ul>", "  <li></li>", "  <li></li>", "</ul>"]
+        );
+    }
+
+    #[test]
+    fn ids_classes_siblings_and_default_div() {
+        assert_eq!(
+            expand_abbreviation("div.container>ul>li*3", "\t").expect("expands"),
+            [
+                "<div class=\"container\">",
+                "\t<ul>",
+                "\t\t<li></li>",
+                "\t\t<li></li>",
+                "\t\t<li></li>",
+                "\t</ul>",
+                "</div>",
+            ]
+        );
+        assert_eq!(
+            expand_abbreviation("#main.a.b+p>br", "  ").expect("expands"),
+            [
+                "<div id=\"main\" class=\"a b\"></div>",
+                "<p>",
+                "  <br>",
+                "</p>",
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_abbreviations_are_rejected() {
+        for abbr in [
+            "", "ul>", "li*", "li*0", "a+", "div..x", "p#", "a b", "li*1000",
+        ] {
+            assert_eq!(
+                expand_abbreviation(abbr, "  "),
+                Err(EmmetError::Malformed),
+                "{abbr:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn nested_repeats_are_capped_in_total() {
+        assert_eq!(
+            expand_abbreviation("div*100>div*100>div*100", "  "),
+            Err(EmmetError::TooLarge)
+        );
+        let lines = expand_abbreviation("ul*10>li*98", "  ").expect("expands");
+        assert_eq!(lines.len(), MAX_LINES);
+    }
+}
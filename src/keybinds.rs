@@ -32,6 +32,21 @@ pub(crate) enum KeyAction {
     TreeCollapseAll,
     TreeExpandRecursive,
     TreeCollapseRecursive,
+    NextSearchResult,
+    PrevSearchResult,
+    KeepTabOpen,
+    ReopenClosedTab,
+    NewUntitled,
+    GoToSymbol,
+    CycleTreeSort,
+    LastUsedTab,
+    ToggleZen,
+    NavigateBack,
+    NavigateForward,
+    GoToLastEdit,
+    ShowDiagnostics,
+    IncreaseFilesPane,
+    DecreaseFilesPane,
     // Editor
     GoToDefinition,
     FoldToggle,
@@ -40,6 +55,8 @@ pub(crate) enum KeyAction {
     Unfold,
     FoldAll,
     UnfoldAll,
+    NextFold,
+    PrevFold,
     FindNext,
     FindPrev,
     DupLineDown,
@@ -54,10 +71,19 @@ pub(crate) enum KeyAction {
     CutLine,
     Paste,
     ToggleComment,
+    ExpandEmmet,
+    ReflowParagraph,
+    ToggleTaskCheckbox,
+    ToggleListItem,
     PageDown,
     PageUp,
     GoToStart,
     GoToEnd,
+    CenterCursorLine,
+    ScrollCursorToTop,
+    ScrollCursorToBottom,
+    NextDiagnostic,
+    PrevDiagnostic,
 }
 
 impl KeyAction {
@@ -84,6 +110,21 @@ impl KeyAction {
                 | KeyAction::TreeCollapseAll
                 | KeyAction::TreeExpandRecursive
                 | KeyAction::TreeCollapseRecursive
+                | KeyAction::NextSearchResult
+                | KeyAction::PrevSearchResult
+                | KeyAction::KeepTabOpen
+                | KeyAction::ReopenClosedTab
+                | KeyAction::NewUntitled
+                | KeyAction::GoToSymbol
+                | KeyAction::CycleTreeSort
+                | KeyAction::LastUsedTab
+                | KeyAction::ToggleZen
+                | KeyAction::NavigateBack
+                | KeyAction::NavigateForward
+                | KeyAction::GoToLastEdit
+                | KeyAction::ShowDiagnostics
+                | KeyAction::IncreaseFilesPane
+                | KeyAction::DecreaseFilesPane
         )
     }
 
@@ -91,6 +132,28 @@ impl KeyAction {
         !self.is_global()
     }
 
+    /// Whether running the action changes the buffer's text.
+    pub(crate) fn edits_buffer(self) -> bool {
+        matches!(
+            self,
+            KeyAction::DupLineDown
+                | KeyAction::DupLineUp
+                | KeyAction::Dedent
+                | KeyAction::Completion
+                | KeyAction::Undo
+                | KeyAction::Redo
+                | KeyAction::Cut
+                | KeyAction::CutLine
+                | KeyAction::Paste
+                | KeyAction::ToggleComment
+                | KeyAction::ExpandEmmet
+                | KeyAction::ReflowParagraph
+                | KeyAction::ToggleTaskCheckbox
+                | KeyAction::ToggleListItem
+                | KeyAction::FindReplace
+        )
+    }
+
     pub(crate) fn label(self) -> &'static str {
         match self {
             KeyAction::Save => "Save",
@@ -113,6 +176,21 @@ impl KeyAction {
             KeyAction::TreeCollapseAll => "Collapse All Folders",
             KeyAction::TreeExpandRecursive => "Expand Dir Recursive",
             KeyAction::TreeCollapseRecursive => "Collapse Dir Recursive",
+            KeyAction::NextSearchResult => "Next Search Result",
+            KeyAction::PrevSearchResult => "Previous Search Result",
+            KeyAction::KeepTabOpen => "Keep Tab Open",
+            KeyAction::ReopenClosedTab => "Reopen Closed Tab",
+            KeyAction::NewUntitled => "New Untitled Buffer",
+            KeyAction::GoToSymbol => "Go to Symbol",
+            KeyAction::CycleTreeSort => "Cycle Tree Sort",
+            KeyAction::LastUsedTab => "Last Used Tab",
+            KeyAction::ToggleZen => "Toggle Zen Mode",
+            KeyAction::NavigateBack => "Navigate Back",
+            KeyAction::NavigateForward => "Navigate Forward",
+            KeyAction::GoToLastEdit => "Go to Last Edit",
+            KeyAction::ShowDiagnostics => "Show Problems",
+            KeyAction::IncreaseFilesPane => "Widen Files Pane",
+            KeyAction::DecreaseFilesPane => "Narrow Files Pane",
             KeyAction::GoToDefinition => "Go to Definition",
             KeyAction::FoldToggle => "Toggle Fold",
             KeyAction::FoldAllToggle => "Toggle Fold All",
@@ -120,6 +198,8 @@ impl KeyAction {
             KeyAction::Unfold => "Unfold",
             KeyAction::FoldAll => "Fold All",
             KeyAction::UnfoldAll => "Unfold All",
+            KeyAction::NextFold => "Next Fold",
+            KeyAction::PrevFold => "Previous Fold",
             KeyAction::FindNext => "Find Next",
             KeyAction::FindPrev => "Find Previous",
             KeyAction::DupLineDown => "Duplicate Line Down",
@@ -134,10 +214,19 @@ impl KeyAction {
             KeyAction::CutLine => "Cut Line",
             KeyAction::Paste => "Paste",
             KeyAction::ToggleComment => "Toggle Comment",
+            KeyAction::ExpandEmmet => "Expand Emmet Abbreviation",
+            KeyAction::ReflowParagraph => "Reflow Paragraph",
+            KeyAction::ToggleTaskCheckbox => "Toggle Task Checkbox",
+            KeyAction::ToggleListItem => "Toggle List Item",
             KeyAction::PageDown => "Page Down",
             KeyAction::PageUp => "Page Up",
             KeyAction::GoToStart => "Go to Start",
             KeyAction::GoToEnd => "Go to End",
+            KeyAction::CenterCursorLine => "Center Cursor Line",
+            KeyAction::ScrollCursorToTop => "Scroll Cursor to Top",
+            KeyAction::ScrollCursorToBottom => "Scroll Cursor to Bottom",
+            KeyAction::NextDiagnostic => "Next Diagnostic",
+            KeyAction::PrevDiagnostic => "Previous Diagnostic",
         }
     }
 
@@ -163,6 +252,21 @@ impl KeyAction {
             KeyAction::TreeCollapseAll,
             KeyAction::TreeExpandRecursive,
             KeyAction::TreeCollapseRecursive,
+            KeyAction::NextSearchResult,
+            KeyAction::PrevSearchResult,
+            KeyAction::KeepTabOpen,
+            KeyAction::ReopenClosedTab,
+            KeyAction::NewUntitled,
+            KeyAction::GoToSymbol,
+            KeyAction::CycleTreeSort,
+            KeyAction::LastUsedTab,
+            KeyAction::ToggleZen,
+            KeyAction::NavigateBack,
+            KeyAction::NavigateForward,
+            KeyAction::GoToLastEdit,
+            KeyAction::ShowDiagnostics,
+            KeyAction::IncreaseFilesPane,
+            KeyAction::DecreaseFilesPane,
             KeyAction::GoToDefinition,
             KeyAction::FoldToggle,
             KeyAction::FoldAllToggle,
@@ -170,6 +274,8 @@ impl KeyAction {
             KeyAction::Unfold,
             KeyAction::FoldAll,
             KeyAction::UnfoldAll,
+            KeyAction::NextFold,
+            KeyAction::PrevFold,
             KeyAction::FindNext,
             KeyAction::FindPrev,
             KeyAction::DupLineDown,
@@ -184,10 +290,19 @@ impl KeyAction {
             KeyAction::CutLine,
             KeyAction::Paste,
             KeyAction::ToggleComment,
+            KeyAction::ExpandEmmet,
+            KeyAction::ReflowParagraph,
+            KeyAction::ToggleTaskCheckbox,
+            KeyAction::ToggleListItem,
             KeyAction::PageDown,
             KeyAction::PageUp,
             KeyAction::GoToStart,
             KeyAction::GoToEnd,
+            KeyAction::CenterCursorLine,
+            KeyAction::ScrollCursorToTop,
+            KeyAction::ScrollCursorToBottom,
+            KeyAction::NextDiagnostic,
+            KeyAction::PrevDiagnostic,
         ]
     }
 }
@@ -530,6 +645,21 @@ impl KeyBindings {
         bind(KeyAction::TreeCollapseAll, "ctrl+shift+c");
         bind(KeyAction::TreeExpandRecursive, "shift+right");
         bind(KeyAction::TreeCollapseRecursive, "shift+left");
+        bind(KeyAction::NextSearchResult, "f7");
+        bind(KeyAction::PrevSearchResult, "shift+f7");
+        bind(KeyAction::KeepTabOpen, "ctrl+alt+k");
+        bind(KeyAction::ReopenClosedTab, "ctrl+shift+t");
+        bind(KeyAction::NewUntitled, "ctrl+alt+n");
+        bind(KeyAction::GoToSymbol, "ctrl+alt+o");
+        bind(KeyAction::CycleTreeSort, "ctrl+alt+s");
+        bind(KeyAction::LastUsedTab, "ctrl+tab");
+        bind(KeyAction::ToggleZen, "ctrl+alt+z");
+        bind(KeyAction::NavigateBack, "alt+left");
+        bind(KeyAction::NavigateForward, "alt+right");
+        bind(KeyAction::GoToLastEdit, "ctrl+alt+l");
+        bind(KeyAction::ShowDiagnostics, "ctrl+alt+p");
+        bind(KeyAction::IncreaseFilesPane, "ctrl+alt+right");
+        bind(KeyAction::DecreaseFilesPane, "ctrl+alt+left");
 
         // Editor
         bind(KeyAction::GoToDefinition, "ctrl+d");
@@ -540,6 +670,8 @@ impl KeyBindings {
         bind(KeyAction::Unfold, "ctrl+shift+]");
         bind(KeyAction::FoldAll, "ctrl+alt+[");
         bind(KeyAction::UnfoldAll, "ctrl+alt+]");
+        bind(KeyAction::NextFold, "alt+pagedown");
+        bind(KeyAction::PrevFold, "alt+pageup");
         bind(KeyAction::FindNext, "f3");
         bind(KeyAction::FindPrev, "shift+f3");
         bind(KeyAction::DupLineDown, "shift+alt+down");
@@ -549,6 +681,10 @@ impl KeyBindings {
         bind(KeyAction::Completion, "ctrl+.");
         bind(KeyAction::GoToLine, "ctrl+g");
         bind(KeyAction::ToggleComment, "ctrl+/");
+        bind(KeyAction::ExpandEmmet, "ctrl+e");
+        bind(KeyAction::ReflowParagraph, "alt+q");
+        bind(KeyAction::ToggleTaskCheckbox, "alt+x");
+        bind(KeyAction::ToggleListItem, "alt+l");
         bind(KeyAction::Undo, "ctrl+z");
         bind(KeyAction::Redo, "ctrl+shift+z");
         bind(KeyAction::Redo, "ctrl+y");
@@ -561,6 +697,11 @@ impl KeyBindings {
         bind(KeyAction::PageUp, "pageup");
         bind(KeyAction::GoToStart, "ctrl+home");
         bind(KeyAction::GoToEnd, "ctrl+end");
+        bind(KeyAction::CenterCursorLine, "ctrl+l");
+        bind(KeyAction::ScrollCursorToTop, "ctrl+alt+up");
+        bind(KeyAction::ScrollCursorToBottom, "ctrl+alt+down");
+        bind(KeyAction::NextDiagnostic, "f8");
+        bind(KeyAction::PrevDiagnostic, "shift+f8");
 
         KeyBindings { map }
     }
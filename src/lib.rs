@@ -15,9 +15,14 @@ use ratatui::crossterm::terminal::{
 };
 
 mod app;
+mod config;
+mod diff;
+mod emmet;
 mod keybinds;
 mod lsp_client;
+mod markdown;
 mod persistence;
+mod spellcheck;
 mod syntax;
 mod tab;
 mod theme;
@@ -97,6 +102,11 @@ pub fn run() -> io::Result<()> {
 
     let mut app = App::new(root)?;
     app.enhanced_keys = enhanced_keys;
+    // Save the recent projects list with this project at the front.
+    app.persist_state();
+    if let Err(err) = app.restore_autosaved_buffers() {
+        app.set_status(format!("Autosave recovery failed: {err}"));
+    }
     let result = run_app(terminal, app);
 
     disable_raw_mode()?;
@@ -118,12 +128,21 @@ fn run_app(mut terminal: Terminal<CrosstermBackend<Stdout>>, mut app: App) -> io
     loop {
         app.poll_lsp();
         app.poll_git_results();
+        app.poll_file_index();
+        app.poll_project_search();
+        app.poll_batch_rename();
         app.poll_wrap_rebuild();
         if let Err(err) = app.poll_fs_changes() {
             app.set_status(format!("Filesystem update error: {err}"));
         }
-        if let Err(err) = app.poll_autosave() {
-            app.set_status(format!("Autosave error: {err}"));
+        if let Err(err) = app.poll_recovery_snapshot() {
+            app.set_status(format!("Recovery snapshot error: {err}"));
+        }
+        if let Err(err) = app.poll_external_changes() {
+            app.set_status(format!("External change check failed: {err}"));
+        }
+        if let Err(err) = app.poll_auto_save() {
+            app.set_status(format!("Auto-save failed: {err}"));
         }
         app.update_status_for_cursor();
         terminal.draw(|f| draw(&mut app, f))?;
@@ -10,9 +10,91 @@ use std::time::Duration;
 use serde_json::{Value, json};
 use url::Url;
 
+use crate::types::ProjectKind;
+
+/// How to start a language server and which files to give it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LspServerSpec {
+    /// Executable run when no `lsp_server` is configured.
+    pub(crate) command: &'static str,
+    pub(crate) args: &'static [&'static str],
+    /// File extensions the server handles, with the `languageId` each is
+    /// opened as.
+    pub(crate) languages: &'static [(&'static str, &'static str)],
+}
+
+pub(crate) const RUST_ANALYZER: LspServerSpec = LspServerSpec {
+    command: "rust-analyzer",
+    args: &[],
+    languages: &[("rs", "rust")],
+};
+
+pub(crate) const TYPESCRIPT_LANGUAGE_SERVER: LspServerSpec = LspServerSpec {
+    command: "typescript-language-server",
+    args: &["--stdio"],
+    languages: &[
+        ("ts", "typescript"),
+        ("tsx", "typescriptreact"),
+        ("js", "javascript"),
+        ("jsx", "javascriptreact"),
+        ("mjs", "javascript"),
+        ("cjs", "javascript"),
+    ],
+};
+
+pub(crate) const GOPLS: LspServerSpec = LspServerSpec {
+    command: "gopls",
+    args: &[],
+    languages: &[("go", "go")],
+};
+
+pub(crate) const PYRIGHT: LspServerSpec = LspServerSpec {
+    command: "pyright-langserver",
+    args: &["--stdio"],
+    languages: &[("py", "python")],
+};
+
+impl LspServerSpec {
+    /// Server for the first of `kinds`; rust-analyzer when there are none.
+    pub(crate) fn for_project(kinds: &[ProjectKind]) -> Self {
+        match kinds.first() {
+            None | Some(ProjectKind::Rust) => RUST_ANALYZER,
+            Some(ProjectKind::Node) => TYPESCRIPT_LANGUAGE_SERVER,
+            Some(ProjectKind::Go) => GOPLS,
+            Some(ProjectKind::Python) => PYRIGHT,
+        }
+    }
+
+    /// `languageId` for `path`, or `None` when the server doesn't handle it.
+    pub(crate) fn language_id(&self, path: &Path) -> Option<&'static str> {
+        let ext = path.extension()?.to_str()?;
+        self.languages
+            .iter()
+            .find(|(e, _)| e.eq_ignore_ascii_case(ext))
+            .map(|(_, id)| *id)
+    }
+
+    /// The executable to run: rust-analyzer is searched for in the usual
+    /// install locations, other servers are left to `PATH`.
+    pub(crate) fn resolve_bin(&self) -> PathBuf {
+        let found = if *self == RUST_ANALYZER {
+            resolve_rust_analyzer_bin()
+        } else {
+            None
+        };
+        found.unwrap_or_else(|| PathBuf::from(self.command))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct LspDiagnostic {
+    /// 1-based line the range starts on.
     pub(crate) line: usize,
+    /// 0-based char column the range starts at.
+    pub(crate) col: usize,
+    /// 1-based line and 0-based char column the range ends at (exclusive).
+    pub(crate) end_line: usize,
+    pub(crate) end_col: usize,
     pub(crate) severity: String,
     pub(crate) message: String,
 }
@@ -30,16 +112,28 @@ pub(crate) enum LspInbound {
     Response { id: i64, result: Value },
 }
 
+/// An outgoing request and the editor state that triggered it. Its response
+/// only applies while the editor is still in that state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PendingLspRequest {
+    pub(crate) id: i64,
+    pub(crate) path: PathBuf,
+    pub(crate) cursor: (usize, usize),
+    pub(crate) version: i32,
+}
+
 pub(crate) struct LspClient {
     pub(crate) writer: Arc<Mutex<ChildStdin>>,
     pub(crate) rx: Receiver<LspInbound>,
     pub(crate) next_id: i64,
+    /// The server accepts ranged `didChange` edits instead of whole texts.
+    pub(crate) incremental_sync: bool,
 }
 
 impl LspClient {
-    pub(crate) fn new_rust_analyzer(root: &Path) -> io::Result<Self> {
-        let ra_bin = resolve_rust_analyzer_bin().unwrap_or_else(|| PathBuf::from("rust-analyzer"));
-        let mut child = Command::new(ra_bin)
+    pub(crate) fn start(bin: &Path, args: &[&str], root: &Path) -> io::Result<Self> {
+        let mut child = Command::new(bin)
+            .args(args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::null())
@@ -47,11 +141,11 @@ impl LspClient {
         let stdin = child
             .stdin
             .take()
-            .ok_or_else(|| io::Error::other("failed to open rust-analyzer stdin"))?;
+            .ok_or_else(|| io::Error::other("failed to open language server stdin"))?;
         let stdout = child
             .stdout
             .take()
-            .ok_or_else(|| io::Error::other("failed to open rust-analyzer stdout"))?;
+            .ok_or_else(|| io::Error::other("failed to open language server stdout"))?;
 
         let writer = Arc::new(Mutex::new(stdin));
         let (tx, rx) = mpsc::channel::<LspInbound>();
@@ -60,6 +154,7 @@ impl LspClient {
             writer,
             rx,
             next_id: 1,
+            incremental_sync: false,
         };
         let root_uri = Url::from_directory_path(root)
             .map_err(|_| io::Error::other("invalid root path for URI"))?
@@ -78,12 +173,15 @@ impl LspClient {
                 "clientInfo": { "name": "lazyide", "version": "0.1.0" },
             }),
         )?;
-        client.wait_for_initialize(init_id)?;
+        let init = client.wait_for_initialize(init_id)?;
+        client.incremental_sync = init
+            .get("capabilities")
+            .is_some_and(supports_incremental_sync);
         client.send_notification("initialized", json!({}))?;
         Ok(client)
     }
 
-    pub(crate) fn wait_for_initialize(&self, init_id: i64) -> io::Result<()> {
+    pub(crate) fn wait_for_initialize(&self, init_id: i64) -> io::Result<Value> {
         let deadline = std::time::Instant::now() + Duration::from_secs(3);
         loop {
             let now = std::time::Instant::now();
@@ -99,7 +197,7 @@ impl LspClient {
                             result
                         )));
                     }
-                    return Ok(());
+                    return Ok(result);
                 }
                 Ok(_) => continue,
                 Err(_) => return Err(io::Error::other("LSP initialize response missing")),
@@ -127,6 +225,11 @@ impl LspClient {
         Ok(id)
     }
 
+    /// Ask the server to drop a request whose result is no longer wanted.
+    pub(crate) fn cancel_request(&self, id: i64) -> io::Result<()> {
+        self.send_notification("$/cancelRequest", json!({ "id": id }))
+    }
+
     pub(crate) fn send_raw(&self, value: Value) -> io::Result<()> {
         let payload = serde_json::to_vec(&value)
             .map_err(|e| io::Error::other(format!("lsp serialize error: {e}")))?;
@@ -142,6 +245,56 @@ impl LspClient {
     }
 }
 
+/// Whether server `capabilities` ask for incremental document sync, given
+/// either as a bare kind or as `{ "change": kind }`.
+pub(crate) fn supports_incremental_sync(capabilities: &Value) -> bool {
+    let sync = capabilities.get("textDocumentSync");
+    let kind = sync.and_then(|s| s.get("change")).or(sync);
+    kind.and_then(Value::as_u64) == Some(2)
+}
+
+/// The `contentChanges` entry that turns `old` into `new`: the smallest
+/// replaced range when `incremental`, the whole text otherwise. `None` when
+/// nothing changed.
+pub(crate) fn content_change(old: &str, new: &str, incremental: bool) -> Option<Value> {
+    if old == new {
+        return None;
+    }
+    if !incremental {
+        return Some(json!({ "text": new }));
+    }
+    let same = |(a, b): &(char, char)| a == b;
+    let prefix: usize = old
+        .chars()
+        .zip(new.chars())
+        .take_while(same)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+    // Matched from the end of what follows the prefix, so the two can't overlap.
+    let suffix: usize = old[prefix..]
+        .chars()
+        .rev()
+        .zip(new[prefix..].chars().rev())
+        .take_while(same)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+    Some(json!({
+        "range": {
+            "start": lsp_position(old, prefix),
+            "end": lsp_position(old, old.len() - suffix),
+        },
+        "text": &new[prefix..new.len() - suffix],
+    }))
+}
+
+/// LSP position (line, UTF-16 column) of byte offset `offset` in `text`.
+fn lsp_position(text: &str, offset: usize) -> Value {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    json!({ "line": before.matches('\n').count(), "character": character })
+}
+
 pub(crate) fn resolve_rust_analyzer_bin() -> Option<PathBuf> {
     let mut candidates: Vec<PathBuf> = Vec::new();
     if let Some(path) = env::var_os("PATH") {
@@ -217,7 +370,8 @@ pub(crate) fn lsp_reader_loop(stdout: impl Read, tx: Sender<LspInbound>) {
 #[cfg(test)]
 mod lsp_and_struct_tests {
     use super::*;
-    use crate::tab::{FoldRange, Tab};
+    use crate::syntax::SyntaxLang;
+    use crate::tab::{FoldRange, IndentSettings, Tab};
     use crate::tree_item::TreeItem;
     use crate::util::file_uri;
     use serde_json::json;
@@ -421,6 +575,9 @@ mod lsp_and_struct_tests {
     fn test_lsp_diagnostic_construction() {
         let d = LspDiagnostic {
             line: 10,
+            col: 4,
+            end_line: 10,
+            end_col: 9,
             severity: "Error".to_string(),
             message: "unused variable".to_string(),
         };
@@ -433,6 +590,9 @@ mod lsp_and_struct_tests {
     fn test_lsp_diagnostic_clone() {
         let d = LspDiagnostic {
             line: 100,
+            col: 0,
+            end_line: 100,
+            end_col: 1,
             severity: "Error".to_string(),
             message: "type mismatch".to_string(),
         };
@@ -481,26 +641,45 @@ mod lsp_and_struct_tests {
     fn test_tab_struct_construction() {
         let tab = Tab {
             path: PathBuf::from("/test/file.rs"),
+            untitled: false,
+            lang_override: None,
+            detected_lang: SyntaxLang::Rust,
+            indent: IndentSettings::default(),
             is_preview: false,
+            read_only: false,
+            pinned: false,
+            binary_text: false,
             editor: TextArea::default(),
+            char_count: 0,
             dirty: false,
             open_disk_snapshot: None,
+            disk_mtime: None,
             editor_scroll_row: 0,
             editor_scroll_col: 0,
             fold_ranges: Vec::new(),
             bracket_depths: Vec::new(),
             folded_starts: HashSet::new(),
+            fold_lines: Vec::new(),
             visible_rows_map: Vec::new(),
             visible_row_starts: Vec::new(),
             visible_row_ends: Vec::new(),
             open_doc_uri: None,
             open_doc_version: 0,
+            lsp_synced_text: String::new(),
             diagnostics: Vec::new(),
+            pending_diagnostics: None,
             conflict_prompt_open: false,
             conflict_disk_text: None,
             recovery_prompt_open: false,
             recovery_text: None,
+            prompt_diff: Vec::new(),
             git_line_status: Vec::new(),
+            last_edit: None,
+            auto_save_failed: false,
+            last_edit_pos: None,
+            undo_groups: Vec::new(),
+            redo_groups: Vec::new(),
+            typing_run: None,
         };
         assert_eq!(tab.path, PathBuf::from("/test/file.rs"));
         assert!(!tab.is_preview);
@@ -513,10 +692,19 @@ mod lsp_and_struct_tests {
         editor.insert_str("fn main() {}");
         let tab = Tab {
             path: PathBuf::from("/src/main.rs"),
+            untitled: false,
+            lang_override: None,
+            detected_lang: SyntaxLang::Rust,
+            indent: IndentSettings::default(),
             is_preview: true,
+            read_only: false,
+            pinned: false,
+            binary_text: false,
             editor,
+            char_count: 0,
             dirty: true,
             open_disk_snapshot: Some("old".to_string()),
+            disk_mtime: None,
             editor_scroll_row: 10,
             editor_scroll_col: 0,
             fold_ranges: vec![FoldRange {
@@ -529,21 +717,34 @@ mod lsp_and_struct_tests {
                 s.insert(5);
                 s
             },
+            fold_lines: Vec::new(),
             visible_rows_map: vec![0, 1, 2, 16, 17],
             visible_row_starts: vec![0, 0, 0, 0, 0],
             visible_row_ends: vec![10, 10, 10, 10, 10],
             open_doc_uri: Some("file:///src/main.rs".to_string()),
             open_doc_version: 3,
+            lsp_synced_text: String::new(),
             diagnostics: vec![LspDiagnostic {
                 line: 1,
+                col: 0,
+                end_line: 1,
+                end_col: 3,
                 severity: "Warning".to_string(),
                 message: "unused".to_string(),
             }],
+            pending_diagnostics: None,
             conflict_prompt_open: true,
             conflict_disk_text: Some("disk".to_string()),
             recovery_prompt_open: false,
             recovery_text: None,
+            prompt_diff: Vec::new(),
             git_line_status: Vec::new(),
+            last_edit: None,
+            auto_save_failed: false,
+            last_edit_pos: None,
+            undo_groups: Vec::new(),
+            redo_groups: Vec::new(),
+            typing_run: None,
         };
         assert!(tab.is_preview);
         assert!(tab.dirty);
@@ -560,6 +761,10 @@ mod lsp_and_struct_tests {
             depth: 2,
             is_dir: false,
             expanded: false,
+            size: None,
+            modified: None,
+            child_count: None,
+            marker: None,
         };
         assert_eq!(item.name, "main.rs");
         assert_eq!(item.depth, 2);
@@ -574,6 +779,10 @@ mod lsp_and_struct_tests {
             depth: 1,
             is_dir: true,
             expanded: true,
+            size: None,
+            modified: None,
+            child_count: None,
+            marker: None,
         };
         assert!(item.is_dir);
         assert!(item.expanded);
@@ -587,9 +796,81 @@ mod lsp_and_struct_tests {
             depth: 1,
             is_dir: false,
             expanded: false,
+            size: None,
+            modified: None,
+            child_count: None,
+            marker: None,
         };
         let c = item.clone();
         assert_eq!(item.path, c.path);
         assert_eq!(item.name, c.name);
     }
+
+    #[test]
+    fn single_line_edit_sends_an_incremental_range() {
+        let old = "fn main() {\n    let value = 1;\n}";
+        let new = "fn main() {\n    let values = 1;\n}";
+        assert_eq!(
+            content_change(old, new, true),
+            Some(json!({
+                "range": {
+                    "start": { "line": 1, "character": 13 },
+                    "end": { "line": 1, "character": 13 },
+                },
+                "text": "s",
+            }))
+        );
+        // Columns count UTF-16 code units; an emoji takes two.
+        let change = content_change("// 😀 x\nend", "// 😀 y\nend", true).expect("change");
+        assert_eq!(
+            change["range"]["start"],
+            json!({ "line": 0, "character": 6 })
+        );
+        assert_eq!(change["range"]["end"], json!({ "line": 0, "character": 7 }));
+        // Deleting a whole line spans the line break.
+        let change = content_change("a\nb\nc", "a\nc", true).expect("change");
+        assert_eq!(
+            change["range"]["start"],
+            json!({ "line": 1, "character": 0 })
+        );
+        assert_eq!(change["range"]["end"], json!({ "line": 2, "character": 0 }));
+        assert_eq!(change["text"], "");
+    }
+
+    #[test]
+    fn full_sync_sends_the_whole_text() {
+        let new = "fn main() {\n    let values = 1;\n}";
+        assert_eq!(
+            content_change("fn main() {}", new, false),
+            Some(json!({ "text": new }))
+        );
+        assert_eq!(content_change(new, new, false), None);
+        assert_eq!(content_change(new, new, true), None);
+    }
+
+    #[test]
+    fn incremental_sync_read_from_either_capability_form() {
+        assert!(supports_incremental_sync(&json!({ "textDocumentSync": 2 })));
+        assert!(supports_incremental_sync(
+            &json!({ "textDocumentSync": { "openClose": true, "change": 2 } })
+        ));
+        assert!(!supports_incremental_sync(
+            &json!({ "textDocumentSync": 1 })
+        ));
+        assert!(!supports_incremental_sync(
+            &json!({ "textDocumentSync": { "openClose": true } })
+        ));
+        assert!(!supports_incremental_sync(&json!({})));
+    }
+
+    #[test]
+    fn server_spec_follows_the_first_project_kind() {
+        assert_eq!(LspServerSpec::for_project(&[]), RUST_ANALYZER);
+        let spec = LspServerSpec::for_project(&[ProjectKind::Node, ProjectKind::Rust]);
+        assert_eq!(spec, TYPESCRIPT_LANGUAGE_SERVER);
+        let tsx = Path::new("src/app.TSX");
+        assert_eq!(spec.language_id(tsx), Some("typescriptreact"));
+        assert_eq!(spec.language_id(Path::new("src/main.rs")), None);
+        assert_eq!(PYRIGHT.resolve_bin(), PathBuf::from("pyright-langserver"));
+    }
 }
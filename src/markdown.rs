@@ -0,0 +1,446 @@
+use crate::types::LineContinuation;
+use crate::util::leading_indent_bytes;
+
+/// Words in `text`: whitespace-separated runs holding a letter or digit, so
+/// lone punctuation like `-` or `|` isn't counted. For Markdown, fenced code
+/// blocks, list numbers and link targets are left out as well.
+pub(crate) fn count_words(text: &str, markdown: bool) -> usize {
+    let is_word = |token: &str| token.chars().any(char::is_alphanumeric);
+    let mut in_fence = false;
+    let mut count = 0;
+    for line in text.lines() {
+        if !markdown {
+            count += line.split_whitespace().filter(|w| is_word(w)).count();
+            continue;
+        }
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let line = strip_link_targets(trimmed);
+        let mut words = line.split_whitespace().peekable();
+        let list_number = |w: &&str| {
+            w.strip_suffix(['.', ')'])
+                .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        };
+        words.next_if(list_number);
+        count += words.filter(|w| is_word(w)).count();
+    }
+    count
+}
+
+/// `line` with the `(target)` of each `[text](target)` link removed.
+fn strip_link_targets(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(idx) = rest.find("](") {
+        let Some(close) = rest[idx..].find(')') else {
+            break;
+        };
+        out.push_str(&rest[..=idx]);
+        rest = &rest[idx + close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Byte length of the Markdown list marker `text` starts with, counting
+/// the space after it: `- `, `* `, `+ `, `1. ` or `1) `.
+fn list_marker_len(text: &str) -> Option<usize> {
+    if let Some(after) = text.strip_prefix(['-', '*', '+']) {
+        return after.starts_with(' ').then_some(2);
+    }
+    let digits = text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let after = &text[digits..];
+    (digits > 0 && (after.starts_with(". ") || after.starts_with(") "))).then_some(digits + 2)
+}
+
+/// Whether `line` is a Markdown list item, at any indentation.
+pub(crate) fn is_list_item(line: &str) -> bool {
+    list_marker_len(&line[leading_indent_bytes(line)..]).is_some()
+}
+
+/// `line` as a `- ` list item when `bullet` is set, or as plain text with
+/// its list marker and task checkbox removed. Indentation is kept and blank
+/// lines are left alone.
+pub(crate) fn set_list_item(line: &str, bullet: bool) -> String {
+    let indent = leading_indent_bytes(line);
+    let rest = &line[indent..];
+    let marker = list_marker_len(rest);
+    if rest.trim().is_empty() || bullet == marker.is_some() {
+        return line.to_string();
+    }
+    let text = match marker {
+        Some(len) => {
+            let body = &rest[len..];
+            match body.get(..4) {
+                Some("[ ] " | "[x] " | "[X] ") => &body[4..],
+                _ => body,
+            }
+        }
+        None => return format!("{}- {rest}", &line[..indent]),
+    };
+    format!("{}{text}", &line[..indent])
+}
+
+/// `line` with its Markdown task checkbox flipped between `[ ]` and `[x]`.
+/// A list item without a box gets an unchecked one and a plain line becomes
+/// an unchecked `- [ ]` item. Blank lines are left alone.
+pub(crate) fn toggle_task_checkbox(line: &str) -> String {
+    let indent = leading_indent_bytes(line);
+    let rest = &line[indent..];
+    if rest.trim().is_empty() {
+        return line.to_string();
+    }
+    let (marker, body) = match list_marker_len(rest) {
+        Some(len) => rest.split_at(len),
+        None => ("- ", rest),
+    };
+    let body = match body.get(..3) {
+        Some("[ ]") => format!("[x]{}", &body[3..]),
+        Some("[x]" | "[X]") => format!("[ ]{}", &body[3..]),
+        _ => format!("[ ] {body}"),
+    };
+    format!("{}{marker}{body}", &line[..indent])
+}
+
+/// How Enter continues the Markdown list item `line`: the next item's
+/// marker, numbered one higher and with an unchecked box if the item has
+/// one, or `Exit` when the item is empty. `None` if `line` is not a list item.
+pub(crate) fn markdown_list_continuation(line: &str) -> Option<LineContinuation> {
+    let (quote, marker, text) = split_markdown_prefix(line);
+    if marker.is_empty() {
+        return None;
+    }
+    let checkbox = matches!(text.get(..3), Some("[ ]" | "[x]" | "[X]"));
+    let text = if checkbox { text[3..].trim() } else { text };
+    if text.is_empty() {
+        return Some(LineContinuation::Exit(quote.trim_end().to_string()));
+    }
+    let digits = marker.trim_end_matches([' ', '.', ')']);
+    let next = match digits.parse::<u64>() {
+        Ok(n) => format!("{}{}", n + 1, &marker[digits.len()..]),
+        Err(_) => marker.to_string(),
+    };
+    let checkbox = if checkbox { "[ ] " } else { "" };
+    Some(LineContinuation::Prefix(format!("{quote}{next}{checkbox}")))
+}
+
+/// The cells of a Markdown table row, trimmed, without the outer pipes.
+/// Escaped pipes (`\|`) stay inside their cell.
+fn table_cells(row: &str) -> Vec<&str> {
+    let row = row.trim();
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let row = match row.strip_suffix('|') {
+        Some(inner) if !inner.ends_with('\\') => inner,
+        _ => row,
+    };
+    let mut cells = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, ch) in row.char_indices() {
+        match ch {
+            '|' if !escaped => {
+                cells.push(row[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        escaped = ch == '\\' && !escaped;
+    }
+    cells.push(row[start..].trim());
+    cells
+}
+
+/// Column alignment from a separator cell such as `:---:`, or `None` when
+/// `cell` isn't one.
+fn table_alignment(cell: &str) -> Option<(bool, bool)> {
+    let left = cell.starts_with(':');
+    let right = cell.len() > 1 && cell.ends_with(':');
+    let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+    (!dashes.is_empty() && dashes.chars().all(|c| c == '-')).then_some((left, right))
+}
+
+/// Align the Markdown table in `lines`: pad every column to its widest
+/// cell, honouring `:---`, `:---:` and `---:` alignment markers, and
+/// rewrite the separator row to match. Short rows get empty cells.
+pub(crate) fn format_markdown_table(lines: &[String]) -> Vec<String> {
+    let indent = lines.first().map_or("", |l| &l[..leading_indent_bytes(l)]);
+    let rows: Vec<Vec<&str>> = lines.iter().map(|l| table_cells(l)).collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let separator = rows
+        .iter()
+        .position(|cells| cells.iter().all(|c| table_alignment(c).is_some()));
+    let align: Vec<(bool, bool)> = (0..columns)
+        .map(|i| {
+            separator
+                .and_then(|row| rows[row].get(i))
+                .and_then(|c| table_alignment(c))
+                .unwrap_or((false, false))
+        })
+        .collect();
+    let cell_width = |cell: &str| unicode_width::UnicodeWidthStr::width(cell);
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .enumerate()
+                .filter(|&(row, _)| Some(row) != separator)
+                .filter_map(|(_, cells)| cells.get(i))
+                .map(|c| cell_width(c))
+                .max()
+                .unwrap_or(0)
+                .max(3)
+        })
+        .collect();
+    rows.iter()
+        .enumerate()
+        .map(|(row, cells)| {
+            let padded = (0..columns).map(|i| {
+                let width = widths[i];
+                let (left, right) = align[i];
+                if Some(row) == separator {
+                    let dashes = "-".repeat(width - usize::from(left) - usize::from(right));
+                    let (l, r) = (if left { ":" } else { "" }, if right { ":" } else { "" });
+                    return format!("{l}{dashes}{r}");
+                }
+                let cell = cells.get(i).copied().unwrap_or("");
+                let gap = width - cell_width(cell);
+                let before = match (left, right) {
+                    (false, true) => gap,
+                    (true, true) => gap / 2,
+                    _ => 0,
+                };
+                format!("{}{cell}{}", " ".repeat(before), " ".repeat(gap - before))
+            });
+            format!("{indent}| {} |", padded.collect::<Vec<_>>().join(" | "))
+        })
+        .collect()
+}
+
+/// Split a Markdown line into its block-quote prefix (indent and `>`
+/// markers), list marker with the space after it, and the text.
+pub(crate) fn split_markdown_prefix(line: &str) -> (&str, &str, &str) {
+    let mut quote_end = leading_indent_bytes(line);
+    while let Some(rest) = line[quote_end..].strip_prefix('>') {
+        quote_end = line.len() - rest.len();
+        quote_end += rest.len() - rest.trim_start_matches(' ').len();
+    }
+    let marker_end = quote_end + list_marker_len(&line[quote_end..]).unwrap_or(0);
+    (
+        &line[..quote_end],
+        &line[quote_end..marker_end],
+        line[marker_end..].trim(),
+    )
+}
+
+/// A paragraph being gathered by `reflow_paragraphs`.
+struct ReflowParagraph<'a> {
+    quote_depth: usize,
+    first_prefix: String,
+    rest_prefix: String,
+    words: Vec<&'a str>,
+}
+
+impl ReflowParagraph<'_> {
+    /// Greedily fill lines of at most `width` columns with the words. A word
+    /// longer than the width gets a line of its own.
+    fn wrap_into(self, width: usize, out: &mut Vec<String>) {
+        let mut line = self.first_prefix;
+        let mut has_word = false;
+        for word in self.words {
+            if has_word && line.chars().count() + 1 + word.chars().count() > width {
+                out.push(std::mem::replace(&mut line, self.rest_prefix.clone()));
+                has_word = false;
+            }
+            if has_word {
+                line.push(' ');
+            }
+            line.push_str(word);
+            has_word = true;
+        }
+        out.push(line);
+    }
+}
+
+/// Rewrap `lines` to `width` columns on word boundaries. Paragraphs end at
+/// blank lines, list items and changes of block-quote depth; each keeps its
+/// quote and list marker, with wrapped lines indented under the text.
+/// Headings, table rows and code fences are left as they are.
+pub(crate) fn reflow_paragraphs(lines: &[String], width: usize) -> Vec<String> {
+    let quote_depth = |quote: &str| quote.matches('>').count();
+    let mut out = Vec::new();
+    let mut open: Option<ReflowParagraph> = None;
+    for line in lines {
+        let (quote, marker, text) = split_markdown_prefix(line);
+        let verbatim = text.is_empty()
+            || text.starts_with('#')
+            || text.starts_with('|')
+            || text.starts_with("```")
+            || text.starts_with("~~~");
+        if let Some(para) = open.as_mut()
+            && !verbatim
+            && marker.is_empty()
+            && para.quote_depth == quote_depth(quote)
+        {
+            para.words.extend(text.split_whitespace());
+            continue;
+        }
+        if let Some(para) = open.take() {
+            para.wrap_into(width, &mut out);
+        }
+        if verbatim {
+            out.push(line.clone());
+            continue;
+        }
+        open = Some(ReflowParagraph {
+            quote_depth: quote_depth(quote),
+            first_prefix: format!("{quote}{marker}"),
+            rest_prefix: format!("{quote}{}", " ".repeat(marker.chars().count())),
+            words: text.split_whitespace().collect(),
+        });
+    }
+    if let Some(para) = open {
+        para.wrap_into(width, &mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_skip_markdown_markers_code_and_link_targets() {
+        let text = "# Getting started\n\n\
+                    Install the **tool** with `cargo install`, then read \
+                    [the docs](https://example.com/docs).\n\n\
+                    1. Open a file\n- Save it — done\n```sh\nlazyide .\n```\n";
+        assert_eq!(count_words(text, true), 18);
+        // As plain text the list number, fence and URL count too.
+        assert_eq!(count_words(text, false), 21);
+    }
+
+    #[test]
+    fn reflow_fills_lines_up_to_the_width() {
+        let long = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do \
+                    eiusmod tempor incididunt ut labore et dolore magna aliqua.";
+        let lines = vec![long.to_string(), String::new(), "# Heading".to_string()];
+        let out = reflow_paragraphs(&lines, 40);
+        assert_eq!(
+            out,
+            [
+                "Lorem ipsum dolor sit amet, consectetur",
+                "adipiscing elit, sed do eiusmod tempor",
+                "incididunt ut labore et dolore magna",
+                "aliqua.",
+                "",
+                "# Heading",
+            ]
+        );
+        assert!(out.iter().all(|l| l.chars().count() <= 40));
+    }
+
+    #[test]
+    fn reflow_keeps_quote_prefixes_and_list_markers() {
+        let lines = vec![
+            "> a quoted line that runs on".to_string(),
+            "> and on".to_string(),
+            "- one item that wraps".to_string(),
+            "- two".to_string(),
+        ];
+        assert_eq!(
+            reflow_paragraphs(&lines, 16),
+            [
+                "> a quoted line",
+                "> that runs on",
+                "> and on",
+                "- one item that",
+                "  wraps",
+                "- two",
+            ]
+        );
+        let parts = split_markdown_prefix("  > 12. text ");
+        assert_eq!(parts, ("  > ", "12. ", "text"));
+    }
+
+    #[test]
+    fn list_items_continue_with_the_next_marker() {
+        let next = |line| match markdown_list_continuation(line) {
+            Some(LineContinuation::Prefix(prefix)) => prefix,
+            other => panic!("{line:?} gave {other:?}"),
+        };
+        assert_eq!(next("- a"), "- ");
+        assert_eq!(next("  * [x] done"), "  * [ ] ");
+        assert_eq!(next("> 9. nine"), "> 10. ");
+        assert_eq!(
+            markdown_list_continuation("  - "),
+            Some(LineContinuation::Exit(String::new()))
+        );
+        assert_eq!(
+            markdown_list_continuation("> - [ ]"),
+            Some(LineContinuation::Exit(">".to_string()))
+        );
+        assert_eq!(markdown_list_continuation("plain"), None);
+    }
+
+    #[test]
+    fn ragged_table_columns_are_padded_to_the_widest_cell() {
+        let lines: Vec<String> = [
+            "|Name|Qty|Note|",
+            "|-|-|-|",
+            "| apple | 3 |",
+            "|kiwi|12|ripe|",
+        ]
+        .map(String::from)
+        .into();
+        assert_eq!(
+            format_markdown_table(&lines),
+            [
+                "| Name  | Qty | Note |",
+                "| ----- | --- | ---- |",
+                "| apple | 3   |      |",
+                "| kiwi  | 12  | ripe |",
+            ]
+        );
+    }
+
+    #[test]
+    fn table_alignment_markers_are_kept_and_applied() {
+        let lines: Vec<String> = ["| a | b | c |", "|:-|:-:|-:|", "| left | mid | 7 |"]
+            .map(String::from)
+            .into();
+        assert_eq!(
+            format_markdown_table(&lines),
+            [
+                "| a    |  b  |   c |",
+                "| :--- | :-: | --: |",
+                "| left | mid |   7 |",
+            ]
+        );
+    }
+
+    #[test]
+    fn task_checkbox_toggles_and_plain_lines_become_tasks() {
+        assert_eq!(toggle_task_checkbox("  - [ ] docs"), "  - [x] docs");
+        assert_eq!(toggle_task_checkbox("  - [x] docs"), "  - [ ] docs");
+        assert_eq!(toggle_task_checkbox("1. [X] ship"), "1. [ ] ship");
+        assert_eq!(toggle_task_checkbox("* item"), "* [ ] item");
+        assert_eq!(toggle_task_checkbox("\tplain"), "\t- [ ] plain");
+        assert_eq!(toggle_task_checkbox("   "), "   ");
+    }
+
+    #[test]
+    fn list_items_convert_to_and_from_plain_lines() {
+        assert_eq!(set_list_item("    plain line", true), "    - plain line");
+        assert_eq!(set_list_item("    - plain line", false), "    plain line");
+        assert_eq!(set_list_item("- [x] done", false), "done");
+        assert_eq!(set_list_item("2) second", true), "2) second");
+        assert_eq!(set_list_item("", true), "");
+        assert!(is_list_item("  + nested"));
+        assert!(!is_list_item("-not a marker"));
+    }
+}
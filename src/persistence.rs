@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::collections::hash_map::DefaultHasher;
 use std::fs;
 use std::hash::{Hash, Hasher};
@@ -6,25 +7,149 @@ use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
+use crate::types::{PaneSide, TreeSort};
+
 const STATE_FILE_REL: &str = "lazyide/state.json";
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Files-pane layout remembered for one project root. The width also fixes
+/// where the divider sits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) struct ProjectLayout {
+    pub(crate) files_pane_width: u16,
+    pub(crate) files_view_open: bool,
+}
+
+/// Language override and read-only flag chosen for one file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) struct FileAttributes {
+    /// `SyntaxLang::config_key` of the language picked with "Set Language".
+    #[serde(default)]
+    pub(crate) lang: Option<String>,
+    #[serde(default)]
+    pub(crate) read_only: bool,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub(crate) struct PersistedState {
     pub(crate) theme_name: String,
+    /// Per-project layouts keyed by the project root path.
     #[serde(default)]
-    pub(crate) files_pane_width: Option<u16>,
+    pub(crate) projects: BTreeMap<String, ProjectLayout>,
     #[serde(default)]
     pub(crate) word_wrap: Option<bool>,
+    #[serde(default)]
+    pub(crate) search_context_lines: Option<usize>,
+    #[serde(default)]
+    pub(crate) follow_symlinks: Option<bool>,
+    #[serde(default)]
+    pub(crate) auto_save: Option<bool>,
+    #[serde(default)]
+    pub(crate) auto_save_delay_ms: Option<u64>,
+    #[serde(default)]
+    pub(crate) indent_width: Option<u8>,
+    #[serde(default)]
+    pub(crate) indent_hard_tabs: Option<bool>,
+    #[serde(default)]
+    pub(crate) render_tab_width: Option<u8>,
+    #[serde(default)]
+    pub(crate) highlight_trailing_whitespace: Option<bool>,
+    #[serde(default)]
+    pub(crate) scroll_off: Option<usize>,
+    #[serde(default)]
+    pub(crate) scroll_lines: Option<usize>,
+    #[serde(default)]
+    pub(crate) scroll_acceleration: Option<bool>,
+    #[serde(default)]
+    pub(crate) line_numbers: Option<bool>,
+    #[serde(default)]
+    pub(crate) fold_arrows: Option<bool>,
+    #[serde(default)]
+    pub(crate) lint_mixed_indent: Option<bool>,
+    #[serde(default)]
+    pub(crate) diagnostic_errors_only: Option<bool>,
+    #[serde(default)]
+    pub(crate) diagnostics_delay_ms: Option<u64>,
+    #[serde(default)]
+    pub(crate) diagnostics_max_per_line: Option<usize>,
+    #[serde(default)]
+    pub(crate) reflow_width: Option<usize>,
+    #[serde(default)]
+    pub(crate) recent_projects_limit: Option<usize>,
+    /// Project roots opened most recently first.
+    #[serde(default)]
+    pub(crate) recent_projects: Vec<String>,
+    /// Find, replace and go-to-line input keyed by prompt, newest first.
+    #[serde(default)]
+    pub(crate) prompt_history: BTreeMap<String, Vec<String>>,
+    /// Per-file attributes keyed by the file's path.
+    #[serde(default)]
+    pub(crate) file_attributes: BTreeMap<String, FileAttributes>,
+    #[serde(default)]
+    pub(crate) tree_sort: Option<TreeSort>,
+    #[serde(default)]
+    pub(crate) tree_sort_descending: Option<bool>,
+    #[serde(default)]
+    pub(crate) tree_dirs_first: Option<bool>,
+    #[serde(default)]
+    pub(crate) tree_show_details: Option<bool>,
+    #[serde(default)]
+    pub(crate) files_pane_side: Option<PaneSide>,
+    #[serde(default)]
+    pub(crate) bracket_colorization: Option<bool>,
+    #[serde(default)]
+    pub(crate) rainbow_indent: Option<bool>,
+    #[serde(default)]
+    pub(crate) spell_check: Option<bool>,
+    /// Words added with "Add to Dictionary".
+    #[serde(default)]
+    pub(crate) user_dictionary: Vec<String>,
+    #[serde(default)]
+    pub(crate) zen_width: Option<u16>,
+    #[serde(default)]
+    pub(crate) line_spacing: Option<usize>,
 }
 
-pub(crate) fn autosave_path_for(path: &Path) -> PathBuf {
+impl PersistedState {
+    pub(crate) fn project_layout(&self, root: &Path) -> Option<ProjectLayout> {
+        self.projects.get(root.to_string_lossy().as_ref()).copied()
+    }
+
+    pub(crate) fn set_project_layout(&mut self, root: &Path, layout: ProjectLayout) {
+        self.projects
+            .insert(root.to_string_lossy().into_owned(), layout);
+    }
+}
+
+/// Directory holding the autosaves, next to the state file.
+pub(crate) fn autosave_dir_for(state_path: &Path) -> Option<PathBuf> {
+    state_path.parent().map(|dir| dir.join("autosave"))
+}
+
+pub(crate) fn autosave_path_for(dir: &Path, path: &Path) -> PathBuf {
     let mut hasher = DefaultHasher::new();
     path.hash(&mut hasher);
     let hash = hasher.finish();
-    let base = state_file_path()
-        .and_then(|p| p.parent().map(|pp| pp.to_path_buf()))
-        .unwrap_or_else(|| PathBuf::from("."));
-    base.join("autosave").join(format!("{hash:016x}.autosave"))
+    dir.join(format!("{hash:016x}.autosave"))
+}
+
+/// Sidecar next to an autosave file recording which file it belongs to, so
+/// leftover autosaves can be matched back to their files on startup.
+pub(crate) fn autosave_source_path(autosave: &Path) -> PathBuf {
+    autosave.with_extension("path")
+}
+
+pub(crate) fn write_autosave(dir: &Path, path: &Path, text: &str) -> io::Result<()> {
+    let autosave = autosave_path_for(dir, path);
+    fs::create_dir_all(dir)?;
+    let source = path.to_string_lossy();
+    fs::write(autosave_source_path(&autosave), source.as_bytes())?;
+    fs::write(&autosave, text)
+}
+
+pub(crate) fn remove_autosave(dir: &Path, path: &Path) {
+    let autosave = autosave_path_for(dir, path);
+    let _ = fs::remove_file(autosave_source_path(&autosave));
+    let _ = fs::remove_file(autosave);
 }
 
 pub(crate) fn state_file_path() -> Option<PathBuf> {
@@ -43,16 +168,12 @@ pub(crate) fn state_file_path() -> Option<PathBuf> {
         .map(|home| PathBuf::from(home).join(".config").join(STATE_FILE_REL))
 }
 
-pub(crate) fn load_persisted_state() -> Option<PersistedState> {
-    let path = state_file_path()?;
+pub(crate) fn load_persisted_state(path: &Path) -> Option<PersistedState> {
     let raw = fs::read_to_string(path).ok()?;
     serde_json::from_str::<PersistedState>(&raw).ok()
 }
 
-pub(crate) fn save_persisted_state(state: &PersistedState) -> io::Result<()> {
-    let Some(path) = state_file_path() else {
-        return Ok(());
-    };
+pub(crate) fn save_persisted_state(path: &Path, state: &PersistedState) -> io::Result<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
@@ -0,0 +1,289 @@
+//! Spell-checking for comments and Markdown prose. Words are looked up in a
+//! Hunspell dictionary when built with the `spellcheck` feature; without it
+//! nothing is flagged.
+
+use std::collections::BTreeSet;
+#[cfg(feature = "spellcheck")]
+use std::fs;
+#[cfg(feature = "spellcheck")]
+use std::path::PathBuf;
+
+use ratatui::style::Style;
+
+use crate::syntax::{SyntaxLang, highlight_line};
+use crate::theme::Theme;
+
+/// Hunspell dictionary read from `dictionaries/` in the lazyide config dir,
+/// or else from the first of `SYSTEM_DICTIONARY_DIRS` that has it.
+#[cfg(feature = "spellcheck")]
+const DICTIONARY_NAME: &str = "en_US";
+#[cfg(feature = "spellcheck")]
+const SYSTEM_DICTIONARY_DIRS: &[&str] = &[
+    "/usr/share/hunspell",
+    "/usr/share/myspell",
+    "/usr/local/share/hunspell",
+    "/opt/homebrew/share/hunspell",
+    "/Library/Spelling",
+];
+
+#[derive(Default)]
+pub(crate) struct SpellChecker {
+    #[cfg(feature = "spellcheck")]
+    dictionary: Option<spellbook::Dictionary>,
+    /// Words added with "Add to Dictionary", lowercased.
+    user_words: BTreeSet<String>,
+}
+
+impl SpellChecker {
+    /// A checker over the first dictionary found; with none, or without the
+    /// `spellcheck` feature, every word is accepted.
+    pub(crate) fn load() -> Self {
+        Self {
+            #[cfg(feature = "spellcheck")]
+            dictionary: load_dictionary(),
+            user_words: BTreeSet::new(),
+        }
+    }
+
+    #[cfg(all(test, feature = "spellcheck"))]
+    fn from_hunspell(aff: &str, dic: &str) -> Self {
+        Self {
+            dictionary: spellbook::Dictionary::new(aff, dic).ok(),
+            user_words: BTreeSet::new(),
+        }
+    }
+
+    #[cfg(feature = "spellcheck")]
+    pub(crate) fn has_dictionary(&self) -> bool {
+        self.dictionary.is_some()
+    }
+
+    #[cfg(not(feature = "spellcheck"))]
+    pub(crate) fn has_dictionary(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "spellcheck")]
+    fn dictionary_knows(&self, word: &str) -> bool {
+        self.dictionary.as_ref().is_none_or(|d| d.check(word))
+    }
+
+    #[cfg(not(feature = "spellcheck"))]
+    fn dictionary_knows(&self, _word: &str) -> bool {
+        true
+    }
+
+    pub(crate) fn is_known(&self, word: &str) -> bool {
+        self.user_words.contains(&word.to_lowercase()) || self.dictionary_knows(word)
+    }
+
+    /// Accept `word` from now on; false when it already was a user word.
+    pub(crate) fn add_word(&mut self, word: &str) -> bool {
+        self.user_words.insert(word.to_lowercase())
+    }
+
+    pub(crate) fn user_words(&self) -> impl Iterator<Item = &String> {
+        self.user_words.iter()
+    }
+
+    pub(crate) fn set_user_words(&mut self, words: impl IntoIterator<Item = String>) {
+        self.user_words = words.into_iter().map(|w| w.to_lowercase()).collect();
+    }
+
+    /// Char ranges of the unknown words in `line`'s comments, or anywhere in
+    /// it for Markdown.
+    pub(crate) fn misspelled(
+        &self,
+        line: &str,
+        lang: SyntaxLang,
+        theme: &Theme,
+    ) -> Vec<(usize, usize)> {
+        if !self.has_dictionary() {
+            return Vec::new();
+        }
+        let chars: Vec<char> = line.chars().collect();
+        let ranges = match lang {
+            SyntaxLang::Markdown => vec![(0, chars.len())],
+            _ => comment_ranges(line, lang, theme),
+        };
+        ranges
+            .into_iter()
+            .flat_map(|(start, end)| prose_words(line, start, end))
+            .filter(|&(start, end)| {
+                let word: String = chars[start..end].iter().collect();
+                !self.is_known(&word)
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "spellcheck")]
+fn load_dictionary() -> Option<spellbook::Dictionary> {
+    let config_dir = crate::config::config_file_path()
+        .and_then(|p| p.parent().map(|dir| dir.join("dictionaries")));
+    config_dir
+        .into_iter()
+        .chain(SYSTEM_DICTIONARY_DIRS.iter().map(PathBuf::from))
+        .find_map(|dir| {
+            let aff = fs::read_to_string(dir.join(format!("{DICTIONARY_NAME}.aff"))).ok()?;
+            let dic = fs::read_to_string(dir.join(format!("{DICTIONARY_NAME}.dic"))).ok()?;
+            spellbook::Dictionary::new(&aff, &dic).ok()
+        })
+}
+
+/// Char ranges of the spans `highlight_line` styles as comments.
+pub(crate) fn comment_ranges(line: &str, lang: SyntaxLang, theme: &Theme) -> Vec<(usize, usize)> {
+    let comment_style = Style::default().fg(theme.comment);
+    let mut ranges = Vec::new();
+    let mut col = 0;
+    for span in highlight_line(line, lang, theme, 0, &[]).spans {
+        let len = span.content.chars().count();
+        if span.style == comment_style && len > 0 {
+            ranges.push((col, col + len));
+        }
+        col += len;
+    }
+    ranges
+}
+
+/// Char ranges of the words between chars `start` and `end` of `line` worth
+/// checking. Anything code-like is left alone: `code`, identifiers with
+/// `_`, digits or inner capitals, paths and URLs.
+pub(crate) fn prose_words(line: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = line.chars().collect();
+    let end = end.min(chars.len());
+    let mut words = Vec::new();
+    let mut in_code = false;
+    let mut i = start;
+    while i < end {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let token_start = i;
+        while i < end && !chars[i].is_whitespace() {
+            i += 1;
+        }
+        let ticks = chars[token_start..i].iter().filter(|&&c| c == '`').count();
+        if in_code || ticks > 0 {
+            in_code ^= ticks % 2 == 1;
+            continue;
+        }
+        let mut s = token_start;
+        let mut e = i;
+        while s < e && !chars[s].is_alphanumeric() {
+            s += 1;
+        }
+        while e > s && !chars[e - 1].is_alphanumeric() {
+            e -= 1;
+        }
+        let token = &chars[s..e];
+        if !token
+            .iter()
+            .all(|&c| c.is_alphabetic() || c == '\'' || c == '-')
+        {
+            continue;
+        }
+        // Hyphenated words are checked part by part.
+        let mut part_start = s;
+        for (offset, &c) in token.iter().chain(std::iter::once(&'-')).enumerate() {
+            if c != '-' {
+                continue;
+            }
+            let part_end = s + offset;
+            let part = &chars[part_start..part_end];
+            let camel = part.iter().skip(1).any(|c| c.is_uppercase());
+            if part.len() > 1 && !camel {
+                words.push((part_start, part_end));
+            }
+            part_start = part_end + 1;
+        }
+    }
+    words
+}
+
+/// Rows of fenced code blocks, fences included, which hold no prose.
+pub(crate) fn markdown_code_rows(lines: &[String]) -> Vec<bool> {
+    let mut in_fence = false;
+    lines
+        .iter()
+        .map(|line| {
+            let fence = line.trim_start().starts_with("```");
+            let code = in_fence || fence;
+            in_fence ^= fence;
+            code
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::load_themes;
+
+    fn words<'a>(line: &'a str, ranges: &[(usize, usize)]) -> Vec<&'a str> {
+        ranges
+            .iter()
+            .map(|&(start, end)| {
+                let from = line
+                    .char_indices()
+                    .nth(start)
+                    .map_or(line.len(), |(b, _)| b);
+                let to = line.char_indices().nth(end).map_or(line.len(), |(b, _)| b);
+                &line[from..to]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn prose_words_skip_code_like_tokens() {
+        let line = "// Parse the `raw_input` via foo_bar, fooBar or src/main.rs (twice) well-known";
+        let found = prose_words(line, 0, line.chars().count());
+        assert_eq!(
+            words(line, &found),
+            ["Parse", "the", "via", "or", "twice", "well", "known"]
+        );
+        let found = prose_words("don't see http://x.io HTTP v2", 0, 29);
+        assert_eq!(
+            words("don't see http://x.io HTTP v2", &found),
+            ["don't", "see"]
+        );
+    }
+
+    #[test]
+    fn comment_ranges_follow_the_highlighter() {
+        let theme = load_themes().remove(0);
+        let line = "let x = 1; // trailing note";
+        assert_eq!(comment_ranges(line, SyntaxLang::Rust, &theme), [(11, 27)]);
+        assert!(comment_ranges("let s = \"// no\";", SyntaxLang::Rust, &theme).is_empty());
+    }
+
+    #[test]
+    fn markdown_code_rows_cover_fenced_blocks() {
+        let lines: Vec<String> = ["text", "```rust", "let x;", "```", "more"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(markdown_code_rows(&lines), [false, true, true, true, false]);
+    }
+
+    #[cfg(feature = "spellcheck")]
+    #[test]
+    fn misspelled_comment_words_are_flagged_until_added() {
+        let theme = load_themes().remove(0);
+        let dic = "4\nthe\nquick\nfox\nlet\n";
+        let mut checker = SpellChecker::from_hunspell("SET UTF-8\n", dic);
+        assert!(checker.has_dictionary());
+        let line = "let quikc = 1; // the quikc fox";
+        let flagged = checker.misspelled(line, SyntaxLang::Rust, &theme);
+        assert_eq!(words(line, &flagged), ["quikc"]);
+        assert_eq!(flagged, [(22, 27)]);
+
+        assert!(checker.add_word("Quikc"));
+        assert!(
+            checker
+                .misspelled(line, SyntaxLang::Rust, &theme)
+                .is_empty()
+        );
+        assert_eq!(checker.user_words().collect::<Vec<_>>(), ["quikc"]);
+    }
+}
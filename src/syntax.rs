@@ -1,9 +1,11 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 
 use crate::theme::Theme;
+use crate::util::bracket_depth_after;
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum SyntaxLang {
     Plain,
@@ -18,6 +20,78 @@ pub(crate) enum SyntaxLang {
     Json,
     Markdown,
 }
+
+impl SyntaxLang {
+    pub(crate) const ALL: [SyntaxLang; 11] = [
+        SyntaxLang::Plain,
+        SyntaxLang::Rust,
+        SyntaxLang::Python,
+        SyntaxLang::JsTs,
+        SyntaxLang::Go,
+        SyntaxLang::Php,
+        SyntaxLang::Css,
+        SyntaxLang::HtmlXml,
+        SyntaxLang::Shell,
+        SyntaxLang::Json,
+        SyntaxLang::Markdown,
+    ];
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            SyntaxLang::Plain => "Plain Text",
+            SyntaxLang::Rust => "Rust",
+            SyntaxLang::Python => "Python",
+            SyntaxLang::JsTs => "JavaScript / TypeScript",
+            SyntaxLang::Go => "Go",
+            SyntaxLang::Php => "PHP",
+            SyntaxLang::Css => "CSS",
+            SyntaxLang::HtmlXml => "HTML / XML",
+            SyntaxLang::Shell => "Shell",
+            SyntaxLang::Json => "JSON / TOML / YAML",
+            SyntaxLang::Markdown => "Markdown",
+        }
+    }
+
+    /// The key naming this language in config tables such as `formatters`.
+    pub(crate) fn config_key(self) -> &'static str {
+        match self {
+            SyntaxLang::Plain => "plain",
+            SyntaxLang::Rust => "rust",
+            SyntaxLang::Python => "python",
+            SyntaxLang::JsTs => "javascript",
+            SyntaxLang::Go => "go",
+            SyntaxLang::Php => "php",
+            SyntaxLang::Css => "css",
+            SyntaxLang::HtmlXml => "html",
+            SyntaxLang::Shell => "shell",
+            SyntaxLang::Json => "json",
+            SyntaxLang::Markdown => "markdown",
+        }
+    }
+
+    pub(crate) fn from_config_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|lang| lang.config_key() == key)
+    }
+}
+
+/// Language for `path` from the configured `file_associations`, keyed by
+/// exact file name or by extension, falling back to the built-in map.
+/// Entries naming an unknown language are ignored.
+pub(crate) fn syntax_lang_with_associations(
+    path: &Path,
+    associations: &BTreeMap<String, String>,
+) -> SyntaxLang {
+    let name = path.file_name().and_then(|n| n.to_str());
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    name.and_then(|name| associations.get(name))
+        .or_else(|| ext.and_then(|ext| associations.get(&ext)))
+        .and_then(|key| SyntaxLang::from_config_key(key))
+        .unwrap_or_else(|| syntax_lang_for_path(Some(path)))
+}
+
 pub(crate) fn syntax_lang_for_path(path: Option<&Path>) -> SyntaxLang {
     let Some(path) = path else {
         return SyntaxLang::Plain;
@@ -175,14 +249,184 @@ pub(crate) fn comment_start_for_lang(lang: SyntaxLang) -> Option<&'static str> {
     }
 }
 
+/// Rows in runs of two or more lines holding nothing but comments, such as
+/// commented-out code: whole-line comments and lines inside `/* ... */`.
+/// Blank lines break a run.
+pub(crate) fn commented_out_rows(lines: &[String], lang: SyntaxLang) -> Vec<bool> {
+    let line_comment = comment_start_for_lang(lang).filter(|c| *c != "/*");
+    let block_comments = matches!(
+        lang,
+        SyntaxLang::Rust | SyntaxLang::JsTs | SyntaxLang::Go | SyntaxLang::Php | SyntaxLang::Css
+    );
+    let mut in_block = false;
+    let comment_only: Vec<bool> = lines
+        .iter()
+        .map(|line| {
+            let (mut comment, mut code) = (false, false);
+            let mut rest = line.as_str();
+            loop {
+                if in_block {
+                    comment = true;
+                    match rest.find("*/") {
+                        Some(end) => {
+                            in_block = false;
+                            rest = &rest[end + 2..];
+                        }
+                        None => break,
+                    }
+                }
+                rest = rest.trim_start();
+                let Some(ch) = rest.chars().next() else {
+                    break;
+                };
+                if block_comments && rest.starts_with("/*") {
+                    in_block = true;
+                    rest = &rest[2..];
+                    continue;
+                }
+                if line_comment.is_some_and(|c| rest.starts_with(c)) {
+                    comment = true;
+                    break;
+                }
+                code = true;
+                rest = &rest[ch.len_utf8()..];
+                if ch == '"' || ch == '\'' {
+                    // Skip the string so a `/*` inside it opens nothing.
+                    let mut escaped = false;
+                    let end = rest.char_indices().find(|&(_, c)| {
+                        let closes = !escaped && c == ch;
+                        escaped = !escaped && c == '\\';
+                        closes
+                    });
+                    rest = end.map_or("", |(i, _)| &rest[i + ch.len_utf8()..]);
+                }
+            }
+            comment && !code
+        })
+        .collect();
+    (0..comment_only.len())
+        .map(|i| {
+            comment_only[i]
+                && (i > 0 && comment_only[i - 1] || comment_only.get(i + 1) == Some(&true))
+        })
+        .collect()
+}
+
+fn matching_bracket(open: char) -> char {
+    match open {
+        '(' => ')',
+        '[' => ']',
+        _ => '}',
+    }
+}
+
+/// Index of the `}` closing an interpolation whose expression starts at
+/// `chars[n]`, skipping nested braces and quoted strings; `chars.len()` when
+/// the line ends first.
+fn interpolation_end(chars: &[(usize, char)], mut n: usize) -> usize {
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+    while let Some(&(_, c)) = chars.get(n) {
+        match (quote, c) {
+            (Some(_), '\\') => n += 1,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'' | '`') => quote = Some(c),
+            (None, '{') => depth += 1,
+            (None, '}') if depth == 0 => return n,
+            (None, '}') => depth -= 1,
+            (None, _) => {}
+        }
+        n += 1;
+    }
+    chars.len()
+}
+
+/// Spans for the f-string or template literal whose opening quote is at
+/// byte `start`: literal text in the string color, `{...}` (or `${...}`
+/// with `dollar`) interpolations highlighted as code. `{{` and `}}` stay
+/// literal, as do escaped chars unless `raw`. Returns the spans and the byte
+/// offset past the closing quote.
+fn interpolated_string_spans(
+    line: &str,
+    start: usize,
+    dollar: bool,
+    raw: bool,
+    lang: SyntaxLang,
+    theme: &Theme,
+    bracket_colors: &[Color],
+) -> (Vec<Span<'static>>, usize) {
+    let base = Style::default().fg(theme.fg);
+    let string_style = Style::default().fg(theme.syntax_string);
+    let chars: Vec<(usize, char)> = line[start..].char_indices().collect();
+    let byte_at = |n: usize| start + chars.get(n).map_or(line.len() - start, |&(b, _)| b);
+    let quote = chars[0].1;
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut literal_start = 0usize;
+    let mut n = 1usize;
+    while let Some(&(_, c)) = chars.get(n) {
+        let next = chars.get(n + 1).map(|&(_, c)| c);
+        if c == '\\' && !raw {
+            n = (n + 2).min(chars.len());
+            continue;
+        }
+        if c == quote {
+            n += 1;
+            break;
+        }
+        if !dollar && matches!((c, next), ('{', Some('{')) | ('}', Some('}'))) {
+            n += 2;
+            continue;
+        }
+        let open_len = match (c, next) {
+            ('$', Some('{')) if dollar => 2,
+            ('{', _) if !dollar => 1,
+            _ => {
+                n += 1;
+                continue;
+            }
+        };
+        if literal_start < n {
+            let literal = &line[byte_at(literal_start)..byte_at(n)];
+            spans.push(Span::styled(literal.to_string(), string_style));
+        }
+        let expr_start = n + open_len;
+        let expr_end = interpolation_end(&chars, expr_start);
+        spans.push(Span::styled(
+            line[byte_at(n)..byte_at(expr_start)].to_string(),
+            base,
+        ));
+        let expr = &line[byte_at(expr_start)..byte_at(expr_end)];
+        spans.extend(highlight_line(expr, lang, theme, 0, bracket_colors).spans);
+        n = expr_end;
+        if n < chars.len() {
+            spans.push(Span::styled("}".to_string(), base));
+            n += 1;
+        }
+        literal_start = n;
+    }
+    if literal_start < n {
+        let literal = &line[byte_at(literal_start)..byte_at(n)];
+        spans.push(Span::styled(literal.to_string(), string_style));
+    }
+    (spans, byte_at(n))
+}
+
+/// Brackets cycle through `bracket_colors` by depth; an empty slice draws
+/// them in the base color. A closer with no opener, or one closing a
+/// different kind of bracket opened on this line, gets the error style.
 pub(crate) fn highlight_line(
     line: &str,
     lang: SyntaxLang,
     theme: &Theme,
     bracket_depth: u16,
-    bracket_colors: &[Color; 3],
+    bracket_colors: &[Color],
 ) -> Line<'static> {
     let base = Style::default().fg(theme.fg);
+    let bracket_style = |depth: u16| match bracket_colors.len() {
+        0 => base,
+        len => Style::default().fg(bracket_colors[depth as usize % len]),
+    };
     if lang == SyntaxLang::Plain {
         return Line::from(vec![Span::styled(line.to_string(), base)]);
     }
@@ -202,31 +446,32 @@ pub(crate) fn highlight_line(
         }
         return Line::from(vec![Span::styled(line.to_string(), base)]);
     }
+    // Walk chars, not bytes: every slice below starts and ends on an offset
+    // taken from `char_indices`, so multibyte text can never split a char.
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let byte_at = |n: usize| chars.get(n).map_or(line.len(), |&(b, _)| b);
     if lang == SyntaxLang::HtmlXml {
         let mut spans: Vec<Span<'static>> = Vec::new();
-        let mut i = 0usize;
-        let bytes = line.as_bytes();
+        let mut n = 0usize;
         let tag_style = Style::default()
             .fg(theme.syntax_tag)
             .add_modifier(Modifier::BOLD);
         let attr_style = Style::default().fg(theme.syntax_attribute);
-        while i < bytes.len() {
+        while let Some(&(i, ch)) = chars.get(n) {
             if line[i..].starts_with("<!--") {
                 spans.push(Span::styled(line[i..].to_string(), comment_style));
                 break;
             }
-            let ch = line[i..].chars().next().unwrap_or('\0');
             if ch == '<' {
                 let start = i;
-                i += 1;
-                while i < bytes.len() {
-                    let c = line[i..].chars().next().unwrap_or('\0');
-                    i += c.len_utf8();
+                n += 1;
+                while let Some(&(_, c)) = chars.get(n) {
+                    n += 1;
                     if c == '>' {
                         break;
                     }
                 }
-                let tag = &line[start..i];
+                let tag = &line[start..byte_at(n)];
                 let mut parts = tag.split_whitespace();
                 if let Some(head) = parts.next() {
                     spans.push(Span::styled(head.to_string(), tag_style));
@@ -248,27 +493,33 @@ pub(crate) fn highlight_line(
             if ch == '"' || ch == '\'' {
                 let quote = ch;
                 let start = i;
-                i += ch.len_utf8();
-                while i < bytes.len() {
-                    let c = line[i..].chars().next().unwrap_or('\0');
-                    i += c.len_utf8();
+                n += 1;
+                while let Some(&(_, c)) = chars.get(n) {
+                    n += 1;
                     if c == quote {
                         break;
                     }
                 }
-                spans.push(Span::styled(line[start..i].to_string(), string_style));
+                spans.push(Span::styled(
+                    line[start..byte_at(n)].to_string(),
+                    string_style,
+                ));
                 continue;
             }
             spans.push(Span::styled(ch.to_string(), base));
-            i += ch.len_utf8();
+            n += 1;
         }
         return Line::from(spans);
     }
 
-    let bytes = line.as_bytes();
-    let mut i = 0usize;
+    let mut n = 0usize;
     let mut spans: Vec<Span<'static>> = Vec::new();
     let mut bd = bracket_depth;
+    // Openers seen on this line, to catch closers of the wrong kind.
+    let mut open_brackets: Vec<char> = Vec::new();
+    let stray_bracket_style = Style::default()
+        .fg(theme.error)
+        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
     let is_block_comment_lang = matches!(lang, SyntaxLang::Php | SyntaxLang::Css);
 
     // Heuristic for middle lines of multiline block comments in CSS/PHP.
@@ -276,14 +527,14 @@ pub(crate) fn highlight_line(
     if is_block_comment_lang && (trimmed.starts_with('*') || trimmed.starts_with("*/")) {
         return Line::from(vec![Span::styled(line.to_string(), comment_style)]);
     }
-    while i < bytes.len() {
+    while let Some(&(i, ch)) = chars.get(n) {
         if let Some(comment) = comment_start_for_lang(lang) {
             if comment == "/*" && line[i..].starts_with("/*") {
                 // Style only the block segment when it closes on this line.
                 if let Some(close_rel) = line[i + 2..].find("*/") {
                     let end = i + 2 + close_rel + 2;
                     spans.push(Span::styled(line[i..end].to_string(), comment_style));
-                    i = end;
+                    n = chars.partition_point(|&(b, _)| b < end);
                     continue;
                 }
                 spans.push(Span::styled(line[i..].to_string(), comment_style));
@@ -293,52 +544,75 @@ pub(crate) fn highlight_line(
                 break;
             }
         }
-        let ch = line[i..].chars().next().unwrap_or('\0');
+        if ch == '`' && lang == SyntaxLang::JsTs {
+            let (string_spans, end) =
+                interpolated_string_spans(line, i, true, false, lang, theme, bracket_colors);
+            spans.extend(string_spans);
+            n = chars.partition_point(|&(b, _)| b < end);
+            continue;
+        }
         if ch == '"' || ch == '\'' {
             let quote = ch;
             let start = i;
-            i += ch.len_utf8();
-            while i < bytes.len() {
-                let c = line[i..].chars().next().unwrap_or('\0');
-                i += c.len_utf8();
-                if c == '\\' && i < bytes.len() {
-                    let escaped = line[i..].chars().next().unwrap_or('\0');
-                    i += escaped.len_utf8();
+            n += 1;
+            while let Some(&(_, c)) = chars.get(n) {
+                n += 1;
+                if c == '\\' && n < chars.len() {
+                    n += 1;
                     continue;
                 }
                 if c == quote {
                     break;
                 }
             }
-            spans.push(Span::styled(line[start..i].to_string(), string_style));
+            spans.push(Span::styled(
+                line[start..byte_at(n)].to_string(),
+                string_style,
+            ));
             continue;
         }
         if ch.is_ascii_digit() {
             let start = i;
-            i += ch.len_utf8();
-            while i < bytes.len() {
-                let c = line[i..].chars().next().unwrap_or('\0');
+            n += 1;
+            while let Some(&(_, c)) = chars.get(n) {
                 if c.is_ascii_digit() || c == '_' || c == '.' {
-                    i += c.len_utf8();
+                    n += 1;
                 } else {
                     break;
                 }
             }
-            spans.push(Span::styled(line[start..i].to_string(), number_style));
+            spans.push(Span::styled(
+                line[start..byte_at(n)].to_string(),
+                number_style,
+            ));
             continue;
         }
         if is_ident_char(ch) {
             let start = i;
-            i += ch.len_utf8();
-            while i < bytes.len() {
-                let c = line[i..].chars().next().unwrap_or('\0');
-                if is_ident_char(c) {
-                    i += c.len_utf8();
-                } else {
-                    break;
-                }
+            n += 1;
+            while chars.get(n).is_some_and(|&(_, c)| is_ident_char(c)) {
+                n += 1;
+            }
+            let token = &line[start..byte_at(n)];
+            let quoted = chars.get(n).is_some_and(|&(_, c)| c == '"' || c == '\'');
+            let prefix = token.to_ascii_lowercase();
+            if lang == SyntaxLang::Python && quoted && matches!(prefix.as_str(), "f" | "rf" | "fr")
+            {
+                spans.push(Span::styled(token.to_string(), string_style));
+                let raw = prefix.contains('r');
+                let (string_spans, end) = interpolated_string_spans(
+                    line,
+                    byte_at(n),
+                    false,
+                    raw,
+                    lang,
+                    theme,
+                    bracket_colors,
+                );
+                spans.extend(string_spans);
+                n = chars.partition_point(|&(b, _)| b < end);
+                continue;
             }
-            let token = &line[start..i];
             if keywords_for_lang(lang).contains(&token) {
                 spans.push(Span::styled(token.to_string(), keyword_style));
             } else {
@@ -347,20 +621,72 @@ pub(crate) fn highlight_line(
             continue;
         }
         if ch == '{' || ch == '(' || ch == '[' {
-            let color = bracket_colors[(bd % 3) as usize];
-            spans.push(Span::styled(ch.to_string(), Style::default().fg(color)));
+            spans.push(Span::styled(ch.to_string(), bracket_style(bd)));
+            open_brackets.push(ch);
             bd = bd.saturating_add(1);
         } else if ch == '}' || ch == ')' || ch == ']' {
+            let stray = match open_brackets.pop() {
+                Some(open) => matching_bracket(open) != ch,
+                None => bd == 0,
+            };
             bd = bd.saturating_sub(1);
-            let color = bracket_colors[(bd % 3) as usize];
-            spans.push(Span::styled(ch.to_string(), Style::default().fg(color)));
+            let style = if stray {
+                stray_bracket_style
+            } else {
+                bracket_style(bd)
+            };
+            spans.push(Span::styled(ch.to_string(), style));
         } else {
             spans.push(Span::styled(ch.to_string(), base));
         }
-        i += ch.len_utf8();
+        n += 1;
     }
     Line::from(spans)
 }
+
+/// Code shown beside the theme browser list, one sample per language.
+const THEME_PREVIEW_SAMPLES: [(SyntaxLang, &[&str]); 3] = [
+    (
+        SyntaxLang::Rust,
+        &[
+            "// Greet someone by name.",
+            "fn greet(name: &str) -> String {",
+            "    let count = 42;",
+            "    format!(\"Hello, {name} x{count}\")",
+            "}",
+        ],
+    ),
+    (
+        SyntaxLang::Markdown,
+        &["# Release notes", "Faster startup and fewer redraws."],
+    ),
+    (
+        SyntaxLang::HtmlXml,
+        &[
+            "<!-- navigation -->",
+            "<a href=\"/docs\" class=\"link\">Docs</a>",
+        ],
+    ),
+];
+
+/// The theme preview samples highlighted with `theme`, each under a muted
+/// language label.
+pub(crate) fn theme_preview_lines(theme: &Theme, bracket_colors: &[Color]) -> Vec<Line<'static>> {
+    let label_style = Style::default().fg(theme.fg_muted);
+    let mut lines = Vec::new();
+    for (lang, sample) in THEME_PREVIEW_SAMPLES {
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(Span::styled(lang.label(), label_style)));
+        let mut depth = 0;
+        for line in sample {
+            lines.push(highlight_line(line, lang, theme, depth, bracket_colors));
+            depth = bracket_depth_after(line, lang, depth);
+        }
+    }
+    lines
+}
 #[cfg(test)]
 mod syntax_and_lang_tests {
     use super::*;
@@ -391,9 +717,11 @@ mod syntax_and_lang_tests {
             syntax_number: Color::Rgb(181, 206, 168),
             syntax_tag: Color::Rgb(86, 156, 214),
             syntax_attribute: Color::Rgb(78, 201, 176),
-            bracket_1: Color::Rgb(210, 168, 75),
-            bracket_2: Color::Rgb(176, 82, 204),
-            bracket_3: Color::Rgb(0, 175, 215),
+            brackets: BC.to_vec(),
+            indent_tints: Vec::new(),
+            diff_added: Color::Rgb(143, 181, 115),
+            diff_removed: Color::Rgb(224, 108, 117),
+            error: Color::Rgb(224, 108, 117),
         }
     }
 
@@ -576,6 +904,25 @@ mod syntax_and_lang_tests {
         );
     }
 
+    #[test]
+    fn test_file_associations_override_builtin_detection() {
+        let associations = BTreeMap::from([
+            ("conf".to_string(), "shell".to_string()),
+            ("Jenkinsfile".to_string(), "javascript".to_string()),
+            ("rs".to_string(), "no-such-language".to_string()),
+        ]);
+        let detect = |name: &str| syntax_lang_with_associations(Path::new(name), &associations);
+        assert_eq!(detect("app.conf"), SyntaxLang::Shell);
+        assert_eq!(detect("APP.CONF"), SyntaxLang::Shell);
+        assert_eq!(detect("ci/Jenkinsfile"), SyntaxLang::JsTs);
+        assert_eq!(detect("main.rs"), SyntaxLang::Rust);
+        assert_eq!(detect("notes.md"), SyntaxLang::Markdown);
+        assert_eq!(
+            syntax_lang_with_associations(Path::new("app.conf"), &BTreeMap::new()),
+            SyntaxLang::Plain
+        );
+    }
+
     #[test]
     fn test_is_ident_char_alphanumeric() {
         assert!(is_ident_char('a'));
@@ -857,6 +1204,101 @@ mod syntax_and_lang_tests {
         );
     }
 
+    fn rows(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn test_commented_out_rows_cover_comment_runs_and_blocks() {
+        let lines = rows(&[
+            "// fn old() {",
+            "//     gone();",
+            "fn new() {} // trailing",
+            "// lone note",
+            "let s = \"/*\";",
+            "/* start",
+            "   middle */",
+            "x();",
+        ]);
+        assert_eq!(
+            commented_out_rows(&lines, SyntaxLang::Rust),
+            [true, true, false, false, false, true, true, false]
+        );
+
+        let python = rows(&["# a", "# b", "", "# c", "print()"]);
+        assert_eq!(
+            commented_out_rows(&python, SyntaxLang::Python),
+            [true, true, false, false, false]
+        );
+        assert_eq!(
+            commented_out_rows(&rows(&["// a", "// b"]), SyntaxLang::Plain),
+            [false, false]
+        );
+    }
+
+    /// `(text, color)` for each span of `line` highlighted as `lang`.
+    fn span_colors(line: &str, lang: SyntaxLang, theme: &Theme) -> Vec<(String, Option<Color>)> {
+        highlight_line(line, lang, theme, 0, &[])
+            .spans
+            .iter()
+            .map(|s| (s.content.to_string(), s.style.fg))
+            .collect()
+    }
+
+    #[test]
+    fn test_python_fstring_interpolation_is_highlighted_as_code() {
+        let theme = create_test_theme();
+        let (string, code) = (Some(theme.syntax_string), Some(theme.fg));
+        let expected: Vec<(String, Option<Color>)> = [
+            ("f", string),
+            ("\"a", string),
+            ("{", code),
+            ("b", code),
+            ("}", code),
+            ("c\"", string),
+        ]
+        .into_iter()
+        .map(|(text, fg)| (text.to_string(), fg))
+        .collect();
+        assert_eq!(
+            span_colors(r#"f"a{b}c""#, SyntaxLang::Python, &theme),
+            expected
+        );
+
+        // `{{` stays literal; nested quotes and brackets stay inside the
+        // interpolation.
+        let spans = span_colors(r#"f"{{x}} {d['k']}" + s"#, SyntaxLang::Python, &theme);
+        assert_eq!(spans[1], ("\"{{x}} ".to_string(), string));
+        assert!(spans.contains(&("'k'".to_string(), string)));
+        let close = spans
+            .iter()
+            .position(|s| s.0 == "}")
+            .expect("closing brace");
+        assert_eq!(spans[close + 1], ("\"".to_string(), string));
+        assert_eq!(spans.last(), Some(&("s".to_string(), code)));
+
+        // A plain string keeps its braces.
+        let plain = span_colors(r#""a{b}c""#, SyntaxLang::Python, &theme);
+        assert_eq!(plain, vec![(r#""a{b}c""#.to_string(), string)]);
+    }
+
+    #[test]
+    fn test_js_template_literal_interpolation_is_highlighted_as_code() {
+        let theme = create_test_theme();
+        let (string, code) = (Some(theme.syntax_string), Some(theme.fg));
+        let spans = span_colors("`hi ${n + 1} \\${x}`;", SyntaxLang::JsTs, &theme);
+        assert_eq!(spans[0], ("`hi ".to_string(), string));
+        assert_eq!(spans[1], ("${".to_string(), code));
+        assert_eq!(spans[2], ("n".to_string(), code));
+        assert!(spans.contains(&("1".to_string(), Some(theme.syntax_number))));
+        let close = spans
+            .iter()
+            .position(|s| s.0 == "}")
+            .expect("closing brace");
+        assert_eq!(spans[close + 1], (" \\${x}`".to_string(), string));
+        assert_eq!(spans.last(), Some(&(";".to_string(), code)));
+    }
+
     #[test]
     fn test_highlight_line_markdown() {
         let theme = create_test_theme();
@@ -893,6 +1335,75 @@ mod syntax_and_lang_tests {
         );
     }
 
+    /// Non-whitespace text of the spans; HTML tags re-join their parts with
+    /// single spaces, so whitespace is not kept exactly.
+    fn span_text(line: &Line) -> String {
+        line.spans
+            .iter()
+            .flat_map(|s| s.content.chars())
+            .filter(|c| !c.is_whitespace())
+            .collect()
+    }
+
+    fn strip_ws(line: &str) -> String {
+        line.chars().filter(|c| !c.is_whitespace()).collect()
+    }
+
+    #[test]
+    fn test_highlight_line_multibyte_edge_cases() {
+        let theme = create_test_theme();
+        let cases = [
+            "<div é漢字",
+            "<a title=\"日本\" 🦀",
+            "x = \"caf\u{e9}",
+            "'🦀🦀",
+            "\"\\é",
+            "s = \"e\u{301}\" // ñ",
+            "1.5é _ñ",
+            "/* 🦀 */ é /* ü",
+            "<!-- 🦀",
+            "é<",
+        ];
+        for lang in SyntaxLang::ALL {
+            for line in cases {
+                let result = highlight_line(line, lang, &theme, 0, &BC);
+                assert_eq!(span_text(&result), strip_ws(line), "{lang:?}: {line:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_highlight_line_random_utf8_never_panics() {
+        let theme = create_test_theme();
+        // Syntax-significant ASCII mixed with 2-, 3- and 4-byte chars,
+        // combining marks and a ZWJ emoji sequence.
+        let alphabet: Vec<char> = concat!(
+            "<>\"'\\/*#{}()[]=-!_.09 aZ\t",
+            "\u{e9}\u{301}\u{308}\u{20ac}\u{6f22}\u{1f980}\u{1f468}\u{200d}\u{fe0f}",
+        )
+        .chars()
+        .collect();
+        // xorshift64 keeps the run reproducible without a rand dependency.
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..500 {
+            let len = (next() % 24) as usize;
+            let line: String = (0..len)
+                .map(|_| alphabet[(next() % alphabet.len() as u64) as usize])
+                .collect();
+            for lang in SyntaxLang::ALL {
+                let depth = (next() % 4) as u16;
+                let result = highlight_line(&line, lang, &theme, depth, &BC);
+                assert_eq!(span_text(&result), strip_ws(&line), "{lang:?}: {line:?}");
+            }
+        }
+    }
+
     #[test]
     fn test_syntax_lang_multiple_dots() {
         assert_eq!(
@@ -929,9 +1440,9 @@ mod syntax_and_lang_tests {
     #[test]
     fn test_bracket_pair_colorization() {
         let theme = create_test_theme();
-        let bc = [theme.bracket_1, theme.bracket_2, theme.bracket_3];
+        let bc = &theme.brackets;
         // "{ ( ) }" — { at depth 0, ( at depth 1, ) at depth 1, } at depth 0
-        let result = highlight_line("{ ( ) }", SyntaxLang::Rust, &theme, 0, &bc);
+        let result = highlight_line("{ ( ) }", SyntaxLang::Rust, &theme, 0, bc);
         let bracket_spans: Vec<_> = result
             .spans
             .iter()
@@ -945,7 +1456,7 @@ mod syntax_and_lang_tests {
             open_brace, close_brace,
             "matching brackets should have same color"
         );
-        assert_eq!(open_brace, Some(theme.bracket_1));
+        assert_eq!(open_brace, Some(theme.brackets[0]));
         // ( and ) should both be depth 1 → bracket_2 color
         let open_paren = bracket_spans[1].style.fg;
         let close_paren = bracket_spans[2].style.fg;
@@ -953,11 +1464,112 @@ mod syntax_and_lang_tests {
             open_paren, close_paren,
             "matching brackets should have same color"
         );
-        assert_eq!(open_paren, Some(theme.bracket_2));
+        assert_eq!(open_paren, Some(theme.brackets[1]));
         // Different depths should differ
         assert_ne!(
             open_brace, open_paren,
             "different depth brackets should have different colors"
         );
     }
+
+    #[test]
+    fn test_bracket_colorization_disabled_uses_base_color() {
+        let theme = create_test_theme();
+        let result = highlight_line("f(a[0]) {}", SyntaxLang::Rust, &theme, 2, &[]);
+        let brackets: Vec<_> = result
+            .spans
+            .iter()
+            .filter(|s| matches!(s.content.as_ref(), "(" | ")" | "[" | "]" | "{" | "}"))
+            .collect();
+        assert_eq!(brackets.len(), 6);
+        assert!(brackets.iter().all(|s| s.style.fg == Some(theme.fg)));
+    }
+
+    #[test]
+    fn test_bracket_palette_cycles_by_its_length() {
+        let theme = create_test_theme();
+        let palette: Vec<Color> = (1..=5).map(Color::Indexed).collect();
+        // Opening brackets at depths 4, 5 and 6 wrap back to the palette start.
+        let result = highlight_line("(((", SyntaxLang::Rust, &theme, 4, &palette);
+        let colors: Vec<_> = result.spans.iter().map(|s| s.style.fg).collect();
+        assert_eq!(
+            colors,
+            vec![
+                Some(Color::Indexed(5)),
+                Some(Color::Indexed(1)),
+                Some(Color::Indexed(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stray_closing_bracket_is_error_styled() {
+        let theme = create_test_theme();
+        let result = highlight_line("a)", SyntaxLang::Rust, &theme, 0, &BC);
+        let close = result.spans.iter().find(|s| s.content == ")").expect(")");
+        assert_eq!(close.style.fg, Some(theme.error));
+
+        // Closing the wrong kind of bracket opened on the same line.
+        let result = highlight_line("(]", SyntaxLang::Rust, &theme, 0, &BC);
+        let close = result.spans.iter().find(|s| s.content == "]").expect("]");
+        assert_eq!(close.style.fg, Some(theme.error));
+
+        // An opener carried over from an earlier line is not stray.
+        let result = highlight_line("})", SyntaxLang::Rust, &theme, 1, &BC);
+        assert_eq!(result.spans[0].style.fg, Some(BC[0]));
+        assert_eq!(result.spans[1].style.fg, Some(theme.error));
+    }
+
+    #[test]
+    fn test_balanced_brackets_are_not_error_styled() {
+        let theme = create_test_theme();
+        let result = highlight_line("f(a[0], {b}) }", SyntaxLang::Rust, &theme, 1, &BC);
+        assert!(
+            result
+                .spans
+                .iter()
+                .filter(|s| matches!(s.content.as_ref(), "(" | ")" | "[" | "]" | "{" | "}"))
+                .all(|s| s.style.fg != Some(theme.error))
+        );
+    }
+
+    #[test]
+    fn theme_preview_uses_the_theme_colors_for_each_token() {
+        let theme = create_test_theme();
+        let lines = theme_preview_lines(&theme, &BC);
+        let spans: Vec<&Span> = lines.iter().flat_map(|l| l.spans.iter()).collect();
+        let fg_of = |text: &str| {
+            spans
+                .iter()
+                .find(|s| s.content == text)
+                .unwrap_or_else(|| panic!("no span {text:?}"))
+                .style
+                .fg
+        };
+
+        assert_eq!(fg_of("Rust"), Some(theme.fg_muted));
+        assert_eq!(fg_of("// Greet someone by name."), Some(theme.comment));
+        assert_eq!(fg_of("fn"), Some(theme.accent));
+        assert_eq!(fg_of("42"), Some(theme.syntax_number));
+        assert_eq!(
+            fg_of("\"Hello, {name} x{count}\""),
+            Some(theme.syntax_string)
+        );
+        assert_eq!(fg_of("{"), Some(BC[0]));
+        assert_eq!(fg_of("# Release notes"), Some(theme.syntax_tag));
+        assert_eq!(fg_of("<a"), Some(theme.syntax_tag));
+        assert_eq!(fg_of("<!-- navigation -->"), Some(theme.comment));
+
+        // Brackets nest across lines: `format!(` sits inside the fn body.
+        let format_line = lines
+            .iter()
+            .find(|l| l.spans.iter().any(|s| s.content == "format"))
+            .expect("format line");
+        let paren = format_line
+            .spans
+            .iter()
+            .find(|s| s.content == "(")
+            .expect("paren");
+        assert_eq!(paren.style.fg, Some(BC[1]));
+    }
 }
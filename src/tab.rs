@@ -1,9 +1,13 @@
 use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
 
 use ratatui_textarea::TextArea;
 
 use crate::lsp_client::LspDiagnostic;
+use crate::syntax::{SyntaxLang, is_ident_char};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub(crate) enum GitLineStatus {
@@ -14,6 +18,37 @@ pub(crate) enum GitLineStatus {
     Deleted,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DiffLineKind {
+    Hunk,
+    Context,
+    Added,
+    Removed,
+}
+
+/// One row of a line-level unified diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DiffLine {
+    pub(crate) kind: DiffLineKind,
+    pub(crate) text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DiffRowKind {
+    Same,
+    Changed,
+    Removed,
+    Added,
+}
+
+/// One aligned row of a side-by-side diff; a `None` side is a gap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DiffRow {
+    pub(crate) kind: DiffRowKind,
+    pub(crate) left: Option<String>,
+    pub(crate) right: Option<String>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum GitFileStatus {
     Modified,
@@ -39,6 +74,45 @@ pub(crate) struct ProjectSearchHit {
     pub(crate) path: PathBuf,
     pub(crate) line: usize,
     pub(crate) preview: String,
+    pub(crate) context_before: Vec<String>,
+    pub(crate) context_after: Vec<String>,
+}
+
+/// One rendered row of the grouped project search results list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SearchResultRow {
+    Header {
+        path: PathBuf,
+        count: usize,
+        first_hit: usize,
+    },
+    Context {
+        hit: usize,
+        line: usize,
+        text: String,
+    },
+    Hit(usize),
+}
+
+/// A diagnostic together with the file it was reported for.
+#[derive(Debug, Clone)]
+pub(crate) struct ProjectDiagnostic {
+    pub(crate) path: PathBuf,
+    pub(crate) diagnostic: LspDiagnostic,
+}
+
+/// One rendered row of the grouped project diagnostics panel; `first` and
+/// `Item` index into the `ProjectDiagnostic` list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DiagnosticPanelRow {
+    Header {
+        path: PathBuf,
+        errors: usize,
+        warnings: usize,
+        others: usize,
+        first: usize,
+    },
+    Item(usize),
 }
 
 #[derive(Debug, Clone)]
@@ -47,26 +121,315 @@ pub(crate) struct FoldRange {
     pub(crate) end_line: usize,
 }
 
+/// A named declaration or heading in a file, for the Go to Symbol picker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FileSymbol {
+    pub(crate) line: usize,
+    pub(crate) label: String,
+}
+
+/// How a tab indents: one hard tab, or `width` spaces, per level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IndentSettings {
+    pub(crate) hard_tabs: bool,
+    pub(crate) width: u8,
+    /// Columns a hard tab is drawn across; 0 follows `width`.
+    pub(crate) render_tab_width: u8,
+}
+
+impl Default for IndentSettings {
+    fn default() -> Self {
+        Self {
+            hard_tabs: false,
+            width: 4,
+            render_tab_width: 0,
+        }
+    }
+}
+
+impl IndentSettings {
+    /// Distance between tab stops on screen.
+    pub(crate) fn tab_stop(self) -> usize {
+        match self.render_tab_width {
+            0 => self.width as usize,
+            n => n as usize,
+        }
+    }
+
+    /// Text inserted for one indentation level.
+    pub(crate) fn unit(self) -> String {
+        if self.hard_tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(self.width as usize)
+        }
+    }
+
+    pub(crate) fn label(self) -> String {
+        if self.hard_tabs {
+            format!("tabs ({})", self.width)
+        } else {
+            format!("{} spaces", self.width)
+        }
+    }
+
+    /// Make the Tab key of `editor` insert this indentation.
+    pub(crate) fn apply_to(self, editor: &mut TextArea<'_>) {
+        editor.set_tab_length(self.width);
+        editor.set_hard_tab_indent(self.hard_tabs);
+    }
+}
+
+/// Consecutive textarea history steps that undo and redo as one unit.
+///
+/// The textarea keeps its own history, so a group is tied to the buffer by
+/// fingerprint: the text the group left on the undo stack, the text before
+/// it on the redo stack. It only applies while the buffer still matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct UndoGroup {
+    pub(crate) steps: usize,
+    pub(crate) fingerprint: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TypingKind {
+    Insert,
+    Delete,
+}
+
+/// Keystrokes that the next typed character (or deletion) of the same kind
+/// may join, so a word undoes in one step. It becomes an undo group when it
+/// ends rather than on every keystroke.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TypingRun {
+    pub(crate) kind: TypingKind,
+    pub(crate) steps: usize,
+    /// Cursor right after the last keystroke of the run.
+    pub(crate) cursor: (usize, usize),
+    pub(crate) last_char: char,
+    pub(crate) last_at: Instant,
+}
+
+/// Longest pause between keystrokes that still extends a typing run.
+const TYPING_RUN_WINDOW: Duration = Duration::from_secs(1);
+
+/// A cursor position in a file, kept in the back/forward jump history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NavLocation {
+    pub(crate) path: PathBuf,
+    pub(crate) cursor: (usize, usize),
+}
+
+/// A recently closed tab, kept so it can be reopened where it was left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ClosedTab {
+    pub(crate) path: PathBuf,
+    pub(crate) cursor: (usize, usize),
+    pub(crate) scroll_row: usize,
+    pub(crate) scroll_col: usize,
+}
+
 pub(crate) struct Tab {
     pub(crate) path: PathBuf,
+    /// Scratch buffer not backed by a file yet; `path` is only its
+    /// `untitled-N` display name until the first save picks a location.
+    pub(crate) untitled: bool,
+    /// Language chosen with "Set Language", overriding extension detection.
+    pub(crate) lang_override: Option<SyntaxLang>,
+    /// Language detected from the path and `file_associations` when the tab
+    /// was opened or last moved.
+    pub(crate) detected_lang: SyntaxLang,
+    pub(crate) indent: IndentSettings,
     pub(crate) is_preview: bool,
+    /// Edits and saves are refused; toggled with "Toggle Read-Only".
+    pub(crate) read_only: bool,
+    /// Never closed to make room under `max_open_tabs`.
+    pub(crate) pinned: bool,
+    /// Binary file forced open as text, which stays read-only.
+    pub(crate) binary_text: bool,
     pub(crate) editor: TextArea<'static>,
+    /// Characters in the buffer, newlines excluded. Recounted on content
+    /// changes so the status bar doesn't walk the buffer every frame.
+    pub(crate) char_count: usize,
     pub(crate) dirty: bool,
     pub(crate) open_disk_snapshot: Option<String>,
+    /// Modification time of the file when it was last read or written.
+    pub(crate) disk_mtime: Option<SystemTime>,
+    /// First visible row and display column, kept per tab so switching
+    /// back restores the view.
     pub(crate) editor_scroll_row: usize,
     pub(crate) editor_scroll_col: usize,
     pub(crate) fold_ranges: Vec<FoldRange>,
     pub(crate) bracket_depths: Vec<u16>,
     pub(crate) folded_starts: HashSet<usize>,
+    /// Lines as of the last fold update, to move `folded_starts` along with
+    /// the next edit; empty while nothing is folded.
+    pub(crate) fold_lines: Vec<String>,
     pub(crate) visible_rows_map: Vec<usize>,
     pub(crate) visible_row_starts: Vec<usize>,
     pub(crate) visible_row_ends: Vec<usize>,
     pub(crate) open_doc_uri: Option<String>,
     pub(crate) open_doc_version: i32,
+    /// Document text as the language server last saw it; incremental
+    /// changes are computed against it.
+    pub(crate) lsp_synced_text: String,
     pub(crate) diagnostics: Vec<LspDiagnostic>,
+    /// Latest published diagnostics and when they arrived, held back until
+    /// the display delay passes so mid-edit reports don't flicker.
+    pub(crate) pending_diagnostics: Option<(Instant, Vec<LspDiagnostic>)>,
     pub(crate) conflict_prompt_open: bool,
     pub(crate) conflict_disk_text: Option<String>,
     pub(crate) recovery_prompt_open: bool,
     pub(crate) recovery_text: Option<String>,
+    /// Diff of the buffer against `conflict_disk_text` or `recovery_text`,
+    /// computed once when that prompt opens.
+    pub(crate) prompt_diff: Vec<DiffLine>,
     pub(crate) git_line_status: Vec<GitLineStatus>,
+    /// Time of the most recent edit, used to schedule idle auto-save.
+    pub(crate) last_edit: Option<Instant>,
+    /// Auto-save failed for this tab; retried only after its next edit.
+    pub(crate) auto_save_failed: bool,
+    /// Cursor position right after the most recent edit.
+    pub(crate) last_edit_pos: Option<(usize, usize)>,
+    pub(crate) undo_groups: Vec<UndoGroup>,
+    pub(crate) redo_groups: Vec<UndoGroup>,
+    pub(crate) typing_run: Option<TypingRun>,
+}
+
+impl Tab {
+    /// Language used for highlighting, folding and comments.
+    pub(crate) fn syntax_lang(&self) -> SyntaxLang {
+        self.lang_override.unwrap_or(self.detected_lang)
+    }
+
+    pub(crate) fn recount_chars(&mut self) {
+        self.char_count = self.editor.lines().iter().map(|l| l.chars().count()).sum();
+    }
+
+    pub(crate) fn buffer_fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.editor.lines().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Make the last `steps` edits undo and redo together.
+    pub(crate) fn group_last_edits(&mut self, steps: usize) {
+        self.typing_run = None;
+        self.push_undo_group(steps);
+        self.redo_groups.clear();
+    }
+
+    fn push_undo_group(&mut self, steps: usize) {
+        // The textarea drops edits past its history limit, so a longer
+        // group could never be undone as a whole.
+        let steps = steps.min(self.editor.max_histories());
+        if steps < 2 {
+            return;
+        }
+        let fingerprint = self.buffer_fingerprint();
+        self.undo_groups.push(UndoGroup { steps, fingerprint });
+    }
+
+    pub(crate) fn clear_undo_groups(&mut self) {
+        self.undo_groups.clear();
+        self.redo_groups.clear();
+        self.typing_run = None;
+    }
+
+    /// Whether typing or deleting `ch` now would extend the current run: it
+    /// is the same kind, the cursor is where the run left it, it comes
+    /// quickly enough and it does not start a new word.
+    pub(crate) fn joins_typing_run(&self, kind: TypingKind, ch: char) -> bool {
+        self.typing_run.is_some_and(|run| {
+            run.kind == kind
+                && run.cursor == self.editor.cursor()
+                && run.last_at.elapsed() < TYPING_RUN_WINDOW
+                && (is_ident_char(run.last_char) || !is_ident_char(ch))
+        })
+    }
+
+    /// Record a keystroke that just typed or deleted `ch`. It extends the
+    /// current run when `joins` (from `joins_typing_run` before the edit)
+    /// says so and starts a new one otherwise.
+    pub(crate) fn record_typing(&mut self, kind: TypingKind, ch: char, joins: bool) {
+        let steps = match self.typing_run {
+            Some(run) if joins => run.steps + 1,
+            _ => 1,
+        };
+        self.typing_run = Some(TypingRun {
+            kind,
+            steps,
+            cursor: self.editor.cursor(),
+            last_char: ch,
+            last_at: Instant::now(),
+        });
+        self.redo_groups.clear();
+    }
+
+    /// Close the current typing run, grouping its keystrokes for undo. Call
+    /// it before any other edit so the group matches the text the run left.
+    pub(crate) fn end_typing_run(&mut self) {
+        if let Some(run) = self.typing_run.take() {
+            self.push_undo_group(run.steps);
+        }
+    }
+
+    /// Forget a run that an edit other than its own keystrokes has moved
+    /// the cursor away from; its steps are no longer the latest history.
+    pub(crate) fn drop_stale_typing_run(&mut self) {
+        if self
+            .typing_run
+            .is_some_and(|run| run.cursor != self.editor.cursor())
+        {
+            self.typing_run = None;
+        }
+    }
+
+    /// Undo one unit: a whole group when the buffer is where the latest
+    /// group left it, otherwise a single textarea step.
+    pub(crate) fn undo(&mut self) -> bool {
+        self.end_typing_run();
+        let now = self.buffer_fingerprint();
+        let Some(group) = self
+            .undo_groups
+            .last()
+            .copied()
+            .filter(|g| g.fingerprint == now)
+        else {
+            return self.editor.undo();
+        };
+        self.undo_groups.pop();
+        let mut undone = false;
+        for _ in 0..group.steps {
+            undone |= self.editor.undo();
+        }
+        self.redo_groups.push(UndoGroup {
+            fingerprint: self.buffer_fingerprint(),
+            ..group
+        });
+        undone
+    }
+
+    pub(crate) fn redo(&mut self) -> bool {
+        self.end_typing_run();
+        let now = self.buffer_fingerprint();
+        let Some(group) = self
+            .redo_groups
+            .last()
+            .copied()
+            .filter(|g| g.fingerprint == now)
+        else {
+            return self.editor.redo();
+        };
+        self.redo_groups.pop();
+        let mut redone = false;
+        for _ in 0..group.steps {
+            redone |= self.editor.redo();
+        }
+        self.undo_groups.push(UndoGroup {
+            fingerprint: self.buffer_fingerprint(),
+            ..group
+        });
+        redone
+    }
 }
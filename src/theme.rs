@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use std::sync::OnceLock;
 
 use include_dir::{Dir, include_dir};
-use ratatui::style::Color;
+use ratatui::style::{Color, Modifier, Style};
 use serde::Deserialize;
 
 fn supports_true_color() -> bool {
@@ -123,9 +123,26 @@ pub(crate) struct Theme {
     pub(crate) syntax_number: Color,
     pub(crate) syntax_tag: Color,
     pub(crate) syntax_attribute: Color,
-    pub(crate) bracket_1: Color,
-    pub(crate) bracket_2: Color,
-    pub(crate) bracket_3: Color,
+    /// Bracket pair colors, cycled by nesting depth.
+    pub(crate) brackets: Vec<Color>,
+    /// Indentation background tints, cycled by indent level.
+    pub(crate) indent_tints: Vec<Color>,
+    pub(crate) diff_added: Color,
+    pub(crate) diff_removed: Color,
+    pub(crate) error: Color,
+}
+
+impl Theme {
+    /// Style for lines that are commented out: the comment color faded
+    /// halfway into the background, or dimmed when the colors can't mix.
+    pub(crate) fn commented_out_style(&self) -> Style {
+        match blend(self.comment, self.bg, 50) {
+            Some(color) => Style::default().fg(color),
+            None => Style::default()
+                .fg(self.comment)
+                .add_modifier(Modifier::DIM),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -157,6 +174,12 @@ pub(crate) struct ThemeColors {
     pub(crate) purple: Option<String>,
     #[serde(default)]
     pub(crate) cyan: Option<String>,
+    #[serde(default)]
+    pub(crate) green: Option<String>,
+    #[serde(default)]
+    pub(crate) red: Option<String>,
+    #[serde(default)]
+    pub(crate) error: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -171,6 +194,13 @@ pub(crate) struct ThemeSyntaxColors {
     pub(crate) tag: Option<String>,
     #[serde(default)]
     pub(crate) attribute: Option<String>,
+    /// Bracket pair colors by depth; replaces yellow/purple/cyan when set.
+    #[serde(default)]
+    pub(crate) brackets: Option<Vec<String>>,
+    /// Indent level background tints; defaults to the bracket colors faded
+    /// into the background.
+    #[serde(default)]
+    pub(crate) indents: Option<Vec<String>>,
 }
 
 pub(crate) fn color_from_hex(input: &str, fallback: Color) -> Color {
@@ -188,15 +218,59 @@ pub(crate) fn color_from_hex(input: &str, fallback: Color) -> Color {
     fallback
 }
 
+/// `color` faded into `bg`, keeping `percent` of it. Only RGB colors mix.
+fn blend(color: Color, bg: Color, percent: u16) -> Option<Color> {
+    let (Color::Rgb(r, g, b), Color::Rgb(bg_r, bg_g, bg_b)) = (color, bg) else {
+        return None;
+    };
+    let mix = |c: u8, base: u8| ((c as u16 * percent + base as u16 * (100 - percent)) / 100) as u8;
+    Some(Color::Rgb(mix(r, bg_r), mix(g, bg_g), mix(b, bg_b)))
+}
+
 pub(crate) fn theme_from_file(tf: ThemeFile) -> Theme {
     let syn = tf.syntax.as_ref();
     let border_color = color_from_hex(&tf.colors.border, make_color(127, 122, 88));
     let fg_muted = color_from_hex(&tf.colors.foreground_muted, make_color(100, 100, 120));
+    let bg = color_from_hex(&tf.colors.background, make_color(20, 22, 31));
+    let bg_alt = color_from_hex(&tf.colors.background_alt, make_color(25, 28, 39));
+    let brackets = match syn.and_then(|s| s.brackets.as_ref()) {
+        Some(list) if !list.is_empty() => list
+            .iter()
+            .map(|c| color_from_hex(c, make_color(210, 168, 75)))
+            .collect(),
+        _ => vec![
+            tf.colors
+                .yellow
+                .as_ref()
+                .map_or(make_color(210, 168, 75), |c| {
+                    color_from_hex(c, make_color(210, 168, 75))
+                }),
+            tf.colors
+                .purple
+                .as_ref()
+                .map_or(make_color(176, 82, 204), |c| {
+                    color_from_hex(c, make_color(176, 82, 204))
+                }),
+            tf.colors
+                .cyan
+                .as_ref()
+                .map_or(make_color(0, 175, 215), |c| {
+                    color_from_hex(c, make_color(0, 175, 215))
+                }),
+        ],
+    };
+    let indent_tints = match syn.and_then(|s| s.indents.as_ref()) {
+        Some(list) if !list.is_empty() => list.iter().map(|c| color_from_hex(c, bg_alt)).collect(),
+        _ => brackets
+            .iter()
+            .map(|&c| blend(c, bg, 20).unwrap_or(bg_alt))
+            .collect(),
+    };
     Theme {
         name: tf.name,
         theme_type: tf.theme_type,
-        bg: color_from_hex(&tf.colors.background, make_color(20, 22, 31)),
-        bg_alt: color_from_hex(&tf.colors.background_alt, make_color(25, 28, 39)),
+        bg,
+        bg_alt,
         fg: color_from_hex(&tf.colors.foreground, make_color(215, 213, 189)),
         fg_muted,
         border: border_color,
@@ -232,26 +306,29 @@ pub(crate) fn theme_from_file(tf: ThemeFile) -> Theme {
             .map_or(make_color(78, 201, 176), |c| {
                 color_from_hex(c, make_color(78, 201, 176))
             }),
-        bracket_1: tf
+        brackets,
+        indent_tints,
+        diff_added: tf
             .colors
-            .yellow
+            .green
             .as_ref()
-            .map_or(make_color(210, 168, 75), |c| {
-                color_from_hex(c, make_color(210, 168, 75))
+            .map_or(make_color(143, 181, 115), |c| {
+                color_from_hex(c, make_color(143, 181, 115))
             }),
-        bracket_2: tf
+        diff_removed: tf
             .colors
-            .purple
+            .red
             .as_ref()
-            .map_or(make_color(176, 82, 204), |c| {
-                color_from_hex(c, make_color(176, 82, 204))
+            .map_or(make_color(224, 108, 117), |c| {
+                color_from_hex(c, make_color(224, 108, 117))
             }),
-        bracket_3: tf
+        error: tf
             .colors
-            .cyan
+            .error
             .as_ref()
-            .map_or(make_color(0, 175, 215), |c| {
-                color_from_hex(c, make_color(0, 175, 215))
+            .or(tf.colors.red.as_ref())
+            .map_or(make_color(224, 108, 117), |c| {
+                color_from_hex(c, make_color(224, 108, 117))
             }),
     }
 }
@@ -311,10 +388,12 @@ pub(crate) fn load_themes() -> Vec<Theme> {
 #[cfg(test)]
 mod theme_and_persistence_tests {
     use super::*;
-    use crate::persistence::PersistedState;
+    use crate::persistence::{PersistedState, ProjectLayout};
+    use crate::types::{PaneSide, TreeSort};
     use ratatui::style::Color;
+    use std::collections::BTreeMap;
     use std::fs;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn test_theme_file_deserialize_all_fields() {
@@ -353,27 +432,94 @@ mod theme_and_persistence_tests {
     fn test_persisted_state_round_trip() {
         let state = PersistedState {
             theme_name: "Dracula".to_string(),
-            files_pane_width: Some(30),
+            projects: Default::default(),
             word_wrap: Some(true),
+            search_context_lines: Some(2),
+            follow_symlinks: Some(false),
+            auto_save: Some(true),
+            auto_save_delay_ms: Some(750),
+            indent_width: Some(2),
+            indent_hard_tabs: Some(true),
+            render_tab_width: Some(8),
+            highlight_trailing_whitespace: Some(true),
+            scroll_off: Some(5),
+            scroll_lines: Some(6),
+            scroll_acceleration: Some(true),
+            line_numbers: Some(false),
+            fold_arrows: Some(false),
+            lint_mixed_indent: Some(true),
+            diagnostic_errors_only: Some(true),
+            diagnostics_delay_ms: Some(250),
+            diagnostics_max_per_line: Some(4),
+            reflow_width: Some(72),
+            recent_projects_limit: Some(5),
+            recent_projects: vec!["/work/a".to_string(), "/work/b".to_string()],
+            prompt_history: BTreeMap::from([(
+                "find".to_string(),
+                vec!["fn main".to_string(), "TODO".to_string()],
+            )]),
+            tree_sort: Some(TreeSort::Modified),
+            tree_sort_descending: Some(true),
+            tree_dirs_first: Some(false),
+            tree_show_details: Some(true),
+            files_pane_side: Some(PaneSide::Right),
+            bracket_colorization: Some(false),
+            rainbow_indent: Some(true),
+            spell_check: Some(true),
+            user_dictionary: vec!["lazyide".to_string()],
+            zen_width: Some(88),
+            line_spacing: Some(2),
+            file_attributes: Default::default(),
         };
         let json = serde_json::to_string(&state).unwrap();
         let de: PersistedState = serde_json::from_str(&json).unwrap();
         assert_eq!(de.theme_name, "Dracula");
-        assert_eq!(de.files_pane_width, Some(30));
+        assert!(de.projects.is_empty());
         assert_eq!(de.word_wrap, Some(true));
+        assert_eq!(de.search_context_lines, Some(2));
+        assert_eq!(de.follow_symlinks, Some(false));
+        assert_eq!(de.auto_save, Some(true));
+        assert_eq!(de.auto_save_delay_ms, Some(750));
+        assert_eq!(de.indent_width, Some(2));
+        assert_eq!(de.indent_hard_tabs, Some(true));
+        assert_eq!(de.render_tab_width, Some(8));
+        assert_eq!(de.highlight_trailing_whitespace, Some(true));
+        assert_eq!(de.scroll_off, Some(5));
+        assert_eq!(de.scroll_lines, Some(6));
+        assert_eq!(de.scroll_acceleration, Some(true));
+        assert_eq!(de.line_numbers, Some(false));
+        assert_eq!(de.fold_arrows, Some(false));
+        assert_eq!(de.lint_mixed_indent, Some(true));
+        assert_eq!(de.diagnostic_errors_only, Some(true));
+        assert_eq!(de.diagnostics_delay_ms, Some(250));
+        assert_eq!(de.diagnostics_max_per_line, Some(4));
+        assert_eq!(de.reflow_width, Some(72));
+        assert_eq!(de.recent_projects_limit, Some(5));
+        assert_eq!(de.recent_projects, ["/work/a", "/work/b"]);
+        assert_eq!(de.prompt_history["find"], ["fn main", "TODO"]);
+        assert_eq!(de.tree_sort, Some(TreeSort::Modified));
+        assert_eq!(de.tree_sort_descending, Some(true));
+        assert_eq!(de.tree_dirs_first, Some(false));
+        assert_eq!(de.tree_show_details, Some(true));
+        assert_eq!(de.files_pane_side, Some(PaneSide::Right));
+        assert_eq!(de.bracket_colorization, Some(false));
+        assert_eq!(de.rainbow_indent, Some(true));
+        assert_eq!(de.spell_check, Some(true));
+        assert_eq!(de.user_dictionary, ["lazyide"]);
+        assert_eq!(de.zen_width, Some(88));
+        assert_eq!(de.line_spacing, Some(2));
     }
 
     #[test]
     fn test_persisted_state_round_trip_without_optional() {
         let state = PersistedState {
             theme_name: "Nord".to_string(),
-            files_pane_width: None,
-            word_wrap: None,
+            ..Default::default()
         };
         let json = serde_json::to_string(&state).unwrap();
         let de: PersistedState = serde_json::from_str(&json).unwrap();
         assert_eq!(de.theme_name, "Nord");
-        assert_eq!(de.files_pane_width, None);
+        assert!(de.projects.is_empty());
         assert_eq!(de.word_wrap, None);
     }
 
@@ -381,10 +527,43 @@ mod theme_and_persistence_tests {
     fn test_persisted_state_missing_optional_defaults() {
         let de: PersistedState = serde_json::from_str(r##"{"theme_name":"Monokai Pro"}"##).unwrap();
         assert_eq!(de.theme_name, "Monokai Pro");
-        assert_eq!(de.files_pane_width, None);
+        assert!(de.projects.is_empty());
         assert_eq!(de.word_wrap, None);
     }
 
+    #[test]
+    fn test_project_layouts_round_trip_per_root() {
+        let alpha = PathBuf::from("/work/alpha");
+        let beta = PathBuf::from("/work/beta");
+        let mut state = PersistedState {
+            theme_name: "Nord".to_string(),
+            ..Default::default()
+        };
+        state.set_project_layout(
+            &alpha,
+            ProjectLayout {
+                files_pane_width: 24,
+                files_view_open: true,
+            },
+        );
+        state.set_project_layout(
+            &beta,
+            ProjectLayout {
+                files_pane_width: 50,
+                files_view_open: false,
+            },
+        );
+        let json = serde_json::to_string(&state).unwrap();
+        let de: PersistedState = serde_json::from_str(&json).unwrap();
+        let alpha_layout = de.project_layout(&alpha).unwrap();
+        assert_eq!(alpha_layout.files_pane_width, 24);
+        assert!(alpha_layout.files_view_open);
+        let beta_layout = de.project_layout(&beta).unwrap();
+        assert_eq!(beta_layout.files_pane_width, 50);
+        assert!(!beta_layout.files_view_open);
+        assert!(de.project_layout(Path::new("/work/gamma")).is_none());
+    }
+
     #[test]
     fn test_persisted_state_missing_required_fails() {
         assert!(serde_json::from_str::<PersistedState>(r##"{"files_pane_width":20}"##).is_err());
@@ -417,6 +596,38 @@ mod theme_and_persistence_tests {
         // No syntax section → falls back to defaults
         assert_eq!(theme.syntax_string, Color::Rgb(156, 220, 140));
         assert_eq!(theme.syntax_number, Color::Rgb(181, 206, 168));
+        assert_eq!(
+            theme.brackets,
+            vec![
+                Color::Rgb(210, 168, 75),
+                Color::Rgb(176, 82, 204),
+                Color::Rgb(0, 175, 215),
+            ]
+        );
+        // Indent tints fade the bracket colors into the background.
+        assert_eq!(theme.indent_tints[0], Color::Rgb(58, 51, 39));
+    }
+
+    #[test]
+    fn test_theme_bracket_list_replaces_default_trio() {
+        let json = r##"{"name":"Brackets","type":"dark","colors":{"background":"#000000","backgroundAlt":"#000000","foreground":"#ffffff","foregroundMuted":"#888888","border":"#444444","accent":"#ff0000","selection":"#333333","yellow":"#ffff00"},"syntax":{"brackets":["#010101","#020202","#030303","#040404","#050505"]}}"##;
+        let tf: ThemeFile = serde_json::from_str(json).unwrap();
+        let theme = theme_from_file(tf);
+        assert_eq!(theme.brackets.len(), 5);
+        assert_eq!(theme.brackets[0], Color::Rgb(1, 1, 1));
+        assert_eq!(theme.brackets[4], Color::Rgb(5, 5, 5));
+        assert_eq!(theme.indent_tints.len(), 5);
+    }
+
+    #[test]
+    fn test_theme_indent_list_sets_tints() {
+        let json = r##"{"name":"Indents","type":"dark","colors":{"background":"#000000","backgroundAlt":"#000000","foreground":"#ffffff","foregroundMuted":"#888888","border":"#444444","accent":"#ff0000","selection":"#333333"},"syntax":{"indents":["#101010","#202020"]}}"##;
+        let tf: ThemeFile = serde_json::from_str(json).unwrap();
+        let theme = theme_from_file(tf);
+        assert_eq!(
+            theme.indent_tints,
+            vec![Color::Rgb(16, 16, 16), Color::Rgb(32, 32, 32)]
+        );
     }
 
     // Note: load_themes() tests that use set_current_dir are omitted because
@@ -1,4 +1,5 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 #[derive(Debug, Clone)]
 pub(crate) struct TreeItem {
@@ -7,4 +8,40 @@ pub(crate) struct TreeItem {
     pub(crate) depth: usize,
     pub(crate) is_dir: bool,
     pub(crate) expanded: bool,
+    /// File size in bytes; only read while tree details are shown.
+    pub(crate) size: Option<u64>,
+    pub(crate) modified: Option<SystemTime>,
+    /// Number of entries in a directory, shown in place of a size.
+    pub(crate) child_count: Option<usize>,
+    /// Set on the row standing in for the contents of an expanded directory
+    /// that is empty or unreadable; `path` is then that directory.
+    pub(crate) marker: Option<TreeMarker>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TreeMarker {
+    Empty,
+    /// Reading the directory failed, e.g. "permission denied".
+    Error(String),
+}
+
+impl TreeItem {
+    /// The placeholder row under `dir`, e.g. "(empty)".
+    pub(crate) fn marker(dir: &Path, depth: usize, marker: TreeMarker) -> Self {
+        let name = match &marker {
+            TreeMarker::Empty => "(empty)".to_string(),
+            TreeMarker::Error(reason) => format!("({reason})"),
+        };
+        Self {
+            path: dir.to_path_buf(),
+            name,
+            depth,
+            is_dir: false,
+            expanded: false,
+            size: None,
+            modified: None,
+            child_count: None,
+            marker: Some(marker),
+        }
+    }
 }
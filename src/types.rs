@@ -1,17 +1,248 @@
 use std::path::PathBuf;
 
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum Focus {
     Tree,
     Editor,
 }
 
+/// Desktop platform, for commands that differ per OS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Platform {
+    MacOs,
+    Windows,
+    Linux,
+}
+
+impl Platform {
+    pub(crate) fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else if cfg!(windows) {
+            Platform::Windows
+        } else {
+            Platform::Linux
+        }
+    }
+}
+
+/// Which edge of the window the files pane sits on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PaneSide {
+    Left,
+    Right,
+}
+
+impl PaneSide {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            PaneSide::Left => "left",
+            PaneSide::Right => "right",
+        }
+    }
+
+    pub(crate) fn flipped(self) -> Self {
+        match self {
+            PaneSide::Left => PaneSide::Right,
+            PaneSide::Right => PaneSide::Left,
+        }
+    }
+}
+
+/// What Esc does in the editor once no menu, prompt or selection takes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EscAction {
+    /// Clear the selection and search highlight.
+    Clear,
+    /// Close the file, asking first when it has unsaved changes.
+    Close,
+    Nothing,
+}
+
+/// Which key confirms a save-and-quit, save-and-close or delete prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ConfirmKey {
+    /// Enter, or the prompt's letter (S to save, Y to delete).
+    Enter,
+    /// Only the primary modifier chord (Ctrl+S to save, Ctrl+D to delete),
+    /// so a stray Enter cannot confirm.
+    Chord,
+}
+
+/// Kind of project, recognized by a marker file at the root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProjectKind {
+    Rust,
+    Node,
+    Go,
+    Python,
+}
+
+impl ProjectKind {
+    pub(crate) const ALL: [ProjectKind; 4] = [
+        ProjectKind::Rust,
+        ProjectKind::Node,
+        ProjectKind::Go,
+        ProjectKind::Python,
+    ];
+
+    pub(crate) fn marker(self) -> &'static str {
+        match self {
+            ProjectKind::Rust => "Cargo.toml",
+            ProjectKind::Node => "package.json",
+            ProjectKind::Go => "go.mod",
+            ProjectKind::Python => "pyproject.toml",
+        }
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            ProjectKind::Rust => "Rust",
+            ProjectKind::Node => "Node",
+            ProjectKind::Go => "Go",
+            ProjectKind::Python => "Python",
+        }
+    }
+}
+
+/// Column widths of the editor gutter: line numbers, fold arrows, then the
+/// diagnostic marker, git status and a spacer, which are always drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct GutterLayout {
+    /// Digits reserved for line numbers; 0 when they are hidden.
+    pub(crate) number_digits: u16,
+    pub(crate) fold_arrows: bool,
+}
+
+/// The part of the gutter a screen column falls in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GutterColumn {
+    Number,
+    Fold,
+    Markers,
+}
+
+impl GutterLayout {
+    /// Line numbers never take fewer digits than this, so short files
+    /// don't shift the text as they grow past 9 or 99 lines.
+    pub(crate) const MIN_NUMBER_DIGITS: u16 = 3;
+    const FOLD_WIDTH: u16 = 2;
+    const MARKERS_WIDTH: u16 = 3;
+
+    pub(crate) fn new(line_count: usize, line_numbers: bool, fold_arrows: bool) -> Self {
+        let number_digits = if line_numbers {
+            (line_count.max(1).ilog10() as u16 + 1).max(Self::MIN_NUMBER_DIGITS)
+        } else {
+            0
+        };
+        Self {
+            number_digits,
+            fold_arrows,
+        }
+    }
+
+    /// Width of the line number column, including the space after it.
+    pub(crate) fn number_width(self) -> u16 {
+        if self.number_digits == 0 {
+            0
+        } else {
+            self.number_digits + 1
+        }
+    }
+
+    pub(crate) fn fold_width(self) -> u16 {
+        if self.fold_arrows {
+            Self::FOLD_WIDTH
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn width(self) -> u16 {
+        self.number_width() + self.fold_width() + Self::MARKERS_WIDTH
+    }
+
+    /// Which column `x` (relative to the gutter's left edge) is in, or
+    /// `None` past the gutter.
+    pub(crate) fn column_at(self, x: u16) -> Option<GutterColumn> {
+        let fold_start = self.number_width();
+        let markers_start = fold_start + self.fold_width();
+        if x < fold_start {
+            Some(GutterColumn::Number)
+        } else if x < markers_start {
+            Some(GutterColumn::Fold)
+        } else if x < self.width() {
+            Some(GutterColumn::Markers)
+        } else {
+            None
+        }
+    }
+}
+
+/// How Enter carries a list item or line comment onto the next line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LineContinuation {
+    /// Start the new line with this prefix.
+    Prefix(String),
+    /// The item is empty: replace the line with this text to end the list.
+    Exit(String),
+}
+
+/// What the file tree orders entries by within each folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TreeSort {
+    Name,
+    Modified,
+    Size,
+    Extension,
+}
+
+impl TreeSort {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            TreeSort::Name => "Name",
+            TreeSort::Modified => "Modified",
+            TreeSort::Size => "Size",
+            TreeSort::Extension => "Extension",
+        }
+    }
+
+    pub(crate) fn next(self) -> Self {
+        match self {
+            TreeSort::Name => TreeSort::Modified,
+            TreeSort::Modified => TreeSort::Size,
+            TreeSort::Size => TreeSort::Extension,
+            TreeSort::Extension => TreeSort::Name,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum PendingAction {
     None,
-    Quit,
+    QuitPrompt,
     ClosePrompt,
-    Delete(PathBuf),
+    /// `summary` names the targets and, for folders, how much they hold.
+    Delete {
+        paths: Vec<PathBuf>,
+        summary: String,
+    },
+    /// Binary file activated from the tree or a picker, waiting for the
+    /// choice between an external app and a read-only text tab.
+    OpenWith {
+        path: PathBuf,
+        as_preview: bool,
+    },
+    /// Previewed batch rename, as (from, to) pairs.
+    BatchRename {
+        renames: Vec<(PathBuf, PathBuf)>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -23,6 +254,38 @@ pub(crate) enum PromptMode {
     FindInProject,
     ReplaceInFile { search: String },
     GoToLine,
+    SaveAs,
+    ZenWidth,
+    BatchRenameFind { paths: Vec<PathBuf> },
+    BatchRenameReplace { paths: Vec<PathBuf>, find: String },
+}
+
+impl PendingAction {
+    /// The chord letter and, if any, the bare letter that confirm this
+    /// prompt, or `None` when it has no destructive confirm.
+    pub(crate) fn confirm_keys(&self) -> Option<(char, Option<char>)> {
+        match self {
+            PendingAction::QuitPrompt => Some(('s', Some('s'))),
+            PendingAction::ClosePrompt => Some(('s', None)),
+            PendingAction::Delete { .. } => Some(('d', Some('y'))),
+            PendingAction::BatchRename { .. } => Some(('r', Some('y'))),
+            PendingAction::None | PendingAction::OpenWith { .. } => None,
+        }
+    }
+}
+
+impl PromptMode {
+    /// Name of the remembered input history Up/Down recall in this prompt,
+    /// or `None` when it keeps none.
+    pub(crate) fn history_key(&self) -> Option<&'static str> {
+        match self {
+            PromptMode::FindInFile => Some("find"),
+            PromptMode::FindInProject => Some("find_in_project"),
+            PromptMode::ReplaceInFile { .. } => Some("replace"),
+            PromptMode::GoToLine => Some("go_to_line"),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -30,6 +293,8 @@ pub(crate) enum CommandAction {
     Theme,
     Help,
     QuickOpen,
+    FindInFolder,
+    BatchRename,
     FindInFile,
     FindInProject,
     SaveFile,
@@ -40,6 +305,78 @@ pub(crate) enum CommandAction {
     GoToLine,
     Keybinds,
     ToggleWordWrap,
+    KeepTabOpen,
+    ReopenClosedTab,
+    NewUntitled,
+    SetLanguage,
+    GoToSymbol,
+    GoToLastEdit,
+    GoToMatchingTag,
+    WordCount,
+    ReflowParagraph,
+    ToggleTaskCheckbox,
+    ToggleListItem,
+    FormatMarkdownTable,
+    FormatDocument,
+    ConvertIndentationToSpaces,
+    ConvertIndentationToTabs,
+    ToggleTrailingWhitespace,
+    ToggleBracketColorization,
+    ToggleRainbowIndent,
+    ToggleSpellCheck,
+    ToggleLineNumbers,
+    ToggleFoldArrows,
+    ToggleMixedIndentLint,
+    FixMixedIndentation,
+    ToggleAutoSave,
+    CycleTreeSort,
+    ToggleTreeSortDirection,
+    ToggleTreeDirsFirst,
+    ToggleTreeDetails,
+    RevealInFileManager,
+    ToggleFilesPaneSide,
+    ToggleZenMode,
+    SetZenWidth,
+    CycleLineSpacing,
+    ToggleReadOnly,
+    TogglePinTab,
+    DiffTabs,
+    ShowUnsavedDiff,
+    OpenFolder,
+    OpenRecentProject,
+    ToggleDiagnosticErrorsOnly,
+    ShowDiagnostics,
+    RestartLsp,
+    ReloadConfig,
+}
+
+/// Progress of a long-running project operation (search, batch rename),
+/// shown in the status bar until it finishes or Esc cancels it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct OperationProgress {
+    /// What is running, e.g. "Searching 'foo'".
+    pub(crate) label: String,
+    pub(crate) done: usize,
+    /// Items in all, when known up front.
+    pub(crate) total: Option<usize>,
+}
+
+impl OperationProgress {
+    pub(crate) fn new(label: impl Into<String>, total: Option<usize>) -> Self {
+        Self {
+            label: label.into(),
+            done: 0,
+            total,
+        }
+    }
+
+    /// Status bar text, e.g. "Renaming: 50 of 120 | Esc cancel".
+    pub(crate) fn status_text(&self) -> String {
+        match self.total {
+            Some(total) => format!("{}: {} of {total} | Esc cancel", self.label, self.done),
+            None => format!("{}: {} so far | Esc cancel", self.label, self.done),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -48,6 +385,9 @@ pub(crate) struct PromptState {
     pub(crate) value: String,
     pub(crate) cursor: usize,
     pub(crate) mode: PromptMode,
+    /// While recalling history: the entry shown (0 is the newest) and the
+    /// text typed before the first Up.
+    pub(crate) history: Option<(usize, String)>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -57,6 +397,9 @@ pub(crate) enum ContextAction {
     NewFolder,
     Rename,
     Delete,
+    Reveal,
+    ExpandAll,
+    CollapseAll,
     Cancel,
 }
 
@@ -66,5 +409,6 @@ pub(crate) enum EditorContextAction {
     Cut,
     Paste,
     SelectAll,
+    AddToDictionary,
     Cancel,
 }
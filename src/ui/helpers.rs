@@ -1,5 +1,5 @@
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
-use ratatui::style::{Modifier, Style};
+use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders};
 
@@ -24,6 +24,14 @@ pub(crate) fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect
         .split(popup_layout[1])[1]
 }
 
+/// Full-height column of `width` cells centered in `area`, or all of
+/// `area` when it is narrower.
+pub(crate) fn centered_column(width: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let x = area.x + (area.width - width) / 2;
+    Rect::new(x, area.y, width, area.height)
+}
+
 pub(crate) fn help_keybind_line<'a>(
     entries: &[(&str, &str)],
     key_style: Style,
@@ -239,6 +247,30 @@ pub(crate) fn apply_indent_guides(
     result
 }
 
+/// Tint the background of each indent level's 4 columns within the leading
+/// whitespace (spaces or drawn guides), cycling through `tints` by depth.
+pub(crate) fn apply_indent_tints(
+    spans: Vec<Span<'static>>,
+    depth: usize,
+    tints: &[Color],
+) -> Vec<Span<'static>> {
+    if tints.is_empty() {
+        return spans;
+    }
+    let ws_end = spans
+        .iter()
+        .flat_map(|s| s.content.chars())
+        .take_while(|&ch| ch == ' ' || ch == '│')
+        .count();
+    (0..depth)
+        .map(|level| (level, level * 4, ((level + 1) * 4).min(ws_end)))
+        .take_while(|&(_, start, end)| start < end)
+        .fold(spans, |spans, (level, start, end)| {
+            let tint = Style::default().bg(tints[level % tints.len()]);
+            apply_selection_to_spans(spans, start, end, tint)
+        })
+}
+
 #[cfg(test)]
 mod indent_guide_tests {
     use super::*;
@@ -294,6 +326,35 @@ mod indent_guide_tests {
         let full: String = result.iter().map(|s| s.content.as_ref()).collect();
         assert_eq!(full, "│   │   ");
     }
+
+    #[test]
+    fn test_indent_tints_follow_nesting_depth() {
+        let tints = [Color::Red, Color::Green];
+        let guide_style = Style::default().fg(Color::Gray);
+        let block = [
+            "fn main() {",
+            "    if x {",
+            "        y();",
+            "            z",
+            "    }",
+        ];
+        let bg_at = |line: &str, depth: usize, col: usize| {
+            let spans = apply_indent_guides(vec![Span::raw(line.to_string())], depth, guide_style);
+            let spans = apply_indent_tints(spans, depth, &tints);
+            spans
+                .iter()
+                .flat_map(|s| s.content.chars().map(move |_| s.style))
+                .nth(col)
+                .and_then(|style| style.bg)
+        };
+        assert_eq!(bg_at(block[0], 0, 0), None);
+        assert_eq!(bg_at(block[1], 1, 0), Some(Color::Red));
+        assert_eq!(bg_at(block[1], 1, 4), None, "text is not tinted");
+        assert_eq!(bg_at(block[2], 2, 3), Some(Color::Red));
+        assert_eq!(bg_at(block[2], 2, 4), Some(Color::Green));
+        assert_eq!(bg_at(block[3], 3, 8), Some(Color::Red), "tints cycle");
+        assert_eq!(bg_at(block[4], 1, 2), Some(Color::Red));
+    }
 }
 
 #[cfg(test)]
@@ -318,6 +379,23 @@ mod selection_span_tests {
         assert_eq!(result[0].style, Style::default());
     }
 
+    #[test]
+    fn test_trailing_whitespace_background_keeps_foreground() {
+        let line = "let x  ";
+        let spans = vec![
+            Span::styled("let", Style::default().fg(Color::Magenta)),
+            Span::raw(" x  "),
+        ];
+        let (start, end) = crate::util::trailing_whitespace_range(line).expect("trailing");
+        let result = apply_selection_to_spans(spans, start, end, sel_style());
+        assert_eq!(collect_text(&result), line);
+        assert_eq!(result[0].style, Style::default().fg(Color::Magenta));
+        assert_eq!(result[1].content.as_ref(), " x");
+        assert_eq!(result[1].style.bg, None);
+        assert_eq!(result[2].content.as_ref(), "  ");
+        assert_eq!(result[2].style.bg, Some(Color::Yellow));
+    }
+
     #[test]
     fn test_inverted_range_returns_unchanged() {
         let spans = vec![Span::raw("hello")];
@@ -410,3 +488,20 @@ mod selection_span_tests {
         assert!(result.is_empty());
     }
 }
+
+#[cfg(test)]
+mod centered_column_tests {
+    use super::*;
+
+    #[test]
+    fn centers_narrower_column() {
+        let area = Rect::new(10, 2, 100, 30);
+        assert_eq!(centered_column(60, area), Rect::new(30, 2, 60, 30));
+    }
+
+    #[test]
+    fn clamps_to_area_width() {
+        let area = Rect::new(0, 0, 40, 10);
+        assert_eq!(centered_column(60, area), area);
+    }
+}
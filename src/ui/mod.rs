@@ -5,6 +5,7 @@ mod overlays;
 pub(crate) use helpers::centered_rect;
 
 use std::collections::HashSet;
+use std::time::SystemTime;
 
 use ratatui::Frame;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
@@ -16,43 +17,103 @@ use unicode_width::UnicodeWidthStr;
 use crate::app::App;
 use crate::keybinds::KeyAction;
 use crate::lsp_client::LspDiagnostic;
-use crate::syntax::{highlight_line, syntax_lang_for_path};
-use crate::tab::{FoldRange, GitLineStatus};
+use crate::spellcheck::markdown_code_rows;
+use crate::syntax::{SyntaxLang, commented_out_rows, highlight_line};
+use crate::tab::{FoldRange, GitLineStatus, IndentSettings};
+use crate::tree_item::TreeMarker;
 use crate::types::Focus;
+use crate::types::PaneSide;
 use crate::types::PendingAction;
-use crate::util::{relative_path, segment_has_selection};
-use helpers::{apply_indent_guides, apply_selection_to_spans, clip_spans_by_columns};
+use crate::util::{
+    TAB_CHEVRON_WIDTH, bracket_depth_after, buffer_stats_label, diagnostic_underlines, display_col,
+    expand_tabs, has_mixed_indent, identifier_range_at, last_visible_tab, matching_tag,
+    relative_path, segment_has_selection, spaced_screen_rows, tab_label, trailing_whitespace_range,
+    tree_item_details_label, visible_diagnostics, word_occurrences,
+};
+use helpers::{
+    apply_indent_guides, apply_indent_tints, apply_selection_to_spans, centered_column,
+    clip_spans_by_columns,
+};
 use overlays::*;
 
+/// Gutter marker and underline color for an LSP severity name.
+fn severity_color(severity: &str) -> Color {
+    match severity {
+        "error" => Color::Red,
+        "warning" => Color::Yellow,
+        "info" => Color::Cyan,
+        _ => Color::Blue,
+    }
+}
+
 fn slice_chars(s: &str, start: usize, end: usize) -> String {
     let count = end.saturating_sub(start);
     s.chars().skip(start).take(count).collect()
 }
 
+/// Draw `app` off screen into a `width` x `height` buffer, so tests can
+/// check what ends up on screen without a terminal.
+#[cfg(test)]
+pub(crate) fn render_to_buffer(app: &mut App, width: u16, height: u16) -> ratatui::buffer::Buffer {
+    let backend = ratatui::backend::TestBackend::new(width, height);
+    let mut terminal = ratatui::Terminal::new(backend).expect("test backend");
+    terminal.draw(|frame| draw(app, frame)).expect("draw");
+    terminal.backend().buffer().clone()
+}
+
+/// The text of each row of a rendered buffer.
+#[cfg(test)]
+pub(crate) fn buffer_rows(buffer: &ratatui::buffer::Buffer) -> Vec<String> {
+    let area = buffer.area;
+    (area.top()..area.bottom())
+        .map(|y| {
+            (area.left()..area.right())
+                .map(|x| buffer[(x, y)].symbol())
+                .collect()
+        })
+        .collect()
+}
+
 pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
     let theme = app.active_theme().clone();
     let size = frame.area();
+    let zen = app.zen_restore.is_some();
+    let bar_height = if zen { 0 } else { 3 };
     let vertical = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3),
+            Constraint::Length(bar_height),
             Constraint::Min(5),
-            Constraint::Length(3),
+            Constraint::Length(bar_height),
         ])
         .split(size);
-    let (tree_area, editor_area) = if app.files_view_open {
+    let (tree_area, editor_area) = if zen && !app.files_view_open {
+        app.divider_rect = Rect::default();
+        frame.render_widget(
+            Block::default().style(Style::default().bg(theme.bg_alt)),
+            vertical[1],
+        );
+        // Text column plus gutter and the (blank) border on each side
+        let width = app.zen_width.saturating_add(app.gutter().width() + 2);
+        (None, centered_column(width, vertical[1]))
+    } else if app.files_view_open {
         app.clamp_files_pane_width(vertical[1].width);
         let divider_w = 1;
+        let files = Constraint::Length(app.files_pane_width);
+        let editor = Constraint::Min(App::MIN_EDITOR_PANE_WIDTH);
+        let constraints = match app.files_pane_side {
+            PaneSide::Left => [files, Constraint::Length(divider_w), editor],
+            PaneSide::Right => [editor, Constraint::Length(divider_w), files],
+        };
         let main = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Length(app.files_pane_width),
-                Constraint::Length(divider_w),
-                Constraint::Min(App::MIN_EDITOR_PANE_WIDTH),
-            ])
+            .constraints(constraints)
             .split(vertical[1]);
         app.divider_rect = main[1];
-        (Some(main[0]), main[2])
+        match app.files_pane_side {
+            PaneSide::Left => (Some(main[0]), main[2]),
+            PaneSide::Right => (Some(main[2]), main[0]),
+        }
     } else {
         app.divider_rect = Rect::default();
         (None, vertical[1])
@@ -66,6 +127,9 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
             if app.is_dirty() {
                 s.push_str(" *");
             }
+            if app.active_tab().is_some_and(|t| t.read_only) {
+                s.push_str(" [read-only]");
+            }
             s
         }
         None => "no file".to_string(),
@@ -84,9 +148,15 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
             app.git_change_summary.deletions
         )
     };
+    let project_label = app
+        .project_label()
+        .map(|label| format!(" [{label}]"))
+        .unwrap_or_default();
     let top_text = format!(
-        "lazyide   root: {}   file: {}{}",
+        "{}   root: {}{}   file: {}{}",
+        app.title_label(),
         app.root.display(),
+        project_label,
         file_label,
         git_label
     );
@@ -111,17 +181,37 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
     };
 
     if let Some(tree_area) = tree_area {
+        // Details are only formatted for rows that can be on screen. The list
+        // keeps the selected row in view, so the window follows it.
+        let inner_rows = tree_area.height.saturating_sub(2) as usize;
+        let inner_width = tree_area.width.saturating_sub(2) as usize;
+        let first_row = app
+            .tree_state
+            .offset()
+            .min(app.selected)
+            .max((app.selected + 1).saturating_sub(inner_rows));
+        let visible_rows = first_row..first_row + inner_rows;
+        let now = SystemTime::now();
         let tree_items: Vec<ListItem> = app
             .tree
             .iter()
-            .map(|item| {
+            .enumerate()
+            .map(|(idx, item)| {
                 let indent = "  ".repeat(item.depth);
-                let icon = if item.is_dir {
+                let icon = if item.marker.is_some() {
+                    "  "
+                } else if item.is_dir {
                     if item.expanded { "▾ " } else { "▸ " }
                 } else {
                     "· "
                 };
-                let style = if item.is_dir {
+                let style = if let Some(marker) = &item.marker {
+                    let fg = match marker {
+                        TreeMarker::Empty => theme.fg_muted,
+                        TreeMarker::Error(_) => severity_color("warning"),
+                    };
+                    Style::default().fg(fg).add_modifier(Modifier::ITALIC)
+                } else if item.is_dir {
                     Style::default()
                         .fg(theme.accent)
                         .add_modifier(Modifier::BOLD)
@@ -134,10 +224,28 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
                     };
                     Style::default().fg(fg)
                 };
-                ListItem::new(Line::from(Span::styled(
-                    format!("{indent}{icon}{}", item.name),
-                    style,
-                )))
+                let style = if app.tree_selection.contains(&item.path) {
+                    style.bg(theme.accent_secondary).fg(theme.bg_alt)
+                } else {
+                    style
+                };
+                let label = format!("{indent}{icon}{}", item.name);
+                let details = if app.tree_show_details && visible_rows.contains(&idx) {
+                    tree_item_details_label(item, now)
+                } else {
+                    None
+                };
+                let gap = details
+                    .as_ref()
+                    .map_or(0, |d| inner_width.saturating_sub(label.width() + d.width()));
+                let mut spans = vec![Span::styled(label, style)];
+                if let Some(details) = details
+                    && gap > 0
+                {
+                    spans.push(Span::raw(" ".repeat(gap)));
+                    spans.push(Span::styled(details, Style::default().fg(theme.fg_muted)));
+                }
+                ListItem::new(Line::from(spans))
             })
             .collect();
         app.tree_state.select(Some(app.selected));
@@ -183,20 +291,36 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
         }
     }
 
-    // Build tab bar title
-    let tab_title: Line = if app.tabs.is_empty() {
+    // Build tab bar title, scrolled so it fits inside the editor border
+    let tab_bar_width = editor_area.width.saturating_sub(2) as usize;
+    if app.tab_bar_width != tab_bar_width {
+        app.tab_bar_width = tab_bar_width;
+        app.ensure_active_tab_visible();
+    }
+    app.tab_scroll_offset = app.tab_scroll_offset.min(app.tabs.len().saturating_sub(1));
+    let tab_labels: Vec<String> = app.tabs.iter().map(tab_label).collect();
+    let tab_widths: Vec<usize> = tab_labels.iter().map(|l| l.width()).collect();
+    let first_tab = app.tab_scroll_offset;
+    let last_tab = if app.tabs.is_empty() {
+        0
+    } else {
+        last_visible_tab(&tab_widths, first_tab, tab_bar_width)
+    };
+    let chevron_style = Style::default()
+        .fg(theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let tab_title: Line = if zen {
+        Line::default()
+    } else if app.tabs.is_empty() {
         Line::from("Working View")
     } else {
         let mut spans = Vec::new();
         app.tab_rects.clear();
-        for (i, tab) in app.tabs.iter().enumerate() {
-            let fname = tab
-                .path
-                .file_name()
-                .map(|f| f.to_string_lossy().to_string())
-                .unwrap_or_else(|| "untitled".to_string());
-            let prefix = if tab.dirty { "*" } else { "" };
-            let label = format!(" {prefix}{fname} [x] ");
+        if first_tab > 0 {
+            spans.push(Span::styled("‹ ", chevron_style));
+        }
+        let visible_tabs = app.tabs.iter().zip(&tab_labels).enumerate();
+        for (i, (tab, label)) in visible_tabs.take(last_tab + 1).skip(first_tab) {
             let style = if i == app.active_tab {
                 let mut s = Style::default().fg(theme.fg).bg(theme.bg);
                 if tab.is_preview {
@@ -210,17 +334,22 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
                 }
                 s
             };
-            if !spans.is_empty() {
+            if i > first_tab {
                 spans.push(Span::styled("│", Style::default().fg(theme.border)));
             }
-            spans.push(Span::styled(label, style));
+            spans.push(Span::styled(label.clone(), style));
+        }
+        if last_tab + 1 < app.tabs.len() {
+            spans.push(Span::styled(" ›", chevron_style));
         }
         Line::from(spans)
     };
+    // Zen keeps the border cells so mouse math is unchanged, but blanks them.
+    let editor_border = if zen { theme.bg_alt } else { right_border };
     let editor_block = Block::default()
         .title(tab_title)
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(right_border))
+        .border_style(Style::default().fg(editor_border))
         .style(Style::default().bg(theme.bg_alt).fg(theme.fg));
     frame.render_widget(editor_block, editor_area);
     let inner = Rect::new(
@@ -230,20 +359,17 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
         editor_area.height.saturating_sub(2),
     );
 
-    // Compute tab_rects for click detection (position within the title bar)
-    {
-        app.tab_rects.clear();
+    // Compute tab_rects for click detection (visible tabs only)
+    app.tab_rects.clear();
+    if !app.tabs.is_empty() && !zen {
         let mut x_offset = editor_area.x + 1; // +1 for border
-        for (i, tab) in app.tabs.iter().enumerate() {
-            let fname = tab
-                .path
-                .file_name()
-                .map(|f| f.to_string_lossy().to_string())
-                .unwrap_or_else(|| "untitled".to_string());
-            let prefix = if tab.dirty { "*" } else { "" };
-            let label_text = format!(" {prefix}{fname} [x] ");
-            let label_len = label_text.width() as u16;
-            if i > 0 {
+        if first_tab > 0 {
+            x_offset += TAB_CHEVRON_WIDTH as u16;
+        }
+        let visible_widths = tab_widths.iter().enumerate();
+        for (i, width) in visible_widths.take(last_tab + 1).skip(first_tab) {
+            let label_len = *width as u16;
+            if i > first_tab {
                 x_offset += 1; // separator
             }
             // Name rect (clickable to switch)
@@ -267,7 +393,9 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
     }
 
     frame.render_widget(Clear, inner);
-    let wrap_width = inner.width.saturating_sub(App::EDITOR_GUTTER_WIDTH) as usize;
+    let gutter = app.gutter();
+    let gutter_width = gutter.width();
+    let wrap_width = inner.width.saturating_sub(gutter_width) as usize;
     if app.wrap_width_cache != wrap_width {
         app.wrap_width_cache = wrap_width;
         if app.word_wrap {
@@ -278,7 +406,13 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
             app.rebuild_visible_rows();
         }
     }
-    let lang = syntax_lang_for_path(app.open_path().map(|p| p.as_path()));
+    let lang = app
+        .active_tab()
+        .map_or(SyntaxLang::Plain, |tab| tab.syntax_lang());
+    let tab_width = app
+        .active_tab()
+        .map_or(IndentSettings::default(), |tab| tab.indent)
+        .tab_stop();
     let visible_rows = inner.height as usize;
     if app
         .active_tab()
@@ -307,7 +441,6 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
     };
     // Provide empty fallbacks for the no-tab case
     let empty_lines: Vec<String> = vec![String::new()];
-    let empty_diagnostics: Vec<LspDiagnostic> = Vec::new();
     let empty_fold_ranges: Vec<FoldRange> = Vec::new();
     let empty_folded_starts: HashSet<usize> = HashSet::new();
     let empty_visible_rows: Vec<usize> = vec![0usize];
@@ -320,10 +453,40 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
     } else {
         &empty_lines
     };
-    let diagnostics_ref: &[LspDiagnostic] = if has_tab {
-        &app.tabs[tab_idx].diagnostics
+    // Word under the cursor, highlighted wherever else it appears on screen
+    let cursor_word = if has_tab && selection.is_none() {
+        lines_ref.get(cursor_row).and_then(|line| {
+            identifier_range_at(line, cursor_col)
+                .map(|(start, end)| (slice_chars(line, start, end), start))
+        })
     } else {
-        &empty_diagnostics
+        None
+    };
+    // Name of the HTML/XML tag under the cursor and of its partner
+    let tag_pair = if has_tab && selection.is_none() && lang == SyntaxLang::HtmlXml {
+        matching_tag(lines_ref, (cursor_row, cursor_col))
+    } else {
+        None
+    };
+    // Fenced code in Markdown is left out of spell checking
+    let spell_check = has_tab && app.spell_check && app.spell_checker.has_dictionary();
+    let markdown_code = if spell_check && lang == SyntaxLang::Markdown {
+        markdown_code_rows(lines_ref)
+    } else {
+        Vec::new()
+    };
+    // Runs of comment-only lines fade below the comment color
+    let commented_out = if has_tab && app.dim_commented_code {
+        commented_out_rows(lines_ref, lang)
+    } else {
+        Vec::new()
+    };
+    let dimmed_style = theme.commented_out_style();
+    // Only the per-line capped set is drawn; the status bar counts the rest
+    let diagnostics_ref: Vec<&LspDiagnostic> = if has_tab {
+        visible_diagnostics(&app.tabs[tab_idx].diagnostics, app.diagnostics_max_per_line)
+    } else {
+        Vec::new()
     };
     let fold_ranges_ref: &[FoldRange] = if has_tab {
         &app.tabs[tab_idx].fold_ranges
@@ -360,6 +523,23 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
     } else {
         &empty_git_line_status
     };
+    let last_edit_row = if has_tab {
+        app.tabs[tab_idx].last_edit_pos.map(|(row, _)| row)
+    } else {
+        None
+    };
+    let search_regex = if has_tab {
+        app.tabs[tab_idx].editor.search_pattern()
+    } else {
+        None
+    };
+    let search_style = Style::default().fg(theme.bg).bg(theme.accent_secondary);
+    let trailing_style = Style::default().bg(theme.error);
+    let word_style = Style::default().bg(theme.selection);
+    let tag_style = Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let spell_style = Style::default()
+        .add_modifier(Modifier::UNDERLINED)
+        .underline_color(severity_color("warning"));
     let inner_w = inner.width as usize;
     let blank_line = Line::from(Span::styled(
         " ".repeat(inner_w),
@@ -373,7 +553,7 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
         // First pass: compute depth for non-blank lines, mark blanks
         for i in 0..total {
             let line = &lines_ref[i];
-            let expanded = line.replace('\t', "    ");
+            let expanded = expand_tabs(line, tab_width);
             let leading = expanded.len() - expanded.trim_start_matches(' ').len();
             if expanded.trim().is_empty() {
                 is_blank[i] = true;
@@ -408,13 +588,20 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
     };
     let guide_style = Style::default().fg(theme.fg_muted);
 
+    let screen_rows = spaced_screen_rows(
+        visible_rows_map_ref,
+        start_row,
+        visible_rows,
+        app.line_spacing,
+    );
     let mut lines_out: Vec<Line> = Vec::with_capacity(visible_rows);
     for visual_row in 0..visible_rows {
-        let visible_idx = start_row + visual_row;
-        let Some(&row) = visible_rows_map_ref.get(visible_idx) else {
+        // Spacer rows and rows past the end of the buffer draw blank
+        let Some(Some(visible_idx)) = screen_rows.get(visual_row).copied() else {
             lines_out.push(blank_line.clone());
             continue;
         };
+        let row = visible_rows_map_ref[visible_idx];
         let seg_start = visible_row_starts_ref
             .get(visible_idx)
             .copied()
@@ -429,48 +616,52 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
             continue;
         }
         let mut spans = Vec::new();
-        let line_num = if is_first_segment {
-            format!("{:>5} ", row + 1)
-        } else {
-            "      ".to_string()
-        };
-        let line_num_style = if row == cursor_row {
-            Style::default().fg(theme.accent)
-        } else {
-            Style::default().fg(theme.fg_muted)
-        };
-        spans.push(Span::styled(line_num, line_num_style));
+        if gutter.number_digits > 0 {
+            let digits = gutter.number_digits as usize;
+            let line_num = if is_first_segment {
+                format!("{:>digits$} ", row + 1)
+            } else {
+                " ".repeat(digits + 1)
+            };
+            let line_num_style = if row == cursor_row {
+                Style::default().fg(theme.accent)
+            } else {
+                Style::default().fg(theme.fg_muted)
+            };
+            spans.push(Span::styled(line_num, line_num_style));
+        }
 
-        let fold_indicator = if is_first_segment {
-            if let Some(fr) = fold_ranges_ref.iter().find(|fr| fr.start_line == row) {
-                if folded_starts_ref.contains(&fr.start_line) {
-                    "▸ "
+        if gutter.fold_arrows {
+            let fold_indicator = if is_first_segment {
+                if let Some(fr) = fold_ranges_ref.iter().find(|fr| fr.start_line == row) {
+                    if folded_starts_ref.contains(&fr.start_line) {
+                        "▸ "
+                    } else {
+                        "▾ "
+                    }
                 } else {
-                    "▾ "
+                    "  "
                 }
             } else {
-                "  "
-            }
-        } else {
-            "↪ "
-        };
-        spans.push(Span::styled(
-            fold_indicator,
-            Style::default()
-                .fg(theme.fg_muted)
-                .add_modifier(Modifier::BOLD),
-        ));
+                "↪ "
+            };
+            spans.push(Span::styled(
+                fold_indicator,
+                Style::default()
+                    .fg(theme.fg_muted)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
 
         let diag_for_row = diagnostics_ref.iter().find(|d| d.line == row + 1);
         if is_first_segment {
             if let Some(diag) = diag_for_row {
-                let color = match diag.severity.as_str() {
-                    "error" => Color::Red,
-                    "warning" => Color::Yellow,
-                    "info" => Color::Cyan,
-                    _ => Color::Blue,
-                };
-                spans.push(Span::styled("●", Style::default().fg(color)));
+                spans.push(Span::styled(
+                    "●",
+                    Style::default().fg(severity_color(&diag.severity)),
+                ));
+            } else if app.lint_mixed_indent && has_mixed_indent(&lines_ref[row]) {
+                spans.push(Span::styled("⇥", Style::default().fg(Color::Yellow)));
             } else {
                 spans.push(Span::raw(" "));
             }
@@ -499,18 +690,37 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
                 spans.push(Span::raw(" "));
             }
         }
-        spans.push(Span::raw(" "));
-        let segment_text = slice_chars(&lines_ref[row], seg_start, seg_end).replace('\t', "    ");
-        let bracket_colors = [theme.bracket_1, theme.bracket_2, theme.bracket_3];
-        let bd = bracket_depths_ref.get(row).copied().unwrap_or(0);
-        let hl = highlight_line(&segment_text, lang, &theme, bd, &bracket_colors);
+        if is_first_segment && last_edit_row == Some(row) {
+            spans.push(Span::styled("▎", Style::default().fg(theme.fg_muted)));
+        } else {
+            spans.push(Span::raw(" "));
+        }
+        let segment = slice_chars(&lines_ref[row], seg_start, seg_end);
+        let segment_text = expand_tabs(&segment, tab_width);
+        let mut bd = bracket_depths_ref.get(row).copied().unwrap_or(0);
+        if !is_first_segment {
+            // Continue from the depth the start of the line reached, so a
+            // closer on a wrapped row isn't taken for a stray one.
+            let prefix = slice_chars(&lines_ref[row], 0, seg_start);
+            bd = bracket_depth_after(&prefix, lang, bd);
+        }
+        let hl = highlight_line(&segment_text, lang, &theme, bd, app.bracket_colors());
+        let hl_spans: Vec<Span> = if commented_out.get(row).copied().unwrap_or(false) {
+            hl.spans
+                .into_iter()
+                .map(|span| span.patch_style(dimmed_style))
+                .collect()
+        } else {
+            hl.spans
+        };
         let guide_depth = indent_depths.get(row).copied().unwrap_or(0);
         let content_spans = if is_first_segment {
-            apply_indent_guides(hl.spans, guide_depth, guide_style)
+            let guided = apply_indent_guides(hl_spans, guide_depth, guide_style);
+            apply_indent_tints(guided, guide_depth, app.indent_tints())
         } else {
-            hl.spans
+            hl_spans
         };
-        let content_width = inner_w.saturating_sub(App::EDITOR_GUTTER_WIDTH as usize);
+        let content_width = inner_w.saturating_sub(gutter_width as usize);
         let content_spans = if !app.word_wrap && scroll_col > 0 {
             clip_spans_by_columns(content_spans, scroll_col, content_width)
         } else if !app.word_wrap {
@@ -518,6 +728,117 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
         } else {
             content_spans
         };
+        // Convert original char positions to display columns (tabs run to the next stop)
+        let char_to_display = |n: usize| -> usize { display_col(&segment, n, tab_width) };
+        let effective_scroll = if !app.word_wrap { scroll_col } else { 0 };
+        // Highlight find-in-file matches within this segment
+        let content_spans = if let Some(re) = search_regex {
+            let line = &lines_ref[row];
+            let mut spans = content_spans;
+            for m in re.find_iter(line).filter(|m| !m.is_empty()) {
+                let start = line[..m.start()].chars().count().max(seg_start);
+                let end = line[..m.end()].chars().count().min(seg_end);
+                if start >= end {
+                    continue;
+                }
+                let display_start = char_to_display(start - seg_start);
+                let display_end = char_to_display(end - seg_start);
+                spans = apply_selection_to_spans(
+                    spans,
+                    display_start.saturating_sub(effective_scroll),
+                    display_end.saturating_sub(effective_scroll),
+                    search_style,
+                );
+            }
+            spans
+        } else {
+            content_spans
+        };
+        // Flag trailing whitespace, except on the line being typed on
+        let trailing = if app.highlight_trailing_whitespace && row != cursor_row {
+            trailing_whitespace_range(&lines_ref[row])
+        } else {
+            None
+        };
+        let content_spans = match trailing {
+            Some((start, end)) if start.max(seg_start) < end.min(seg_end) => {
+                let start = start.max(seg_start);
+                let end = end.min(seg_end);
+                apply_selection_to_spans(
+                    content_spans,
+                    char_to_display(start - seg_start).saturating_sub(effective_scroll),
+                    char_to_display(end - seg_start).saturating_sub(effective_scroll),
+                    trailing_style,
+                )
+            }
+            _ => content_spans,
+        };
+        let content_spans = match cursor_word.as_ref() {
+            Some((word, word_start)) => word_occurrences(&lines_ref[row], word)
+                .into_iter()
+                .filter(|&(start, _)| row != cursor_row || start != *word_start)
+                .map(|(start, end)| (start.max(seg_start), end.min(seg_end)))
+                .filter(|(start, end)| start < end)
+                .fold(content_spans, |spans, (start, end)| {
+                    apply_selection_to_spans(
+                        spans,
+                        char_to_display(start - seg_start).saturating_sub(effective_scroll),
+                        char_to_display(end - seg_start).saturating_sub(effective_scroll),
+                        word_style,
+                    )
+                }),
+            None => content_spans,
+        };
+        let content_spans = tag_pair
+            .into_iter()
+            .flatten()
+            .filter(|&(tag_row, _, _)| tag_row == row)
+            .map(|(_, start, end)| (start.max(seg_start), end.min(seg_end)))
+            .filter(|(start, end)| start < end)
+            .fold(content_spans, |spans, (start, end)| {
+                apply_selection_to_spans(
+                    spans,
+                    char_to_display(start - seg_start).saturating_sub(effective_scroll),
+                    char_to_display(end - seg_start).saturating_sub(effective_scroll),
+                    tag_style,
+                )
+            });
+        // Underline unknown words in comments and Markdown prose
+        let misspelled = if spell_check && !markdown_code.get(row).copied().unwrap_or(false) {
+            app.spell_checker.misspelled(&lines_ref[row], lang, &theme)
+        } else {
+            Vec::new()
+        };
+        let content_spans = misspelled
+            .into_iter()
+            .map(|(start, end)| (start.max(seg_start), end.min(seg_end)))
+            .filter(|(start, end)| start < end)
+            .fold(content_spans, |spans, (start, end)| {
+                apply_selection_to_spans(
+                    spans,
+                    char_to_display(start - seg_start).saturating_sub(effective_scroll),
+                    char_to_display(end - seg_start).saturating_sub(effective_scroll),
+                    spell_style,
+                )
+            });
+        // Underline diagnostic ranges in their severity color
+        let line_len_chars = lines_ref[row].chars().count();
+        let underlines =
+            diagnostic_underlines(diagnostics_ref.iter().copied(), row, line_len_chars);
+        let content_spans = underlines
+            .into_iter()
+            .map(|(start, end, severity)| (start.max(seg_start), end.min(seg_end), severity))
+            .filter(|(start, end, _)| start < end)
+            .fold(content_spans, |spans, (start, end, severity)| {
+                apply_selection_to_spans(
+                    spans,
+                    char_to_display(start - seg_start).saturating_sub(effective_scroll),
+                    char_to_display(end - seg_start).saturating_sub(effective_scroll),
+                    Style::default()
+                        .add_modifier(Modifier::UNDERLINED)
+                        .underline_color(severity_color(severity)),
+                )
+            });
         // Apply character-level selection highlighting to content spans
         let (content_spans, sel_extends_to_eol) =
             if segment_has_selection(row, seg_start, seg_end, selection) {
@@ -533,28 +854,12 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
                 // Clamp to segment boundaries
                 let clamped_start = sel_start_col.max(seg_start).min(seg_end);
                 let clamped_end = sel_end_col.min(seg_end).max(seg_start);
-                // Convert original char positions to display columns (tab=4 cols)
-                let orig_chars: Vec<char> = lines_ref[row]
-                    .chars()
-                    .skip(seg_start)
-                    .take(seg_end - seg_start)
-                    .collect();
-                let char_to_display = |n: usize| -> usize {
-                    orig_chars.iter().take(n).fold(0, |acc, ch| {
-                        acc + if *ch == '\t' {
-                            4
-                        } else {
-                            unicode_width::UnicodeWidthChar::width(*ch).unwrap_or(0)
-                        }
-                    })
-                };
                 let display_start = char_to_display(clamped_start - seg_start);
                 let display_end = if sel_end_col >= seg_end {
-                    char_to_display(orig_chars.len())
+                    char_to_display(seg_end - seg_start)
                 } else {
                     char_to_display(clamped_end - seg_start)
                 };
-                let effective_scroll = if !app.word_wrap { scroll_col } else { 0 };
                 let clipped_start = display_start.saturating_sub(effective_scroll);
                 let clipped_end = display_end.saturating_sub(effective_scroll);
                 let sel_style = Style::default().bg(theme.selection);
@@ -580,15 +885,6 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
             ));
         }
         let hl = Line::from(spans);
-        let hl = if diagnostics_ref
-            .iter()
-            .any(|d| d.line == row + 1 && d.severity == "error")
-        {
-            hl.patch_style(Style::default().add_modifier(Modifier::UNDERLINED))
-        } else {
-            hl
-        };
-        let line_len_chars = lines_ref[row].chars().count();
         let cursor_on_segment = row == cursor_row
             && cursor_col >= seg_start
             && (cursor_col < seg_end || (cursor_col == seg_end && seg_end == line_len_chars));
@@ -618,8 +914,7 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
     frame.render_widget(editor_text, inner);
     if app.focus == Focus::Editor && has_tab {
         let cursor_visible = app.visible_index_of_source_position(cursor_row, cursor_col);
-        let cursor_y = cursor_visible.saturating_sub(start_row);
-        if cursor_y < visible_rows {
+        if let Some(cursor_y) = screen_rows.iter().position(|r| *r == Some(cursor_visible)) {
             let seg_start = visible_row_starts_ref
                 .get(cursor_visible)
                 .copied()
@@ -628,28 +923,18 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
                 .get(cursor_visible)
                 .copied()
                 .unwrap_or(seg_start);
-            let max_x = inner
-                .width
-                .saturating_sub(1)
-                .saturating_sub(App::EDITOR_GUTTER_WIDTH) as usize;
-            let logical_x = cursor_col
-                .clamp(seg_start, seg_end)
-                .saturating_sub(seg_start);
+            let max_x = inner.width.saturating_sub(1).saturating_sub(gutter_width) as usize;
+            let cursor_line = lines_ref.get(cursor_row).map_or("", String::as_str);
             // When not wrapping, compute display-width offset for cursor and
             // subtract the horizontal scroll so it renders at the right screen column.
             let logical_x = if !app.word_wrap {
-                // Compute display-width of chars before cursor on this line
-                let line_chars: Vec<char> = lines_ref
-                    .get(cursor_row)
-                    .map(|l| l.replace('\t', "    ").chars().collect())
-                    .unwrap_or_default();
-                let mut dw = 0usize;
-                for i in 0..cursor_col.min(line_chars.len()) {
-                    dw += unicode_width::UnicodeWidthChar::width(line_chars[i]).unwrap_or(0);
-                }
-                dw.saturating_sub(scroll_col)
+                display_col(cursor_line, cursor_col, tab_width).saturating_sub(scroll_col)
             } else {
-                logical_x
+                let segment = slice_chars(cursor_line, seg_start, seg_end);
+                let logical_x = cursor_col
+                    .clamp(seg_start, seg_end)
+                    .saturating_sub(seg_start);
+                display_col(&segment, logical_x, tab_width)
             };
             let cursor_x = logical_x.min(max_x);
             // If cursor would be off-screen horizontally (scrolled past), skip rendering
@@ -658,17 +943,17 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
             } else
             if let Some(ghost) = app.completion.ghost.as_ref() {
                 if !ghost.is_empty()
-                    && (cursor_x as u16 + App::EDITOR_GUTTER_WIDTH) < inner.width.saturating_sub(1)
+                    && (cursor_x as u16 + gutter_width) < inner.width.saturating_sub(1)
                 {
                     let ghost_area = Rect::new(
                         inner
                             .x
-                            .saturating_add(App::EDITOR_GUTTER_WIDTH)
+                            .saturating_add(gutter_width)
                             .saturating_add(cursor_x as u16),
                         inner.y.saturating_add(cursor_y as u16),
                         inner
                             .width
-                            .saturating_sub(App::EDITOR_GUTTER_WIDTH)
+                            .saturating_sub(gutter_width)
                             .saturating_sub(cursor_x as u16),
                         1,
                     );
@@ -680,7 +965,7 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
             frame.set_cursor_position((
                 inner
                     .x
-                    .saturating_add(App::EDITOR_GUTTER_WIDTH)
+                    .saturating_add(gutter_width)
                     .saturating_add(cursor_x as u16),
                 inner.y.saturating_add(cursor_y as u16),
             ));
@@ -688,25 +973,46 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
     }
 
     let kb = &app.keybinds;
-    let status = Paragraph::new(format!(
-        "{} Cmd   {} Open   {} Help   {} Files   {} Close   {} Save   {} Quit   {} Wrap:{}",
-        kb.display_for(KeyAction::CommandPalette),
-        kb.display_for(KeyAction::QuickOpen),
-        kb.display_for(KeyAction::Help),
-        kb.display_for(KeyAction::ToggleFiles),
-        kb.display_for(KeyAction::CloseTab),
-        kb.display_for(KeyAction::Save),
-        kb.display_for(KeyAction::Quit),
-        kb.display_for(KeyAction::ToggleWordWrap),
-        if app.word_wrap { "on" } else { "off" },
-    ))
-    .style(Style::default().fg(theme.fg).bg(theme.bg_alt))
-    .wrap(Wrap { trim: true })
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(theme.border)),
-    );
+    let indent = app
+        .active_tab()
+        .map(|tab| format!("   Indent:{}", tab.indent.label()))
+        .unwrap_or_default();
+    let stats = app
+        .active_tab()
+        .map(|tab| {
+            let selection = tab.editor.selection_range();
+            format!(
+                "   {}",
+                buffer_stats_label(tab.editor.lines(), tab.char_count, selection)
+            )
+        })
+        .unwrap_or_default();
+    // A running search or batch rename takes the bar over until it ends.
+    let status_text = match &app.progress {
+        Some(progress) => progress.status_text(),
+        None => format!(
+            "{} Cmd   {} Open   {} Help   {} Files   {} Close   {} Save   {} Quit   {} Wrap:{}{}{}",
+            kb.display_for(KeyAction::CommandPalette),
+            kb.display_for(KeyAction::QuickOpen),
+            kb.display_for(KeyAction::Help),
+            kb.display_for(KeyAction::ToggleFiles),
+            kb.display_for(KeyAction::CloseTab),
+            kb.display_for(KeyAction::Save),
+            kb.display_for(KeyAction::Quit),
+            kb.display_for(KeyAction::ToggleWordWrap),
+            if app.word_wrap { "on" } else { "off" },
+            indent,
+            stats,
+        ),
+    };
+    let status = Paragraph::new(status_text)
+        .style(Style::default().fg(theme.fg).bg(theme.bg_alt))
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.border)),
+        );
     frame.render_widget(status, vertical[2]);
 
     if app.menu_open {
@@ -718,6 +1024,27 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
     if app.theme_browser_open {
         render_theme_browser(app, frame);
     }
+    if app.language_picker.open {
+        render_language_picker(app, frame);
+    }
+    if app.symbol_picker.open {
+        render_symbol_picker(app, frame);
+    }
+    if app.diff_tab_picker.open {
+        render_diff_tab_picker(app, frame);
+    }
+    if app.folder_picker.open {
+        render_folder_picker(app, frame);
+    }
+    if app.recent_project_picker.open {
+        render_recent_project_picker(app, frame);
+    }
+    if app.tab_diff.is_some() {
+        render_tab_diff(app, frame);
+    }
+    if app.diagnostics_panel.open {
+        render_diagnostics_panel(app, frame);
+    }
     if app.search_results.open {
         render_search_results(app, frame);
     }
@@ -742,13 +1069,142 @@ pub(crate) fn draw(app: &mut App, frame: &mut Frame<'_>) {
     if matches!(app.pending, PendingAction::ClosePrompt) {
         render_close_prompt(app, frame);
     }
-    if matches!(app.pending, PendingAction::Delete(_)) {
+    if matches!(app.pending, PendingAction::QuitPrompt) {
+        render_quit_prompt(app, frame);
+    }
+    if matches!(app.pending, PendingAction::Delete { .. }) {
         render_delete_prompt(app, frame);
     }
+    if matches!(app.pending, PendingAction::BatchRename { .. }) {
+        render_batch_rename_prompt(app, frame);
+    }
+    if matches!(app.pending, PendingAction::OpenWith { .. }) {
+        render_open_with_prompt(app, frame);
+    }
     if app.active_tab().is_some_and(|t| t.conflict_prompt_open) {
         render_conflict_prompt(app, frame);
     }
     if app.active_tab().is_some_and(|t| t.recovery_prompt_open) {
         render_recovery_prompt(app, frame);
     }
+    if app.unsaved_diff_open {
+        render_unsaved_diff(app, frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn app_with_open_files(root: &std::path::Path, files: &[(&str, &str)]) -> App {
+        let mut app =
+            App::with_paths(root.to_path_buf(), None, None).expect("app should initialize");
+        app.active_theme_index = 0;
+        for (name, text) in files {
+            let path = root.join(name);
+            fs::write(&path, text).expect("write");
+            app.open_file(path).expect("open");
+        }
+        app
+    }
+
+    #[test]
+    fn rendered_tab_bar_names_every_open_file() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = app_with_open_files(
+            tmp.path(),
+            &[("main.rs", "fn main() {}\n"), ("notes.md", "# Notes\n")],
+        );
+
+        let rows = buffer_rows(&render_to_buffer(&mut app, 100, 30));
+        assert!(
+            rows.iter()
+                .any(|row| row.contains("main.rs") && row.contains("notes.md")),
+            "no tab bar row in {rows:#?}"
+        );
+    }
+
+    #[test]
+    fn rendered_rust_keyword_uses_the_theme_keyword_style() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = app_with_open_files(tmp.path(), &[("main.rs", "fn main() {}\n")]);
+        let accent = app.active_theme().accent;
+
+        let buffer = render_to_buffer(&mut app, 100, 30);
+        let rows = buffer_rows(&buffer);
+        let (y, x) = rows
+            .iter()
+            .enumerate()
+            .find_map(|(y, row)| {
+                let byte = row.find("fn main()")?;
+                Some((y as u16, row[..byte].chars().count() as u16))
+            })
+            .expect("code row rendered");
+        for dx in 0..2 {
+            let cell = &buffer[(x + dx, y)];
+            assert_eq!(cell.fg, accent);
+            assert!(cell.modifier.contains(Modifier::BOLD));
+        }
+        assert_ne!(buffer[(x + 3, y)].fg, accent);
+    }
+
+    /// Buffer position of the first rendered `text`, as `(x, y)`.
+    fn find_text(rows: &[String], text: &str) -> (u16, u16) {
+        rows.iter()
+            .enumerate()
+            .find_map(|(y, row)| {
+                let byte = row.find(text)?;
+                Some((row[..byte].chars().count() as u16, y as u16))
+            })
+            .unwrap_or_else(|| panic!("{text:?} not rendered in {rows:#?}"))
+    }
+
+    #[test]
+    fn status_bar_shows_selection_stats_or_buffer_totals() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = app_with_open_files(tmp.path(), &[("notes.txt", "alpha\nbeta gamma\n")]);
+
+        let rows = buffer_rows(&render_to_buffer(&mut app, 200, 30));
+        find_text(&rows, "3 lines, 15 chars");
+
+        if let Some(tab) = app.active_tab_mut() {
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(0, 2));
+            tab.editor.start_selection();
+            tab.editor
+                .move_cursor(ratatui_textarea::CursorMove::Jump(1, 4));
+        }
+        let rows = buffer_rows(&render_to_buffer(&mut app, 200, 30));
+        find_text(&rows, "Sel: 2 lines, 7 chars");
+    }
+
+    #[test]
+    fn commented_out_run_renders_dimmed_next_to_normal_code() {
+        let tmp = tempdir().expect("tempdir");
+        let text = "// fn old() {\n//     gone();\n// }\nfn main() {}\n";
+        let mut app = app_with_open_files(tmp.path(), &[("main.rs", text)]);
+        app.dim_commented_code = true;
+        let theme = app.active_theme().clone();
+        let dimmed = theme.commented_out_style();
+        assert_ne!(dimmed, Style::default().fg(theme.comment));
+
+        let buffer = render_to_buffer(&mut app, 100, 30);
+        let rows = buffer_rows(&buffer);
+        for text in ["// fn old()", "//     gone();", "// }"] {
+            let (x, y) = find_text(&rows, text);
+            let cell = &buffer[(x, y)];
+            assert_eq!(Some(cell.fg), dimmed.fg, "{text}");
+            assert!(cell.modifier.contains(dimmed.add_modifier), "{text}");
+        }
+        let (x, y) = find_text(&rows, "fn main()");
+        assert_eq!(buffer[(x, y)].fg, theme.accent);
+
+        app.dim_commented_code = false;
+        let buffer = render_to_buffer(&mut app, 100, 30);
+        let (x, y) = find_text(&buffer_rows(&buffer), "// fn old()");
+        assert_eq!(buffer[(x, y)].fg, theme.comment);
+        assert!(!buffer[(x, y)].modifier.contains(Modifier::DIM));
+    }
 }
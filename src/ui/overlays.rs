@@ -1,18 +1,22 @@
 use ratatui::Frame;
-use ratatui::layout::Rect;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Clear, List, ListItem, Paragraph, Wrap};
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap};
 
 use crate::app::App;
 use crate::keybinds::KeyAction;
+use crate::syntax::{SyntaxLang, theme_preview_lines};
+use crate::tab::{DiagnosticPanelRow, DiffLine, DiffLineKind, DiffRowKind, SearchResultRow};
 use crate::types::PendingAction;
 use crate::util::{
-    command_action_label, context_actions, context_label, editor_context_actions,
-    editor_context_label, primary_mod_label, relative_path,
+    command_action_label, confirm_key_label, context_actions, context_label, diagnostic_panel_rows,
+    editor_context_actions, editor_context_label, expand_tabs, primary_mod_label, relative_path,
+    search_result_rows,
 };
 
 use super::helpers::{centered_rect, help_keybind_line, list_item_style, themed_block};
+use super::severity_color;
 
 pub(crate) fn render_menu(app: &mut App, frame: &mut Frame<'_>) {
     let theme = app.active_theme().clone();
@@ -53,28 +57,298 @@ pub(crate) fn render_menu(app: &mut App, frame: &mut Frame<'_>) {
 
 pub(crate) fn render_theme_browser(app: &mut App, frame: &mut Frame<'_>) {
     let theme = app.active_theme().clone();
-    let area = centered_rect(62, 70, frame.area());
+    let popup = centered_rect(80, 70, frame.area());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+        .split(popup);
+    let (area, preview_area) = (columns[0], columns[1]);
     app.theme_browser_rect = area;
+    app.theme_preview_rect = preview_area;
+    frame.render_widget(Clear, popup);
+    let mut list_items: Vec<ListItem> = app
+        .theme_results
+        .iter()
+        .map(|&idx| {
+            let t = &app.themes[idx];
+            let label = format!("{} [{}]", t.name, t.theme_type);
+            let style = list_item_style(idx == app.theme_index, &theme);
+            ListItem::new(Line::from(Span::styled(label, style)))
+        })
+        .collect();
+    if list_items.is_empty() {
+        list_items.push(ListItem::new(Line::from(Span::styled(
+            "No matching themes",
+            Style::default().fg(theme.fg_muted),
+        ))));
+    }
+    let title = if app.theme_query.is_empty() {
+        "Theme Picker (Live Preview, type to filter)".to_string()
+    } else {
+        format!("Theme Picker: {}", app.theme_query)
+    };
+    let list = List::new(list_items).block(themed_block(&theme).title(title));
+    frame.render_widget(list, area);
+
+    // Sample code in the highlighted theme, on its own background.
+    let preview = Paragraph::new(theme_preview_lines(&theme, app.bracket_colors()))
+        .style(Style::default().fg(theme.fg).bg(theme.bg))
+        .block(
+            themed_block(&theme)
+                .title("Preview")
+                .style(Style::default().bg(theme.bg)),
+        );
+    frame.render_widget(preview, preview_area);
+}
+
+pub(crate) fn render_language_picker(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let area = centered_rect(40, 50, frame.area());
+    app.language_picker.rect = area;
+    frame.render_widget(Clear, area);
+    let detected = app
+        .active_tab()
+        .map_or(SyntaxLang::Plain, |tab| tab.detected_lang);
+    let labels = std::iter::once(format!("Auto Detect ({})", detected.label()))
+        .chain(SyntaxLang::ALL.iter().map(|lang| lang.label().to_string()));
+    let list_items: Vec<ListItem> = labels
+        .enumerate()
+        .map(|(idx, label)| {
+            let style = list_item_style(idx == app.language_picker.index, &theme);
+            ListItem::new(Line::from(Span::styled(label, style)))
+        })
+        .collect();
+    let list = List::new(list_items).block(themed_block(&theme).title("Set Language"));
+    frame.render_widget(list, area);
+}
+
+pub(crate) fn render_diff_tab_picker(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let area = centered_rect(50, 50, frame.area());
+    app.diff_tab_picker.rect = area;
     frame.render_widget(Clear, area);
     let list_items: Vec<ListItem> = app
-        .themes
+        .diff_tab_picker
+        .tabs
         .iter()
         .enumerate()
-        .map(|(idx, t)| {
-            let label = format!("{} [{}]", t.name, t.theme_type);
-            let style = if idx == app.theme_index {
-                list_item_style(true, &theme)
-            } else {
-                list_item_style(false, &theme)
-            };
+        .map(|(idx, &tab_idx)| {
+            let label = app
+                .tabs
+                .get(tab_idx)
+                .map(|t| relative_path(&app.root, &t.path).display().to_string())
+                .unwrap_or_default();
+            let style = list_item_style(idx == app.diff_tab_picker.index, &theme);
             ListItem::new(Line::from(Span::styled(label, style)))
         })
         .collect();
-    let list =
-        List::new(list_items).block(themed_block(&theme).title("Theme Picker (Live Preview)"));
+    let list = List::new(list_items).block(themed_block(&theme).title("Diff With Tab"));
     frame.render_widget(list, area);
 }
 
+pub(crate) fn render_folder_picker(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let area = centered_rect(50, 60, frame.area());
+    app.folder_picker.rect = area;
+    frame.render_widget(Clear, area);
+    // Scroll just enough to keep the highlighted row in view.
+    let visible = area.height.saturating_sub(2).max(1) as usize;
+    let picker = &mut app.folder_picker;
+    picker.offset = picker
+        .offset
+        .min(picker.index)
+        .max((picker.index + 1).saturating_sub(visible));
+    let offset = picker.offset;
+    let picker = &app.folder_picker;
+    let subdirs = picker.entries.iter().map(|p| {
+        let name = p
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        format!("{name}/")
+    });
+    let labels = [format!("Open {}", picker.dir.display()), "../".to_string()]
+        .into_iter()
+        .chain(subdirs);
+    let list_items: Vec<ListItem> = labels
+        .enumerate()
+        .skip(offset)
+        .map(|(idx, label)| {
+            let style = list_item_style(idx == picker.index, &theme);
+            ListItem::new(Line::from(Span::styled(label, style)))
+        })
+        .collect();
+    let list = List::new(list_items).block(
+        themed_block(&theme).title("Open Folder (Enter: open/enter, Backspace: up, Esc: cancel)"),
+    );
+    frame.render_widget(list, area);
+}
+
+pub(crate) fn render_recent_project_picker(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let area = centered_rect(60, 50, frame.area());
+    app.recent_project_picker.rect = area;
+    frame.render_widget(Clear, area);
+    let list_items: Vec<ListItem> = app
+        .recent_project_picker
+        .projects
+        .iter()
+        .enumerate()
+        .map(|(idx, path)| {
+            let style = list_item_style(idx == app.recent_project_picker.index, &theme);
+            ListItem::new(Line::from(Span::styled(path.display().to_string(), style)))
+        })
+        .collect();
+    let list = List::new(list_items).block(themed_block(&theme).title("Open Recent Project"));
+    frame.render_widget(list, area);
+}
+
+/// One side of a side-by-side diff row: line number, change marker and
+/// text, or a hatched gap where only the other side has a line.
+fn tab_diff_side(
+    text: Option<&str>,
+    line_no: usize,
+    marker: char,
+    style: Style,
+    muted: Style,
+    width: u16,
+) -> Line<'static> {
+    match text {
+        Some(text) => Line::from(vec![
+            Span::styled(format!("{line_no:>5} "), muted),
+            Span::styled(format!("{marker} {}", expand_tabs(text, 4)), style),
+        ]),
+        None => Line::from(Span::styled("╱".repeat(width as usize), muted)),
+    }
+}
+
+/// The active tab (left) and the picked tab (right) side by side, with
+/// changed, removed and added rows aligned and scrolled together.
+pub(crate) fn render_tab_diff(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let area = centered_rect(96, 90, frame.area());
+    let root = app.root.clone();
+    let Some(diff) = app.tab_diff.as_mut() else {
+        return;
+    };
+    let block = themed_block(&theme).title(format!(
+        "Diff: {} | {}",
+        relative_path(&root, &diff.left).display(),
+        relative_path(&root, &diff.right).display()
+    ));
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    let page = inner.height.saturating_sub(1) as usize;
+    diff.page = page;
+    diff.scroll = diff.scroll.min(diff.rows.len().saturating_sub(page));
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(Rect::new(inner.x, inner.y, inner.width, page as u16));
+
+    let text = Style::default().fg(theme.fg);
+    let removed = Style::default().fg(theme.diff_removed);
+    let added = Style::default().fg(theme.diff_added);
+    let muted = Style::default().fg(theme.fg_muted);
+    let (mut left_no, mut right_no) = (0, 0);
+    let (mut left_lines, mut right_lines) = (Vec::new(), Vec::new());
+    for (i, row) in diff.rows.iter().enumerate() {
+        left_no += usize::from(row.left.is_some());
+        right_no += usize::from(row.right.is_some());
+        if i < diff.scroll {
+            continue;
+        }
+        if i >= diff.scroll + page {
+            break;
+        }
+        let (marker, left_style, right_style) = match row.kind {
+            DiffRowKind::Same => (' ', text, text),
+            DiffRowKind::Changed => ('~', removed, added),
+            DiffRowKind::Removed => ('-', removed, text),
+            DiffRowKind::Added => ('+', text, added),
+        };
+        left_lines.push(tab_diff_side(
+            row.left.as_deref(),
+            left_no,
+            marker,
+            left_style,
+            muted,
+            columns[0].width,
+        ));
+        right_lines.push(tab_diff_side(
+            row.right.as_deref(),
+            right_no,
+            marker,
+            right_style,
+            muted,
+            columns[1].width,
+        ));
+    }
+    if diff.rows.is_empty() {
+        left_lines.push(Line::from(Span::styled("(both tabs are empty)", muted)));
+    }
+
+    frame.render_widget(
+        Paragraph::new(left_lines).block(
+            Block::default()
+                .borders(Borders::RIGHT)
+                .border_style(Style::default().fg(theme.border)),
+        ),
+        columns[0],
+    );
+    frame.render_widget(Paragraph::new(right_lines), columns[1]);
+    let hints = Rect::new(inner.x, inner.y + page as u16, inner.width, 1);
+    frame.render_widget(
+        Paragraph::new(Line::from(Span::styled(
+            "Up/Down/PgUp/PgDn/Home/End: Scroll both sides   Esc: Close",
+            muted,
+        ))),
+        hints,
+    );
+}
+
+pub(crate) fn render_symbol_picker(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let area = centered_rect(60, 60, frame.area());
+    app.symbol_picker.rect = area;
+    frame.render_widget(Clear, area);
+    let picker = &app.symbol_picker;
+    let mut lines: Vec<Line> = Vec::new();
+    lines.push(Line::from(vec![
+        Span::styled("Symbol: ", Style::default().fg(theme.fg_muted)),
+        Span::styled(picker.query.clone(), Style::default().fg(theme.fg)),
+    ]));
+    lines.push(Line::from(""));
+    if picker.results.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No matching symbols",
+            Style::default().fg(theme.fg_muted),
+        )));
+    } else {
+        for (idx, &sym_idx) in picker.results.iter().take(25).enumerate() {
+            let symbol = &picker.symbols[sym_idx];
+            let style = list_item_style(idx == picker.index, &theme);
+            lines.push(Line::from(vec![
+                Span::styled(symbol.label.clone(), style),
+                Span::styled(
+                    format!("  :{}", symbol.line + 1),
+                    Style::default().fg(theme.fg_muted),
+                ),
+            ]));
+        }
+    }
+    let paragraph = Paragraph::new(lines)
+        .style(Style::default().fg(theme.fg).bg(theme.bg_alt))
+        .block(
+            themed_block(&theme)
+                .title("Go to Symbol")
+                .style(Style::default().bg(theme.bg_alt)),
+        );
+    frame.render_widget(paragraph, area);
+}
+
 pub(crate) fn render_file_picker(app: &mut App, frame: &mut Frame<'_>) {
     let theme = app.active_theme().clone();
     let area = centered_rect(72, 65, frame.area());
@@ -107,7 +381,10 @@ pub(crate) fn render_file_picker(app: &mut App, frame: &mut Frame<'_>) {
         .wrap(Wrap { trim: false })
         .block(
             themed_block(&theme)
-                .title(format!("Quick Open ({}+P)", primary_mod_label()))
+                .title(match &app.file_picker_scope {
+                    Some(dir) => format!("Find in {}", relative_path(&app.root, dir).display()),
+                    None => format!("Quick Open ({}+P)", primary_mod_label()),
+                })
                 .style(Style::default().bg(theme.bg_alt)),
         );
     frame.render_widget(paragraph, area);
@@ -118,22 +395,60 @@ pub(crate) fn render_search_results(app: &mut App, frame: &mut Frame<'_>) {
     let area = centered_rect(78, 72, frame.area());
     app.search_results_rect = area;
     frame.render_widget(Clear, area);
+    let rows = search_result_rows(&app.search_results.results);
+    let visible = area.height.saturating_sub(2) as usize;
+    if let Some(selected_row) = rows
+        .iter()
+        .position(|row| *row == SearchResultRow::Hit(app.search_results.index))
+    {
+        let scroll = &mut app.search_results.scroll;
+        if selected_row < *scroll {
+            // Keep the file header in view when selecting a group's first hit.
+            *scroll = if selected_row > 0
+                && matches!(rows[selected_row - 1], SearchResultRow::Header { .. })
+            {
+                selected_row - 1
+            } else {
+                selected_row
+            };
+        } else if visible > 0 && selected_row >= *scroll + visible {
+            *scroll = selected_row + 1 - visible;
+        }
+    }
     let list_items: Vec<ListItem> = if app.search_results.results.is_empty() {
         vec![ListItem::new(Line::from("No results"))]
     } else {
-        app.search_results
-            .results
-            .iter()
-            .enumerate()
-            .map(|(idx, hit)| {
-                let rel = relative_path(&app.root, &hit.path);
-                let label = format!("{}:{}  {}", rel.display(), hit.line, hit.preview);
-                let style = if idx == app.search_results.index {
-                    list_item_style(true, &theme)
-                } else {
-                    list_item_style(false, &theme)
-                };
-                ListItem::new(Line::from(Span::styled(label, style)))
+        rows.iter()
+            .skip(app.search_results.scroll)
+            .take(visible)
+            .map(|row| match row {
+                SearchResultRow::Header { path, count, .. } => {
+                    let rel = relative_path(&app.root, path);
+                    ListItem::new(Line::from(vec![
+                        Span::styled(
+                            rel.display().to_string(),
+                            Style::default()
+                                .fg(theme.accent_secondary)
+                                .add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(
+                            format!("  ({} match{})", count, if *count == 1 { "" } else { "es" }),
+                            Style::default().fg(theme.fg_muted),
+                        ),
+                    ]))
+                }
+                SearchResultRow::Context { line, text, .. } => {
+                    ListItem::new(Line::from(Span::styled(
+                        format!("{:>6}- {}", line, text),
+                        Style::default().fg(theme.fg_muted),
+                    )))
+                }
+                SearchResultRow::Hit(idx) => {
+                    let hit = &app.search_results.results[*idx];
+                    let label = format!("{:>6}: {}", hit.line, hit.preview);
+                    let style = list_item_style(*idx == app.search_results.index, &theme);
+                    ListItem::new(Line::from(Span::styled(label, style)))
+                }
             })
             .collect()
     };
@@ -142,6 +457,81 @@ pub(crate) fn render_search_results(app: &mut App, frame: &mut Frame<'_>) {
     frame.render_widget(list, area);
 }
 
+pub(crate) fn render_diagnostics_panel(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let area = centered_rect(78, 72, frame.area());
+    app.diagnostics_panel.rect = area;
+    frame.render_widget(Clear, area);
+    let entries = app.project_diagnostics();
+    let rows = diagnostic_panel_rows(&entries);
+    let panel = &mut app.diagnostics_panel;
+    panel.index = panel.index.min(entries.len().saturating_sub(1));
+    let visible = area.height.saturating_sub(2) as usize;
+    if let Some(selected_row) = rows
+        .iter()
+        .position(|row| *row == DiagnosticPanelRow::Item(panel.index))
+    {
+        if selected_row < panel.scroll {
+            // Keep the file header in view when selecting a file's first problem.
+            panel.scroll = selected_row.saturating_sub(1);
+        } else if visible > 0 && selected_row >= panel.scroll + visible {
+            panel.scroll = selected_row + 1 - visible;
+        }
+    }
+    let list_items: Vec<ListItem> = if entries.is_empty() {
+        vec![ListItem::new(Line::from("No problems"))]
+    } else {
+        rows.iter()
+            .skip(panel.scroll)
+            .take(visible)
+            .map(|row| match row {
+                DiagnosticPanelRow::Header {
+                    path,
+                    errors,
+                    warnings,
+                    others,
+                    ..
+                } => {
+                    let counts: Vec<String> =
+                        [(errors, "error"), (warnings, "warning"), (others, "other")]
+                            .into_iter()
+                            .filter(|(n, _)| **n > 0)
+                            .map(|(n, what)| {
+                                format!("{n} {what}{}", if *n == 1 { "" } else { "s" })
+                            })
+                            .collect();
+                    ListItem::new(Line::from(vec![
+                        Span::styled(
+                            relative_path(&app.root, path).display().to_string(),
+                            Style::default()
+                                .fg(theme.accent_secondary)
+                                .add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(
+                            format!("  ({})", counts.join(", ")),
+                            Style::default().fg(theme.fg_muted),
+                        ),
+                    ]))
+                }
+                DiagnosticPanelRow::Item(idx) => {
+                    let diag = &entries[*idx].diagnostic;
+                    let style = list_item_style(*idx == panel.index, &theme);
+                    ListItem::new(Line::from(vec![
+                        Span::styled(format!("{:>6}:{:<4}", diag.line, diag.col + 1), style),
+                        Span::styled(
+                            format!("{:<8}", diag.severity),
+                            style.fg(severity_color(&diag.severity)),
+                        ),
+                        Span::styled(diag.message.lines().next().unwrap_or("").to_string(), style),
+                    ]))
+                }
+            })
+            .collect()
+    };
+    let list = List::new(list_items).block(themed_block(&theme).title("Problems"));
+    frame.render_widget(list, area);
+}
+
 pub(crate) fn render_completion_popup(app: &mut App, frame: &mut Frame<'_>) {
     let theme = app.active_theme().clone();
     let width = 56;
@@ -351,6 +741,7 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
                 ),
                 (&kb.display_for(KeyAction::QuickOpen), "quick open"),
                 (&kb.display_for(KeyAction::GoToLine), "go to line"),
+                (&kb.display_for(KeyAction::GoToSymbol), "go to symbol"),
             ],
             key_s,
             desc_s,
@@ -361,6 +752,23 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
                 (&kb.display_for(KeyAction::ToggleFiles), "toggle files"),
                 (&kb.display_for(KeyAction::RefreshTree), "refresh tree"),
                 (&kb.display_for(KeyAction::ToggleWordWrap), "toggle wrap"),
+                (&kb.display_for(KeyAction::CycleTreeSort), "tree sort"),
+                (&kb.display_for(KeyAction::ToggleZen), "zen mode"),
+            ],
+            key_s,
+            desc_s,
+            sep_s,
+        ),
+        help_keybind_line(
+            &[
+                (
+                    &kb.display_for(KeyAction::IncreaseFilesPane),
+                    "widen files pane",
+                ),
+                (
+                    &kb.display_for(KeyAction::DecreaseFilesPane),
+                    "narrow files pane",
+                ),
             ],
             key_s,
             desc_s,
@@ -376,6 +784,15 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
             desc_s,
             sep_s,
         ),
+        help_keybind_line(
+            &[
+                (&kb.display_for(KeyAction::NextSearchResult), "next result"),
+                (&kb.display_for(KeyAction::PrevSearchResult), "prev result"),
+            ],
+            key_s,
+            desc_s,
+            sep_s,
+        ),
         help_keybind_line(
             &[(
                 &kb.display_for(KeyAction::GoToDefinition),
@@ -405,6 +822,15 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
             desc_s,
             sep_s,
         ),
+        help_keybind_line(
+            &[
+                (&kb.display_for(KeyAction::NextFold), "next fold"),
+                (&kb.display_for(KeyAction::PrevFold), "previous fold"),
+            ],
+            key_s,
+            desc_s,
+            sep_s,
+        ),
         help_keybind_line(
             &[
                 (&kb.display_for(KeyAction::DupLineDown), "dup line down"),
@@ -435,6 +861,29 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
             desc_s,
             sep_s,
         ),
+        help_keybind_line(
+            &[
+                (&kb.display_for(KeyAction::CenterCursorLine), "center line"),
+                (&kb.display_for(KeyAction::ScrollCursorToTop), "line to top"),
+                (
+                    &kb.display_for(KeyAction::ScrollCursorToBottom),
+                    "line to bottom",
+                ),
+            ],
+            key_s,
+            desc_s,
+            sep_s,
+        ),
+        help_keybind_line(
+            &[
+                (&kb.display_for(KeyAction::NextDiagnostic), "next problem"),
+                (&kb.display_for(KeyAction::PrevDiagnostic), "prev problem"),
+                (&kb.display_for(KeyAction::ShowDiagnostics), "all problems"),
+            ],
+            key_s,
+            desc_s,
+            sep_s,
+        ),
         help_keybind_line(
             &[
                 ("Tab", "completion"),
@@ -461,6 +910,17 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
                 (&kb.display_for(KeyAction::CutLine), "cut line"),
                 (&kb.display_for(KeyAction::Paste), "paste"),
                 (&kb.display_for(KeyAction::ToggleComment), "toggle comment"),
+                (&kb.display_for(KeyAction::ExpandEmmet), "emmet (HTML)"),
+            ],
+            key_s,
+            desc_s,
+            sep_s,
+        ),
+        help_keybind_line(
+            &[
+                (&kb.display_for(KeyAction::ReflowParagraph), "reflow"),
+                (&kb.display_for(KeyAction::ToggleTaskCheckbox), "task box"),
+                (&kb.display_for(KeyAction::ToggleListItem), "list item"),
             ],
             key_s,
             desc_s,
@@ -470,6 +930,10 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
             &[
                 (&kb.display_for(KeyAction::PrevTab), "prev tab"),
                 (&kb.display_for(KeyAction::NextTab), "next tab"),
+                (&kb.display_for(KeyAction::LastUsedTab), "last tab"),
+                (&kb.display_for(KeyAction::NavigateBack), "back"),
+                (&kb.display_for(KeyAction::NavigateForward), "forward"),
+                (&kb.display_for(KeyAction::GoToLastEdit), "last edit"),
                 (&kb.display_for(KeyAction::Help), "help"),
             ],
             key_s,
@@ -507,7 +971,26 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
             desc_s,
             sep_s,
         ),
-        help_keybind_line(&[("Delete", "delete selected item")], key_s, desc_s, sep_s),
+        help_keybind_line(
+            &[
+                ("Space", "select"),
+                ("Shift+Up/Down", "select range"),
+                ("Delete", "delete selected items"),
+            ],
+            key_s,
+            desc_s,
+            sep_s,
+        ),
+        help_keybind_line(
+            &[
+                (&kb.display_for(KeyAction::Copy), "copy"),
+                (&kb.display_for(KeyAction::Cut), "cut"),
+                (&kb.display_for(KeyAction::Paste), "paste into folder"),
+            ],
+            key_s,
+            desc_s,
+            sep_s,
+        ),
         Line::from(""),
         Line::from(Span::styled("Mouse", heading)),
         Line::from(""),
@@ -526,7 +1009,11 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
             Span::styled(" to close", desc_s),
         ]),
         Line::from(Span::styled(
-            "Drag divider to resize  |  Right-click: context menus  |  Gutter click: fold",
+            "Drag divider to resize  |  Right-click: context menus  |  Fold arrow click: fold",
+            muted,
+        )),
+        Line::from(Span::styled(
+            "Middle-click in editor: paste the primary selection at the pointer",
             muted,
         )),
         Line::from(Span::styled(
@@ -657,7 +1144,10 @@ pub(crate) fn render_close_prompt(app: &mut App, frame: &mut Frame<'_>) {
     let text = vec![
         "Unsaved changes".to_string(),
         "".to_string(),
-        format!("Enter or {}+S: Save and close", primary_mod_label()),
+        format!(
+            "{}: Save and close",
+            confirm_key_label(&app.pending, app.confirm_key)
+        ),
         "Esc: Discard and close".to_string(),
         "C: Cancel".to_string(),
     ]
@@ -665,50 +1155,217 @@ pub(crate) fn render_close_prompt(app: &mut App, frame: &mut Frame<'_>) {
     render_dialog(area, "Close File", text, theme, frame);
 }
 
+pub(crate) fn render_quit_prompt(app: &mut App, frame: &mut Frame<'_>) {
+    const MAX_LISTED: usize = 8;
+    let theme = app.active_theme();
+    let dirty = app.dirty_tab_paths();
+    let area = centered_rect(64, 50, frame.area());
+    let mut text = vec![format!("{} file(s) have unsaved changes:", dirty.len())];
+    for path in dirty.iter().take(MAX_LISTED) {
+        text.push(format!("- {}", relative_path(&app.root, path).display()));
+    }
+    if dirty.len() > MAX_LISTED {
+        text.push(format!("... and {} more", dirty.len() - MAX_LISTED));
+    }
+    text.extend([
+        "".to_string(),
+        format!(
+            "{}: Save all and quit",
+            confirm_key_label(&app.pending, app.confirm_key)
+        ),
+        "D: Discard all and quit".to_string(),
+        "Esc or C: Cancel".to_string(),
+    ]);
+    render_dialog(area, "Quit", text.join("\n"), theme, frame);
+}
+
 pub(crate) fn render_delete_prompt(app: &mut App, frame: &mut Frame<'_>) {
-    let PendingAction::Delete(path) = &app.pending else {
+    let PendingAction::Delete { summary, .. } = &app.pending else {
         return;
     };
     let theme = app.active_theme();
     let area = centered_rect(64, 28, frame.area());
-    let name = path
-        .file_name()
-        .map(|s| s.to_string_lossy().to_string())
-        .unwrap_or_else(|| path.display().to_string());
     let text = [
-        format!("Delete '{}' ?", name),
+        format!("Delete {summary} ?"),
         "".to_string(),
-        "Enter or Y: Confirm delete".to_string(),
+        format!(
+            "{}: Confirm delete",
+            confirm_key_label(&app.pending, app.confirm_key)
+        ),
         "Esc or N: Cancel".to_string(),
     ]
     .join("\n");
     render_dialog(area, "Confirm Delete", text, theme, frame);
 }
 
-pub(crate) fn render_conflict_prompt(app: &mut App, frame: &mut Frame<'_>) {
+pub(crate) fn render_batch_rename_prompt(app: &mut App, frame: &mut Frame<'_>) {
+    const MAX_LISTED: usize = 8;
+    let PendingAction::BatchRename { renames } = &app.pending else {
+        return;
+    };
+    let theme = app.active_theme();
+    let area = centered_rect(64, 50, frame.area());
+    let mut text = vec![format!("Rename {} item(s):", renames.len())];
+    for (from, to) in renames.iter().take(MAX_LISTED) {
+        text.push(format!(
+            "{} → {}",
+            relative_path(&app.root, from).display(),
+            to.file_name().unwrap_or_default().to_string_lossy()
+        ));
+    }
+    if renames.len() > MAX_LISTED {
+        text.push(format!("... and {} more", renames.len() - MAX_LISTED));
+    }
+    text.extend([
+        "".to_string(),
+        format!(
+            "{}: Rename all",
+            confirm_key_label(&app.pending, app.confirm_key)
+        ),
+        "Esc or N: Cancel".to_string(),
+    ]);
+    render_dialog(area, "Batch Rename", text.join("\n"), theme, frame);
+}
+
+pub(crate) fn render_open_with_prompt(app: &mut App, frame: &mut Frame<'_>) {
+    let PendingAction::OpenWith { path, .. } = &app.pending else {
+        return;
+    };
     let theme = app.active_theme();
-    let area = centered_rect(68, 30, frame.area());
+    let area = centered_rect(64, 28, frame.area());
     let text = [
-        "File changed on disk while you have unsaved edits.",
-        "",
-        "R: Reload disk version (discard current edits)",
-        "K: Keep local edits",
-        "D or Esc: Decide later",
+        format!(
+            "{} looks like a binary file.",
+            relative_path(&app.root, path).display()
+        ),
+        "".to_string(),
+        "O: Open with the system default app".to_string(),
+        "T: Open as text anyway (read-only)".to_string(),
+        "Esc: Cancel".to_string(),
     ]
     .join("\n");
-    render_dialog(area, "External Change Conflict", text, theme, frame);
+    render_dialog(area, "Open With", text, theme, frame);
+}
+
+/// Dialog with a scrollable unified diff of the buffer against `other`
+/// between a heading and the key hints.
+fn render_diff_dialog(
+    app: &mut App,
+    frame: &mut Frame<'_>,
+    title: &str,
+    heading: &str,
+    other_label: &str,
+    keys: &[&str],
+) {
+    render_unified_diff_dialog(
+        app,
+        frame,
+        title,
+        heading,
+        &format!("--- buffer  +++ {other_label}"),
+        |app| app.active_tab().map_or(&[], |t| &t.prompt_diff),
+        keys,
+    );
+}
+
+/// Dialog with a scrollable, already computed unified diff below a heading
+/// and a `---`/`+++` header line. `diff` picks the stored diff out of `app`;
+/// only the rows in view are drawn.
+fn render_unified_diff_dialog(
+    app: &mut App,
+    frame: &mut Frame<'_>,
+    title: &str,
+    heading: &str,
+    header: &str,
+    diff: fn(&App) -> &[DiffLine],
+    keys: &[&str],
+) {
+    let theme = app.active_theme().clone();
+    let area = centered_rect(80, 70, frame.area());
+    let inner_height = area.height.saturating_sub(2) as usize;
+    let page = inner_height.saturating_sub(keys.len() + 4);
+    app.prompt_diff_page = page;
+    app.prompt_diff_scroll = app
+        .prompt_diff_scroll
+        .min(diff(app).len().saturating_sub(page));
+    let diff = diff(app);
+
+    let muted = Style::default().fg(theme.fg_muted);
+    let mut lines = vec![
+        Line::from(heading.to_string()),
+        Line::from(Span::styled(header.to_string(), muted)),
+        Line::from(""),
+    ];
+    if diff.is_empty() {
+        lines.push(Line::from(Span::styled("(no differences)", muted)));
+    }
+    for row in diff.iter().skip(app.prompt_diff_scroll).take(page) {
+        let (prefix, style) = match row.kind {
+            DiffLineKind::Hunk => ("", Style::default().fg(theme.accent_secondary)),
+            DiffLineKind::Context => (" ", Style::default().fg(theme.fg)),
+            DiffLineKind::Added => ("+", Style::default().fg(theme.diff_added)),
+            DiffLineKind::Removed => ("-", Style::default().fg(theme.diff_removed)),
+        };
+        lines.push(Line::from(Span::styled(
+            format!("{prefix}{}", row.text),
+            style,
+        )));
+    }
+    while lines.len() < page + 3 {
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(""));
+    lines.extend(keys.iter().map(|k| Line::from(k.to_string())));
+
+    frame.render_widget(Clear, area);
+    let body = Paragraph::new(lines)
+        .style(Style::default().fg(theme.fg).bg(theme.bg_alt))
+        .block(themed_block(&theme).title(title.to_string()));
+    frame.render_widget(body, area);
+}
+
+pub(crate) fn render_conflict_prompt(app: &mut App, frame: &mut Frame<'_>) {
+    render_diff_dialog(
+        app,
+        frame,
+        "External Change Conflict",
+        "File changed on disk while you have unsaved edits.",
+        "disk",
+        &[
+            "R: Take disk version (discard current edits)",
+            "K: Keep my edits",
+            "D or Esc: Decide later   Up/Down/PgUp/PgDn: Scroll diff",
+        ],
+    );
+}
+
+pub(crate) fn render_unsaved_diff(app: &mut App, frame: &mut Frame<'_>) {
+    let name = app
+        .active_tab()
+        .map(|t| relative_path(&app.root, &t.path).display().to_string())
+        .unwrap_or_default();
+    render_unified_diff_dialog(
+        app,
+        frame,
+        "Unsaved Changes",
+        &format!("Changes to {name} since it was last opened or saved."),
+        "--- disk  +++ buffer",
+        |app| &app.unsaved_diff_lines,
+        &["Up/Down/PgUp/PgDn: Scroll diff   Esc: Close"],
+    );
 }
 
 pub(crate) fn render_recovery_prompt(app: &mut App, frame: &mut Frame<'_>) {
-    let theme = app.active_theme();
-    let area = centered_rect(62, 28, frame.area());
-    let text = [
+    render_diff_dialog(
+        app,
+        frame,
+        "Recover Autosave",
         "Autosave content found for this file.",
-        "",
-        "Enter or R: Recover autosave",
-        "D: Discard autosave",
-        "Esc or C: Cancel",
-    ]
-    .join("\n");
-    render_dialog(area, "Recover Autosave", text, theme, frame);
+        "autosave",
+        &[
+            "Enter or R: Recover autosave",
+            "D: Discard autosave",
+            "Esc or C: Cancel   Up/Down/PgUp/PgDn: Scroll diff",
+        ],
+    );
 }
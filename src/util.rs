@@ -1,14 +1,27 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::{OsStr, OsString};
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::time::{Duration, Instant, SystemTime};
 
 use ratatui::layout::Rect;
+use regex::Regex;
 use url::Url;
 
-use crate::syntax::{SyntaxLang, comment_start_for_lang, syntax_lang_for_path};
-use crate::tab::{FoldRange, GitChangeSummary, GitFileStatus, GitLineStatus, ProjectSearchHit};
-use crate::types::{CommandAction, ContextAction, EditorContextAction, PendingAction};
+use crate::lsp_client::LspDiagnostic;
+use crate::syntax::{SyntaxLang, comment_start_for_lang, is_ident_char, syntax_lang_for_path};
+use crate::tab::{
+    DiagnosticPanelRow, FileSymbol, FoldRange, GitChangeSummary, GitFileStatus, GitLineStatus,
+    IndentSettings, ProjectDiagnostic, ProjectSearchHit, SearchResultRow, Tab,
+};
+use crate::tree_item::TreeItem;
+use crate::types::{
+    CommandAction, ConfirmKey, ContextAction, EditorContextAction, PendingAction, Platform,
+    ProjectKind, TreeSort,
+};
 
 /// Convert a text string to editor lines, preserving a trailing newline as an
 /// empty final line so the cursor can be positioned after the last content line.
@@ -26,23 +39,57 @@ pub(crate) fn text_to_lines(text: &str) -> Vec<String> {
     lines
 }
 
-pub(crate) fn pending_hint(pending: &PendingAction) -> String {
+pub(crate) fn pending_hint(pending: &PendingAction, confirm: ConfirmKey) -> String {
     let m = primary_mod_label();
+    let key = confirm_key_label(pending, confirm);
     match pending {
         PendingAction::None => String::new(),
-        PendingAction::Quit => format!("Pending quit: {}+Q confirm, Esc cancel", m),
+        PendingAction::QuitPrompt => format!(
+            "Pending quit: {key} save all, D discard all, {}+Q quit, Esc/C cancel",
+            m
+        ),
         PendingAction::ClosePrompt => {
-            format!(
-                "Pending close: Enter/{}+S save+close, Esc discard, C cancel",
-                m
-            )
-        }
-        PendingAction::Delete(path) => format!(
-            "Pending delete {}: Enter/Y confirm, Esc/N cancel",
-            path.file_name()
-                .map(|s| s.to_string_lossy().to_string())
-                .unwrap_or_else(|| path.display().to_string()),
+            format!("Pending close: {key} save+close, Esc discard, C cancel")
+        }
+        PendingAction::Delete { paths, .. } => format!(
+            "Pending delete {}: {key} confirm, Esc/N cancel",
+            describe_paths(paths),
+        ),
+        PendingAction::BatchRename { renames } => format!(
+            "Pending rename of {}: {key} confirm, Esc/N cancel",
+            plural(renames.len(), "item"),
         ),
+        PendingAction::OpenWith { path, .. } => format!(
+            "Binary file {}: O open externally, T open as text, Esc cancel",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+        ),
+    }
+}
+
+/// The key(s) that confirm `pending` under the `confirm_key` setting, e.g.
+/// "Enter/Y" or "Ctrl+D"; empty when the prompt has no confirm.
+pub(crate) fn confirm_key_label(pending: &PendingAction, confirm: ConfirmKey) -> String {
+    let Some((chord, letter)) = pending.confirm_keys() else {
+        return String::new();
+    };
+    match (confirm, letter) {
+        (ConfirmKey::Enter, Some(letter)) => format!("Enter/{}", letter.to_ascii_uppercase()),
+        (ConfirmKey::Enter, None) => "Enter".to_string(),
+        (ConfirmKey::Chord, _) => {
+            format!("{}+{}", primary_mod_label(), chord.to_ascii_uppercase())
+        }
+    }
+}
+
+/// Short name for a set of tree targets: the file name of a single path,
+/// otherwise a count.
+pub(crate) fn describe_paths(paths: &[PathBuf]) -> String {
+    match paths {
+        [path] => path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string()),
+        _ => format!("{} items", paths.len()),
     }
 }
 
@@ -55,6 +102,8 @@ pub(crate) fn command_action_label(action: CommandAction) -> &'static str {
         CommandAction::Theme => "Theme Picker",
         CommandAction::Help => "Help",
         CommandAction::QuickOpen => "Quick Open Files",
+        CommandAction::FindInFolder => "Find File in Selected Folder",
+        CommandAction::BatchRename => "Batch Rename Selection",
         CommandAction::FindInFile => "Find in File",
         CommandAction::FindInProject => "Search in Project",
         CommandAction::SaveFile => "Save File",
@@ -65,26 +114,73 @@ pub(crate) fn command_action_label(action: CommandAction) -> &'static str {
         CommandAction::GoToLine => "Go to Line",
         CommandAction::Keybinds => "Keybind Editor",
         CommandAction::ToggleWordWrap => "Toggle Word Wrap",
+        CommandAction::KeepTabOpen => "Keep Tab Open",
+        CommandAction::ReopenClosedTab => "Reopen Closed Tab",
+        CommandAction::NewUntitled => "New Untitled Buffer",
+        CommandAction::GoToSymbol => "Go to Symbol in File",
+        CommandAction::GoToLastEdit => "Go to Last Edit Location",
+        CommandAction::GoToMatchingTag => "Go to Matching Tag",
+        CommandAction::WordCount => "Word Count",
+        CommandAction::ReflowParagraph => "Reflow Paragraph",
+        CommandAction::ToggleTaskCheckbox => "Toggle Task Checkbox",
+        CommandAction::ToggleListItem => "Toggle List Item",
+        CommandAction::FormatMarkdownTable => "Format Markdown Table",
+        CommandAction::FormatDocument => "Format Document",
+        CommandAction::SetLanguage => "Set Language",
+        CommandAction::ConvertIndentationToSpaces => "Convert Indentation to Spaces",
+        CommandAction::ConvertIndentationToTabs => "Convert Indentation to Tabs",
+        CommandAction::ToggleTrailingWhitespace => "Toggle Trailing Whitespace Highlight",
+        CommandAction::ToggleBracketColorization => "Toggle Bracket Pair Colorization",
+        CommandAction::ToggleRainbowIndent => "Toggle Rainbow Indentation",
+        CommandAction::ToggleSpellCheck => "Toggle Spell Check",
+        CommandAction::ToggleLineNumbers => "Toggle Line Numbers",
+        CommandAction::ToggleFoldArrows => "Toggle Fold Arrows",
+        CommandAction::ToggleMixedIndentLint => "Toggle Mixed Indentation Warnings",
+        CommandAction::FixMixedIndentation => "Fix Mixed Indentation",
+        CommandAction::ToggleAutoSave => "Toggle Auto Save",
+        CommandAction::CycleTreeSort => "Tree: Cycle Sort Order",
+        CommandAction::ToggleTreeSortDirection => "Tree: Toggle Sort Direction",
+        CommandAction::ToggleTreeDirsFirst => "Tree: Toggle Folders First",
+        CommandAction::ToggleTreeDetails => "Tree: Toggle Size and Modified Time",
+        CommandAction::RevealInFileManager => "Reveal in File Manager",
+        CommandAction::ToggleFilesPaneSide => "Move Files Pane to Other Side",
+        CommandAction::ToggleZenMode => "Toggle Zen Mode",
+        CommandAction::SetZenWidth => "Set Zen Mode Width",
+        CommandAction::CycleLineSpacing => "Cycle Line Spacing",
+        CommandAction::ToggleReadOnly => "Toggle Read-Only",
+        CommandAction::TogglePinTab => "Pin/Unpin Tab",
+        CommandAction::DiffTabs => "Diff With Tab",
+        CommandAction::ShowUnsavedDiff => "Show Unsaved Changes",
+        CommandAction::OpenFolder => "Open Folder",
+        CommandAction::OpenRecentProject => "Open Recent Project",
+        CommandAction::ToggleDiagnosticErrorsOnly => "Toggle Errors-Only Diagnostic Navigation",
+        CommandAction::ShowDiagnostics => "Show Problems",
+        CommandAction::RestartLsp => "Restart Language Server",
+        CommandAction::ReloadConfig => "Reload Config",
     }
 }
 
-pub(crate) fn context_actions() -> [ContextAction; 6] {
+pub(crate) fn context_actions() -> [ContextAction; 9] {
     [
         ContextAction::Open,
         ContextAction::NewFile,
         ContextAction::NewFolder,
         ContextAction::Rename,
         ContextAction::Delete,
+        ContextAction::Reveal,
+        ContextAction::ExpandAll,
+        ContextAction::CollapseAll,
         ContextAction::Cancel,
     ]
 }
 
-pub(crate) fn editor_context_actions() -> [EditorContextAction; 5] {
+pub(crate) fn editor_context_actions() -> [EditorContextAction; 6] {
     [
         EditorContextAction::Copy,
         EditorContextAction::Cut,
         EditorContextAction::Paste,
         EditorContextAction::SelectAll,
+        EditorContextAction::AddToDictionary,
         EditorContextAction::Cancel,
     ]
 }
@@ -96,6 +192,9 @@ pub(crate) fn context_label(action: ContextAction) -> &'static str {
         ContextAction::NewFolder => "New Folder",
         ContextAction::Rename => "Rename",
         ContextAction::Delete => "Delete",
+        ContextAction::Reveal => "Reveal in File Manager",
+        ContextAction::ExpandAll => "Expand All Under",
+        ContextAction::CollapseAll => "Collapse All",
         ContextAction::Cancel => "Cancel",
     }
 }
@@ -106,6 +205,7 @@ pub(crate) fn editor_context_label(action: EditorContextAction) -> &'static str
         EditorContextAction::Cut => "Cut",
         EditorContextAction::Paste => "Paste",
         EditorContextAction::SelectAll => "Select All",
+        EditorContextAction::AddToDictionary => "Add to Dictionary",
         EditorContextAction::Cancel => "Cancel",
     }
 }
@@ -119,6 +219,475 @@ pub(crate) fn leading_indent_bytes(line: &str) -> usize {
     i
 }
 
+/// Whether `line`'s leading whitespace mixes tabs and spaces.
+pub(crate) fn has_mixed_indent(line: &str) -> bool {
+    let indent = &line.as_bytes()[..leading_indent_bytes(line)];
+    indent.contains(&b'\t') && indent.contains(&b' ')
+}
+
+/// Rows whose indentation mixes tabs and spaces.
+pub(crate) fn mixed_indent_rows(lines: &[String]) -> Vec<usize> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| has_mixed_indent(line))
+        .map(|(row, _)| row)
+        .collect()
+}
+
+/// Char range of the identifier under or just before char column `col`.
+/// Numbers are not treated as identifiers.
+pub(crate) fn identifier_range_at(line: &str, col: usize) -> Option<(usize, usize)> {
+    let chars: Vec<char> = line.chars().collect();
+    let col = col.min(chars.len());
+    let anchor = if chars.get(col).is_some_and(|c| is_ident_char(*c)) {
+        col
+    } else if col > 0 && is_ident_char(chars[col - 1]) {
+        col - 1
+    } else {
+        return None;
+    };
+    let mut start = anchor;
+    while start > 0 && is_ident_char(chars[start - 1]) {
+        start -= 1;
+    }
+    let mut end = anchor + 1;
+    while end < chars.len() && is_ident_char(chars[end]) {
+        end += 1;
+    }
+    if chars[start].is_ascii_digit() {
+        return None;
+    }
+    Some((start, end))
+}
+
+/// Char ranges of whole-word occurrences of `word` in `line`.
+pub(crate) fn word_occurrences(line: &str, word: &str) -> Vec<(usize, usize)> {
+    if word.is_empty() {
+        return Vec::new();
+    }
+    let word_len = word.chars().count();
+    line.match_indices(word)
+        .filter(|(idx, _)| {
+            let before = line[..*idx].chars().next_back();
+            let after = line[idx + word.len()..].chars().next();
+            !before.is_some_and(is_ident_char) && !after.is_some_and(is_ident_char)
+        })
+        .map(|(idx, _)| {
+            let start = line[..idx].chars().count();
+            (start, start + word_len)
+        })
+        .collect()
+}
+
+/// HTML elements that never have a closing tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// An open or close tag found by `scan_tags`. Positions are (row, char
+/// column); `start` is the `<` and `end` the `>`.
+struct TagToken {
+    name: String,
+    closing: bool,
+    start: (usize, usize),
+    end: (usize, usize),
+    name_start: (usize, usize),
+}
+
+/// The open and close tags in `lines`, in order. Comments, declarations,
+/// processing instructions, self-closing tags and void elements are left
+/// out, as is a tag with no closing `>`.
+fn scan_tags(lines: &[String]) -> Vec<TagToken> {
+    let mut chars = lines
+        .iter()
+        .enumerate()
+        .flat_map(|(row, line)| {
+            let end = (row, line.chars().count(), '\n');
+            line.chars()
+                .enumerate()
+                .map(move |(col, ch)| (row, col, ch))
+                .chain(std::iter::once(end))
+        })
+        .peekable();
+    let mut tags = Vec::new();
+    while let Some((row, col, ch)) = chars.next() {
+        if ch != '<' {
+            continue;
+        }
+        if chars.next_if(|&(_, _, c)| c == '!').is_some() {
+            let is_comment = chars.next_if(|&(_, _, c)| c == '-').is_some()
+                && chars.next_if(|&(_, _, c)| c == '-').is_some();
+            let mut dashes = 0;
+            for (_, _, c) in chars.by_ref() {
+                if c == '>' && (!is_comment || dashes >= 2) {
+                    break;
+                }
+                dashes = if c == '-' { dashes + 1 } else { 0 };
+            }
+            continue;
+        }
+        if chars.next_if(|&(_, _, c)| c == '?').is_some() {
+            chars.by_ref().find(|&(_, _, c)| c == '>');
+            continue;
+        }
+        let closing = chars.next_if(|&(_, _, c)| c == '/').is_some();
+        let Some(&(name_row, name_col, first)) = chars.peek() else {
+            break;
+        };
+        if !first.is_ascii_alphabetic() {
+            continue;
+        }
+        let is_name_char =
+            |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.');
+        let mut name = String::new();
+        while let Some((_, _, c)) = chars.next_if(|&(_, _, c)| is_name_char(c)) {
+            name.push(c);
+        }
+        let mut quote = None;
+        let mut last = ' ';
+        let mut end = None;
+        for (r, c, ch) in chars.by_ref() {
+            match (quote, ch) {
+                (Some(q), _) if ch == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(ch),
+                (None, '>') => {
+                    end = Some((r, c));
+                    break;
+                }
+                _ => {}
+            }
+            if !ch.is_whitespace() {
+                last = ch;
+            }
+        }
+        let Some(end) = end else {
+            break;
+        };
+        let void = VOID_ELEMENTS.iter().any(|v| v.eq_ignore_ascii_case(&name));
+        if last == '/' || void {
+            continue;
+        }
+        tags.push(TagToken {
+            name,
+            closing,
+            start: (row, col),
+            end,
+            name_start: (name_row, name_col),
+        });
+    }
+    tags
+}
+
+/// The names of the tag under `cursor` and of its partner, as (row, start
+/// column, end column) char ranges. `None` when the cursor isn't on an open
+/// or close tag or the tag has no partner.
+pub(crate) fn matching_tag(
+    lines: &[String],
+    cursor: (usize, usize),
+) -> Option<[(usize, usize, usize); 2]> {
+    let tags = scan_tags(lines);
+    let here = tags
+        .iter()
+        .position(|t| t.start <= cursor && cursor <= t.end)?;
+    let tag = &tags[here];
+    let mut depth = 0usize;
+    let same_name = |t: &&TagToken| t.name.eq_ignore_ascii_case(&tag.name);
+    let partner = if tag.closing {
+        tags[..here].iter().rev().filter(same_name).find(|t| {
+            if t.closing {
+                depth += 1;
+            } else if depth == 0 {
+                return true;
+            } else {
+                depth -= 1;
+            }
+            false
+        })
+    } else {
+        tags[here + 1..].iter().filter(same_name).find(|t| {
+            if !t.closing {
+                depth += 1;
+            } else if depth == 0 {
+                return true;
+            } else {
+                depth -= 1;
+            }
+            false
+        })
+    }?;
+    let span = |t: &TagToken| {
+        let (row, col) = t.name_start;
+        (row, col, col + t.name.chars().count())
+    };
+    Some([span(tag), span(partner)])
+}
+
+/// Char range `[start, end)` of the spaces and tabs at the end of `line`.
+pub(crate) fn trailing_whitespace_range(line: &str) -> Option<(usize, usize)> {
+    let end = line.chars().count();
+    let trailing = line
+        .chars()
+        .rev()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .count();
+    (trailing > 0).then(|| (end - trailing, end))
+}
+
+/// Rank of an LSP severity name, lower being more severe.
+pub(crate) fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "error" => 0,
+        "warning" => 1,
+        "info" => 2,
+        "hint" => 3,
+        _ => 4,
+    }
+}
+
+/// The diagnostics to draw when at most `max_per_line` may start on any one
+/// line (0 for no cap). The most severe, then leftmost, are kept.
+pub(crate) fn visible_diagnostics(
+    diagnostics: &[LspDiagnostic],
+    max_per_line: usize,
+) -> Vec<&LspDiagnostic> {
+    let mut sorted: Vec<&LspDiagnostic> = diagnostics.iter().collect();
+    if max_per_line == 0 {
+        return sorted;
+    }
+    sorted.sort_by_key(|d| (d.line, severity_rank(&d.severity), d.col));
+    let mut out = Vec::with_capacity(sorted.len());
+    let mut shown_on_line = 0;
+    for (i, d) in sorted.iter().enumerate() {
+        if i == 0 || sorted[i - 1].line != d.line {
+            shown_on_line = 0;
+        }
+        if shown_on_line < max_per_line {
+            out.push(*d);
+            shown_on_line += 1;
+        }
+    }
+    out
+}
+
+/// Char ranges `[start, end)` of line `row` (0-based) covered by
+/// `diagnostics`, each paired with the most severe severity covering it.
+/// Multi-line ranges cover the rest of their first line and the start of
+/// their last; an empty range marks the single char it points at.
+pub(crate) fn diagnostic_underlines<'a>(
+    diagnostics: impl IntoIterator<Item = &'a LspDiagnostic>,
+    row: usize,
+    line_len: usize,
+) -> Vec<(usize, usize, &'a str)> {
+    let mut best: Vec<Option<&str>> = vec![None; line_len];
+    for d in diagnostics {
+        let (first, last) = (d.line.saturating_sub(1), d.end_line.saturating_sub(1));
+        if row < first || row > last.max(first) {
+            continue;
+        }
+        let start = if row == first { d.col } else { 0 };
+        let mut end = if row == last { d.end_col } else { line_len };
+        if row == first && end <= start {
+            end = start + 1;
+        }
+        let start = start.min(line_len.saturating_sub(1));
+        for slot in best.iter_mut().take(end).skip(start) {
+            if slot.is_none_or(|s| severity_rank(&d.severity) < severity_rank(s)) {
+                *slot = Some(&d.severity);
+            }
+        }
+    }
+    let mut out: Vec<(usize, usize, &str)> = Vec::new();
+    for (i, severity) in best.into_iter().enumerate() {
+        let Some(severity) = severity else {
+            continue;
+        };
+        match out.last_mut() {
+            Some((_, end, last)) if *end == i && *last == severity => *end = i + 1,
+            _ => out.push((i, i + 1, severity)),
+        }
+    }
+    out
+}
+
+/// Screen width of `ch` when drawn at display column `col`; a tab runs to the
+/// next multiple of `tab_width`.
+fn char_display_width(ch: char, col: usize, tab_width: usize) -> usize {
+    if ch == '\t' {
+        let tab_width = tab_width.max(1);
+        tab_width - col % tab_width
+    } else {
+        unicode_width::UnicodeWidthChar::width(ch).unwrap_or(0)
+    }
+}
+
+/// Display column at which char index `char_idx` of `text` is drawn.
+pub(crate) fn display_col(text: &str, char_idx: usize, tab_width: usize) -> usize {
+    text.chars()
+        .take(char_idx)
+        .fold(0, |col, ch| col + char_display_width(ch, col, tab_width))
+}
+
+/// Char index of the character drawn over display column `col`, or the char
+/// count when `col` lies past the end of `text`.
+pub(crate) fn char_at_display_col(text: &str, col: usize, tab_width: usize) -> usize {
+    let mut width_acc = 0usize;
+    for (i, ch) in text.chars().enumerate() {
+        width_acc += char_display_width(ch, width_acc, tab_width);
+        if width_acc > col {
+            return i;
+        }
+    }
+    text.chars().count()
+}
+
+/// `text` with each tab replaced by the spaces that reach the next tab stop.
+pub(crate) fn expand_tabs(text: &str, tab_width: usize) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut col = 0usize;
+    for ch in text.chars() {
+        let width = char_display_width(ch, col, tab_width);
+        if ch == '\t' {
+            out.extend(std::iter::repeat_n(' ', width));
+        } else {
+            out.push(ch);
+        }
+        col += width;
+    }
+    out
+}
+
+/// Rewrite each line's leading whitespace as hard tabs (with spaces for any
+/// remainder) or as spaces only, treating a tab as `width` columns.
+pub(crate) fn convert_indentation(lines: &[String], to_tabs: bool, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    lines
+        .iter()
+        .map(|line| {
+            let indent = leading_indent_bytes(line);
+            let mut cols = 0usize;
+            for b in line[..indent].bytes() {
+                cols = if b == b'\t' {
+                    (cols / width + 1) * width
+                } else {
+                    cols + 1
+                };
+            }
+            let new_indent = if to_tabs {
+                format!("{}{}", "\t".repeat(cols / width), " ".repeat(cols % width))
+            } else {
+                " ".repeat(cols)
+            };
+            format!("{new_indent}{}", &line[indent..])
+        })
+        .collect()
+}
+
+/// Match an EditorConfig section glob against a file name. Supports `*`,
+/// `?`, `{a,b}` alternatives and a leading `**/`; patterns naming a
+/// subdirectory are not supported and never match.
+pub(crate) fn editorconfig_glob_matches(pattern: &str, file_name: &str) -> bool {
+    let pattern = pattern.strip_prefix("**/").unwrap_or(pattern);
+    if pattern.contains('/') {
+        return false;
+    }
+    if let (Some(open), Some(close)) = (pattern.find('{'), pattern.find('}')) {
+        if open < close {
+            let (head, tail) = (&pattern[..open], &pattern[close + 1..]);
+            return pattern[open + 1..close]
+                .split(',')
+                .any(|alt| editorconfig_glob_matches(&format!("{head}{alt}{tail}"), file_name));
+        }
+    }
+    fn wildcard(p: &[char], s: &[char]) -> bool {
+        match p.split_first() {
+            None => s.is_empty(),
+            Some(('*', rest)) => (0..=s.len()).any(|i| wildcard(rest, &s[i..])),
+            Some(('?', rest)) => !s.is_empty() && wildcard(rest, &s[1..]),
+            Some((c, rest)) => s.first() == Some(c) && wildcard(rest, &s[1..]),
+        }
+    }
+    let p: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = file_name.chars().collect();
+    wildcard(&p, &s)
+}
+
+/// Indentation for `path`: `defaults` overridden by `indent_style`,
+/// `indent_size` and `tab_width` from `.editorconfig` files between the file
+/// and `root`; `tab_width` also sets how wide hard tabs are drawn. Closer files win; a file with `root = true` stops the search.
+pub(crate) fn indent_settings_for_path(
+    path: &Path,
+    root: &Path,
+    defaults: IndentSettings,
+) -> IndentSettings {
+    let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+        return defaults;
+    };
+    if !path.starts_with(root) {
+        return defaults;
+    }
+    let mut configs = Vec::new();
+    let mut dir = path.parent();
+    while let Some(d) = dir {
+        if let Ok(text) = fs::read_to_string(d.join(".editorconfig")) {
+            let is_root = text.lines().any(|l| {
+                l.split_once('=').is_some_and(|(k, v)| {
+                    k.trim().eq_ignore_ascii_case("root") && v.trim().eq_ignore_ascii_case("true")
+                })
+            });
+            configs.push(text);
+            if is_root {
+                break;
+            }
+        }
+        if d == root {
+            break;
+        }
+        dir = d.parent();
+    }
+    let mut style = None;
+    let mut size = None;
+    let mut tab_width = None;
+    // Apply the outermost config first so nearer ones override it.
+    for text in configs.iter().rev() {
+        let mut active = false;
+        for line in text.lines().map(str::trim) {
+            if line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                active = editorconfig_glob_matches(section, file_name);
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=').filter(|_| active) else {
+                continue;
+            };
+            let value = value.trim().to_ascii_lowercase();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "indent_style" => style = Some(value == "tab"),
+                "indent_size" => size = Some(value),
+                "tab_width" => tab_width = value.parse::<u8>().ok().or(tab_width),
+                _ => {}
+            }
+        }
+    }
+    let hard_tabs = style.unwrap_or(defaults.hard_tabs);
+    let width = match size.as_deref() {
+        Some("tab") => tab_width,
+        Some(n) => n.parse::<u8>().ok().or(tab_width),
+        None => tab_width,
+    };
+    IndentSettings {
+        hard_tabs,
+        width: width.filter(|w| *w > 0).unwrap_or(defaults.width),
+        render_tab_width: tab_width
+            .filter(|w| *w > 0)
+            .unwrap_or(defaults.render_tab_width),
+    }
+}
+
 pub(crate) fn comment_prefix_for_path(path: &Path) -> Option<&'static str> {
     comment_start_for_lang(syntax_lang_for_path(Some(path))).or_else(|| {
         match path
@@ -147,9 +716,136 @@ pub(crate) fn parse_rg_line(line: &str) -> Option<ProjectSearchHit> {
         path: PathBuf::from(path),
         line: line_no,
         preview,
+        context_before: Vec::new(),
+        context_after: Vec::new(),
     })
 }
 
+/// Return up to `context` lines before and after the 1-based `line_no`.
+pub(crate) fn search_context_lines(
+    lines: &[String],
+    line_no: usize,
+    context: usize,
+) -> (Vec<String>, Vec<String>) {
+    if line_no == 0 || line_no > lines.len() {
+        return (Vec::new(), Vec::new());
+    }
+    let idx = line_no - 1;
+    let before = lines[idx.saturating_sub(context)..idx].to_vec();
+    let after_end = (idx + 1 + context).min(lines.len());
+    let after = lines[idx + 1..after_end].to_vec();
+    (before, after)
+}
+
+/// Fill in context lines for each hit, reading every matched file once.
+pub(crate) fn attach_search_context(hits: &mut [ProjectSearchHit], context: usize) {
+    if context == 0 {
+        return;
+    }
+    let mut cache: HashMap<PathBuf, Vec<String>> = HashMap::new();
+    for hit in hits.iter_mut() {
+        let lines = cache.entry(hit.path.clone()).or_insert_with(|| {
+            fs::read_to_string(&hit.path)
+                .map(|text| text.lines().map(ToString::to_string).collect())
+                .unwrap_or_default()
+        });
+        let (before, after) = search_context_lines(lines, hit.line, context);
+        hit.context_before = before;
+        hit.context_after = after;
+    }
+}
+
+/// Group hits by path in first-seen order, returning hit indices per file.
+pub(crate) fn group_search_hits(hits: &[ProjectSearchHit]) -> Vec<(PathBuf, Vec<usize>)> {
+    let mut groups: Vec<(PathBuf, Vec<usize>)> = Vec::new();
+    let mut index_of: HashMap<&Path, usize> = HashMap::new();
+    for (i, hit) in hits.iter().enumerate() {
+        if let Some(&g) = index_of.get(hit.path.as_path()) {
+            groups[g].1.push(i);
+        } else {
+            index_of.insert(hit.path.as_path(), groups.len());
+            groups.push((hit.path.clone(), vec![i]));
+        }
+    }
+    groups
+}
+
+/// Display rows for diagnostics already grouped by file: a header with the
+/// file's severity counts followed by a row per diagnostic.
+pub(crate) fn diagnostic_panel_rows(entries: &[ProjectDiagnostic]) -> Vec<DiagnosticPanelRow> {
+    let mut rows = Vec::new();
+    let mut start = 0;
+    while start < entries.len() {
+        let path = &entries[start].path;
+        let end = entries[start..]
+            .iter()
+            .position(|e| &e.path != path)
+            .map_or(entries.len(), |n| start + n);
+        let group = &entries[start..end];
+        let count = |severity: &str| {
+            group
+                .iter()
+                .filter(|e| e.diagnostic.severity == severity)
+                .count()
+        };
+        let (errors, warnings) = (count("error"), count("warning"));
+        rows.push(DiagnosticPanelRow::Header {
+            path: path.clone(),
+            errors,
+            warnings,
+            others: group.len() - errors - warnings,
+            first: start,
+        });
+        rows.extend((start..end).map(DiagnosticPanelRow::Item));
+        start = end;
+    }
+    rows
+}
+
+/// Flatten grouped hits into display rows: a header per file followed by
+/// each hit with its context, skipping context lines already shown.
+pub(crate) fn search_result_rows(hits: &[ProjectSearchHit]) -> Vec<SearchResultRow> {
+    let mut rows = Vec::new();
+    for (path, idxs) in group_search_hits(hits) {
+        rows.push(SearchResultRow::Header {
+            path,
+            count: idxs.len(),
+            first_hit: idxs[0],
+        });
+        let mut last_shown = 0usize;
+        for (pos, &i) in idxs.iter().enumerate() {
+            let hit = &hits[i];
+            let first_before = hit.line.saturating_sub(hit.context_before.len());
+            for (off, text) in hit.context_before.iter().enumerate() {
+                let line = first_before + off;
+                if line > last_shown {
+                    rows.push(SearchResultRow::Context {
+                        hit: i,
+                        line,
+                        text: text.clone(),
+                    });
+                }
+            }
+            rows.push(SearchResultRow::Hit(i));
+            last_shown = last_shown.max(hit.line);
+            let next_line = idxs.get(pos + 1).map_or(usize::MAX, |&n| hits[n].line);
+            for (off, text) in hit.context_after.iter().enumerate() {
+                let line = hit.line + 1 + off;
+                if line >= next_line {
+                    break;
+                }
+                rows.push(SearchResultRow::Context {
+                    hit: i,
+                    line,
+                    text: text.clone(),
+                });
+                last_shown = line;
+            }
+        }
+    }
+    rows
+}
+
 pub(crate) fn fuzzy_score(query: &str, candidate: &str) -> Option<usize> {
     if query.is_empty() {
         return Some(0);
@@ -174,6 +870,15 @@ pub(crate) fn fuzzy_score(query: &str, candidate: &str) -> Option<usize> {
     None
 }
 
+/// Project kinds whose marker file sits at `root`, in `ProjectKind::ALL`
+/// order.
+pub(crate) fn detect_project_kinds(root: &Path) -> Vec<ProjectKind> {
+    ProjectKind::ALL
+        .into_iter()
+        .filter(|kind| root.join(kind.marker()).is_file())
+        .collect()
+}
+
 pub(crate) fn detect_git_branch(root: &Path) -> Option<String> {
     let output = Command::new("git")
         .arg("-C")
@@ -377,6 +1082,79 @@ pub(crate) fn compute_git_change_summary(root: &Path) -> GitChangeSummary {
     summary
 }
 
+/// Pipe `input` through the formatter `command`, run in `dir`, and return
+/// what it printed. The command is split on whitespace and `{file}` in it
+/// stands for `file`. A failed run gives its stderr, or the exit status
+/// when stderr is empty; a run still going after `timeout` is killed.
+pub(crate) fn run_formatter(
+    command: &str,
+    dir: &Path,
+    file: Option<&Path>,
+    input: &str,
+    timeout: Duration,
+) -> Result<String, String> {
+    let file = file.map(|p| p.to_string_lossy()).unwrap_or_default();
+    let mut args = command
+        .split_whitespace()
+        .map(|arg| arg.replace("{file}", &file));
+    let program = args.next().ok_or("empty formatter command")?;
+    let mut child = Command::new(&program)
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("{program}: {e}"))?;
+    // Fed and drained from threads so a formatter that streams its output
+    // cannot fill a pipe while we are still feeding it, and so a stalled one
+    // can be killed without waiting on its pipes.
+    if let Some(mut stdin) = child.stdin.take() {
+        let input = input.to_string();
+        std::thread::spawn(move || {
+            let _ = stdin.write_all(input.as_bytes());
+        });
+    }
+    let stdout = child.stdout.take().map(read_pipe_in_background);
+    let stderr = child.stderr.take().map(read_pipe_in_background);
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!(
+                "{program} timed out after {:.1}s",
+                timeout.as_secs_f64()
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    let stdout = stdout.and_then(|h| h.join().ok()).unwrap_or_default();
+    let stderr = stderr.and_then(|h| h.join().ok()).unwrap_or_default();
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr).trim().to_string();
+        return Err(if stderr.is_empty() {
+            format!("{program} exited with {status}")
+        } else {
+            stderr
+        });
+    }
+    String::from_utf8(stdout).map_err(|_| format!("{program} printed invalid UTF-8"))
+}
+
+fn read_pipe_in_background(
+    mut pipe: impl std::io::Read + Send + 'static,
+) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        buf
+    })
+}
+
 fn git_status_entries(root: &Path) -> Option<Vec<(String, GitFileStatus)>> {
     let output = Command::new("git")
         .arg("-C")
@@ -455,6 +1233,96 @@ pub(crate) fn file_uri(path: &Path) -> Option<String> {
     Url::from_file_path(abs).ok().map(|u| u.to_string())
 }
 
+/// Bracket depth after `text`, starting from `depth`, skipping strings and
+/// comments the way `compute_fold_ranges` does. Used to start a wrapped
+/// continuation segment at the depth its line reached.
+pub(crate) fn bracket_depth_after(text: &str, lang: SyntaxLang, depth: u16) -> u16 {
+    let comment = comment_start_for_lang(lang);
+    let mut depth = depth;
+    let mut quote: Option<char> = None;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if let Some(q) = quote {
+            if ch == '\\' {
+                chars.next();
+            } else if ch == q {
+                quote = None;
+            }
+            continue;
+        }
+        let next = chars.peek().copied();
+        match (comment, ch, next) {
+            (Some("//"), '/', Some('/')) | (Some("/*"), '/', Some('*')) | (Some("#"), '#', _) => {
+                break;
+            }
+            _ => {}
+        }
+        match ch {
+            '"' | '\'' => quote = Some(ch),
+            '{' | '(' | '[' => depth = depth.saturating_add(1),
+            '}' | ')' | ']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    depth
+}
+
+/// `folded` start rows moved to where their lines ended up when the text
+/// changed from `old` to `new`. Rows above the changed lines stay, rows
+/// below shift by the change in line count, and rows inside it survive only
+/// when the edit replaced lines one for one.
+pub(crate) fn shift_folded_starts(
+    folded: &HashSet<usize>,
+    old: &[String],
+    new: &[String],
+) -> HashSet<usize> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take(old.len().min(new.len()) - prefix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    folded
+        .iter()
+        .filter_map(|&row| {
+            if row < prefix {
+                Some(row)
+            } else if row >= old_end {
+                Some(row - old_end + new_end)
+            } else if old_end - prefix == new_end - prefix {
+                Some(row)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Start rows of the ranges nested at least `level` deep, counting ranges
+/// inside no other as depth 0.
+pub(crate) fn fold_starts_at_level(ranges: &[FoldRange], level: usize) -> Vec<usize> {
+    let mut sorted: Vec<&FoldRange> = ranges.iter().collect();
+    sorted.sort_by_key(|r| (r.start_line, std::cmp::Reverse(r.end_line)));
+    // Brace and indent ranges can share a start; they are the same block.
+    sorted.dedup_by_key(|r| r.start_line);
+    // End rows of the ranges enclosing the current one.
+    let mut enclosing: Vec<usize> = Vec::new();
+    let mut starts = Vec::new();
+    for range in sorted {
+        while enclosing.last().is_some_and(|&end| end < range.end_line) {
+            enclosing.pop();
+        }
+        if enclosing.len() >= level {
+            starts.push(range.start_line);
+        }
+        enclosing.push(range.end_line);
+    }
+    starts
+}
+
 pub(crate) fn compute_fold_ranges(
     lines: &[String],
     lang: SyntaxLang,
@@ -519,8 +1387,10 @@ pub(crate) fn compute_fold_ranges(
         }
     }
 
-    // Indentation folding (good for Python/YAML-like + generally useful)
+    // Indentation folding (good for Python/YAML-like + generally useful).
+    // Blocks end on their last non-blank line so blank lines never extend them.
     let mut indent_stack: Vec<(usize, usize)> = Vec::new();
+    let mut last_content_row = None;
     for (row, line) in lines.iter().enumerate() {
         if line.trim().is_empty() {
             continue;
@@ -529,7 +1399,7 @@ pub(crate) fn compute_fold_ranges(
         while let Some((prev_indent, start_row)) = indent_stack.last().copied() {
             if indent <= prev_indent {
                 indent_stack.pop();
-                let end_row = row.saturating_sub(1);
+                let end_row = last_content_row.unwrap_or(start_row);
                 if end_row > start_row {
                     ranges.push(FoldRange {
                         start_line: start_row,
@@ -541,8 +1411,9 @@ pub(crate) fn compute_fold_ranges(
             }
         }
         indent_stack.push((indent, row));
+        last_content_row = Some(row);
     }
-    if let Some(last_row) = lines.len().checked_sub(1) {
+    if let Some(last_row) = last_content_row {
         while let Some((_, start_row)) = indent_stack.pop() {
             if last_row > start_row {
                 ranges.push(FoldRange {
@@ -597,6 +1468,201 @@ pub(crate) fn compute_fold_ranges(
     ranges.dedup_by(|a, b| a.start_line == b.start_line && a.end_line == b.end_line);
     (ranges, bracket_depths)
 }
+
+/// The leader to start the next line with when `line` is a line comment
+/// opened by `prefix`: its indentation, the comment marker (with any extra
+/// `/`, `!` or repeated marker characters, as in `///` or `##`) and the
+/// space after it.
+pub(crate) fn comment_continuation(line: &str, prefix: &str) -> Option<String> {
+    let indent = leading_indent_bytes(line);
+    let rest = line[indent..].strip_prefix(prefix)?;
+    let last = prefix.chars().last()?;
+    let extra = rest.len() - rest.trim_start_matches([last, '!']).len();
+    let leader = &line[..line.len() - rest.len() + extra];
+    Some(format!("{leader} "))
+}
+
+/// The text of `lines` from `start` up to `end`, as (row, char column)
+/// pairs, with lines joined by newlines.
+pub(crate) fn text_in_range(
+    lines: &[String],
+    start: (usize, usize),
+    end: (usize, usize),
+) -> String {
+    let mut out = String::new();
+    for (row, line) in lines.iter().enumerate().take(end.0 + 1).skip(start.0) {
+        let from = if row == start.0 { start.1 } else { 0 };
+        let to = if row == end.0 {
+            end.1
+        } else {
+            line.chars().count()
+        };
+        if row > start.0 {
+            out.push('\n');
+        }
+        out.extend(line.chars().skip(from).take(to.saturating_sub(from)));
+    }
+    out
+}
+
+/// Status bar summary of a buffer: "Sel: 2 lines, 9 chars" for a
+/// selection, plus a sum when it holds only numbers, or the buffer's totals
+/// (`char_count` being its cached character count) otherwise. Newlines
+/// don't count as characters.
+pub(crate) fn buffer_stats_label(
+    lines: &[String],
+    char_count: usize,
+    selection: Option<((usize, usize), (usize, usize))>,
+) -> String {
+    let Some((start, end)) = selection else {
+        return format!(
+            "{}, {}",
+            plural(lines.len(), "line"),
+            plural(char_count, "char")
+        );
+    };
+    let text = text_in_range(lines, start, end);
+    let chars = text.chars().filter(|&c| c != '\n').count();
+    // A selection ending at the start of a line doesn't take any of it.
+    let last_row = if end.1 == 0 && end.0 > start.0 {
+        end.0 - 1
+    } else {
+        end.0
+    };
+    let mut label = format!(
+        "Sel: {}, {}",
+        plural(last_row - start.0 + 1, "line"),
+        plural(chars, "char")
+    );
+    if let Some(sum) = numeric_sum(&text) {
+        label.push_str(&format!(", Sum: {sum}"));
+    }
+    label
+}
+
+/// Sum of the whitespace-separated numbers in `text` when it holds nothing
+/// else, shown with as many decimals as the most precise of them.
+fn numeric_sum(text: &str) -> Option<String> {
+    let mut sum = 0.0;
+    let mut decimals = 0;
+    let mut any = false;
+    for token in text.split_whitespace() {
+        // `f64` parsing also takes "inf", "NaN" and exponents.
+        if !token
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.'))
+        {
+            return None;
+        }
+        let value: f64 = token.parse().ok()?;
+        sum += value;
+        decimals = decimals.max(token.split_once('.').map_or(0, |(_, frac)| frac.len()));
+        any = true;
+    }
+    any.then(|| format!("{sum:.decimals$}"))
+}
+
+/// Functions, types and headings in `lines`, found by matching declaration
+/// keywords line by line. A rough offline outline for files without an LSP.
+pub(crate) fn extract_symbols(lines: &[String], lang: SyntaxLang) -> Vec<FileSymbol> {
+    // Space-separated modifiers that may precede a declaration keyword.
+    let (modifiers, keywords) = match lang {
+        SyntaxLang::Rust => (
+            "pub pub(crate) pub(super) async const unsafe",
+            "fn struct enum trait impl mod type macro_rules!",
+        ),
+        SyntaxLang::Python => ("async", "def class"),
+        SyntaxLang::JsTs => (
+            "export default async abstract declare",
+            "function class interface",
+        ),
+        SyntaxLang::Go => ("", "func type"),
+        SyntaxLang::Php => (
+            "public protected private static abstract final",
+            "function class interface trait",
+        ),
+        SyntaxLang::Shell => ("", "function"),
+        SyntaxLang::Markdown => return markdown_headings(lines),
+        _ => return Vec::new(),
+    };
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            let label = keyword_symbol(text, modifiers, keywords).or_else(|| {
+                (lang == SyntaxLang::Shell)
+                    .then(|| shell_function(text))
+                    .flatten()
+            })?;
+            Some(FileSymbol { line, label })
+        })
+        .collect()
+}
+
+/// `"<keyword> <name>"` when `line` declares something with one of
+/// `keywords`, after skipping any leading `modifiers`.
+fn keyword_symbol(line: &str, modifiers: &str, keywords: &str) -> Option<String> {
+    let mut rest = line.trim_start();
+    while let Some(stripped) = modifiers
+        .split_whitespace()
+        .find_map(|m| rest.strip_prefix(m).filter(|r| r.starts_with(' ')))
+    {
+        rest = stripped.trim_start();
+    }
+    let (keyword, after) = keywords.split_whitespace().find_map(|kw| {
+        rest.strip_prefix(kw)
+            .filter(|after| after.starts_with([' ', '<']))
+            .map(|after| (kw, after))
+    })?;
+    if keyword == "impl" {
+        let target = after.split(['{', ';']).next().unwrap_or_default();
+        let target = target.split(" where").next().unwrap_or_default().trim_end();
+        return Some(format!("impl{target}"));
+    }
+    // Go methods name their receiver first: `func (s *Server) Start()`.
+    let after = after.trim_start();
+    let after = match after.strip_prefix('(') {
+        Some(receiver) => receiver.split_once(')')?.1.trim_start(),
+        None => after,
+    };
+    let name: String = after
+        .chars()
+        .take_while(|c| is_ident_char(*c) || *c == '$')
+        .collect();
+    (!name.is_empty()).then(|| format!("{keyword} {name}"))
+}
+
+/// `"function <name>"` for a POSIX-style `name() {` shell function.
+fn shell_function(line: &str) -> Option<String> {
+    let name = line.trim_start().split_once("()")?.0.trim_end();
+    (!name.is_empty() && name.chars().all(|c| is_ident_char(c) || c == '-'))
+        .then(|| format!("function {name}"))
+}
+
+/// ATX headings (`#` to `######`), skipping fenced code blocks.
+fn markdown_headings(lines: &[String]) -> Vec<FileSymbol> {
+    let mut in_fence = false;
+    let mut symbols = Vec::new();
+    for (line, text) in lines.iter().enumerate() {
+        let trimmed = text.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        let title = trimmed[level..].trim();
+        if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') && !title.is_empty() {
+            symbols.push(FileSymbol {
+                line,
+                label: format!("{} {title}", "#".repeat(level)),
+            });
+        }
+    }
+    symbols
+}
 #[cfg(test)]
 pub(crate) fn row_has_selection(
     row: usize,
@@ -624,6 +1690,38 @@ pub(crate) fn row_has_selection(
     true
 }
 
+/// Visible-row index drawn on each of up to `height` screen rows from
+/// `start`, with `None` for the blank spacer that follows every `every`-th
+/// source line (never when `every` is 0). Stops at the end of the buffer.
+pub(crate) fn spaced_screen_rows(
+    rows_map: &[usize],
+    start: usize,
+    height: usize,
+    every: usize,
+) -> Vec<Option<usize>> {
+    let mut out = Vec::with_capacity(height);
+    let mut idx = start;
+    while out.len() < height && idx < rows_map.len() {
+        out.push(Some(idx));
+        let row = rows_map[idx];
+        let ends_line = rows_map.get(idx + 1) != Some(&row);
+        if every > 0 && ends_line && (row + 1).is_multiple_of(every) && out.len() < height {
+            out.push(None);
+        }
+        idx += 1;
+    }
+    out
+}
+
+/// Visible rows certain to fit in `height` screen rows when a spacer follows
+/// every `every`-th line; scrolling uses this so the cursor stays on screen.
+pub(crate) fn spaced_text_rows(height: usize, every: usize) -> usize {
+    if every == 0 || height == 0 {
+        return height;
+    }
+    (height * every / (every + 1)).max(1)
+}
+
 pub(crate) fn wrap_segments_for_line(line: &str, wrap_width: usize) -> Vec<(usize, usize)> {
     use unicode_width::UnicodeWidthChar;
 
@@ -717,35 +1815,451 @@ pub(crate) fn inside(x: u16, y: u16, rect: Rect) -> bool {
         && y < rect.y.saturating_add(rect.height)
 }
 
-pub(crate) fn collect_all_files(dir: &Path, out: &mut Vec<PathBuf>) {
+/// Classify a directory entry for tree walks: `Some(true)` for a directory to
+/// descend into, `Some(false)` for a file, `None` to skip. Broken symlinks are
+/// always skipped, and a directory symlink is skipped when symlinks are not
+/// followed or when its target is already on the walk `stack` (a cycle).
+pub(crate) fn classify_walk_entry(
+    path: &Path,
+    follow_symlinks: bool,
+    stack: &HashSet<PathBuf>,
+) -> Option<bool> {
+    let meta = fs::symlink_metadata(path).ok()?;
+    if !meta.file_type().is_symlink() {
+        return Some(meta.is_dir());
+    }
+    if !follow_symlinks {
+        return None;
+    }
+    let target = fs::metadata(path).ok()?;
+    if !target.is_dir() {
+        return Some(false);
+    }
+    let canonical = fs::canonicalize(path).ok()?;
+    if stack.contains(&canonical) {
+        return None;
+    }
+    Some(true)
+}
+
+/// Order one folder's entries for the tree: by `sort`, reversed when
+/// `descending`, with folders ahead of files when `dirs_first`. Ties fall
+/// back to the case-insensitive name.
+pub(crate) fn sort_tree_entries(
+    entries: Vec<PathBuf>,
+    sort: TreeSort,
+    descending: bool,
+    dirs_first: bool,
+) -> Vec<PathBuf> {
+    struct Entry {
+        path: PathBuf,
+        is_dir: bool,
+        name: String,
+        extension: String,
+        modified: Option<SystemTime>,
+        size: u64,
+    }
+    let lower = |s: Option<&std::ffi::OsStr>| {
+        s.map(|s| s.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default()
+    };
+    let mut keyed: Vec<Entry> = entries
+        .into_iter()
+        .map(|path| {
+            let meta = fs::metadata(&path).ok();
+            Entry {
+                is_dir: meta.as_ref().is_some_and(|m| m.is_dir()),
+                name: lower(path.file_name()),
+                extension: lower(path.extension()),
+                modified: meta.as_ref().and_then(|m| m.modified().ok()),
+                size: meta.filter(|m| m.is_file()).map_or(0, |m| m.len()),
+                path,
+            }
+        })
+        .collect();
+    keyed.sort_by(|a, b| {
+        let by_key = match sort {
+            TreeSort::Name => a.name.cmp(&b.name),
+            TreeSort::Modified => a.modified.cmp(&b.modified),
+            TreeSort::Size => a.size.cmp(&b.size),
+            TreeSort::Extension => a.extension.cmp(&b.extension),
+        };
+        let by_key = if descending { by_key.reverse() } else { by_key };
+        let group = if dirs_first {
+            b.is_dir.cmp(&a.is_dir)
+        } else {
+            Ordering::Equal
+        };
+        group.then(by_key).then_with(|| a.name.cmp(&b.name))
+    });
+    keyed.into_iter().map(|e| e.path).collect()
+}
+
+/// Count the files and subfolders under `dir`, visiting at most `limit`
+/// entries. Symlinks are counted as files and not followed, matching what
+/// `remove_dir_all` deletes. Returns `(files, dirs, stopped_at_limit)`.
+pub(crate) fn count_dir_contents(dir: &Path, limit: usize) -> (usize, usize, bool) {
+    let (mut files, mut dirs) = (0, 0);
+    let mut queue = VecDeque::from([dir.to_path_buf()]);
+    while let Some(current) = queue.pop_front() {
+        let Ok(entries) = fs::read_dir(&current) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            if files + dirs >= limit {
+                return (files, dirs, true);
+            }
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                dirs += 1;
+                queue.push_back(entry.path());
+            } else {
+                files += 1;
+            }
+        }
+    }
+    (files, dirs, false)
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+/// Delete confirmation text: the target's name (or an item count) and, when
+/// folders are involved, how many files and subfolders go with them.
+pub(crate) fn delete_summary(paths: &[PathBuf], limit: usize) -> String {
+    let name = match paths {
+        [_] => format!("'{}'", describe_paths(paths)),
+        _ => describe_paths(paths),
+    };
+    let folders: Vec<&PathBuf> = paths
+        .iter()
+        .filter(|p| fs::symlink_metadata(p).is_ok_and(|m| m.is_dir()))
+        .collect();
+    if folders.is_empty() {
+        return name;
+    }
+    let (mut files, mut dirs, mut truncated) = (0, 0, false);
+    for folder in folders {
+        let (f, d, t) = count_dir_contents(folder, limit.saturating_sub(files + dirs));
+        files += f;
+        dirs += d;
+        truncated |= t;
+    }
+    if files + dirs == 0 {
+        return format!("{name} (empty)");
+    }
+    let at_least = if truncated { "at least " } else { "" };
+    format!(
+        "{name} ({at_least}{}, {} inside)",
+        plural(files, "file"),
+        plural(dirs, "folder")
+    )
+}
+
+/// The renames a batch rename of `paths` makes: `find` replaced by `replace`
+/// in each file name, or, when `find` is wrapped in slashes (`/(\w+)\.txt/`),
+/// every regex match replaced, with `$1` naming groups. Unchanged names are
+/// left out. Any invalid or colliding target fails the whole plan.
+pub(crate) fn batch_rename_plan(
+    paths: &[PathBuf],
+    find: &str,
+    replace: &str,
+) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let regex = match find.strip_prefix('/').and_then(|f| f.strip_suffix('/')) {
+        Some(pattern) if !pattern.is_empty() => {
+            Some(Regex::new(pattern).map_err(|e| format!("invalid regex: {e}"))?)
+        }
+        _ => None,
+    };
+    if regex.is_none() && find.is_empty() {
+        return Err("nothing to find".to_string());
+    }
+    let mut plan = Vec::new();
+    let mut targets = HashSet::new();
+    for path in paths {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            continue;
+        };
+        let name = name.to_string_lossy();
+        let renamed = match &regex {
+            Some(re) => re.replace_all(&name, replace).into_owned(),
+            None => name.replace(find, replace),
+        };
+        if renamed == name {
+            continue;
+        }
+        if renamed.trim().is_empty()
+            || renamed == "."
+            || renamed == ".."
+            || renamed.contains(['/', '\\'])
+        {
+            return Err(format!("'{name}' would be renamed to invalid '{renamed}'"));
+        }
+        let target = parent.join(&renamed);
+        if !targets.insert(target.clone()) {
+            return Err(format!("more than one name would become '{renamed}'"));
+        }
+        if rename_target_taken(path, &target) {
+            return Err(format!("'{renamed}' already exists"));
+        }
+        plan.push((path.clone(), target));
+    }
+    Ok(plan)
+}
+
+/// Whether renaming `from` to `to` would replace another entry. A case-only
+/// rename on a case-insensitive file system finds `from` itself and is fine.
+pub(crate) fn rename_target_taken(from: &Path, to: &Path) -> bool {
+    fs::symlink_metadata(to).is_ok() && fs::canonicalize(to).ok() != fs::canonicalize(from).ok()
+}
+
+/// Compact file size for the tree details column: "512 B", "1.5 KB".
+pub(crate) fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Age of `modified` relative to `now`, e.g. "just now", "5m ago", "2h ago".
+/// Timestamps in the future count as "just now".
+pub(crate) fn relative_time(modified: SystemTime, now: SystemTime) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+    const MONTH: u64 = 30 * DAY;
+    const YEAR: u64 = 365 * DAY;
+    let secs = now
+        .duration_since(modified)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    match secs {
+        0..MINUTE => "just now".to_string(),
+        MINUTE..HOUR => format!("{}m ago", secs / MINUTE),
+        HOUR..DAY => format!("{}h ago", secs / HOUR),
+        DAY..MONTH => format!("{}d ago", secs / DAY),
+        MONTH..YEAR => format!("{}mo ago", secs / MONTH),
+        _ => format!("{}y ago", secs / YEAR),
+    }
+}
+
+/// Details column text for a tree row: size (or entry count for folders)
+/// and age, or `None` when no metadata was loaded.
+pub(crate) fn tree_item_details_label(item: &TreeItem, now: SystemTime) -> Option<String> {
+    let size = match (item.size, item.child_count) {
+        (Some(bytes), _) => Some(human_size(bytes)),
+        (None, Some(1)) => Some("1 item".to_string()),
+        (None, Some(n)) => Some(format!("{n} items")),
+        (None, None) => None,
+    };
+    let age = item.modified.map(|m| relative_time(m, now));
+    match (size, age) {
+        (Some(size), Some(age)) => Some(format!("{size}  {age}")),
+        (size, age) => size.or(age),
+    }
+}
+
+/// Program and arguments that show `path` in the platform's file manager.
+/// Finder and Explorer select the item; other platforms open its folder.
+pub(crate) fn reveal_command(path: &Path, platform: Platform) -> (&'static str, Vec<OsString>) {
+    match platform {
+        Platform::MacOs => ("open", vec!["-R".into(), path.into()]),
+        Platform::Windows => {
+            let mut select = OsString::from("/select,");
+            select.push(path);
+            ("explorer", vec![select])
+        }
+        Platform::Linux => {
+            let dir = path.parent().unwrap_or(path);
+            ("xdg-open", vec![dir.into()])
+        }
+    }
+}
+
+/// Program and arguments that open `path` with its default application.
+pub(crate) fn open_command(path: &Path, platform: Platform) -> (&'static str, Vec<OsString>) {
+    match platform {
+        Platform::MacOs => ("open", vec![path.into()]),
+        Platform::Windows => ("explorer", vec![path.into()]),
+        Platform::Linux => ("xdg-open", vec![path.into()]),
+    }
+}
+
+/// Whether `bytes` look like binary data: a NUL in the first 8 KiB, the
+/// same sniff git uses.
+pub(crate) fn looks_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(8192).any(|&b| b == 0)
+}
+
+/// `dir/name`, or `dir/<stem> copy[ N].<ext>` when that name is taken.
+pub(crate) fn unique_child_path(dir: &Path, name: &OsStr) -> PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let name = Path::new(name);
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = name
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| match n {
+            1 => dir.join(format!("{stem} copy{ext}")),
+            n => dir.join(format!("{stem} copy {n}{ext}")),
+        })
+        .find(|p| !p.exists())
+        .unwrap_or(candidate)
+}
+
+/// Copy a directory tree. Symlinks to directories are skipped rather than
+/// followed so a link cycle cannot recurse forever.
+pub(crate) fn copy_dir_recursive(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)?.filter_map(Result::ok) {
+        let path = entry.path();
+        let target = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir_recursive(&path, &target)?;
+        } else if file_type.is_symlink() && path.is_dir() {
+            continue;
+        } else {
+            fs::copy(&path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn collect_all_files(dir: &Path, follow_symlinks: bool, out: &mut Vec<PathBuf>) {
+    let mut stack = HashSet::new();
+    collect_all_files_inner(dir, follow_symlinks, &mut stack, out);
+}
+
+fn collect_all_files_inner(
+    dir: &Path,
+    follow_symlinks: bool,
+    stack: &mut HashSet<PathBuf>,
+    out: &mut Vec<PathBuf>,
+) {
     let Ok(entries) = fs::read_dir(dir) else {
         return;
     };
+    let canonical = fs::canonicalize(dir).ok();
+    if let Some(c) = &canonical {
+        stack.insert(c.clone());
+    }
     for entry in entries.filter_map(Result::ok) {
         let path = entry.path();
-        let Ok(ft) = fs::symlink_metadata(&path).map(|m| m.file_type()) else {
+        let Some(is_dir) = classify_walk_entry(&path, follow_symlinks, stack) else {
             continue;
         };
-        if ft.is_symlink() {
-            continue;
-        }
-        if ft.is_dir() {
+        if is_dir {
             let name = path.file_name().unwrap_or_default().to_string_lossy();
             // Skip hidden dirs and common noisy dirs
             if name.starts_with('.') || name == "target" || name == "node_modules" {
                 continue;
             }
-            collect_all_files(&path, out);
+            collect_all_files_inner(&path, follow_symlinks, stack, out);
         } else {
             out.push(path);
         }
     }
+    if let Some(c) = canonical {
+        stack.remove(&c);
+    }
+}
+
+/// Tab bar label, including the dirty, pinned and read-only markers and
+/// close button.
+pub(crate) fn tab_label(tab: &Tab) -> String {
+    let fname = tab
+        .path
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_else(|| "untitled".to_string());
+    let prefix = if tab.dirty { "*" } else { "" };
+    let pin = if tab.pinned { " [pin]" } else { "" };
+    let suffix = if tab.read_only { " [ro]" } else { "" };
+    format!(" {prefix}{fname}{pin}{suffix} [x] ")
+}
+
+/// Width of the "‹ " / " ›" overflow markers in the tab bar.
+pub(crate) const TAB_CHEVRON_WIDTH: usize = 2;
+
+fn tabs_fit(widths: &[usize], start: usize, last: usize, avail: usize) -> bool {
+    let left = if start > 0 { TAB_CHEVRON_WIDTH } else { 0 };
+    let right = if last + 1 < widths.len() {
+        TAB_CHEVRON_WIDTH
+    } else {
+        0
+    };
+    let tabs: usize = widths[start..=last].iter().sum::<usize>() + (last - start);
+    left + tabs + right <= avail
+}
+
+/// Adjust the tab bar scroll offset so the `active` tab is fully visible.
+pub(crate) fn tab_scroll_offset_for(
+    widths: &[usize],
+    offset: usize,
+    active: usize,
+    avail: usize,
+) -> usize {
+    if widths.is_empty() {
+        return 0;
+    }
+    let active = active.min(widths.len() - 1);
+    let mut offset = offset.min(active);
+    while offset < active && !tabs_fit(widths, offset, active, avail) {
+        offset += 1;
+    }
+    offset
+}
+
+/// Index of the last tab that fits in the tab bar when starting at `offset`.
+/// The first visible tab is always shown, even if it has to be clipped.
+pub(crate) fn last_visible_tab(widths: &[usize], offset: usize, avail: usize) -> usize {
+    let mut last = offset;
+    while last + 1 < widths.len() && tabs_fit(widths, offset, last + 1, avail) {
+        last += 1;
+    }
+    last
 }
 
 pub(crate) fn relative_path(root: &Path, path: &Path) -> PathBuf {
     path.strip_prefix(root).unwrap_or(path).to_path_buf()
 }
 
+/// Move `root` to the front of the recent-projects list, dropping folders
+/// that no longer exist and keeping at most `limit` entries.
+pub(crate) fn push_recent_project(recent: &mut Vec<PathBuf>, root: &Path, limit: usize) {
+    recent.retain(|p| p != root && p.is_dir());
+    recent.insert(0, root.to_path_buf());
+    recent.truncate(limit.max(1));
+}
+
+/// Move `value` to the front of a prompt's input history, keeping at most
+/// `limit` entries. Blank input is not remembered.
+pub(crate) fn push_prompt_history(history: &mut Vec<String>, value: &str, limit: usize) {
+    if value.trim().is_empty() {
+        return;
+    }
+    history.retain(|v| v != value);
+    history.insert(0, value.to_string());
+    history.truncate(limit.max(1));
+}
+
 pub(crate) fn to_u16_saturating(v: usize) -> u16 {
     u16::try_from(v).unwrap_or(u16::MAX)
 }
@@ -1111,6 +2625,22 @@ mod fold_and_selection_tests {
         assert!(ranges.iter().any(|r| r.start_line == 0 && r.end_line == 3));
     }
 
+    #[test]
+    fn test_fold_ranges_trailing_blank_lines_do_not_fold() {
+        let lines = vec![
+            "def test():".to_string(),
+            "    x = 1".to_string(),
+            "".to_string(),
+            "done()".to_string(),
+            "".to_string(),
+            "".to_string(),
+        ];
+        let (ranges, _) = compute_fold_ranges(&lines, SyntaxLang::Python);
+        let spans: Vec<(usize, usize)> =
+            ranges.iter().map(|r| (r.start_line, r.end_line)).collect();
+        assert_eq!(spans, vec![(0, 1)]);
+    }
+
     #[test]
     fn test_fold_ranges_html_simple_tag_pair() {
         let lines = vec![
@@ -1291,6 +2821,20 @@ mod fold_and_selection_tests {
         assert_eq!(segs, vec![(0, 6), (6, 11)]);
     }
 
+    #[test]
+    fn test_wrap_segments_long_prose_line() {
+        // A long Markdown paragraph wrapped at 20 columns breaks after the
+        // last space that fits, so no word is split across rows.
+        let line = "Soft wrap keeps long paragraphs readable without scrolling sideways.";
+        let segs = wrap_segments_for_line(line, 20);
+        assert_eq!(segs, vec![(0, 16), (16, 32), (32, 49), (49, 68)]);
+        let chars: Vec<char> = line.chars().collect();
+        for &(start, end) in &segs[..segs.len() - 1] {
+            assert!(end - start <= 20);
+            assert_eq!(chars[end - 1], ' ');
+        }
+    }
+
     #[test]
     fn test_wrap_segments_no_spaces_hard_break() {
         // "abcdefghij" = 10 chars, no spaces, wrap at 4.
@@ -1576,6 +3120,442 @@ mod utility_tests {
         assert_eq!(result.preview, "pub struct Ast {");
     }
 
+    // tab bar scrolling tests
+
+    #[test]
+    fn test_tab_scroll_offset_keeps_active_visible() {
+        let widths = vec![10; 6];
+        // 6 tabs of width 10 need 65 columns; only 32 available.
+        let offset = tab_scroll_offset_for(&widths, 0, 5, 32);
+        assert!(offset > 0);
+        assert_eq!(last_visible_tab(&widths, offset, 32), 5);
+        assert_eq!(tab_scroll_offset_for(&widths, offset, 0, 32), 0);
+    }
+
+    #[test]
+    fn test_tab_scroll_offset_all_fit() {
+        let widths = vec![5, 5, 5];
+        assert_eq!(tab_scroll_offset_for(&widths, 0, 2, 80), 0);
+        assert_eq!(last_visible_tab(&widths, 0, 80), 2);
+    }
+
+    #[test]
+    fn test_last_visible_tab_reserves_chevrons() {
+        let widths = vec![10, 10, 10, 10];
+        // From offset 1: "‹ " (2) + 10 + 1 + 10 + " ›" (2) = 25
+        assert_eq!(last_visible_tab(&widths, 1, 25), 2);
+        assert_eq!(last_visible_tab(&widths, 1, 24), 1);
+    }
+
+    #[test]
+    fn test_identifier_range_at() {
+        let line = "let foo = foobar(1);";
+        assert_eq!(identifier_range_at(line, 4), Some((4, 7)));
+        assert_eq!(identifier_range_at(line, 7), Some((4, 7)));
+        assert_eq!(identifier_range_at(line, 8), None);
+        assert_eq!(identifier_range_at(line, 17), None);
+        assert_eq!(identifier_range_at("", 0), None);
+    }
+
+    #[test]
+    fn test_word_occurrences_whole_word_only() {
+        let line = "foo(foobar, foo_x, foo) + foo";
+        assert_eq!(
+            word_occurrences(line, "foo"),
+            vec![(0, 3), (19, 22), (26, 29)]
+        );
+        assert!(word_occurrences("foobar barfoo", "foo").is_empty());
+    }
+
+    #[test]
+    fn test_trailing_whitespace_range() {
+        assert_eq!(trailing_whitespace_range("let x = 1;  \t"), Some((10, 13)));
+        assert_eq!(trailing_whitespace_range("    "), Some((0, 4)));
+        assert_eq!(trailing_whitespace_range("  let x = 1;"), None);
+        assert_eq!(trailing_whitespace_range(""), None);
+    }
+
+    fn diag(line: usize, col: usize, end_line: usize, end_col: usize, sev: &str) -> LspDiagnostic {
+        LspDiagnostic {
+            line,
+            col,
+            end_line,
+            end_col,
+            severity: sev.to_string(),
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_diagnostic_underlines_map_ranges_to_columns() {
+        let diags = [diag(2, 4, 2, 9, "warning"), diag(3, 6, 5, 2, "error")];
+        assert_eq!(diagnostic_underlines(&diags, 0, 12), vec![]);
+        assert_eq!(
+            diagnostic_underlines(&diags, 1, 12),
+            vec![(4, 9, "warning")]
+        );
+        assert_eq!(diagnostic_underlines(&diags, 2, 10), vec![(6, 10, "error")]);
+        assert_eq!(diagnostic_underlines(&diags, 3, 5), vec![(0, 5, "error")]);
+        assert_eq!(diagnostic_underlines(&diags, 4, 8), vec![(0, 2, "error")]);
+        // Empty ranges mark one char, even past the end of the line
+        let point = [diag(1, 3, 1, 3, "hint"), diag(2, 7, 2, 7, "info")];
+        assert_eq!(diagnostic_underlines(&point, 0, 8), vec![(3, 4, "hint")]);
+        assert_eq!(diagnostic_underlines(&point, 1, 4), vec![(3, 4, "info")]);
+    }
+
+    #[test]
+    fn test_diagnostic_underlines_overlap_takes_most_severe() {
+        let diags = [
+            diag(1, 0, 1, 10, "warning"),
+            diag(1, 3, 1, 6, "error"),
+            diag(1, 5, 1, 8, "hint"),
+        ];
+        assert_eq!(
+            diagnostic_underlines(&diags, 0, 12),
+            vec![(0, 3, "warning"), (3, 6, "error"), (6, 10, "warning")]
+        );
+    }
+
+    #[test]
+    fn test_visible_diagnostics_caps_each_line_keeping_most_severe() {
+        let diags = [
+            diag(1, 0, 1, 1, "warning"),
+            diag(1, 9, 1, 10, "error"),
+            diag(2, 0, 2, 1, "info"),
+            diag(1, 2, 1, 3, "hint"),
+            diag(1, 4, 1, 5, "error"),
+        ];
+        let kept: Vec<(usize, usize)> = visible_diagnostics(&diags, 2)
+            .into_iter()
+            .map(|d| (d.line, d.col))
+            .collect();
+        assert_eq!(kept, [(1, 4), (1, 9), (2, 0)]);
+        assert_eq!(visible_diagnostics(&diags, 0).len(), 5);
+    }
+
+    #[test]
+    fn test_extract_symbols_rust() {
+        let lines: Vec<String> = [
+            "use std::fmt;",
+            "",
+            "pub(crate) struct Point {",
+            "    x: i32,",
+            "}",
+            "",
+            "impl fmt::Display for Point {",
+            "    pub fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {",
+            "        let fn_name = 1;",
+            "    }",
+            "}",
+            "",
+            "async fn main() {}",
+            "enum Shape<T> {}",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let symbols: Vec<(usize, String)> = extract_symbols(&lines, SyntaxLang::Rust)
+            .into_iter()
+            .map(|s| (s.line, s.label))
+            .collect();
+        assert_eq!(
+            symbols,
+            vec![
+                (2, "struct Point".to_string()),
+                (6, "impl fmt::Display for Point".to_string()),
+                (7, "fn fmt".to_string()),
+                (12, "fn main".to_string()),
+                (13, "enum Shape".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_symbols_markdown_headings() {
+        let lines: Vec<String> = [
+            "# Title",
+            "Intro text with a # sign",
+            "## Install",
+            "```sh",
+            "# not a heading",
+            "```",
+            "#hashtag",
+            "### Usage notes ",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let labels: Vec<String> = extract_symbols(&lines, SyntaxLang::Markdown)
+            .into_iter()
+            .map(|s| s.label)
+            .collect();
+        assert_eq!(labels, vec!["# Title", "## Install", "### Usage notes"]);
+    }
+
+    #[test]
+    fn test_extract_symbols_go_method_and_python() {
+        let go: Vec<String> = ["func (s *Server) Start() error {", "type Server struct {"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        let labels: Vec<String> = extract_symbols(&go, SyntaxLang::Go)
+            .into_iter()
+            .map(|s| s.label)
+            .collect();
+        assert_eq!(labels, vec!["func Start", "type Server"]);
+
+        let py: Vec<String> = ["class Foo(Base):", "    async def run(self):"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        let labels: Vec<String> = extract_symbols(&py, SyntaxLang::Python)
+            .into_iter()
+            .map(|s| s.label)
+            .collect();
+        assert_eq!(labels, vec!["class Foo", "def run"]);
+    }
+
+    #[test]
+    fn test_has_mixed_indent() {
+        assert!(has_mixed_indent("\t  x"));
+        assert!(has_mixed_indent("  \tx"));
+        assert!(!has_mixed_indent("\t\tx"));
+        assert!(!has_mixed_indent("    x"));
+        assert!(!has_mixed_indent("\tx = 1;  \t"));
+        let lines: Vec<String> = ["fn f() {", "\t let x = 1;", "\tx", "}"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        assert_eq!(mixed_indent_rows(&lines), vec![1]);
+    }
+
+    #[test]
+    fn test_display_col_expands_tabs_to_next_stop() {
+        assert_eq!(display_col("\tx", 1, 4), 4);
+        assert_eq!(display_col("ab\tx", 3, 4), 4);
+        assert_eq!(display_col("ab\tx", 3, 8), 8);
+        assert_eq!(display_col("a\t\tx", 3, 4), 8);
+        assert_eq!(expand_tabs("ab\tx", 4), "ab  x");
+    }
+
+    #[test]
+    fn test_char_at_display_col_maps_back_to_chars() {
+        // Every column a tab covers lands on the tab itself.
+        for col in 0..4 {
+            assert_eq!(char_at_display_col("\tx", col, 4), 0);
+        }
+        assert_eq!(char_at_display_col("\tx", 4, 4), 1);
+        assert_eq!(char_at_display_col("\tx", 9, 4), 2);
+        assert_eq!(char_at_display_col("ab\tx", 3, 4), 2);
+    }
+
+    #[test]
+    fn test_convert_indentation_tabs_to_spaces() {
+        let lines: Vec<String> = ["fn main() {", "\tlet x = 1;", "\t\tx", "  \ty", "}"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        assert_eq!(
+            convert_indentation(&lines, false, 4),
+            vec!["fn main() {", "    let x = 1;", "        x", "    y", "}"]
+        );
+    }
+
+    #[test]
+    fn test_convert_indentation_spaces_to_tabs_keeps_remainder() {
+        let lines: Vec<String> = ["        a", "      b", "c"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        assert_eq!(
+            convert_indentation(&lines, true, 4),
+            vec!["\t\ta", "\t  b", "c"]
+        );
+    }
+
+    #[test]
+    fn test_editorconfig_glob_matches() {
+        assert!(editorconfig_glob_matches("*", "main.rs"));
+        assert!(editorconfig_glob_matches("*.rs", "main.rs"));
+        assert!(editorconfig_glob_matches("**/*.rs", "main.rs"));
+        assert!(editorconfig_glob_matches("*.{js,ts}", "app.ts"));
+        assert!(editorconfig_glob_matches("Makefile", "Makefile"));
+        assert!(!editorconfig_glob_matches("*.py", "main.rs"));
+        assert!(!editorconfig_glob_matches("src/*.rs", "main.rs"));
+    }
+
+    #[test]
+    fn test_indent_settings_from_editorconfig() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path();
+        fs::create_dir_all(root.join("web")).expect("mkdir");
+        fs::write(
+            root.join(".editorconfig"),
+            "root = true\n\n[*]\nindent_style = space\nindent_size = 2\n\n[Makefile]\nindent_style = tab\ntab_width = 8\n",
+        )
+        .expect("write root config");
+        fs::write(root.join("web/.editorconfig"), "[*.js]\nindent_size = 8\n")
+            .expect("write nested config");
+        let defaults = IndentSettings::default();
+
+        let rs = indent_settings_for_path(&root.join("main.rs"), root, defaults);
+        assert_eq!((rs.hard_tabs, rs.width), (false, 2));
+        assert_eq!(rs.tab_stop(), 2);
+        let make = indent_settings_for_path(&root.join("Makefile"), root, defaults);
+        assert_eq!((make.hard_tabs, make.width), (true, 2));
+        assert_eq!(make.tab_stop(), 8);
+        let js = indent_settings_for_path(&root.join("web/app.js"), root, defaults);
+        assert_eq!((js.hard_tabs, js.width), (false, 8));
+        let outside = indent_settings_for_path(Path::new("untitled-1"), root, defaults);
+        assert_eq!(outside, defaults);
+    }
+
+    // search context / grouping tests
+
+    fn hit(path: &str, line: usize) -> ProjectSearchHit {
+        ProjectSearchHit {
+            path: PathBuf::from(path),
+            line,
+            preview: format!("line {line}"),
+            context_before: Vec::new(),
+            context_after: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_search_context_lines_at_file_start() {
+        let lines: Vec<String> = ["a", "b", "c"].iter().map(ToString::to_string).collect();
+        let (before, after) = search_context_lines(&lines, 1, 1);
+        assert!(before.is_empty());
+        assert_eq!(after, vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn test_search_context_lines_at_file_end() {
+        let lines: Vec<String> = ["a", "b", "c"].iter().map(ToString::to_string).collect();
+        let (before, after) = search_context_lines(&lines, 3, 2);
+        assert_eq!(before, vec!["a".to_string(), "b".to_string()]);
+        assert!(after.is_empty());
+    }
+
+    #[test]
+    fn test_search_context_lines_out_of_range() {
+        let lines: Vec<String> = vec!["a".to_string()];
+        assert_eq!(search_context_lines(&lines, 0, 1), (Vec::new(), Vec::new()));
+        assert_eq!(search_context_lines(&lines, 5, 1), (Vec::new(), Vec::new()));
+    }
+
+    #[test]
+    fn test_attach_search_context_reads_file() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("f.txt");
+        fs::write(&path, "one\ntwo\nthree\nfour\n").expect("write");
+        let mut hits = vec![
+            hit(path.to_str().unwrap(), 1),
+            hit(path.to_str().unwrap(), 4),
+        ];
+        attach_search_context(&mut hits, 1);
+        assert!(hits[0].context_before.is_empty());
+        assert_eq!(hits[0].context_after, vec!["two".to_string()]);
+        assert_eq!(hits[1].context_before, vec!["three".to_string()]);
+        assert!(hits[1].context_after.is_empty());
+    }
+
+    #[test]
+    fn test_group_search_hits_counts_per_file() {
+        let hits = vec![
+            hit("a.rs", 1),
+            hit("a.rs", 9),
+            hit("b.rs", 3),
+            hit("a.rs", 12),
+        ];
+        let groups = group_search_hits(&hits);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, PathBuf::from("a.rs"));
+        assert_eq!(groups[0].1, vec![0, 1, 3]);
+        assert_eq!(groups[1].0, PathBuf::from("b.rs"));
+        assert_eq!(groups[1].1, vec![2]);
+    }
+
+    #[test]
+    fn test_search_result_rows_headers_and_shared_context() {
+        let mut a = hit("a.rs", 2);
+        a.context_before = vec!["l1".to_string()];
+        a.context_after = vec!["l3".to_string()];
+        let mut b = hit("a.rs", 3);
+        b.context_before = vec!["l2".to_string()];
+        b.context_after = vec!["l4".to_string()];
+        let rows = search_result_rows(&[a, b, hit("b.rs", 7)]);
+        assert_eq!(
+            rows,
+            vec![
+                SearchResultRow::Header {
+                    path: PathBuf::from("a.rs"),
+                    count: 2,
+                    first_hit: 0,
+                },
+                SearchResultRow::Context {
+                    hit: 0,
+                    line: 1,
+                    text: "l1".to_string(),
+                },
+                SearchResultRow::Hit(0),
+                SearchResultRow::Hit(1),
+                SearchResultRow::Context {
+                    hit: 1,
+                    line: 4,
+                    text: "l4".to_string(),
+                },
+                SearchResultRow::Header {
+                    path: PathBuf::from("b.rs"),
+                    count: 1,
+                    first_hit: 2,
+                },
+                SearchResultRow::Hit(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_panel_rows_group_and_count_by_severity() {
+        let entry = |path: &str, severity: &str| ProjectDiagnostic {
+            path: PathBuf::from(path),
+            diagnostic: diag(1, 0, 1, 1, severity),
+        };
+        let entries = [
+            entry("a.rs", "error"),
+            entry("a.rs", "warning"),
+            entry("a.rs", "error"),
+            entry("b.rs", "hint"),
+            entry("b.rs", "info"),
+        ];
+        assert_eq!(
+            diagnostic_panel_rows(&entries),
+            vec![
+                DiagnosticPanelRow::Header {
+                    path: PathBuf::from("a.rs"),
+                    errors: 2,
+                    warnings: 1,
+                    others: 0,
+                    first: 0,
+                },
+                DiagnosticPanelRow::Item(0),
+                DiagnosticPanelRow::Item(1),
+                DiagnosticPanelRow::Item(2),
+                DiagnosticPanelRow::Header {
+                    path: PathBuf::from("b.rs"),
+                    errors: 0,
+                    warnings: 0,
+                    others: 2,
+                    first: 3,
+                },
+                DiagnosticPanelRow::Item(3),
+                DiagnosticPanelRow::Item(4),
+            ]
+        );
+        assert!(diagnostic_panel_rows(&[]).is_empty());
+    }
+
     // fuzzy_score tests
 
     #[test]
@@ -1633,33 +3613,66 @@ mod utility_tests {
 
     #[test]
     fn test_pending_hint_none() {
-        assert_eq!(pending_hint(&PendingAction::None), "");
+        assert_eq!(pending_hint(&PendingAction::None, ConfirmKey::Enter), "");
     }
 
     #[test]
     fn test_pending_hint_quit() {
-        let hint = pending_hint(&PendingAction::Quit);
+        let hint = pending_hint(&PendingAction::QuitPrompt, ConfirmKey::Enter);
         assert!(!hint.is_empty());
         assert!(hint.contains("quit"));
     }
 
     #[test]
     fn test_pending_hint_close_prompt() {
-        let hint = pending_hint(&PendingAction::ClosePrompt);
+        let hint = pending_hint(&PendingAction::ClosePrompt, ConfirmKey::Enter);
         assert!(!hint.is_empty());
         assert!(hint.contains("close"));
     }
 
     #[test]
     fn test_pending_hint_delete() {
-        let hint = pending_hint(&PendingAction::Delete(PathBuf::from(
-            "/home/user/project/file.rs",
-        )));
+        let hint = pending_hint(
+            &PendingAction::Delete {
+                paths: vec![PathBuf::from("/home/user/project/file.rs")],
+                summary: String::new(),
+            },
+            ConfirmKey::Enter,
+        );
         assert!(!hint.is_empty());
         assert!(hint.contains("delete"));
         assert!(hint.contains("file.rs"));
     }
 
+    #[test]
+    fn test_pending_hint_delete_many_counts_items() {
+        let hint = pending_hint(
+            &PendingAction::Delete {
+                paths: vec![PathBuf::from("a.rs"), PathBuf::from("b.rs")],
+                summary: String::new(),
+            },
+            ConfirmKey::Enter,
+        );
+        assert!(hint.contains("2 items"));
+    }
+
+    #[test]
+    fn test_pending_hint_names_the_configured_confirm_key() {
+        let delete = PendingAction::Delete {
+            paths: vec![PathBuf::from("a.rs")],
+            summary: String::new(),
+        };
+        assert!(pending_hint(&delete, ConfirmKey::Enter).contains("Enter/Y confirm"));
+        assert!(pending_hint(&delete, ConfirmKey::Chord).contains("Ctrl+D confirm"));
+        let close = PendingAction::ClosePrompt;
+        assert!(pending_hint(&close, ConfirmKey::Enter).contains("Enter save+close"));
+        assert!(pending_hint(&close, ConfirmKey::Chord).contains("Ctrl+S save+close"));
+        let quit = PendingAction::QuitPrompt;
+        assert!(pending_hint(&quit, ConfirmKey::Enter).contains("Enter/S save all"));
+        assert!(pending_hint(&quit, ConfirmKey::Chord).contains("Ctrl+S save all"));
+        assert!(confirm_key_label(&PendingAction::None, ConfirmKey::Chord).is_empty());
+    }
+
     // command_action_label tests
 
     #[test]
@@ -1706,6 +3719,12 @@ mod utility_tests {
         assert_eq!(context_label(ContextAction::NewFolder), "New Folder");
         assert_eq!(context_label(ContextAction::Rename), "Rename");
         assert_eq!(context_label(ContextAction::Delete), "Delete");
+        assert_eq!(
+            context_label(ContextAction::Reveal),
+            "Reveal in File Manager"
+        );
+        assert_eq!(context_label(ContextAction::ExpandAll), "Expand All Under");
+        assert_eq!(context_label(ContextAction::CollapseAll), "Collapse All");
         assert_eq!(context_label(ContextAction::Cancel), "Cancel");
     }
 
@@ -1720,8 +3739,267 @@ mod utility_tests {
             editor_context_label(EditorContextAction::SelectAll),
             "Select All"
         );
+        assert_eq!(
+            editor_context_label(EditorContextAction::AddToDictionary),
+            "Add to Dictionary"
+        );
         assert_eq!(editor_context_label(EditorContextAction::Cancel), "Cancel");
     }
+
+    #[test]
+    fn unique_child_path_appends_copy_suffix_on_clash() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let dir = tmp.path();
+        let name = OsStr::new("main.rs");
+        assert_eq!(unique_child_path(dir, name), dir.join("main.rs"));
+        fs::write(dir.join("main.rs"), "").expect("write");
+        assert_eq!(unique_child_path(dir, name), dir.join("main copy.rs"));
+        fs::write(dir.join("main copy.rs"), "").expect("write");
+        assert_eq!(unique_child_path(dir, name), dir.join("main copy 2.rs"));
+    }
+
+    #[test]
+    fn reveal_command_per_platform() {
+        let path = Path::new("/work/src/main.rs");
+        assert_eq!(
+            reveal_command(path, Platform::MacOs),
+            ("open", vec!["-R".into(), "/work/src/main.rs".into()])
+        );
+        assert_eq!(
+            reveal_command(path, Platform::Windows),
+            ("explorer", vec!["/select,/work/src/main.rs".into()])
+        );
+        assert_eq!(
+            reveal_command(path, Platform::Linux),
+            ("xdg-open", vec!["/work/src".into()])
+        );
+    }
+
+    #[test]
+    fn bracket_depth_after_skips_strings_and_comments() {
+        assert_eq!(bracket_depth_after("f(a, [b", SyntaxLang::Rust, 1), 3);
+        let quoted = r#"x("(\"(", '{'"#;
+        assert_eq!(bracket_depth_after(quoted, SyntaxLang::Rust, 0), 1);
+        assert_eq!(bracket_depth_after("g() // (((", SyntaxLang::Rust, 0), 0);
+        assert_eq!(bracket_depth_after("]) # ((", SyntaxLang::Python, 1), 0);
+    }
+
+    #[test]
+    fn spaced_screen_rows_inserts_spacers_after_lines() {
+        // Line 1 wraps onto two visible rows.
+        let rows_map = [0, 1, 1, 2, 3];
+        assert_eq!(
+            spaced_screen_rows(&rows_map, 0, 10, 0),
+            vec![Some(0), Some(1), Some(2), Some(3), Some(4)]
+        );
+        let spaced = spaced_screen_rows(&rows_map, 0, 10, 1);
+        let spacers: Vec<usize> = (0..spaced.len()).filter(|&i| spaced[i].is_none()).collect();
+        assert_eq!(spacers, vec![1, 4, 6, 8]);
+        assert_eq!(spaced.iter().flatten().count(), rows_map.len());
+        assert_eq!(
+            spaced_screen_rows(&rows_map, 1, 4, 2),
+            vec![Some(1), Some(2), None, Some(3)]
+        );
+        assert_eq!(spaced_text_rows(10, 0), 10);
+        assert_eq!(spaced_text_rows(10, 1), 5);
+        assert_eq!(spaced_text_rows(1, 1), 1);
+    }
+
+    #[test]
+    fn open_command_per_platform() {
+        let path = Path::new("/work/doc.pdf");
+        assert_eq!(
+            open_command(path, Platform::MacOs),
+            ("open", vec!["/work/doc.pdf".into()])
+        );
+        assert_eq!(
+            open_command(path, Platform::Linux),
+            ("xdg-open", vec!["/work/doc.pdf".into()])
+        );
+    }
+
+    #[test]
+    fn looks_binary_sniffs_leading_nul() {
+        assert!(!looks_binary(b"fn main() {}\n"));
+        assert!(!looks_binary("héllo".as_bytes()));
+        assert!(looks_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+        let mut late = vec![b'a'; 9000];
+        late.push(0);
+        assert!(!looks_binary(&late));
+    }
+
+    #[test]
+    fn count_dir_contents_stops_at_limit() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let dir = tmp.path();
+        fs::create_dir(dir.join("sub")).expect("mkdir");
+        for name in ["a", "b", "sub/c"] {
+            fs::write(dir.join(name), "").expect("write");
+        }
+        assert_eq!(count_dir_contents(dir, 100), (3, 1, false));
+        let (files, dirs, truncated) = count_dir_contents(dir, 2);
+        assert_eq!(files + dirs, 2);
+        assert!(truncated);
+        assert!(delete_summary(&[dir.to_path_buf()], 2).contains("(at least "));
+    }
+
+    #[test]
+    fn batch_rename_plan_maps_plain_and_regex_patterns() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let dir = tmp.path();
+        let paths: Vec<PathBuf> = ["a_old.txt", "b_old.txt", "c.rs"]
+            .iter()
+            .map(|name| dir.join(name))
+            .collect();
+
+        let plan = batch_rename_plan(&paths, "_old", "_new").expect("plain plan");
+        assert_eq!(
+            plan,
+            vec![
+                (dir.join("a_old.txt"), dir.join("a_new.txt")),
+                (dir.join("b_old.txt"), dir.join("b_new.txt")),
+            ]
+        );
+        let plan = batch_rename_plan(&paths, r"/^(\w)_old\.txt$/", "${1}.md").expect("regex");
+        assert_eq!(plan[1], (dir.join("b_old.txt"), dir.join("b.md")));
+
+        assert!(batch_rename_plan(&paths, "/[/", "").is_err());
+        assert!(batch_rename_plan(&paths, "/.+/", "same").is_err());
+        assert!(batch_rename_plan(&paths, "c.rs", "").is_err());
+    }
+
+    #[test]
+    fn human_size_scales_bytes_kb_and_mb() {
+        assert_eq!(human_size(0), "0 B");
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(1024), "1.0 KB");
+        assert_eq!(human_size(1536), "1.5 KB");
+        assert_eq!(human_size(5 * 1024 * 1024), "5.0 MB");
+        assert_eq!(human_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn relative_time_picks_the_largest_whole_unit() {
+        let secs = std::time::Duration::from_secs;
+        let now = SystemTime::UNIX_EPOCH + secs(100_000_000);
+        let ago = |n| relative_time(now - secs(n), now);
+        assert_eq!(ago(5), "just now");
+        assert_eq!(ago(5 * 60), "5m ago");
+        assert_eq!(ago(2 * 3_600 + 59), "2h ago");
+        assert_eq!(ago(3 * 86_400), "3d ago");
+        assert_eq!(ago(65 * 86_400), "2mo ago");
+        assert_eq!(ago(800 * 86_400), "2y ago");
+        // Clock skew: a future mtime is not reported as negative age.
+        assert_eq!(relative_time(now + secs(60), now), "just now");
+    }
+
+    #[test]
+    fn buffer_stats_sum_a_numeric_selection() {
+        let lines: Vec<String> = ["price 10", "2.5", "-0.25 7"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(buffer_stats_label(&lines, 18, None), "3 lines, 18 chars");
+        assert_eq!(
+            buffer_stats_label(&lines, 18, Some(((0, 6), (2, 7)))),
+            "Sel: 3 lines, 12 chars, Sum: 19.25"
+        );
+        // A word in the selection means it isn't a column of numbers.
+        assert_eq!(
+            buffer_stats_label(&lines, 18, Some(((0, 0), (1, 3)))),
+            "Sel: 2 lines, 11 chars"
+        );
+        let inf = vec!["1 inf".to_string()];
+        assert_eq!(
+            buffer_stats_label(&inf, 5, Some(((0, 0), (0, 5)))),
+            "Sel: 1 line, 5 chars"
+        );
+        // Whole lines selected down to the start of the next one.
+        assert_eq!(
+            buffer_stats_label(&lines, 18, Some(((0, 0), (2, 0)))),
+            "Sel: 2 lines, 11 chars"
+        );
+    }
+
+    #[test]
+    fn text_in_range_spans_lines() {
+        let lines = vec!["one two three".to_string(), "four five".to_string()];
+        assert_eq!(text_in_range(&lines, (0, 4), (1, 4)), "two three\nfour");
+        assert_eq!(text_in_range(&lines, (0, 4), (0, 7)), "two");
+    }
+
+    #[test]
+    fn nested_tags_match_their_own_partner() {
+        let lines: Vec<String> = [
+            "<div class=\"a > b\">",
+            "  <div><br/><br><img src='x'>",
+            "  </div>",
+            "  <!-- </div> -->",
+            "</div>",
+        ]
+        .map(String::from)
+        .into();
+        assert_eq!(matching_tag(&lines, (0, 2)), Some([(0, 1, 4), (4, 2, 5)]));
+        assert_eq!(matching_tag(&lines, (2, 4)), Some([(2, 4, 7), (1, 3, 6)]));
+        // Self-closing and void elements have no partner.
+        assert_eq!(matching_tag(&lines, (1, 9)), None);
+        assert_eq!(matching_tag(&lines, (1, 14)), None);
+    }
+
+    #[test]
+    fn unmatched_tags_have_no_partner() {
+        let lines: Vec<String> = ["<p>", "<span>text</p>"].map(String::from).into();
+        assert_eq!(matching_tag(&lines, (1, 1)), None);
+        let pair = matching_tag(&lines, (1, 12));
+        assert_eq!(pair, Some([(1, 12, 13), (0, 1, 2)]));
+        assert_eq!(matching_tag(&lines, (1, 7)), None);
+    }
+
+    #[test]
+    fn line_comments_continue_with_their_leader() {
+        assert_eq!(comment_continuation("\t// a", "//"), Some("\t// ".into()));
+        assert_eq!(comment_continuation("/// docs", "//"), Some("/// ".into()));
+        assert_eq!(comment_continuation("//! crate", "//"), Some("//! ".into()));
+        assert_eq!(comment_continuation("## heading", "#"), Some("## ".into()));
+        assert_eq!(comment_continuation("let x = 1; // trailing", "//"), None);
+    }
+
+    #[test]
+    fn push_recent_project_keeps_mru_order_cap_and_existing_dirs() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let [a, b, c, gone] = ["a", "b", "c", "gone"].map(|n| tmp.path().join(n));
+        for dir in [&a, &b, &c, &gone] {
+            fs::create_dir(dir).expect("mkdir");
+        }
+        let mut recent = Vec::new();
+        for dir in [&a, &b, &c] {
+            push_recent_project(&mut recent, dir, 10);
+        }
+        assert_eq!(recent, vec![c.clone(), b.clone(), a.clone()]);
+
+        push_recent_project(&mut recent, &a, 10);
+        assert_eq!(recent, vec![a.clone(), c.clone(), b.clone()]);
+
+        push_recent_project(&mut recent, &b, 2);
+        assert_eq!(recent, vec![b.clone(), a.clone()]);
+
+        push_recent_project(&mut recent, &gone, 10);
+        fs::remove_dir(&gone).expect("rmdir");
+        push_recent_project(&mut recent, &c, 10);
+        assert_eq!(recent, vec![c, b, a]);
+    }
+
+    #[test]
+    fn push_prompt_history_collapses_duplicates_and_caps() {
+        let mut history = Vec::new();
+        for value in ["foo", "bar", "foo", "  ", "baz"] {
+            push_prompt_history(&mut history, value, 10);
+        }
+        assert_eq!(history, ["baz", "foo", "bar"]);
+
+        push_prompt_history(&mut history, "qux", 2);
+        assert_eq!(history, ["qux", "baz"]);
+    }
 }
 
 #[cfg(test)]